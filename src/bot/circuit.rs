@@ -0,0 +1,237 @@
+//! Circuit breaker for Telegram API outages.
+//!
+//! Tracks consecutive API failures globally. After too many failures the
+//! circuit opens and non-critical sends (greetings, auto-replies, notices)
+//! are skipped for a cooldown that grows exponentially on repeated trips. Once the cooldown
+//! passes the circuit is half-open: traffic flows again, and a single failure
+//! re-opens it, while a quiet probe window closes it.
+//!
+//! Moderation itself (deleting, muting, banning) never waits on the circuit.
+
+use std::future::IntoFuture;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use teloxide::RequestError;
+use tracing::{debug, info, warn};
+
+/// Failures further apart than this are not considered consecutive.
+const FAILURE_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// Consecutive outage failures seen while closed
+    failures: u32,
+    /// When the last failure happened
+    last_failure: Option<Instant>,
+    /// Circuit is open until this instant
+    open_until: Option<Instant>,
+    /// When the circuit became half-open
+    half_open_since: Option<Instant>,
+    /// Number of trips since the last full recovery (drives backoff)
+    trips: u32,
+}
+
+/// Global circuit breaker shared through `AppState`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    /// Consecutive failures before the circuit opens
+    threshold: u32,
+    /// Cooldown after the first trip (doubled on each further trip)
+    base_cooldown: Duration,
+    /// Upper bound for the cooldown
+    max_cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, base_cooldown: Duration, max_cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(BreakerState::default()),
+            threshold,
+            base_cooldown,
+            max_cooldown,
+        }
+    }
+
+    /// Check whether non-critical API work may proceed.
+    pub fn allow_request(&self) -> bool {
+        self.allow_request_at(Instant::now())
+    }
+
+    fn allow_request_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock();
+
+        if let Some(until) = state.open_until {
+            if now < until {
+                return false;
+            }
+            state.open_until = None;
+            state.half_open_since = Some(now);
+            info!("Telegram circuit half-open, probing recovery");
+        }
+
+        // A probe window without failures means Telegram has recovered
+        if let Some(since) = state.half_open_since
+            && now.duration_since(since) >= self.base_cooldown
+        {
+            *state = BreakerState::default();
+            info!("Telegram circuit closed");
+        }
+
+        true
+    }
+
+    /// Record an API failure, opening the circuit if needed.
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut state = self.state.lock();
+
+        if state.open_until.is_some() {
+            return;
+        }
+
+        if state
+            .last_failure
+            .is_some_and(|last| now.duration_since(last) > FAILURE_WINDOW)
+        {
+            state.failures = 0;
+        }
+        state.failures += 1;
+        state.last_failure = Some(now);
+
+        if state.half_open_since.is_some() || state.failures >= self.threshold {
+            state.trips += 1;
+            let factor = 2u32.saturating_pow(state.trips - 1);
+            let cooldown = self.base_cooldown.saturating_mul(factor).min(self.max_cooldown);
+
+            state.open_until = Some(now + cooldown);
+            state.half_open_since = None;
+            state.failures = 0;
+
+            warn!(
+                "Telegram circuit opened for {:?} (trip {})",
+                cooldown, state.trips
+            );
+        }
+    }
+
+    /// Record a handler error if it looks like a Telegram outage.
+    ///
+    /// Regular API errors (bad request, missing rights) are ignored.
+    pub fn record_error(&self, error: &anyhow::Error) {
+        if is_outage_error(error) {
+            self.record_failure();
+        }
+    }
+
+    /// Run a non-critical send, skipping it while the circuit is open.
+    ///
+    /// Evaluates to `Ok(None)` when skipped. Errors are returned as-is and
+    /// reach the breaker through the handler's error path.
+    pub async fn send<T, E>(&self, send: impl IntoFuture<Output = Result<T, E>>) -> Result<Option<T>, E> {
+        if !self.allow_request() {
+            debug!("Circuit open, skipping non-critical send");
+            return Ok(None);
+        }
+        send.await.map(Some)
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30), Duration::from_secs(600))
+    }
+}
+
+/// Check if an error indicates Telegram is unreachable.
+///
+/// Rate limits (`RetryAfter`) don't count: a busy chat causes them on its
+/// own, and the throttle already waits them out.
+fn is_outage_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<RequestError>(),
+        Some(RequestError::Network(_) | RequestError::InvalidJson { .. } | RequestError::Io(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(3, Duration::from_secs(10), Duration::from_secs(60))
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let breaker = breaker();
+        let t0 = Instant::now();
+
+        breaker.record_failure_at(t0);
+        breaker.record_failure_at(t0);
+        assert!(breaker.allow_request_at(t0));
+
+        breaker.record_failure_at(t0);
+        assert!(!breaker.allow_request_at(t0));
+        assert!(!breaker.allow_request_at(t0 + Duration::from_secs(9)));
+    }
+
+    #[test]
+    fn test_spread_out_failures_stay_closed() {
+        let breaker = breaker();
+        let t0 = Instant::now();
+
+        for i in 0..5 {
+            breaker.record_failure_at(t0 + FAILURE_WINDOW * 2 * i);
+        }
+        assert!(breaker.allow_request_at(t0 + FAILURE_WINDOW * 10));
+    }
+
+    #[test]
+    fn test_half_open_failure_reopens_with_backoff() {
+        let breaker = breaker();
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(t0);
+        }
+
+        // Cooldown over: half-open lets traffic through
+        let t1 = t0 + Duration::from_secs(10);
+        assert!(breaker.allow_request_at(t1));
+
+        // One failure while half-open re-opens for twice as long
+        breaker.record_failure_at(t1);
+        assert!(!breaker.allow_request_at(t1 + Duration::from_secs(19)));
+        assert!(breaker.allow_request_at(t1 + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_quiet_probe_closes() {
+        let breaker = breaker();
+        let t0 = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure_at(t0);
+        }
+
+        let t1 = t0 + Duration::from_secs(10);
+        assert!(breaker.allow_request_at(t1));
+        // A probe window without failures closes the circuit
+        assert!(breaker.allow_request_at(t1 + Duration::from_secs(10)));
+
+        // Closed again: a single failure no longer trips it
+        breaker.record_failure_at(t1 + Duration::from_secs(11));
+        assert!(breaker.allow_request_at(t1 + Duration::from_secs(11)));
+    }
+
+    #[test]
+    fn test_rate_limits_are_not_outages() {
+        let retry = anyhow::Error::from(RequestError::RetryAfter(teloxide::types::Seconds::from_seconds(5)));
+        assert!(!is_outage_error(&retry));
+        let io = anyhow::Error::from(RequestError::Io(std::io::Error::other("down")));
+        assert!(is_outage_error(&io));
+    }
+}
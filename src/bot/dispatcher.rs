@@ -3,20 +3,27 @@
 //! Builds the dispatcher with all command handlers and event handlers.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use teloxide::adaptors::Throttle;
 use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
+use tracing::{error, info, warn};
 
-use crate::cache::CacheRegistry;
+use super::circuit::CircuitBreaker;
+use super::usage::CommandUsage;
+use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
+use crate::constants::REPORT_COOLDOWN;
+use crate::database::models::MessageContext;
 use crate::database::{
     Database, UserRepo, FilterRepository, NoteRepository,
     MessageContextRepository, WelcomeRepository, ByeRepository,
-    RulesRepository, WarnsRepository,
+    RulesRepository, WarnsRepository, GlobalDefaultsRepository,
 };
-use crate::events::{self, FloodTracker};
+use crate::events::{self, FloodTracker, RecentMessages};
 use crate::permissions::Permissions;
 use crate::plugins;
+use crate::utils::{Clock, SystemClock};
 
 /// Bot type with Throttle adaptor for automatic rate limiting.
 pub type ThrottledBot = Throttle<Bot>;
@@ -59,11 +66,33 @@ pub struct AppState {
     /// Warns repository.
     pub warns: Arc<WarnsRepository>,
 
+    /// Recently seen group messages, for user-targeted cleanup.
+    pub recent_messages: Arc<RecentMessages>,
+
+    /// Global defaults repository (templates for new chats).
+    pub defaults: Arc<GlobalDefaultsRepository>,
+
+    /// Circuit breaker for Telegram API outages.
+    pub circuit: Arc<CircuitBreaker>,
+
+    /// Per-chat command counts since startup.
+    pub usage: Arc<CommandUsage>,
+
+    /// Time source for restriction expiry.
+    pub clock: Arc<dyn Clock>,
+
     /// Owner user IDs (bypass all restrictions).
     pub owner_ids: Vec<u64>,
 
     /// Bot username (without @) for deep link construction.
     pub bot_username: String,
+
+    /// AFK notices sent recently, per (chat, AFK user). `None` when the
+    /// AFK cooldown is off.
+    pub afk_notices: Option<TypedCache<(i64, u64), ()>>,
+
+    /// Reports sent recently, per (chat, reporter), for the report cooldown.
+    pub recent_reports: TypedCache<(i64, u64), ()>,
 }
 
 impl AppState {
@@ -74,19 +103,29 @@ impl AppState {
         cache: Arc<CacheRegistry>,
         owner_ids: Vec<u64>,
         bot_username: String,
+        afk_cooldown: Duration,
     ) -> Self {
         // Note: Permissions needs the inner Bot for API calls
         let permissions = Permissions::with_owners(bot.inner().clone(), cache.clone(), owner_ids.clone());
 
         // Create repositories
+        let defaults = Arc::new(GlobalDefaultsRepository::new(&db, &cache));
         let users = Arc::new(UserRepo::new(&db, &cache));
         let filters = Arc::new(FilterRepository::new(&db, &cache));
+        filters.spawn_hits_flusher();
         let notes = Arc::new(NoteRepository::new(&db, &cache));
-        let message_context = Arc::new(MessageContextRepository::new(&db, &cache));
-        let welcome = Arc::new(WelcomeRepository::new(&db, &cache));
+        let message_context = Arc::new(MessageContextRepository::new(&db, &cache, defaults.clone()));
+        let welcome = Arc::new(WelcomeRepository::new(&db, &cache, defaults.clone()));
         let bye = Arc::new(ByeRepository::new(&db, &cache));
         let rules = Arc::new(RulesRepository::new(&db, &cache));
-        let warns = Arc::new(WarnsRepository::new(&db, &cache));
+        let warns = Arc::new(WarnsRepository::new(&db, &cache, defaults.clone()));
+        let recent_messages = Arc::new(RecentMessages::new(&cache));
+
+        // Cooldowns: an entry expires when the cooldown ends
+        let afk_notices = (!afk_cooldown.is_zero()).then(|| {
+            cache.get_or_create("afk_notice_cooldown", CacheConfig::with_capacity(10_000).ttl(afk_cooldown))
+        });
+        let recent_reports = cache.get_or_create("report_cooldown", CacheConfig::with_capacity(10_000).ttl(REPORT_COOLDOWN));
 
         Self {
             db,
@@ -100,8 +139,15 @@ impl AppState {
             bye,
             rules,
             warns,
+            recent_messages,
+            defaults,
+            circuit: Arc::new(CircuitBreaker::default()),
+            usage: Arc::new(CommandUsage::new(chrono::Utc::now())),
+            clock: Arc::new(SystemClock),
             owner_ids,
             bot_username,
+            afk_notices,
+            recent_reports,
         }
     }
 
@@ -110,47 +156,156 @@ impl AppState {
         self.owner_ids.contains(&user_id)
     }
 
+    /// Check if a user bypasses automated enforcement (antiflood, locks, blocklist...).
+    ///
+    /// Owners, approved users and chat admins are exempt. Every automated
+    /// enforcement path should go through this so approvals are honored uniformly.
+    ///
+    /// Takes the chat's already loaded message context for the approval list.
+    pub async fn is_exempt(&self, ctx: &MessageContext, user_id: UserId) -> bool {
+        // Bot owners bypass all restrictions
+        if self.is_owner(user_id.0) {
+            return true;
+        }
+
+        // Approved users (small list on the message context)
+        if ctx.is_approved(user_id.0) {
+            return true;
+        }
+
+        // Admins (cached permission lookup)
+        self.permissions
+            .is_admin(ChatId(ctx.chat_id), user_id)
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Copy of the state for handling one update.
+    ///
+    /// Permission lookups are memoized for the lifetime of the copy.
+    pub fn for_update(&self) -> Self {
+        Self {
+            permissions: self.permissions.scoped(),
+            ..self.clone()
+        }
+    }
+
+    /// Drop every cached entry for a chat so the next access reads MongoDB.
+    ///
+    /// Invalidation is targeted at the chat's keys. Used by /reload after
+    /// out-of-band database edits and when the bot leaves a chat.
+    pub fn invalidate_chat(&self, chat_id: ChatId) {
+        self.permissions.invalidate_chat(chat_id);
+        self.message_context.invalidate_chat(chat_id.0);
+        self.welcome.invalidate_chat(chat_id.0);
+        self.bye.invalidate_chat(chat_id.0);
+        self.rules.invalidate_chat(chat_id.0);
+        self.warns.invalidate_chat(chat_id.0);
+        self.filters.invalidate_chat(chat_id.0);
+        self.notes.invalidate_chat(chat_id.0);
+    }
+
     /// Resolve locale for a context (User + Chat).
+    ///
+    /// The group language wins over the user's. This is the default for
+    /// replies; moderation code splits it with [`Self::locale_for_group`]
+    /// (announcements) and [`Self::locale_for_user`] (errors to the admin).
     pub async fn get_locale(&self, chat_id: Option<i64>, user_id: Option<u64>) -> String {
         let mut group_lang = None;
         let mut user_lang = None;
 
         // check group lang
-        if let Some(chat) = chat_id {
-             if let Ok(ctx) = self.message_context.get_or_default(chat).await {
-                 if let Some(info) = ctx.group_info {
-                     group_lang = info.lang;
-                 }
-                 // If not set, maybe check antiflood config or other settings? Nah.
-             }
-        }
+        if let Some(chat) = chat_id
+            && let Ok(ctx) = self.message_context.get_or_default(chat).await
+            && let Some(info) = ctx.group_info {
+                group_lang = info.lang;
+            }
 
-        // check user lang
-        if let Some(uid) = user_id {
-            if let Ok(Some(u)) = self.users.get_by_id(uid).await {
-                user_lang = u.lang;
+        // check user lang (their Telegram client's if they never picked one)
+        if let Some(uid) = user_id
+            && let Ok(Some(u)) = self.users.get_by_id(uid).await {
+                user_lang = u.preferred_lang();
             }
-        }
         
         crate::i18n::resolve_locale(group_lang.as_deref(), user_lang.as_deref())
     }
+
+    /// Locale for automated announcements seen by the whole group
+    /// (antiflood, warn limits, bans, greetings): the group language only.
+    pub async fn locale_for_group(&self, chat_id: i64) -> String {
+        match self.message_context.get_or_default(chat_id).await {
+            Ok(ctx) => Self::locale_for_group_in(&ctx),
+            Err(_) => crate::i18n::resolve_locale(None, None),
+        }
+    }
+
+    /// Same as [`Self::locale_for_group`], using a loaded message context.
+    pub fn locale_for_group_in(ctx: &MessageContext) -> String {
+        let group_lang = ctx.group_info.as_ref().and_then(|info| info.lang.as_deref());
+        crate::i18n::resolve_locale(group_lang, None)
+    }
+
+    /// Locale for command errors meant for the invoking user: the language
+    /// they picked first, then the group's, then their Telegram client's.
+    pub async fn locale_for_user(&self, chat_id: i64, user_id: u64) -> String {
+        let user = self.users.get_by_id(user_id).await.ok().flatten();
+        if let Some(lang) = user.as_ref().and_then(|u| u.lang.as_deref()) {
+            return crate::i18n::resolve_locale(None, Some(lang));
+        }
+        let group_lang = match self.message_context.get_or_default(chat_id).await {
+            Ok(ctx) => ctx.group_info.and_then(|info| info.lang),
+            Err(_) => None,
+        };
+        let client_lang = user.and_then(|u| u.client_lang());
+        crate::i18n::resolve_locale(group_lang.as_deref(), client_lang.as_deref())
+    }
+
+    /// Same as [`Self::get_locale`], using an already loaded message context
+    /// for the group language.
+    pub async fn get_locale_in(&self, ctx: &MessageContext, user_id: Option<u64>) -> String {
+        let group_lang = ctx.group_info.as_ref().and_then(|info| info.lang.as_deref());
+
+        let mut user_lang = None;
+        if let Some(uid) = user_id
+            && let Ok(Some(u)) = self.users.get_by_id(uid).await {
+                user_lang = u.preferred_lang();
+            }
+
+        crate::i18n::resolve_locale(group_lang, user_lang.as_deref())
+    }
 }
 
 /// Build the dispatcher with all handlers.
+///
+/// Also returns the shared state, so buffered data can be written out once
+/// the dispatcher stops.
 pub fn build_dispatcher(
     bot: ThrottledBot,
     db: Arc<Database>,
     cache: Arc<CacheRegistry>,
     owner_ids: Vec<u64>,
     bot_username: String,
-) -> Dispatcher<ThrottledBot, anyhow::Error, teloxide::dispatching::DefaultKey> {
-    let state = AppState::new(bot.clone(), db, cache, owner_ids, bot_username);
+    afk_cooldown: Duration,
+) -> (Dispatcher<ThrottledBot, anyhow::Error, teloxide::dispatching::DefaultKey>, AppState) {
+    let state = AppState::new(bot.clone(), db, cache, owner_ids, bot_username, afk_cooldown);
     let flood_tracker = FloodTracker::new();
+    flood_tracker.spawn_reaper();
+    events::welcome::rearm_verify_timeouts(bot.clone(), state.clone());
+    let circuit = state.circuit.clone();
 
-    Dispatcher::builder(bot, schema())
-        .dependencies(dptree::deps![state, flood_tracker])
-        .enable_ctrlc_handler()
-        .build()
+    let dispatcher = Dispatcher::builder(bot, schema())
+        .dependencies(dptree::deps![state.clone(), flood_tracker])
+        .error_handler(Arc::new(move |err: anyhow::Error| {
+            // Feed outage errors into the circuit breaker
+            circuit.record_error(&err);
+            async move {
+                error!("Error from update handler: {:?}", err);
+            }
+        }))
+        // Ctrl+C and SIGTERM are handled by bot::runtime
+        .build();
+
+    (dispatcher, state)
 }
 
 /// Build the handler schema.
@@ -168,18 +323,48 @@ fn schema() -> UpdateHandler<anyhow::Error> {
     let member_handler = Update::filter_chat_member()
         .branch(events::event_handler());
 
+    // Bot's own membership changes (cache cleanup on leave)
+    let my_member_handler = Update::filter_my_chat_member()
+        .endpoint(handle_my_chat_member);
+
+    // Join requests (auto-approval for gated groups)
+    let join_request_handler = events::join_request::handler();
+
     // Callback query handler
     let callback_handler = plugins::callback_handler();
 
-    dptree::entry()
+    // Every update gets its own permission memo
+    dptree::map(|state: AppState| state.for_update())
         .branch(message_handler)
         .branch(member_handler)
+        .branch(my_member_handler)
+        .branch(join_request_handler)
         .branch(callback_handler)
 }
 
+/// Drop a chat's cached data once the bot is no longer in it.
+async fn handle_my_chat_member(update: ChatMemberUpdated, state: AppState) -> anyhow::Result<()> {
+    let new = &update.new_chat_member;
+    if new.is_left() || new.is_banned() {
+        state.invalidate_chat(update.chat.id);
+        info!("Left chat {}, dropped cached data", update.chat.id);
+    }
+    Ok(())
+}
+
 /// Track user from message (runs before all handlers).
+///
+/// In PM the record is written before handlers run, so a first-time user's
+/// client language already applies to their first /start or /help.
 async fn track_user(msg: Message, state: AppState) {
-    if let Some(user) = msg.from.as_ref() {
+    let Some(user) = msg.from.as_ref() else {
+        return;
+    };
+    if msg.chat.is_private() {
+        if let Err(e) = state.users.upsert(user).await {
+            warn!("Failed to upsert user {}: {}", user.id, e);
+        }
+    } else {
         state.users.clone().upsert_background(user.clone());
     }
 }
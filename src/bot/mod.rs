@@ -1,7 +1,9 @@
 //! Bot module - Core bot functionality.
 
+pub mod circuit;
 pub mod dispatcher;
 mod runtime;
+pub mod usage;
 pub mod webhook;
 
 pub use dispatcher::build_dispatcher;
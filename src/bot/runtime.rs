@@ -1,11 +1,19 @@
 //! Bot runtime - Polling and Webhook runners.
+//!
+//! Both modes stop on Ctrl+C or SIGTERM (what container runtimes send). The
+//! dispatcher stops taking updates and finishes the ones in flight before
+//! `run` returns, so a redeploy doesn't drop them.
+
+use std::sync::Arc;
 
 use teloxide::prelude::*;
-use tracing::info;
+use teloxide::dispatching::ShutdownToken;
+use tracing::{info, warn};
 
 use super::dispatcher::ThrottledBot;
 use super::webhook;
 use crate::config::{BotMode, Config};
+use crate::database::Database;
 
 /// Run the bot with the configured mode.
 ///
@@ -14,7 +22,10 @@ pub async fn run(
     config: &Config,
     mut dispatcher: Dispatcher<ThrottledBot, anyhow::Error, teloxide::dispatching::DefaultKey>,
     bot: ThrottledBot,
+    db: Arc<Database>,
 ) {
+    spawn_shutdown_listener(dispatcher.shutdown_token());
+
     match config.bot_mode {
         BotMode::Polling => {
             info!("🔄 Starting bot in polling mode...");
@@ -22,7 +33,60 @@ pub async fn run(
         }
         BotMode::Webhook => {
             info!("🌐 Starting bot in webhook mode...");
-            webhook::start_webhook(config, dispatcher, bot).await;
+            webhook::start_webhook(config, dispatcher, bot, db).await;
+        }
+    }
+
+    info!("👋 Bot stopped");
+}
+
+/// Shut the dispatcher down on the first Ctrl+C or SIGTERM.
+fn spawn_shutdown_listener(token: ShutdownToken) {
+    tokio::spawn(async move {
+        loop {
+            shutdown_signal().await;
+
+            match token.shutdown() {
+                Ok(done) => {
+                    info!("🛑 Shutdown signal received, finishing in-flight updates...");
+                    done.await;
+                }
+                // Still starting up (e.g. registering the webhook)
+                Err(_) => warn!("Shutdown signal received before the dispatcher started, ignoring"),
+            }
         }
+    });
+}
+
+/// Wait for Ctrl+C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
     }
 }
@@ -0,0 +1,79 @@
+//! Per-chat command usage counters.
+//!
+//! Counts are kept in memory only, so they cover the time since the bot
+//! started. /usage shows them alongside that start time.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+
+/// Command counts per chat since startup.
+#[derive(Debug)]
+pub struct CommandUsage {
+    chats: DashMap<i64, HashMap<String, u64>>,
+    since: DateTime<Utc>,
+}
+
+impl CommandUsage {
+    /// Start counting from `since`.
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self {
+            chats: DashMap::new(),
+            since,
+        }
+    }
+
+    /// When counting started.
+    pub fn since(&self) -> DateTime<Utc> {
+        self.since
+    }
+
+    /// Count one run of the command in `text` (e.g. `/notes@bot arg`).
+    pub fn record(&self, chat_id: i64, text: &str) {
+        let Some(command) = command_name(text) else {
+            return;
+        };
+        *self.chats.entry(chat_id).or_default().entry(command).or_insert(0) += 1;
+    }
+
+    /// A chat's commands and counts, most used first.
+    pub fn for_chat(&self, chat_id: i64) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self
+            .chats
+            .get(&chat_id)
+            .map(|c| c.iter().map(|(cmd, n)| (cmd.clone(), *n)).collect())
+            .unwrap_or_default();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+/// Lowercase command name without the slash or bot mention.
+fn command_name(text: &str) -> Option<String> {
+    let command = text.split_whitespace().next()?.strip_prefix('/')?;
+    let command = command.split('@').next().unwrap_or(command);
+    (!command.is_empty()).then(|| command.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_counts() {
+        let usage = CommandUsage::new(Utc::now());
+        usage.record(1, "/notes");
+        usage.record(1, "/Get@ElysiumBot rules");
+        usage.record(1, "/get x");
+        usage.record(1, "/ban");
+        usage.record(2, "/ban");
+        usage.record(1, "not a command");
+
+        assert_eq!(
+            usage.for_chat(1),
+            vec![("get".to_string(), 2), ("ban".to_string(), 1), ("notes".to_string(), 1)]
+        );
+        assert!(usage.for_chat(3).is_empty());
+    }
+}
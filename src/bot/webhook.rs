@@ -2,33 +2,48 @@
 //!
 //! Uses teloxide's built-in axum webhook support to:
 //! - Automatically call `setWebhook` on Telegram
-//! - Spawn an axum HTTP server to receive updates
-//! - Automatically call `deleteWebhook` on shutdown
+//! - Run an axum HTTP server to receive updates, plus a `/healthz` route
+//!   for load balancers
+//! - Call `deleteWebhook` on shutdown, so Telegram queues updates until the
+//!   next start instead of retrying against a dead server
 
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
 use teloxide::prelude::*;
 use teloxide::update_listeners::webhooks::{self, Options};
-use tracing::info;
+use teloxide::update_listeners::UpdateListener;
+use tracing::{error, info, warn};
 use url::Url;
 
 use super::dispatcher::ThrottledBot;
 use crate::config::Config;
+use crate::database::Database;
+
+/// How long `/healthz` waits for MongoDB before reporting it down.
+const HEALTH_PING_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Start the bot in webhook mode.
 ///
 /// This function:
 /// 1. Parses the webhook URL from config
 /// 2. Configures webhook options (address, URL, secret)
-/// 3. Sets up the webhook with Telegram
-/// 4. Spawns an axum server to receive updates
+/// 3. Binds the server address and sets up the webhook with Telegram
+/// 4. Spawns an axum server for updates and `/healthz`
 /// 5. Dispatches updates through the provided dispatcher
 ///
-/// On shutdown (Ctrl+C), the webhook is automatically deleted.
+/// Returns once the dispatcher is shut down (see [`super::runtime`]), after
+/// the HTTP server has stopped and the webhook is deleted.
 pub async fn start_webhook(
     config: &Config,
     mut dispatcher: Dispatcher<ThrottledBot, anyhow::Error, teloxide::dispatching::DefaultKey>,
     bot: ThrottledBot,
+    db: Arc<Database>,
 ) {
     // Parse webhook URL from config
     let webhook_url = config
@@ -38,8 +53,8 @@ pub async fn start_webhook(
 
     let url = Url::parse(webhook_url).expect("Invalid WEBHOOK_URL format");
 
-    // Server address - listen on all interfaces at the configured port
-    let address = SocketAddr::from(([0, 0, 0, 0], config.webhook_port));
+    // Server address - WEBHOOK_BIND (all interfaces by default) at the configured port
+    let address = SocketAddr::new(config.webhook_bind, config.webhook_port);
 
     // Configure webhook options
     let mut options = Options::new(address, url.clone());
@@ -50,22 +65,39 @@ pub async fn start_webhook(
         info!("Webhook secret token configured");
     }
 
+    // Bind before registering, so a taken port doesn't leave Telegram
+    // pointed at a server that never started
+    let tcp_listener = tokio::net::TcpListener::bind(address)
+        .await
+        .expect("Couldn't bind to WEBHOOK_BIND/WEBHOOK_PORT");
+
     info!("🔗 Setting webhook URL: {}", url);
     info!("📡 Listening on: {}", address);
 
-    // Create the webhook listener
-    // This automatically:
+    // Create the webhook listener and its routes. This:
     // 1. Calls setWebhook to register with Telegram
-    // 2. Spawns an axum HTTP server
-    // 3. Handles incoming updates from Telegram
-    // 4. Calls deleteWebhook on shutdown
+    // 2. Returns the router that receives updates from Telegram
+    // 3. Returns a stop future that calls deleteWebhook when the listener stops
     //
     // Note: We use bot.inner() to get the underlying Bot without Throttle,
     // because the webhook setup only needs basic API access.
-    let listener = webhooks::axum(bot.inner().clone(), options)
+    let (mut listener, stop_flag, router) = webhooks::axum_to_router(bot.inner().clone(), options)
         .await
         .expect("Failed to setup webhook");
 
+    let app = router.merge(health_router(db));
+    let stop_token = listener.stop_token();
+    let server = tokio::spawn(async move {
+        let result = axum::serve(tcp_listener, app)
+            .with_graceful_shutdown(stop_flag)
+            .await;
+        if let Err(e) = result {
+            // Without a server no updates arrive, so stop dispatching too
+            error!("Webhook server error: {}", e);
+            stop_token.stop();
+        }
+    });
+
     info!("✅ Webhook setup complete, waiting for updates...");
 
     // Create a default error handler that logs errors
@@ -75,4 +107,36 @@ pub async fn start_webhook(
     dispatcher
         .dispatch_with_listener(listener, error_handler)
         .await;
+
+    // The server deletes the webhook on its way down; wait for it so the
+    // process doesn't exit first
+    match server.await {
+        Ok(()) => info!("🔌 Webhook server stopped"),
+        Err(e) => warn!("Webhook server task failed: {}", e),
+    }
+}
+
+/// Routes for load balancer health checks.
+fn health_router(db: Arc<Database>) -> Router {
+    Router::new().route("/healthz", get(healthz)).with_state(db)
+}
+
+/// `GET /healthz` - whether this instance can serve updates.
+///
+/// Answers 200 `ok` when MongoDB responds to a ping within
+/// [`HEALTH_PING_TIMEOUT`], and 503 otherwise: nearly every update touches
+/// the database, so an instance that can't reach it shouldn't get traffic.
+/// Telegram isn't checked, its outages affect every instance alike.
+async fn healthz(State(db): State<Arc<Database>>) -> (StatusCode, &'static str) {
+    match tokio::time::timeout(HEALTH_PING_TIMEOUT, db.ping()).await {
+        Ok(Ok(())) => (StatusCode::OK, "ok"),
+        Ok(Err(e)) => {
+            warn!("Health check failed, MongoDB ping error: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, "database unavailable")
+        }
+        Err(_) => {
+            warn!("Health check failed, MongoDB ping timed out");
+            (StatusCode::SERVICE_UNAVAILABLE, "database unavailable")
+        }
+    }
 }
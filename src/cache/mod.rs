@@ -27,4 +27,4 @@ mod typed;
 
 pub use config::CacheConfig;
 pub use registry::CacheRegistry;
-pub use typed::TypedCache;
+pub use typed::{CacheStats, TypedCache};
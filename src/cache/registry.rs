@@ -1,13 +1,13 @@
 //! Cache registry - Central management for all caches.
 
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
 use std::sync::{Arc, RwLock};
 
 use tracing::{debug, info};
 
-use super::{CacheConfig, TypedCache};
+use super::{CacheConfig, CacheStats, TypedCache};
 
 /// Central registry for managing multiple typed caches.
 ///
@@ -35,6 +35,8 @@ struct CacheEntry {
     cache: Box<dyn Any + Send + Sync>,
     type_id: TypeId,
     type_name: &'static str,
+    /// Reads the cache's stats without knowing its key and value types
+    stats: Box<dyn Fn() -> CacheStats + Send + Sync>,
 }
 
 impl CacheRegistry {
@@ -79,12 +81,14 @@ impl CacheRegistry {
 
         debug!("Creating cache: {}", name);
 
+        let stats_source = cache.clone();
         caches.insert(
             name.to_string(),
             CacheEntry {
                 cache: Box::new(cache.clone()),
                 type_id: TypeId::of::<TypedCache<K, V>>(),
                 type_name: std::any::type_name::<TypedCache<K, V>>(),
+                stats: Box::new(move || stats_source.stats()),
             },
         );
 
@@ -167,6 +171,16 @@ impl CacheRegistry {
         self.caches.read().unwrap().is_empty()
     }
 
+    /// Snapshot of every registered cache's stats, by cache name.
+    pub fn stats(&self) -> BTreeMap<String, CacheStats> {
+        self.caches
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| (name.clone(), (entry.stats)()))
+            .collect()
+    }
+
     /// Get a list of all registered cache names.
     #[allow(dead_code)]
     pub fn cache_names(&self) -> Vec<String> {
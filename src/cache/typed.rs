@@ -1,9 +1,14 @@
 //! Typed cache wrapper around Moka.
 
+use std::future::Future;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use dashmap::DashMap;
 use moka::sync::Cache;
+use tokio::sync::Mutex;
+use tracing::warn;
 
 use super::CacheConfig;
 
@@ -20,6 +25,45 @@ where
 {
     inner: Arc<Cache<K, V>>,
     name: Arc<str>,
+    counters: Arc<Counters>,
+    /// One lock per key being loaded by `get_or_insert_with_async`
+    loading: Arc<DashMap<K, Arc<Mutex<()>>>>,
+}
+
+/// Lookup counters shared by all clones of a cache.
+///
+/// Relaxed ordering: the numbers only feed stats, so a snapshot doesn't
+/// need to be consistent across counters.
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    inserts: AtomicU64,
+}
+
+impl Counters {
+    fn record_lookup(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time usage numbers for one cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    /// Approximate, see [`TypedCache::entry_count`]
+    pub entries: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache, or `None` before any lookup.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
 }
 
 // Manual Clone implementation that doesn't require K: Clone, V: Clone
@@ -32,6 +76,8 @@ where
         Self {
             inner: Arc::clone(&self.inner),
             name: Arc::clone(&self.name),
+            counters: Arc::clone(&self.counters),
+            loading: Arc::clone(&self.loading),
         }
     }
 }
@@ -43,7 +89,9 @@ where
 {
     /// Create a new typed cache with the given name and config.
     pub fn new(name: impl Into<Arc<str>>, config: CacheConfig) -> Self {
-        let mut builder = Cache::builder().max_capacity(config.max_capacity);
+        let mut builder = Cache::builder()
+            .max_capacity(config.max_capacity)
+            .support_invalidation_closures();
 
         if let Some(ttl) = config.ttl {
             builder = builder.time_to_live(ttl);
@@ -56,6 +104,8 @@ where
         Self {
             inner: Arc::new(builder.build()),
             name: name.into(),
+            counters: Arc::default(),
+            loading: Arc::default(),
         }
     }
 
@@ -67,6 +117,7 @@ where
 
     /// Insert a key-value pair into the cache.
     pub fn insert(&self, key: K, value: V) {
+        self.counters.inserts.fetch_add(1, Ordering::Relaxed);
         self.inner.insert(key, value);
     }
 
@@ -74,7 +125,9 @@ where
     ///
     /// Returns `Some(value)` if the key exists and hasn't expired.
     pub fn get(&self, key: &K) -> Option<V> {
-        self.inner.get(key)
+        let value = self.inner.get(key);
+        self.counters.record_lookup(value.is_some());
+        value
     }
 
     /// Check if a key exists in the cache.
@@ -88,6 +141,27 @@ where
         self.inner.invalidate(key);
     }
 
+    /// Remove a key from the cache, returning its value if it was present.
+    ///
+    /// Unlike `contains` followed by `invalidate`, only one caller can take
+    /// a given entry.
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.inner.remove(key)
+    }
+
+    /// Remove all entries whose key matches a predicate.
+    ///
+    /// Matching entries stop being returned immediately; Moka evicts them
+    /// lazily, so this is cheap compared to scanning or `invalidate_all`.
+    pub fn invalidate_if<F>(&self, predicate: F)
+    where
+        F: Fn(&K) -> bool + Send + Sync + 'static,
+    {
+        if let Err(e) = self.inner.invalidate_entries_if(move |key, _| predicate(key)) {
+            warn!("Failed to invalidate entries in cache '{}': {}", self.name, e);
+        }
+    }
+
     /// Remove all entries from the cache.
     #[allow(dead_code)]
     pub fn invalidate_all(&self) {
@@ -102,6 +176,19 @@ where
         self.inner.entry_count()
     }
 
+    /// Snapshot of this cache's lookup counters and size.
+    ///
+    /// Counts `get` and the `get_or_*` helpers; `contains` and `remove`
+    /// don't count as lookups.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            inserts: self.counters.inserts.load(Ordering::Relaxed),
+            entries: self.inner.entry_count(),
+        }
+    }
+
     /// Get or insert a value using a closure.
     ///
     /// If the key exists, returns the cached value.
@@ -112,7 +199,49 @@ where
         F: FnOnce() -> V,
         K: Clone,
     {
-        self.inner.get_with(key, f)
+        let mut hit = true;
+        let value = self.inner.get_with(key, || {
+            hit = false;
+            self.counters.inserts.fetch_add(1, Ordering::Relaxed);
+            f()
+        });
+        self.counters.record_lookup(hit);
+        value
+    }
+
+    /// Get a value, or load it with an async fallible loader on a miss.
+    ///
+    /// Concurrent misses for the same key share one load: the first caller
+    /// runs `init` while the others wait, then read what it cached. Errors
+    /// aren't cached, so after a failed load the next waiter tries again.
+    pub async fn get_or_insert_with_async<F, Fut, E>(&self, key: K, init: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        K: Clone,
+    {
+        if let Some(value) = self.get(&key) {
+            return Ok(value);
+        }
+
+        let lock = Arc::clone(self.loading.entry(key.clone()).or_default().value());
+        let result = {
+            let _guard = lock.lock().await;
+            // Loaded by whoever held the lock before us
+            if let Some(value) = self.inner.get(&key) {
+                Ok(value)
+            } else {
+                let result = init().await;
+                if let Ok(value) = &result {
+                    self.insert(key.clone(), value.clone());
+                }
+                result
+            }
+        };
+
+        // Last one out drops the lock (the map and `lock` hold a reference each)
+        self.loading.remove_if(&key, |_, l| Arc::strong_count(l) <= 2);
+        result
     }
 
     /// Get or try to insert a value using a fallible closure.
@@ -126,7 +255,17 @@ where
         E: Send + Sync + 'static,
         K: Clone,
     {
-        self.inner.try_get_with(key, f)
+        let mut hit = true;
+        let value = self.inner.try_get_with(key, || {
+            hit = false;
+            let value = f();
+            if value.is_ok() {
+                self.counters.inserts.fetch_add(1, Ordering::Relaxed);
+            }
+            value
+        });
+        self.counters.record_lookup(hit);
+        value
     }
 }
 
@@ -142,3 +281,52 @@ where
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_count_lookups() {
+        let cache: TypedCache<u32, u32> = TypedCache::new("test", CacheConfig::with_capacity(10));
+        assert_eq!(cache.stats().hit_rate(), None);
+
+        cache.insert(1, 10);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get_or_insert_with(2, || 20), 20);
+        assert_eq!(cache.get_or_insert_with(2, || 0), 20);
+
+        // Clones share the counters
+        let stats = cache.clone().stats();
+        assert_eq!((stats.hits, stats.misses, stats.inserts), (2, 2, 2));
+        assert_eq!(stats.hit_rate(), Some(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_get_or_insert_with_async_loads_once() {
+        let cache: TypedCache<u32, u32> = TypedCache::new("test", CacheConfig::with_capacity(10));
+        let loads = Arc::new(AtomicU64::new(0));
+
+        let requests = (0..10).map(|_| {
+            let cache = cache.clone();
+            let loads = Arc::clone(&loads);
+            tokio::spawn(async move {
+                cache
+                    .get_or_insert_with_async(1, || async {
+                        loads.fetch_add(1, Ordering::Relaxed);
+                        // Keep the load in flight while the others arrive
+                        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                        Ok::<_, ()>(42)
+                    })
+                    .await
+            })
+        });
+        for result in futures::future::join_all(requests).await {
+            assert_eq!(result.unwrap(), Ok(42));
+        }
+
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+        assert!(cache.loading.is_empty());
+    }
+}
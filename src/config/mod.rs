@@ -4,6 +4,7 @@
 
 use serde::Deserialize;
 use std::env;
+use std::net::{IpAddr, Ipv4Addr};
 
 /// Bot running mode
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -24,6 +25,8 @@ pub struct Config {
     pub bot_mode: BotMode,
     pub webhook_url: Option<String>,
     pub webhook_port: u16,
+    /// Address the webhook server (and its `/healthz`) listens on.
+    pub webhook_bind: IpAddr,
     pub webhook_secret: Option<String>,
 
     /// Bot username (without @) for deep link construction.
@@ -34,9 +37,16 @@ pub struct Config {
     /// These users have full access to all bot features.
     pub owner_ids: Vec<u64>,
 
+    /// Minimum seconds between "X is AFK" notices for the same user in a chat.
+    pub afk_cooldown_secs: u64,
+
     // MongoDB
     pub mongodb_uri: String,
     pub mongodb_database: String,
+    /// Connection attempts at startup before giving up (at least 1).
+    pub mongodb_connect_attempts: u32,
+    /// Wait before the first retry, doubled after each failed attempt.
+    pub mongodb_retry_base_ms: u64,
 }
 
 impl Config {
@@ -61,6 +71,11 @@ impl Config {
             .ok()
             .and_then(|s| s.parse().ok())
             .unwrap_or(8443);
+        let webhook_bind = env::var("WEBHOOK_BIND")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().expect("WEBHOOK_BIND must be an IP address"))
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
 
         // Validate webhook URL is set if mode is webhook
         if bot_mode == BotMode::Webhook && webhook_url.is_none() {
@@ -80,6 +95,23 @@ impl Config {
             .map(|s| s.trim_start_matches('@').to_string())
             .filter(|s| !s.is_empty());
 
+        // Parse AFK notice cooldown
+        let afk_cooldown_secs = env::var("AFK_COOLDOWN_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+
+        // Parse MongoDB startup retry settings
+        let mongodb_connect_attempts = env::var("MONGODB_CONNECT_ATTEMPTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5)
+            .max(1);
+        let mongodb_retry_base_ms = env::var("MONGODB_RETRY_BASE_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1_000);
+
         // Parse webhook secret
         let webhook_secret = env::var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
 
@@ -88,12 +120,16 @@ impl Config {
             bot_mode,
             webhook_url,
             webhook_port,
+            webhook_bind,
             webhook_secret,
             bot_username,
             owner_ids,
+            afk_cooldown_secs,
             mongodb_uri: env::var("MONGODB_URI").expect("MONGODB_URI must be set"),
             mongodb_database: env::var("MONGODB_DATABASE")
                 .unwrap_or_else(|_| "elysium".to_string()),
+            mongodb_connect_attempts,
+            mongodb_retry_base_ms,
         }
     }
 }
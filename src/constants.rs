@@ -0,0 +1,46 @@
+//! Shared defaults and limits.
+//!
+//! Values that several modules must agree on live here, so behavior stays
+//! consistent and can be tuned in one place.
+
+use std::time::Duration;
+
+/// Default flood penalty duration for mutes and temp-bans (5 minutes).
+pub const DEFAULT_FLOOD_PENALTY_SECS: u64 = 300;
+
+/// Default duration of the tban/tmute warn actions (1 day).
+pub const DEFAULT_WARN_ACTION_SECS: u64 = 86_400;
+
+/// Stored penalty duration meaning "permanent".
+///
+/// Permanent restrictions are sent without an `until_date`. A far-future date
+/// doesn't work: Telegram only treats restrictions of *more* than 366 days as
+/// permanent, so a "366 day" mute quietly expired after a year.
+pub const PERMANENT_RESTRICTION_MARKER: u64 = 0;
+
+/// Shortest temporary restriction; Telegram treats anything shorter as permanent.
+pub const MIN_TEMP_RESTRICTION: Duration = Duration::from_secs(30);
+
+/// Longest welcome cooldown; rejoins are only remembered for this long.
+pub const MAX_WELCOME_COOLDOWN_SECS: u64 = 86_400;
+
+/// Longest antiflood join grace; join times are only remembered for this long.
+pub const MAX_JOIN_GRACE_SECS: u64 = 86_400;
+
+/// How long a member waits between reports in the same chat.
+pub const REPORT_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Most user IDs one /ban or /mute can list. Each costs several API calls.
+pub const MAX_TARGET_IDS: usize = 20;
+
+/// Most message IDs one /purge or /purgeto can cover.
+pub const MAX_PURGE_RANGE: usize = 1_000;
+
+/// How long a member's admin rights stay cached.
+pub const CACHE_ADMIN_TTL_SECS: u64 = 300;
+
+/// How long unused admin rights stay cached.
+pub const CACHE_ADMIN_TTI_SECS: u64 = 120;
+
+/// How long a chat's admin list stays cached.
+pub const CACHE_ADMIN_LIST_TTL_SECS: u64 = 60;
@@ -12,11 +12,13 @@ pub use repository::{
     NoteRepository,
     MessageContextRepository,
     WelcomeRepository,
+    LastWelcome,
     ByeRepository,
     RulesRepository,
     WarnsRepository,
+    GlobalDefaultsRepository,
 };
 pub use users::UserRepo;
 
 // Re-export for backwards compatibility
-pub use models::db_filter::{DbFilter, MatchType};
+pub use models::db_filter::{build_regex, filter_key, DbFilter, MatchType};
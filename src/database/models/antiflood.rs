@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::constants::DEFAULT_FLOOD_PENALTY_SECS;
+
 /// Penalty type for antiflood violations.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -47,6 +49,39 @@ pub struct AntifloodConfig {
     /// Number of warnings before penalty (0 = immediate penalty)
     #[serde(default)]
     pub warnings_before_penalty: u32,
+
+    /// Track each forum topic separately
+    #[serde(default = "default_per_topic")]
+    pub per_topic: bool,
+
+    /// Alert admins with mute/ignore buttons when a user floods
+    #[serde(default)]
+    pub notify_admins: bool,
+
+    /// Only alert admins, skipping the automatic penalty
+    #[serde(default)]
+    pub notify_only: bool,
+
+    /// Users with a raised flood limit
+    #[serde(default)]
+    pub trusted_users: Vec<u64>,
+
+    /// Limit multiplier for trusted users
+    #[serde(default = "default_trusted_multiplier")]
+    pub trusted_multiplier: u32,
+
+    /// New members get a raised limit for this long after their first
+    /// message, in seconds (0 = off)
+    #[serde(default)]
+    pub join_grace_secs: u64,
+
+    /// How many messages a sticker or GIF counts as
+    #[serde(default = "default_weight")]
+    pub sticker_weight: u32,
+
+    /// How many messages other media (photos, videos, files, voice) count as
+    #[serde(default = "default_weight")]
+    pub media_weight: u32,
 }
 
 fn default_max_messages() -> u32 {
@@ -58,7 +93,19 @@ fn default_time_window() -> u32 {
 }
 
 fn default_penalty_duration() -> u64 {
-    300 // 5 minutes
+    DEFAULT_FLOOD_PENALTY_SECS
+}
+
+fn default_per_topic() -> bool {
+    true
+}
+
+fn default_trusted_multiplier() -> u32 {
+    2
+}
+
+fn default_weight() -> u32 {
+    1
 }
 
 impl Default for AntifloodConfig {
@@ -68,8 +115,60 @@ impl Default for AntifloodConfig {
             max_messages: 5,
             time_window_secs: 5,
             penalty: FloodPenalty::Mute,
-            penalty_duration_secs: 300,
+            penalty_duration_secs: DEFAULT_FLOOD_PENALTY_SECS,
             warnings_before_penalty: 1,
+            per_topic: true,
+            notify_admins: false,
+            notify_only: false,
+            trusted_users: Vec::new(),
+            trusted_multiplier: default_trusted_multiplier(),
+            join_grace_secs: 0,
+            sticker_weight: default_weight(),
+            media_weight: default_weight(),
+        }
+    }
+}
+
+impl AntifloodConfig {
+    /// The config with chat-specific data dropped, for use as a global default.
+    ///
+    /// Trusted users are members of one chat and mean nothing in another.
+    pub fn as_template(&self) -> Self {
+        Self {
+            trusted_users: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Check if a user is trusted.
+    pub fn is_trusted(&self, user_id: u64) -> bool {
+        self.trusted_users.contains(&user_id)
+    }
+
+    /// Trust a user, or stop trusting them if they already are.
+    ///
+    /// Returns whether the user is trusted afterwards.
+    pub fn toggle_trusted(&mut self, user_id: u64) -> bool {
+        if let Some(pos) = self.trusted_users.iter().position(|&id| id == user_id) {
+            self.trusted_users.remove(pos);
+            false
+        } else {
+            self.trusted_users.push(user_id);
+            true
+        }
+    }
+
+    /// Message limit for trusted users.
+    pub fn trusted_limit(&self) -> u32 {
+        self.max_messages.saturating_mul(self.trusted_multiplier.max(1))
+    }
+
+    /// Message limit that applies to a user.
+    pub fn limit_for(&self, user_id: u64) -> u32 {
+        if self.is_trusted(user_id) {
+            self.trusted_limit()
+        } else {
+            self.max_messages
         }
     }
 }
@@ -34,6 +34,14 @@ pub struct ByeSettings {
     /// Inline buttons for the goodbye message
     #[serde(default)]
     pub buttons: Vec<Vec<InlineButton>>,
+
+    /// Skip the goodbye when the bot itself banned or kicked the member
+    #[serde(default = "default_skip_on_ban")]
+    pub skip_on_ban: bool,
+}
+
+fn default_skip_on_ban() -> bool {
+    true
 }
 
 impl Default for ByeSettings {
@@ -46,6 +54,7 @@ impl Default for ByeSettings {
             media_file_id: None,
             media_type: None,
             buttons: Vec::new(),
+            skip_on_ban: default_skip_on_ban(),
         }
     }
 }
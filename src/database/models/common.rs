@@ -1,6 +1,9 @@
 //! Common shared models.
 
 use serde::{Deserialize, Serialize};
+use teloxide::types::{ParseMode, UserId};
+
+use crate::utils::{escape_markdown, html_escape};
 
 /// Generic inline button for messages.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,3 +23,57 @@ impl InlineButton {
         }
     }
 }
+
+/// Markup of user-written content (greetings, notes).
+///
+/// HTML is the default; `{markdown}` and `{noformat}` tags select the others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    /// Telegram HTML
+    #[default]
+    Html,
+    /// Telegram MarkdownV2
+    Markdown,
+    /// Literal text, nothing is interpreted
+    Plain,
+}
+
+impl ContentFormat {
+    /// Parse mode to send with.
+    ///
+    /// Plain text goes out as escaped HTML so fillings like `{mention}`
+    /// still render as links.
+    pub fn parse_mode(self) -> ParseMode {
+        match self {
+            Self::Html | Self::Plain => ParseMode::Html,
+            Self::Markdown => ParseMode::MarkdownV2,
+        }
+    }
+
+    /// Turn a template written in this format into one for [`Self::parse_mode`].
+    pub fn prepare(self, template: &str) -> String {
+        match self {
+            Self::Plain => html_escape(template),
+            Self::Html | Self::Markdown => template.to_string(),
+        }
+    }
+
+    /// Escape a filling value for this format.
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            Self::Html | Self::Plain => html_escape(text),
+            Self::Markdown => escape_markdown(text),
+        }
+    }
+
+    /// Link to a user, showing `name`.
+    pub fn mention(self, user_id: UserId, name: &str) -> String {
+        match self {
+            Self::Html | Self::Plain => {
+                format!("<a href=\"tg://user?id={}\">{}</a>", user_id, html_escape(name))
+            }
+            Self::Markdown => format!("[{}](tg://user?id={})", escape_markdown(name), user_id),
+        }
+    }
+}
@@ -1,10 +1,56 @@
 //! Filter model for distinct collection.
 
 use mongodb::bson::oid::ObjectId;
+use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Serialize};
 
 use crate::database::InlineButton;
 
+/// Largest compiled size of a regex trigger, in bytes.
+///
+/// The regex engine runs in linear time, so patterns can't backtrack
+/// catastrophically; this bounds how big (and slow) a pattern can get.
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// Compile a regex trigger. Matching is case-insensitive.
+pub fn build_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+/// Storage and lookup key for a trigger, as typed or as stored.
+///
+/// Triggers match case-insensitively, so they're keyed in lowercase. Regex
+/// triggers keep their `regex:` prefix and their case: `\D` and `\d` are
+/// different patterns.
+pub fn filter_key(trigger: &str) -> String {
+    if trigger.starts_with("regex:") {
+        trigger.to_string()
+    } else {
+        trigger.to_lowercase()
+    }
+}
+
+/// Whether `needle` occurs in `haystack` on word boundaries.
+///
+/// Works for phrases too: "good morning" matches "well, good morning!".
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return false;
+    }
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    haystack.match_indices(needle).any(|(start, _)| {
+        let before = haystack[..start].chars().next_back();
+        let after = haystack[start + needle.len()..].chars().next();
+        // A boundary is only needed where the trigger itself starts/ends with a word character
+        (!is_word(before) || !is_word(needle.chars().next()))
+            && (!is_word(after) || !is_word(needle.chars().next_back()))
+    })
+}
+
 /// How to match the trigger.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -12,12 +58,14 @@ pub enum MatchType {
     /// Match as whole word (default) - "nak" matches "aku nak" but not "naknak"
     #[default]
     Word,
-    /// Match anywhere in message (legacy contains)
+    /// Match as whole words anywhere in message - "cat" doesn't match "category"
     Keyword,
     /// Match only if entire message equals trigger
     Exact,
     /// Match if message starts with trigger
     Prefix,
+    /// Match the regex in `pattern` anywhere in the message
+    Regex,
 }
 
 /// A single filter document (stored in `filters` collection).
@@ -30,13 +78,18 @@ pub struct DbFilter {
     /// Chat ID this filter belongs to
     pub chat_id: i64,
 
-    /// Trigger word/phrase (indexed)
+    /// Trigger word/phrase (indexed), see [`filter_key`]
     pub trigger: String,
 
     /// How to match the trigger
     #[serde(default)]
     pub match_type: MatchType,
 
+    /// Regex source as typed, for `MatchType::Regex` (filters saved before
+    /// regex triggers were keyed as `regex:<source>` have a lowercased trigger)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+
     /// Reply text
     pub reply: String,
 
@@ -67,27 +120,133 @@ pub struct DbFilter {
     /// Reply to the user being replied to
     #[serde(default)]
     pub replytag: bool,
+
+    /// Show link previews (disabled unless `{preview}` is set)
+    #[serde(default)]
+    pub preview: bool,
+
+    /// How many times this filter has fired
+    #[serde(default)]
+    pub hits: u64,
+
+    /// User who first created this filter
+    #[serde(default)]
+    pub created_by: Option<u64>,
+
+    /// Unix timestamp of creation
+    #[serde(default)]
+    pub created_at: Option<i64>,
+
+    /// User who last edited this filter
+    #[serde(default)]
+    pub updated_by: Option<u64>,
+
+    /// Unix timestamp of the last edit
+    #[serde(default)]
+    pub updated_at: Option<i64>,
 }
 
 impl DbFilter {
     /// Check if a message matches this filter's trigger.
-    pub fn matches(&self, message: &str) -> bool {
+    ///
+    /// Regex filters match through `regex`, the trigger compiled once by
+    /// `FilterRepository::get_regex_triggers`, and never match without it.
+    pub fn matches(&self, message: &str, regex: Option<&Regex>) -> bool {
         let msg_lower = message.to_lowercase();
         let trigger_lower = self.trigger.to_lowercase();
 
         match self.match_type {
-            MatchType::Word => {
-                // Match as whole word - split by whitespace and check each word
-                // Also strip punctuation from word boundaries
-                msg_lower.split_whitespace().any(|word| {
-                    let cleaned = word.trim_matches(|c: char| !c.is_alphanumeric());
-                    cleaned == trigger_lower || word == trigger_lower
-                })
-            }
-            MatchType::Keyword => msg_lower.contains(&trigger_lower),
+            MatchType::Word | MatchType::Keyword => contains_words(&msg_lower, &trigger_lower),
             MatchType::Exact => msg_lower.trim() == trigger_lower,
             MatchType::Prefix => msg_lower.starts_with(&trigger_lower),
+            MatchType::Regex => regex.is_some_and(|re| re.is_match(message)),
+        }
+    }
+
+    /// Record who saved this filter, keeping creation info from the previous version.
+    pub fn stamp_audit(&mut self, previous: Option<&Self>, user_id: u64) {
+        let now = chrono::Utc::now().timestamp();
+        match previous {
+            Some(prev) => {
+                self.created_by = prev.created_by;
+                self.created_at = prev.created_at;
+            }
+            None => {
+                self.created_by = Some(user_id);
+                self.created_at = Some(now);
+            }
         }
+        self.updated_by = Some(user_id);
+        self.updated_at = Some(now);
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(trigger: &str, match_type: MatchType) -> DbFilter {
+        DbFilter {
+            id: None,
+            chat_id: 1,
+            trigger: trigger.to_lowercase(),
+            pattern: (match_type == MatchType::Regex).then(|| trigger.to_string()),
+            match_type,
+            reply: String::new(),
+            buttons: Vec::new(),
+            media_file_id: None,
+            media_type: None,
+            admin_only: false,
+            user_only: false,
+            protect: false,
+            replytag: false,
+            preview: false,
+            hits: 0,
+            created_by: None,
+            created_at: None,
+            updated_by: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_match_types() {
+        let word = filter("nak", MatchType::Word);
+        assert!(word.matches("aku nak, makan", None));
+        assert!(!word.matches("naknak", None));
+
+        let keyword = filter("Good Morning", MatchType::Keyword);
+        assert!(keyword.matches("well, good morning!", None));
+        assert!(!filter("cat", MatchType::Keyword).matches("category", None));
+        assert!(filter("cat", MatchType::Keyword).matches("my cat.", None));
+
+        let exact = filter("hello there", MatchType::Exact);
+        assert!(exact.matches("  Hello There ", None));
+        assert!(!exact.matches("hello there you", None));
+
+        let prefix = filter("!rep", MatchType::Prefix);
+        assert!(prefix.matches("!report this", None));
+        assert!(!prefix.matches("please !report", None));
+    }
+
+    #[test]
+    fn test_regex_trigger() {
+        // The source keeps its case, so \d isn't lowercased into a literal
+        let regex = filter(r"^\d{3}-\d{4}$", MatchType::Regex);
+        let compiled = build_regex(regex.pattern.as_deref().unwrap()).unwrap();
+        assert!(regex.matches("555-1234", Some(&compiled)));
+        assert!(!regex.matches("call 555-1234", Some(&compiled)));
+        assert!(!regex.matches("555-1234", None));
+
+        // Patterns differing only in case are different filters
+        assert_eq!(filter_key(r"regex:\D+"), r"regex:\D+");
+        assert_ne!(filter_key(r"regex:\D+"), filter_key(r"regex:\d+"));
+        assert_eq!(filter_key("Good Morning"), "good morning");
+
+        assert!(build_regex("HeLLo").unwrap().is_match("hello"));
+        assert!(build_regex("(unclosed").is_err());
+        // Patterns compiling past the size limit are rejected
+        assert!(build_regex(r"\w{1000}{1000}").is_err());
+    }
+}
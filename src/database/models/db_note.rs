@@ -3,7 +3,7 @@
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
-use crate::database::InlineButton;
+use crate::database::{ContentFormat, InlineButton};
 
 /// A single note document (stored in `notes` collection).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +40,39 @@ pub struct DbNote {
     /// Admin only view
     #[serde(default)]
     pub admin_only: bool,
+
+    /// Non-admin only view (`{user}`)
+    #[serde(default)]
+    pub user_only: bool,
+
+    /// Send to PM (`{private}`) or to the chat (`{noprivate}`), overriding
+    /// the chat's /privatenotes setting; `None` follows the setting
+    #[serde(default)]
+    pub private: Option<bool>,
+
+    /// Show link previews (disabled unless `{preview}` is set)
+    #[serde(default)]
+    pub preview: bool,
+
+    /// Markup of `content` (`{markdown}` / `{noformat}` tags)
+    #[serde(default)]
+    pub format: ContentFormat,
+
+    /// User who first created this note
+    #[serde(default)]
+    pub created_by: Option<u64>,
+
+    /// Unix timestamp of creation
+    #[serde(default)]
+    pub created_at: Option<i64>,
+
+    /// User who last edited this note
+    #[serde(default)]
+    pub updated_by: Option<u64>,
+
+    /// Unix timestamp of the last edit
+    #[serde(default)]
+    pub updated_at: Option<i64>,
 }
 
 impl DbNote {
@@ -55,6 +88,56 @@ impl DbNote {
             file_type: None,
             protect: false,
             admin_only: false,
+            user_only: false,
+            private: None,
+            preview: false,
+            format: ContentFormat::default(),
+            created_by: None,
+            created_at: None,
+            updated_by: None,
+            updated_at: None,
+        }
+    }
+
+    /// Whether a user may fetch this note, given whether they're a chat admin.
+    pub fn visible_to(&self, is_admin: bool) -> bool {
+        !(self.admin_only && !is_admin || self.user_only && is_admin)
+    }
+
+    /// Record who saved this note, keeping creation info from the previous version.
+    pub fn stamp_audit(&mut self, previous: Option<&Self>, user_id: u64) {
+        let now = chrono::Utc::now().timestamp();
+        match previous {
+            Some(prev) => {
+                self.created_by = prev.created_by;
+                self.created_at = prev.created_at;
+            }
+            None => {
+                self.created_by = Some(user_id);
+                self.created_at = Some(now);
+            }
         }
+        self.updated_by = Some(user_id);
+        self.updated_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_to() {
+        let mut note = DbNote::new(1, "rules", "text");
+        assert!(note.visible_to(true) && note.visible_to(false));
+
+        note.admin_only = true;
+        assert!(note.visible_to(true));
+        assert!(!note.visible_to(false));
+
+        note.admin_only = false;
+        note.user_only = true;
+        assert!(!note.visible_to(true));
+        assert!(note.visible_to(false));
     }
 }
@@ -0,0 +1,30 @@
+//! Global default settings model.
+//!
+//! Operator-defined templates applied when a chat's settings are first created.
+
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+use super::antiflood::AntifloodConfig;
+use super::warn::WarnConfig;
+use super::welcome_settings::WelcomeSettings;
+
+/// Global defaults stored as a single document in its own collection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GlobalDefaults {
+    /// MongoDB document ID
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+
+    /// Antiflood config for new chats (None = built-in defaults)
+    #[serde(default)]
+    pub antiflood: Option<AntifloodConfig>,
+
+    /// Warn config for new chats (None = built-in defaults)
+    #[serde(default)]
+    pub warn: Option<WarnConfig>,
+
+    /// Welcome template for new chats (chat_id is ignored)
+    #[serde(default)]
+    pub welcome: Option<WelcomeSettings>,
+}
@@ -32,6 +32,89 @@ pub struct MessageContext {
     /// Antiflood configuration
     #[serde(default)]
     pub antiflood: AntifloodConfig,
+
+    /// Topic where commands are allowed (None = anywhere, 1 = General)
+    #[serde(default)]
+    pub command_thread: Option<i32>,
+
+    /// Who the command restriction applies to
+    #[serde(default)]
+    pub command_scope: CommandScope,
+
+    /// How join requests are handled in groups with member approval
+    #[serde(default)]
+    pub join_requests: JoinRequestMode,
+
+    /// Deliver notes to the requester's PM instead of the group
+    #[serde(default)]
+    pub private_notes: bool,
+
+    /// Let members flag messages to admins with /report
+    #[serde(default)]
+    pub reports_enabled: bool,
+
+    /// Delete Telegram's join/leave service messages
+    #[serde(default)]
+    pub clean_service: bool,
+
+    /// Answer filter triggers (off pauses them without deleting any)
+    #[serde(default = "default_true")]
+    pub filters_enabled: bool,
+
+    /// Answer `#notename` shortcuts (/get keeps working either way)
+    #[serde(default = "default_true")]
+    pub hashtag_notes: bool,
+
+    /// Start point set by /purgefrom, waiting for /purgeto
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_marker: Option<PurgeMarker>,
+
+    /// Announcement saved by `/permapin -a`, shown by /announcement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announcement: Option<Announcement>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A pending `/purgefrom` start point.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PurgeMarker {
+    pub message_id: i32,
+    /// When the marker was set (unix seconds)
+    pub set_at: i64,
+}
+
+/// A group announcement that outlives its pinned message.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Announcement {
+    /// Announcement text (HTML)
+    pub text: String,
+    /// When it was pinned (unix seconds)
+    pub set_at: i64,
+}
+
+/// Who is affected by the command thread restriction.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandScope {
+    /// Only non-admin commands are dropped outside the thread
+    #[default]
+    Users,
+    /// Every command is dropped outside the thread
+    All,
+}
+
+/// Handling of `ChatJoinRequest` updates.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinRequestMode {
+    /// Leave requests to the admins
+    #[default]
+    Manual,
+    /// Approve requests automatically unless they look like spam
+    Auto,
 }
 
 impl MessageContext {
@@ -44,6 +127,16 @@ impl MessageContext {
             group_info: None,
             approved_users: Vec::new(),
             antiflood: AntifloodConfig::default(),
+            command_thread: None,
+            command_scope: CommandScope::default(),
+            join_requests: JoinRequestMode::default(),
+            private_notes: false,
+            reports_enabled: false,
+            clean_service: false,
+            filters_enabled: true,
+            hashtag_notes: true,
+            purge_marker: None,
+            announcement: None,
         }
     }
 
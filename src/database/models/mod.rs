@@ -13,17 +13,19 @@ pub mod welcome_settings;
 pub mod bye_settings;
 pub mod rules_settings;
 pub mod warns_data;
+pub mod global_defaults;
 
 pub use antiflood::{AntifloodConfig, FloodPenalty};
-pub use common::InlineButton;
+pub use common::{ContentFormat, InlineButton};
 pub use user::CachedUser;
 pub use warn::{WarnMode, Warning};
 
 pub use db_filter::DbFilter;
 pub use db_note::DbNote;
-pub use message_context::MessageContext;
-pub use welcome_settings::WelcomeSettings;
+pub use message_context::{Announcement, CommandScope, JoinRequestMode, MessageContext, PurgeMarker};
+pub use welcome_settings::{PendingVerification, WelcomeSettings, WelcomeVariant};
 pub use bye_settings::ByeSettings;
 pub use rules_settings::RulesSettings;
 pub use warns_data::WarnsData;
+pub use global_defaults::GlobalDefaults;
 
@@ -3,7 +3,7 @@
 //! Stores user data from Telegram and internal states (AFK).
 
 use serde::{Deserialize, Serialize};
-use teloxide::types::User;
+use teloxide::types::{User, UserId};
 
 /// Cached user data from Telegram + Internal State.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,9 +31,33 @@ pub struct CachedUser {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub afk_time: Option<i64>,
 
+    /// File ID of the media shown with AFK notices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub afk_media_file_id: Option<String>,
+
+    /// Type of the AFK media (photo, video, sticker, ...).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub afk_media_type: Option<String>,
+
     /// Language code (e.g., "en", "id").
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub lang: Option<String>,
+
+    /// Language of the user's Telegram client (IETF tag, e.g. "en-US").
+    /// Used when `lang` isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_code: Option<String>,
+
+    // --- Profile Photo ---
+
+    /// File ID of the latest profile photo. None if the user has none or
+    /// it was never looked up (see `photo_checked_at`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photo_file_id: Option<String>,
+
+    /// When the profile photo was last looked up (unix seconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photo_checked_at: Option<i64>,
 }
 
 impl CachedUser {
@@ -51,9 +75,16 @@ impl CachedUser {
             // Default to Not AFK
             afk_reason: None,
             afk_time: None,
+            afk_media_file_id: None,
+            afk_media_type: None,
             
             // Default language
             lang: None,
+            language_code: user.language_code.clone(),
+
+            // Looked up on first use
+            photo_file_id: None,
+            photo_checked_at: None,
         }
     }
 
@@ -66,16 +97,45 @@ impl CachedUser {
             || self.username_display.as_ref() != new_username_display
             || self.first_name != other.first_name
             || self.last_name != other.last_name
+            || self.language_code != other.language_code
     }
 
-    /// Update user data from Telegram User, preserving internal state (AFK, lang).
+    /// Update user data from Telegram User, preserving internal state (AFK, lang, photo).
     pub fn update_from_telegram(&mut self, user: &User) {
         self.username = user.username.as_ref().map(|u| u.to_lowercase());
         self.username_display = user.username.clone();
         self.first_name = user.first_name.clone();
         self.last_name = user.last_name.clone();
+        self.language_code = user.language_code.clone();
         self.updated_at = chrono::Utc::now().timestamp();
-        // afk_reason, afk_time, lang are preserved (not touched)
+        // afk_reason, afk_time, afk media, lang, photo are preserved (not touched)
+    }
+
+    /// Telegram `User` built from the cached data, for placeholder fillings.
+    pub fn to_telegram(&self) -> User {
+        User {
+            id: UserId(self.user_id),
+            is_bot: false,
+            first_name: self.first_name.clone(),
+            last_name: self.last_name.clone(),
+            username: self.username_display.clone(),
+            language_code: self.language_code.clone(),
+            is_premium: false,
+            added_to_attachment_menu: false,
+        }
+    }
+
+    /// Language the user picked with /setlang, else their client's
+    /// language if the bot has it.
+    pub fn preferred_lang(&self) -> Option<String> {
+        self.lang.clone().or_else(|| self.client_lang())
+    }
+
+    /// Bot language matching the user's Telegram client, if any.
+    pub fn client_lang(&self) -> Option<String> {
+        self.language_code
+            .as_deref()
+            .and_then(crate::i18n::locale_from_language_code)
     }
 
     /// Get display name (first name or username).
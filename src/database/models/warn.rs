@@ -2,8 +2,12 @@
 //!
 //! Data structures for user warnings in groups.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::constants::DEFAULT_WARN_ACTION_SECS;
+
 /// Warn mode - action when limit reached.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -38,13 +42,19 @@ impl WarnMode {
         }
     }
 
-    pub fn description(&self) -> &'static str {
+    /// Whether the penalty lasts for `action_duration_secs` only.
+    pub fn is_temporary(&self) -> bool {
+        matches!(self, Self::TBan | Self::TMute)
+    }
+
+    /// i18n key of the mode's short description.
+    pub fn description_key(&self) -> &'static str {
         match self {
-            Self::Ban => "Ban permanen",
-            Self::Mute => "Mute permanen",
-            Self::Kick => "Kick (bisa join lagi)",
-            Self::TBan => "Ban sementara",
-            Self::TMute => "Mute sementara",
+            Self::Ban => "warn.mode_desc_ban",
+            Self::Mute => "warn.mode_desc_mute",
+            Self::Kick => "warn.mode_desc_kick",
+            Self::TBan => "warn.mode_desc_tban",
+            Self::TMute => "warn.mode_desc_tmute",
         }
     }
 }
@@ -67,6 +77,10 @@ pub struct WarnConfig {
     /// Duration for tban/tmute in seconds (default: 1 day)
     #[serde(default = "default_action_duration")]
     pub action_duration_secs: u64,
+
+    /// Named quick-reasons, expanded from `:name` in /warn
+    #[serde(default)]
+    pub reasons: BTreeMap<String, String>,
 }
 
 fn default_limit() -> u32 {
@@ -74,7 +88,7 @@ fn default_limit() -> u32 {
 }
 
 fn default_action_duration() -> u64 {
-    86400 // 1 day
+    DEFAULT_WARN_ACTION_SECS
 }
 
 impl Default for WarnConfig {
@@ -84,6 +98,7 @@ impl Default for WarnConfig {
             mode: WarnMode::default(),
             warn_time_secs: None,
             action_duration_secs: default_action_duration(),
+            reasons: BTreeMap::new(),
         }
     }
 }
@@ -110,11 +125,13 @@ impl Warning {
 
     /// Check if warning has expired.
     pub fn is_expired(&self, warn_time_secs: Option<u64>) -> bool {
+        self.is_expired_at(warn_time_secs, chrono::Utc::now().timestamp())
+    }
+
+    /// Check if warning has expired at `now` (Unix timestamp).
+    pub fn is_expired_at(&self, warn_time_secs: Option<u64>, now: i64) -> bool {
         match warn_time_secs {
-            Some(ttl) => {
-                let now = chrono::Utc::now().timestamp();
-                (now - self.timestamp) >= ttl as i64
-            }
+            Some(ttl) => (now - self.timestamp) >= ttl as i64,
             None => false, // No expiry
         }
     }
@@ -145,6 +162,13 @@ impl UserWarns {
         self.warnings.pop()
     }
 
+    /// Drop warnings expired at `now` (Unix timestamp), returning how many were removed.
+    pub fn prune_expired(&mut self, warn_time_secs: Option<u64>, now: i64) -> usize {
+        let before = self.warnings.len();
+        self.warnings.retain(|w| !w.is_expired_at(warn_time_secs, now));
+        before - self.warnings.len()
+    }
+
     /// Get active (non-expired) warnings count.
     pub fn active_count(&self, warn_time_secs: Option<u64>) -> usize {
         self.warnings
@@ -153,3 +177,26 @@ impl UserWarns {
             .count()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_expired() {
+        let now = 10_000;
+        let mut user = UserWarns::new(1);
+        for timestamp in [now - 200, now - 100, now - 10] {
+            user.add_warning(Warning { reason: None, warned_by: 2, timestamp });
+        }
+
+        // Warnings as old as the window are expired
+        assert_eq!(user.prune_expired(Some(100), now), 2);
+        assert_eq!(user.warnings.len(), 1);
+        assert_eq!(user.warnings[0].timestamp, now - 10);
+
+        // Permanent warnings are never pruned
+        assert_eq!(user.prune_expired(None, now + 1_000_000), 0);
+        assert_eq!(user.warnings.len(), 1);
+    }
+}
@@ -7,7 +7,7 @@ use super::warn::{WarnConfig, UserWarns};
 
 /// Warns data stored in its own collection.
 /// Contains both configuration and per-user warnings.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WarnsData {
     /// MongoDB document ID
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -25,16 +25,6 @@ pub struct WarnsData {
     pub user_warns: Vec<UserWarns>,
 }
 
-impl Default for WarnsData {
-    fn default() -> Self {
-        Self {
-            id: None,
-            chat_id: 0,
-            config: WarnConfig::default(),
-            user_warns: Vec::new(),
-        }
-    }
-}
 
 impl WarnsData {
     /// Create new warns data for a chat.
@@ -60,6 +50,18 @@ impl WarnsData {
         self.user_warns.iter().find(|u| u.user_id == user_id)
     }
 
+    /// Drop expired warnings, and users left without any.
+    ///
+    /// Without an expiry nothing expires, so only users with no warnings at
+    /// all are dropped.
+    pub fn compact(&mut self, now: i64) {
+        let warn_time_secs = self.config.warn_time_secs;
+        self.user_warns.retain_mut(|user| {
+            user.prune_expired(warn_time_secs, now);
+            !user.warnings.is_empty()
+        });
+    }
+
     /// Remove a user's warns entirely.
     pub fn remove_user(&mut self, user_id: u64) -> bool {
         if let Some(idx) = self.user_warns.iter().position(|u| u.user_id == user_id) {
@@ -70,3 +72,37 @@ impl WarnsData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::Warning;
+
+    fn warning(timestamp: i64) -> Warning {
+        Warning { reason: None, warned_by: 1, timestamp }
+    }
+
+    #[test]
+    fn test_compact() {
+        let now = 10_000;
+        let mut data = WarnsData::new(-100);
+        data.config.warn_time_secs = Some(100);
+        // One active warning plus expired ones
+        data.get_or_create_user(1).warnings = vec![warning(now - 500), warning(now - 150), warning(now - 10)];
+        // Only expired warnings
+        data.get_or_create_user(2).warnings = vec![warning(now - 150)];
+        data.get_or_create_user(3);
+
+        data.compact(now);
+        assert_eq!(data.user_warns.len(), 1);
+        let timestamps: Vec<i64> = data.user_warns[0].warnings.iter().map(|w| w.timestamp).collect();
+        assert_eq!(timestamps, vec![now - 10]);
+
+        // Without expiry, warnings are kept
+        let mut data = WarnsData::new(-100);
+        data.get_or_create_user(1).warnings = vec![warning(0)];
+        data.get_or_create_user(2);
+        data.compact(now);
+        assert_eq!(data.user_warns.len(), 1);
+    }
+}
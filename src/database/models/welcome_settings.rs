@@ -38,12 +38,49 @@ pub struct WelcomeSettings {
     /// Delete previous welcome message when new member joins
     #[serde(default)]
     pub clean_welcome: bool,
+
+    /// Seconds before the same user can be welcomed again (0 = always welcome)
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+
+    /// Mute new members until they press the verify button on the welcome
+    #[serde(default)]
+    pub welcome_mute: bool,
+
+    /// Alternative welcomes (from /addwelcome), one picked at random per
+    /// join in place of the message, media and buttons above
+    #[serde(default)]
+    pub variants: Vec<WelcomeVariant>,
+}
+
+/// One of several welcome messages, each with its own media and buttons.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WelcomeVariant {
+    /// Message template
+    #[serde(default)]
+    pub message: Option<String>,
+
+    /// Media file ID (if any)
+    #[serde(default)]
+    pub media_file_id: Option<String>,
+
+    /// Media type (photo, video, animation, sticker)
+    #[serde(default)]
+    pub media_type: Option<String>,
+
+    /// Inline buttons
+    #[serde(default)]
+    pub buttons: Vec<Vec<InlineButton>>,
 }
 
 fn default_enabled() -> bool {
     false
 }
 
+fn default_cooldown_secs() -> u64 {
+    3600
+}
+
 impl Default for WelcomeSettings {
     fn default() -> Self {
         Self {
@@ -55,6 +92,9 @@ impl Default for WelcomeSettings {
             media_type: None,
             buttons: Vec::new(),
             clean_welcome: false,
+            cooldown_secs: default_cooldown_secs(),
+            welcome_mute: false,
+            variants: Vec::new(),
         }
     }
 }
@@ -67,4 +107,51 @@ impl WelcomeSettings {
             ..Default::default()
         }
     }
+
+    /// The welcome to send for a join: the variant `roll` lands on, or the
+    /// single message when no variants are set.
+    pub fn pick_variant(&self, roll: usize) -> WelcomeVariant {
+        if self.variants.is_empty() {
+            return WelcomeVariant {
+                message: self.message.clone(),
+                media_file_id: self.media_file_id.clone(),
+                media_type: self.media_type.clone(),
+                buttons: self.buttons.clone(),
+            };
+        }
+        self.variants[roll % self.variants.len()].clone()
+    }
+}
+
+/// A member muted on join who hasn't pressed the verify button yet.
+///
+/// Stored so the timeout kick (and the button) survive a restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingVerification {
+    /// Telegram chat ID
+    pub chat_id: i64,
+
+    /// Muted member
+    pub user_id: u64,
+
+    /// Unix time the member is kicked at if still unverified
+    pub kick_at: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_variant() {
+        let mut settings = WelcomeSettings::new(-100);
+        settings.message = Some("single".to_string());
+        assert_eq!(settings.pick_variant(7).message.as_deref(), Some("single"));
+
+        settings.variants = ["a", "b", "c"]
+            .map(|m| WelcomeVariant { message: Some(m.to_string()), ..Default::default() })
+            .to_vec();
+        assert_eq!(settings.pick_variant(0).message.as_deref(), Some("a"));
+        assert_eq!(settings.pick_variant(4).message.as_deref(), Some("b"));
+    }
 }
@@ -1,7 +1,12 @@
 //! MongoDB database wrapper.
 
+use std::time::Duration;
+
 use mongodb::{options::ClientOptions, Client, Collection};
-use tracing::info;
+use tracing::{info, warn};
+
+/// Longest wait between two connection attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 /// Database wrapper for MongoDB operations.
 #[derive(Debug, Clone)]
@@ -13,27 +18,67 @@ pub struct Database {
 impl Database {
     /// Connect to MongoDB with the given URI and database name.
     ///
+    /// At startup MongoDB may come up slightly after the bot, so a failed
+    /// attempt is retried with exponential backoff, starting at `base_delay`.
+    ///
     /// # Arguments
     /// * `uri` - MongoDB connection string
     /// * `db_name` - Database name to use
+    /// * `max_attempts` - Attempts before giving up (at least one is made)
+    /// * `base_delay` - Wait before the first retry, doubled after each failure
     ///
     /// # Errors
-    /// Returns error if connection fails.
-    pub async fn connect(uri: &str, db_name: &str) -> anyhow::Result<Self> {
+    /// Returns the last error if every attempt fails.
+    pub async fn connect(
+        uri: &str,
+        db_name: &str,
+        max_attempts: u32,
+        base_delay: Duration,
+    ) -> anyhow::Result<Self> {
+        let mut attempt = 1;
+        loop {
+            match Self::try_connect(uri, db_name).await {
+                Ok(database) => return Ok(database),
+                Err(e) if attempt < max_attempts => {
+                    let delay = retry_delay(base_delay, attempt);
+                    warn!(
+                        "MongoDB connection attempt {}/{} failed: {}. Retrying in {:?}",
+                        attempt, max_attempts, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    warn!("MongoDB connection attempt {}/{} failed, giving up", attempt, max_attempts);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Make a single connection attempt.
+    async fn try_connect(uri: &str, db_name: &str) -> anyhow::Result<Self> {
         let options = ClientOptions::parse(uri).await?;
         let client = Client::with_options(options)?;
 
+        let db = client.database(db_name);
+        let database = Self { client, db };
+
         // Ping the database to verify connection
-        client
-            .database("admin")
-            .run_command(mongodb::bson::doc! { "ping": 1 })
-            .await?;
+        database.ping().await?;
 
         info!("Successfully connected to MongoDB");
 
-        let db = client.database(db_name);
+        Ok(database)
+    }
 
-        Ok(Self { client, db })
+    /// Check that MongoDB answers.
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        self.client
+            .database("admin")
+            .run_command(mongodb::bson::doc! { "ping": 1 })
+            .await?;
+        Ok(())
     }
 
     /// Get a reference to the underlying MongoDB client.
@@ -57,3 +102,24 @@ impl Database {
         self.db.collection(name)
     }
 }
+
+/// Wait before retrying after failed attempt number `attempt` (from 1).
+fn retry_delay(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt - 1)).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        let base = Duration::from_secs(1);
+        assert_eq!(retry_delay(base, 1), Duration::from_secs(1));
+        assert_eq!(retry_delay(base, 2), Duration::from_secs(2));
+        assert_eq!(retry_delay(base, 4), Duration::from_secs(8));
+        // Capped, even where the doubling would overflow
+        assert_eq!(retry_delay(base, 6), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(base, 64), MAX_RETRY_DELAY);
+    }
+}
@@ -1,6 +1,9 @@
 //! Bye/Goodbye repository with lazy loading.
 //!
 //! Low TTL (5min) since bye events are rare.
+//!
+//! Also remembers members the bot itself just banned or kicked, so their
+//! departure doesn't get a goodbye.
 
 use std::time::Duration;
 
@@ -17,6 +20,8 @@ use crate::database::Database;
 pub struct ByeRepository {
     collection: Collection<ByeSettings>,
     cache: TypedCache<i64, ByeSettings>,
+    /// `(chat_id, user_id)` pairs the bot just removed from a chat
+    recent_removals: TypedCache<(i64, u64), ()>,
 }
 
 impl ByeRepository {
@@ -27,9 +32,16 @@ impl ByeRepository {
                 .ttl(Duration::from_secs(300)), // 5 minutes (lazy load)
         );
 
+        // Only has to outlive the gap between our ban and its chat_member update
+        let recent_removals = cache.get_or_create(
+            "bye_recent_removals",
+            CacheConfig::with_capacity(10_000).ttl(Duration::from_secs(60)),
+        );
+
         Self {
             collection: db.collection("bye"),
             cache: bye_cache,
+            recent_removals,
         }
     }
 
@@ -50,14 +62,20 @@ impl ByeRepository {
     }
 
     /// Get or create bye settings with defaults.
+    ///
+    /// Concurrent misses for the same chat share one load.
     pub async fn get_or_create(&self, chat_id: i64) -> Result<ByeSettings> {
-        if let Some(settings) = self.get(chat_id).await? {
-            return Ok(settings);
-        }
-
-        let settings = ByeSettings::new(chat_id);
-        self.save(&settings).await?;
-        Ok(settings)
+        self.cache
+            .get_or_insert_with_async(chat_id, || async {
+                if let Some(settings) = self.get(chat_id).await? {
+                    return Ok(settings);
+                }
+
+                let settings = ByeSettings::new(chat_id);
+                self.save(&settings).await?;
+                Ok(settings)
+            })
+            .await
     }
 
     /// Save bye settings (upsert).
@@ -77,4 +95,19 @@ impl ByeRepository {
 
         Ok(())
     }
+
+    /// Drop the cached settings for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.cache.invalidate(&chat_id);
+    }
+
+    /// Remember that the bot is about to ban or kick a member.
+    pub fn mark_removed(&self, chat_id: i64, user_id: u64) {
+        self.recent_removals.insert((chat_id, user_id), ());
+    }
+
+    /// Consume a removal mark, returning whether the bot removed the member.
+    pub fn take_removed(&self, chat_id: i64, user_id: u64) -> bool {
+        self.recent_removals.remove(&(chat_id, user_id)).is_some()
+    }
 }
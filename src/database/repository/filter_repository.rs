@@ -1,8 +1,10 @@
 //! Filter repository with tiered caching.
 //!
 //! Implements L1 (Keys), L2 (Content), and L2-Hot (Frequently Accessed) caching.
+//! Trigger statistics are buffered in memory and flushed to MongoDB in batches.
 
 use std::collections::HashSet;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
@@ -11,26 +13,34 @@ use dashmap::DashMap;
 use futures::StreamExt;
 use mongodb::bson::{doc, Document};
 use mongodb::Collection;
-use tracing::debug;
+use regex::Regex;
+use tracing::{debug, warn};
 
 use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
 use crate::database::models::DbFilter;
-use crate::database::Database;
+use crate::database::{build_regex, filter_key, Database};
 
 /// Threshold for promoting to hot cache (access count).
 const HOT_PROMOTION_THRESHOLD: u64 = 3;
 
+/// How often buffered trigger counts are written to MongoDB.
+const HITS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Repository for filters with hot cache tier.
 pub struct FilterRepository {
     collection: Collection<DbFilter>,
     /// L1 Cache: ChatID -> Set of Triggers (1 hour TTL)
     triggers_cache: TypedCache<i64, HashSet<String>>,
+    /// L1 Cache: ChatID -> Compiled regex triggers (1 hour TTL)
+    regex_cache: TypedCache<i64, Vec<(String, Regex)>>,
     /// L2 Cache: (ChatID, Trigger) -> Filter Content (1 min TTL)
     filter_cache: TypedCache<(i64, String), DbFilter>,
     /// L2-Hot Cache: (ChatID, Trigger) -> Filter Content (10 min TTL, promoted items)
     hot_cache: TypedCache<(i64, String), DbFilter>,
     /// Hit counter for promotion decisions
     hit_counter: DashMap<(i64, String), AtomicU64>,
+    /// Trigger counts not yet flushed to MongoDB
+    pending_hits: DashMap<(i64, String), u64>,
 }
 
 impl FilterRepository {
@@ -42,6 +52,13 @@ impl FilterRepository {
                 .max_capacity(5000),
         );
 
+        let regex_cache = cache.get_or_create(
+            "filter_regexes",
+            CacheConfig::default()
+                .ttl(Duration::from_secs(3600)) // 1 hour
+                .max_capacity(5000),
+        );
+
         let filter_cache = cache.get_or_create(
             "filter_content",
             CacheConfig::hot_data() // 1 min TTL
@@ -58,10 +75,89 @@ impl FilterRepository {
         Self {
             collection: db.collection("filters"),
             triggers_cache,
+            regex_cache,
             filter_cache,
             hot_cache,
             hit_counter: DashMap::with_capacity(1_000),
+            pending_hits: DashMap::new(),
+        }
+    }
+
+    /// Spawn the background task that periodically flushes trigger counts.
+    pub fn spawn_hits_flusher(self: &Arc<Self>) {
+        let repo = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HITS_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = repo.flush_hits().await {
+                    warn!("Failed to flush filter hits: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Record that a filter fired (buffered, flushed in batches).
+    pub fn record_trigger(&self, chat_id: i64, trigger: &str) {
+        *self
+            .pending_hits
+            .entry((chat_id, filter_key(trigger)))
+            .or_insert(0) += 1;
+    }
+
+    /// Write buffered trigger counts to MongoDB.
+    pub async fn flush_hits(&self) -> Result<()> {
+        let keys: Vec<(i64, String)> = self.pending_hits.iter().map(|e| e.key().clone()).collect();
+
+        for key in keys {
+            let Some((key, count)) = self.pending_hits.remove(&key) else {
+                continue;
+            };
+
+            let filter_doc = doc! { "chat_id": key.0, "trigger": &key.1 };
+            let update = doc! { "$inc": { "hits": count as i64 } };
+
+            if let Err(e) = self.collection.update_one(filter_doc, update).await {
+                // Put the count back so it is retried on the next flush
+                *self.pending_hits.entry(key).or_insert(0) += count;
+                return Err(e.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get trigger hit counts for a chat, most-triggered first.
+    ///
+    /// Includes counts that have not been flushed yet.
+    pub async fn get_hit_counts(&self, chat_id: i64) -> Result<Vec<(String, u64)>> {
+        let raw_coll: Collection<Document> = self.collection.clone_with_type();
+        let filter = doc! { "chat_id": chat_id };
+        let options = mongodb::options::FindOptions::builder()
+            .projection(doc! { "trigger": 1, "hits": 1, "_id": 0 })
+            .build();
+
+        let mut cursor = raw_coll.find(filter).with_options(options).await?;
+        let mut counts = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Ok(doc) = result
+                && let Ok(trigger) = doc.get_str("trigger")
+            {
+                let stored = doc.get_i64("hits")
+                    .or_else(|_| doc.get_i32("hits").map(i64::from))
+                    .unwrap_or(0) as u64;
+                let pending = self
+                    .pending_hits
+                    .get(&(chat_id, trigger.to_string()))
+                    .map(|c| *c)
+                    .unwrap_or(0);
+                counts.push((trigger.to_string(), stored + pending));
+            }
         }
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
     }
 
     /// L1: Get all triggers for a chat.
@@ -87,18 +183,48 @@ impl FilterRepository {
         let mut triggers = HashSet::new();
 
         while let Some(result) = cursor.next().await {
-            if let Ok(doc) = result {
-                if let Ok(trigger) = doc.get_str("trigger") {
+            if let Ok(doc) = result
+                && let Ok(trigger) = doc.get_str("trigger") {
                     triggers.insert(trigger.to_string());
                 }
-            }
         }
         Ok(triggers)
     }
 
+    /// L1: Get the compiled regex triggers for a chat, with their trigger keys.
+    pub async fn get_regex_triggers(&self, chat_id: i64) -> Result<Vec<(String, Regex)>> {
+        if let Some(regexes) = self.regex_cache.get(&chat_id) {
+            return Ok(regexes);
+        }
+
+        let raw_coll: Collection<Document> = self.collection.clone_with_type();
+        let filter = doc! { "chat_id": chat_id, "match_type": "regex" };
+        let options = mongodb::options::FindOptions::builder()
+            .projection(doc! { "trigger": 1, "pattern": 1, "_id": 0 })
+            .build();
+
+        let mut cursor = raw_coll.find(filter).with_options(options).await?;
+        let mut regexes = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            if let Ok(doc) = result
+                && let Ok(trigger) = doc.get_str("trigger") {
+                    let pattern = doc.get_str("pattern").unwrap_or(trigger);
+                    // Patterns are validated on save, but skip any that stopped compiling
+                    match build_regex(pattern) {
+                        Ok(re) => regexes.push((trigger.to_string(), re)),
+                        Err(e) => warn!("Skipping regex filter '{}' in chat {}: {}", trigger, chat_id, e),
+                    }
+                }
+        }
+
+        self.regex_cache.insert(chat_id, regexes.clone());
+        Ok(regexes)
+    }
+
     /// L2/Hot: Get specific filter content with automatic hot promotion.
     pub async fn get_filter(&self, chat_id: i64, trigger: &str) -> Result<Option<DbFilter>> {
-        let key = (chat_id, trigger.to_lowercase());
+        let key = (chat_id, filter_key(trigger));
 
         // Check Hot Cache first (fastest)
         if let Some(filter) = self.hot_cache.get(&key) {
@@ -116,7 +242,7 @@ impl FilterRepository {
         // Fetch from DB
         let filter_doc = doc! {
             "chat_id": chat_id,
-            "trigger": trigger.to_lowercase()
+            "trigger": filter_key(trigger)
         };
 
         let result = self.collection.find_one(filter_doc).await?;
@@ -150,18 +276,25 @@ impl FilterRepository {
     }
 
     /// Save a filter.
+    ///
+    /// The stored hit count is left alone: flushes add to it with `$inc`,
+    /// and the count on `filter` may come from a stale cached copy.
     pub async fn save_filter(&self, filter: &DbFilter) -> Result<()> {
         let filter_doc = doc! {
             "chat_id": filter.chat_id,
             "trigger": &filter.trigger
         };
-        let options = mongodb::options::ReplaceOptions::builder()
-            .upsert(true)
-            .build();
+        let mut fields = mongodb::bson::to_document(filter)?;
+        fields.remove("_id");
+        fields.remove("hits");
+        let update = doc! {
+            "$set": fields,
+            "$setOnInsert": { "hits": 0_i64 },
+        };
 
         self.collection
-            .replace_one(filter_doc, filter)
-            .with_options(options)
+            .update_one(filter_doc, update)
+            .upsert(true)
             .await?;
 
         let key = (filter.chat_id, filter.trigger.clone());
@@ -175,6 +308,7 @@ impl FilterRepository {
 
         // Invalidate L1 (trigger list may have changed)
         self.triggers_cache.invalidate(&filter.chat_id);
+        self.regex_cache.invalidate(&filter.chat_id);
 
         Ok(())
     }
@@ -183,21 +317,32 @@ impl FilterRepository {
     pub async fn delete_filter(&self, chat_id: i64, trigger: &str) -> Result<bool> {
         let filter_doc = doc! {
             "chat_id": chat_id,
-            "trigger": trigger.to_lowercase()
+            "trigger": filter_key(trigger)
         };
 
         let result = self.collection.delete_one(filter_doc).await?;
 
         if result.deleted_count > 0 {
-            let key = (chat_id, trigger.to_lowercase());
+            let key = (chat_id, filter_key(trigger));
             // Remove from all caches
             self.filter_cache.invalidate(&key);
             self.hot_cache.invalidate(&key);
             self.hit_counter.remove(&key);
+            self.pending_hits.remove(&key);
             self.triggers_cache.invalidate(&chat_id);
+            self.regex_cache.invalidate(&chat_id);
             return Ok(true);
         }
 
         Ok(false)
     }
-}
\ No newline at end of file
+
+    /// Drop every cached entry for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.triggers_cache.invalidate(&chat_id);
+        self.regex_cache.invalidate(&chat_id);
+        self.filter_cache.invalidate_if(move |key| key.0 == chat_id);
+        self.hot_cache.invalidate_if(move |key| key.0 == chat_id);
+        self.hit_counter.retain(|key, _| key.0 != chat_id);
+    }
+}
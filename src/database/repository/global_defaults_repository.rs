@@ -0,0 +1,75 @@
+//! Global defaults repository.
+//!
+//! Single cached document, read whenever a chat's settings are first created.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use mongodb::bson::doc;
+use mongodb::Collection;
+use tracing::{debug, warn};
+
+use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
+use crate::database::models::GlobalDefaults;
+use crate::database::Database;
+
+/// Cache key for the single defaults document.
+const DEFAULTS_KEY: u8 = 0;
+
+/// Repository for operator-wide default settings.
+pub struct GlobalDefaultsRepository {
+    collection: Collection<GlobalDefaults>,
+    cache: TypedCache<u8, GlobalDefaults>,
+}
+
+impl GlobalDefaultsRepository {
+    pub fn new(db: &Database, cache: &CacheRegistry) -> Self {
+        let defaults_cache = cache.get_or_create(
+            "global_defaults",
+            CacheConfig::with_capacity(1)
+                .ttl(Duration::from_secs(600)), // 10 minutes
+        );
+
+        Self {
+            collection: db.collection("global_defaults"),
+            cache: defaults_cache,
+        }
+    }
+
+    /// Get the global defaults (empty if none were configured).
+    pub async fn get(&self) -> Result<GlobalDefaults> {
+        if let Some(defaults) = self.cache.get(&DEFAULTS_KEY) {
+            return Ok(defaults);
+        }
+
+        let defaults = self.collection.find_one(doc! {}).await?.unwrap_or_default();
+        self.cache.insert(DEFAULTS_KEY, defaults.clone());
+
+        Ok(defaults)
+    }
+
+    /// Get the global defaults, falling back to built-ins on database errors.
+    pub async fn load(&self) -> GlobalDefaults {
+        self.get().await.unwrap_or_else(|e| {
+            warn!("Failed to load global defaults: {}", e);
+            GlobalDefaults::default()
+        })
+    }
+
+    /// Save the global defaults (upsert).
+    pub async fn save(&self, defaults: &GlobalDefaults) -> Result<()> {
+        let options = mongodb::options::ReplaceOptions::builder()
+            .upsert(true)
+            .build();
+
+        self.collection
+            .replace_one(doc! {}, defaults)
+            .with_options(options)
+            .await?;
+
+        self.cache.insert(DEFAULTS_KEY, defaults.clone());
+        debug!("Saved global defaults");
+
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 //! Stores antiflood config + approved users for per-message checks.
 //! Aggressively cached with 10min TTL.
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -11,19 +12,24 @@ use mongodb::Collection;
 use tracing::debug;
 
 use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
-use crate::database::models::MessageContext;
+use crate::database::models::{Announcement, MessageContext, PurgeMarker};
 use crate::database::models::message_context::GroupInfo;
-use crate::database::Database;
+use crate::database::{Database, GlobalDefaultsRepository};
 
 /// Repository for message context (antiflood + approved users).
 pub struct MessageContextRepository {
     collection: Collection<MessageContext>,
     cache: TypedCache<i64, MessageContext>,
     group_cache: TypedCache<i64, GroupInfo>,
+    defaults: Arc<GlobalDefaultsRepository>,
 }
 
 impl MessageContextRepository {
-    pub fn new(db: &Database, cache: &CacheRegistry) -> Self {
+    pub fn new(
+        db: &Database,
+        cache: &CacheRegistry,
+        defaults: Arc<GlobalDefaultsRepository>,
+    ) -> Self {
         let context_cache = cache.get_or_create(
             "message_context",
             CacheConfig::with_capacity(10_000)
@@ -40,38 +46,47 @@ impl MessageContextRepository {
             collection: db.collection("message_context"),
             cache: context_cache,
             group_cache,
+            defaults,
         }
     }
 
-    /// Get context, returning default if not exists.
-    pub async fn get_or_default(&self, chat_id: i64) -> Result<MessageContext> {
-        if let Some(mut ctx) = self.cache.get(&chat_id) {
-             // Inject Group Info from separate cache if missing or just ensuring freshness?
-             // For now, if missing in context but present in group_cache, inject it.
-             if ctx.group_info.is_none() {
-                 if let Some(info) = self.group_cache.get(&chat_id) {
-                     ctx.group_info = Some(info);
-                 }
-             }
-            return Ok(ctx);
+    /// Build a fresh context seeded from the global defaults.
+    async fn new_context(&self, chat_id: i64) -> MessageContext {
+        let mut ctx = MessageContext::new(chat_id);
+        // Defaults saved before templates dropped trusted users may still carry them
+        if let Some(antiflood) = self.defaults.load().await.antiflood {
+            ctx.antiflood = antiflood.as_template();
         }
+        ctx
+    }
 
-        let filter = doc! { "chat_id": chat_id };
-        let result = self.collection.find_one(filter).await?;
+    /// Get context, returning default if not exists.
+    ///
+    /// Every message reads this, so concurrent misses for the same chat
+    /// share one load instead of each querying MongoDB.
+    pub async fn get_or_default(&self, chat_id: i64) -> Result<MessageContext> {
+        let mut ctx = self
+            .cache
+            .get_or_insert_with_async(chat_id, || async {
+                let filter = doc! { "chat_id": chat_id };
+                let ctx = match self.collection.find_one(filter).await? {
+                    Some(ctx) => ctx,
+                    None => self.new_context(chat_id).await,
+                };
 
-        let mut ctx = result.unwrap_or_else(|| MessageContext::new(chat_id));
-        
-        // If loaded from DB, populate group_cache if present
-        if let Some(info) = &ctx.group_info {
-            self.group_cache.insert(chat_id, info.clone());
-        } else {
-             // Try to fill from group_cache if DB didn't have it (unlikely if strictly consistent, but good fallback)
-             if let Some(info) = self.group_cache.get(&chat_id) {
-                 ctx.group_info = Some(info);
-             }
-        }
+                // If loaded from DB, populate group_cache if present
+                if let Some(info) = &ctx.group_info {
+                    self.group_cache.insert(chat_id, info.clone());
+                }
+                anyhow::Ok(ctx)
+            })
+            .await?;
 
-        self.cache.insert(chat_id, ctx.clone());
+        // Inject Group Info from the separate cache if the context lacks it
+        if ctx.group_info.is_none()
+            && let Some(info) = self.group_cache.get(&chat_id) {
+                ctx.group_info = Some(info);
+            }
 
         Ok(ctx)
     }
@@ -121,6 +136,89 @@ impl MessageContextRepository {
         self.save(&ctx).await
     }
 
+    /// Update command thread restriction.
+    pub async fn update_command_restriction(
+        &self,
+        chat_id: i64,
+        thread: Option<i32>,
+        scope: crate::database::models::CommandScope,
+    ) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.command_thread = thread;
+        ctx.command_scope = scope;
+        self.save(&ctx).await
+    }
+
+    /// Update join request handling.
+    pub async fn update_join_requests(
+        &self,
+        chat_id: i64,
+        mode: crate::database::models::JoinRequestMode,
+    ) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.join_requests = mode;
+        self.save(&ctx).await
+    }
+
+    /// Update private notes delivery.
+    pub async fn update_private_notes(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.private_notes = enabled;
+        self.save(&ctx).await
+    }
+
+    /// Update whether members can /report.
+    pub async fn update_reports(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.reports_enabled = enabled;
+        self.save(&ctx).await
+    }
+
+    /// Update whether join/leave service messages are deleted.
+    pub async fn update_clean_service(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.clean_service = enabled;
+        self.save(&ctx).await
+    }
+
+    /// Update whether filters answer their triggers.
+    pub async fn update_filters_enabled(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.filters_enabled = enabled;
+        self.save(&ctx).await
+    }
+
+    /// Update whether `#notename` shortcuts are answered.
+    pub async fn update_hashtag_notes(&self, chat_id: i64, enabled: bool) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.hashtag_notes = enabled;
+        self.save(&ctx).await
+    }
+
+    /// Set or clear the pending /purgefrom marker.
+    pub async fn set_purge_marker(&self, chat_id: i64, marker: Option<PurgeMarker>) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.purge_marker = marker;
+        self.save(&ctx).await
+    }
+
+    /// Remove and return the pending /purgefrom marker, if any.
+    pub async fn take_purge_marker(&self, chat_id: i64) -> Result<Option<PurgeMarker>> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        let marker = ctx.purge_marker.take();
+        if marker.is_some() {
+            self.save(&ctx).await?;
+        }
+        Ok(marker)
+    }
+
+    /// Set or clear the group announcement.
+    pub async fn set_announcement(&self, chat_id: i64, announcement: Option<Announcement>) -> Result<()> {
+        let mut ctx = self.get_or_default(chat_id).await?;
+        ctx.announcement = announcement;
+        self.save(&ctx).await
+    }
+
     /// Approve a user.
     pub async fn approve_user(&self, chat_id: i64, user_id: u64) -> Result<bool> {
         let mut ctx = self.get_or_default(chat_id).await?;
@@ -164,4 +262,10 @@ impl MessageContextRepository {
         // We can just save the whole context, simpler than partial update for now
         self.save(&ctx).await
     }
+
+    /// Drop the cached context for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.cache.invalidate(&chat_id);
+        self.group_cache.invalidate(&chat_id);
+    }
 }
@@ -7,14 +7,16 @@ mod welcome_repository;
 mod bye_repository;
 mod rules_repository;
 mod warns_repository;
+mod global_defaults_repository;
 
 
 pub use filter_repository::FilterRepository;
 pub use note_repository::NoteRepository;
 pub use message_context_repository::MessageContextRepository;
-pub use welcome_repository::WelcomeRepository;
+pub use welcome_repository::{LastWelcome, WelcomeRepository};
 pub use bye_repository::ByeRepository;
 pub use rules_repository::RulesRepository;
 pub use warns_repository::WarnsRepository;
+pub use global_defaults_repository::GlobalDefaultsRepository;
 
 
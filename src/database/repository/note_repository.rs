@@ -78,11 +78,10 @@ impl NoteRepository {
         let mut names = Vec::new();
 
         while let Some(result) = cursor.next().await {
-            if let Ok(doc) = result {
-                if let Ok(name) = doc.get_str("name") {
+            if let Ok(doc) = result
+                && let Ok(name) = doc.get_str("name") {
                     names.push(name.to_string());
                 }
-            }
         }
 
         self.names_cache.insert(chat_id, names.clone());
@@ -194,22 +193,22 @@ impl NoteRepository {
     }
 
     /// Delete all notes for a chat.
-    pub async fn _delete_all(&self, chat_id: i64) -> Result<u64> {
-        // Get all names first to clear hit counters
-        if let Ok(names) = self.get_names(chat_id).await {
-            for name in names {
-                let key = (chat_id, name.to_lowercase());
-                self.note_cache.invalidate(&key);
-                self.hot_cache.invalidate(&key);
-                self.hit_counter.remove(&key);
-            }
-        }
-
+    ///
+    /// Drops the chat from the L1 names cache and the L2/hot content caches.
+    pub async fn clear_all(&self, chat_id: i64) -> Result<u64> {
         let filter = doc! { "chat_id": chat_id };
         let result = self.collection.delete_many(filter).await?;
 
-        self.names_cache.invalidate(&chat_id);
+        self.invalidate_chat(chat_id);
 
         Ok(result.deleted_count)
     }
-}
\ No newline at end of file
+
+    /// Drop every cached entry for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.names_cache.invalidate(&chat_id);
+        self.note_cache.invalidate_if(move |key| key.0 == chat_id);
+        self.hot_cache.invalidate_if(move |key| key.0 == chat_id);
+        self.hit_counter.retain(|key, _| key.0 != chat_id);
+    }
+}
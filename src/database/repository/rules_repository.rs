@@ -13,10 +13,15 @@ use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
 use crate::database::models::RulesSettings;
 use crate::database::Database;
 
+/// How long a deep-link lookup is reused, hits and misses alike.
+const DEEPLINK_TTL: Duration = Duration::from_secs(60);
+
 /// Repository for rules settings.
 pub struct RulesRepository {
     collection: Collection<RulesSettings>,
     cache: TypedCache<i64, RulesSettings>,
+    /// Short-lived results for PM deep links, including groups without rules
+    deeplink_cache: TypedCache<i64, Option<RulesSettings>>,
 }
 
 impl RulesRepository {
@@ -27,9 +32,15 @@ impl RulesRepository {
                 .ttl(Duration::from_secs(600)), // 10 minutes (very lazy)
         );
 
+        let deeplink_cache = cache.get_or_create(
+            "rules_deeplink",
+            CacheConfig::with_capacity(2_000).ttl(DEEPLINK_TTL),
+        );
+
         Self {
             collection: db.collection("rules"),
             cache: rules_cache,
+            deeplink_cache,
         }
     }
 
@@ -49,15 +60,36 @@ impl RulesRepository {
         Ok(result)
     }
 
-    /// Get or create rules settings with defaults.
-    pub async fn get_or_create(&self, chat_id: i64) -> Result<RulesSettings> {
-        if let Some(settings) = self.get(chat_id).await? {
-            return Ok(settings);
+    /// Get rules settings for a PM deep link.
+    ///
+    /// Join bursts send many members through the same group's rules link.
+    /// Unlike [`get`](Self::get), this also remembers groups without rules,
+    /// so repeated clicks don't each go to MongoDB.
+    pub async fn get_for_deeplink(&self, chat_id: i64) -> Result<Option<RulesSettings>> {
+        if let Some(result) = self.deeplink_cache.get(&chat_id) {
+            return Ok(result);
         }
 
-        let settings = RulesSettings::new(chat_id);
-        self.save(&settings).await?;
-        Ok(settings)
+        let result = self.get(chat_id).await?;
+        self.deeplink_cache.insert(chat_id, result.clone());
+        Ok(result)
+    }
+
+    /// Get or create rules settings with defaults.
+    ///
+    /// Concurrent misses for the same chat share one load.
+    pub async fn get_or_create(&self, chat_id: i64) -> Result<RulesSettings> {
+        self.cache
+            .get_or_insert_with_async(chat_id, || async {
+                if let Some(settings) = self.get(chat_id).await? {
+                    return Ok(settings);
+                }
+
+                let settings = RulesSettings::new(chat_id);
+                self.save(&settings).await?;
+                Ok(settings)
+            })
+            .await
     }
 
     /// Save rules settings (upsert).
@@ -73,6 +105,7 @@ impl RulesRepository {
             .await?;
 
         self.cache.insert(settings.chat_id, settings.clone());
+        self.deeplink_cache.invalidate(&settings.chat_id);
         debug!("Saved RulesSettings for chat {}", settings.chat_id);
 
         Ok(())
@@ -89,4 +122,10 @@ impl RulesRepository {
     pub async fn clear_rules(&self, chat_id: i64) -> Result<()> {
         self.set_rules(chat_id, None).await
     }
+
+    /// Drop the cached settings for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.cache.invalidate(&chat_id);
+        self.deeplink_cache.invalidate(&chat_id);
+    }
 }
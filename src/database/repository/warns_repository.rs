@@ -2,6 +2,7 @@
 //!
 //! Medium TTL (5min) since warns are command-triggered.
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
@@ -11,16 +12,21 @@ use tracing::debug;
 
 use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
 use crate::database::models::{WarnsData, Warning};
-use crate::database::Database;
+use crate::database::{Database, GlobalDefaultsRepository};
 
 /// Repository for warns data.
 pub struct WarnsRepository {
     collection: Collection<WarnsData>,
     cache: TypedCache<i64, WarnsData>,
+    defaults: Arc<GlobalDefaultsRepository>,
 }
 
 impl WarnsRepository {
-    pub fn new(db: &Database, cache: &CacheRegistry) -> Self {
+    pub fn new(
+        db: &Database,
+        cache: &CacheRegistry,
+        defaults: Arc<GlobalDefaultsRepository>,
+    ) -> Self {
         let warns_cache = cache.get_or_create(
             "warns_data",
             CacheConfig::with_capacity(3_000)
@@ -30,19 +36,26 @@ impl WarnsRepository {
         Self {
             collection: db.collection("warns"),
             cache: warns_cache,
+            defaults,
         }
     }
 
     /// Get warns data, returning None if not exists.
+    ///
+    /// Expired warnings are pruned on every load, so callers only ever see
+    /// active ones. Storage catches up on the next save.
     pub async fn get(&self, chat_id: i64) -> Result<Option<WarnsData>> {
-        if let Some(data) = self.cache.get(&chat_id) {
+        let now = chrono::Utc::now().timestamp();
+        if let Some(mut data) = self.cache.get(&chat_id) {
+            data.compact(now);
             return Ok(Some(data));
         }
 
         let filter = doc! { "chat_id": chat_id };
-        let result = self.collection.find_one(filter).await?;
+        let mut result = self.collection.find_one(filter).await?;
 
-        if let Some(d) = &result {
+        if let Some(d) = &mut result {
+            d.compact(now);
             self.cache.insert(chat_id, d.clone());
         }
 
@@ -50,30 +63,45 @@ impl WarnsRepository {
     }
 
     /// Get or create warns data with defaults.
+    ///
+    /// Concurrent misses for the same chat share one load.
     pub async fn get_or_create(&self, chat_id: i64) -> Result<WarnsData> {
-        if let Some(data) = self.get(chat_id).await? {
-            return Ok(data);
-        }
-
-        let data = WarnsData::new(chat_id);
-        self.save(&data).await?;
+        let mut data = self
+            .cache
+            .get_or_insert_with_async(chat_id, || async {
+                if let Some(data) = self.get(chat_id).await? {
+                    return Ok(data);
+                }
+
+                let mut data = WarnsData::new(chat_id);
+                if let Some(config) = self.defaults.load().await.warn {
+                    data.config = config;
+                }
+                self.save(&data).await?;
+                anyhow::Ok(data)
+            })
+            .await?;
+        data.compact(chrono::Utc::now().timestamp());
         Ok(data)
     }
 
-    /// Save warns data (upsert).
+    /// Save warns data (upsert), pruning expired warnings first.
     pub async fn save(&self, data: &WarnsData) -> Result<()> {
+        let mut data = data.clone();
+        data.compact(chrono::Utc::now().timestamp());
+
         let filter = doc! { "chat_id": data.chat_id };
         let options = mongodb::options::ReplaceOptions::builder()
             .upsert(true)
             .build();
 
         self.collection
-            .replace_one(filter, data)
+            .replace_one(filter, &data)
             .with_options(options)
             .await?;
 
-        self.cache.insert(data.chat_id, data.clone());
         debug!("Saved WarnsData for chat {}", data.chat_id);
+        self.cache.insert(data.chat_id, data);
 
         Ok(())
     }
@@ -110,6 +138,36 @@ impl WarnsRepository {
         Ok(removed)
     }
 
+    /// Remove a user's active warning at `index` (0-based, oldest first, as
+    /// numbered by /warns). Returns `None` if there's no such warning.
+    pub async fn remove_warning_at(
+        &self,
+        chat_id: i64,
+        user_id: u64,
+        index: usize,
+    ) -> Result<Option<Warning>> {
+        let mut data = self.get_or_create(chat_id).await?;
+        let warn_time_secs = data.config.warn_time_secs;
+        let Some(user_warns) = data.user_warns.iter_mut().find(|u| u.user_id == user_id) else {
+            return Ok(None);
+        };
+
+        let Some(position) = user_warns
+            .warnings
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| !w.is_expired(warn_time_secs))
+            .nth(index)
+            .map(|(i, _)| i)
+        else {
+            return Ok(None);
+        };
+
+        let removed = user_warns.warnings.remove(position);
+        self.save(&data).await?;
+        Ok(Some(removed))
+    }
+
     /// Reset all warnings for a user.
     pub async fn reset_warnings(&self, chat_id: i64, user_id: u64) -> Result<bool> {
         let mut data = self.get_or_create(chat_id).await?;
@@ -131,4 +189,9 @@ impl WarnsRepository {
             .unwrap_or(0);
         Ok(count)
     }
+
+    /// Drop the cached warns data for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.cache.invalidate(&chat_id);
+    }
 }
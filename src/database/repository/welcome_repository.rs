@@ -1,35 +1,87 @@
 //! Welcome repository with lazy loading.
 //!
 //! Low TTL (5min) since welcome events are rare.
+//! Also tracks recently welcomed members to throttle rejoin spam, and the
+//! members still to verify (stored, so their timeout survives a restart).
 
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::Result;
+use futures::StreamExt;
 use mongodb::bson::doc;
 use mongodb::Collection;
+use teloxide::types::MessageId;
 use tracing::debug;
 
 use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
-use crate::database::models::WelcomeSettings;
-use crate::database::Database;
+use crate::constants::MAX_WELCOME_COOLDOWN_SECS;
+use crate::database::models::{PendingVerification, WelcomeSettings};
+use crate::database::{Database, GlobalDefaultsRepository};
 
 /// Repository for welcome settings.
 pub struct WelcomeRepository {
     collection: Collection<WelcomeSettings>,
     cache: TypedCache<i64, WelcomeSettings>,
+    /// Last welcome timestamp per (chat_id, user_id)
+    recent_welcomes: TypedCache<(i64, u64), i64>,
+    /// Members muted on join who haven't pressed the verify button yet
+    pending_collection: Collection<PendingVerification>,
+    /// In-memory copy of `pending_collection` for quick checks
+    pending_verifications: TypedCache<(i64, u64), ()>,
+    /// Last welcome sent per chat, for clean_welcome
+    last_welcomes: TypedCache<i64, LastWelcome>,
+    defaults: Arc<GlobalDefaultsRepository>,
+}
+
+/// A welcome message the bot sent, remembered so clean_welcome can delete it.
+#[derive(Debug, Clone)]
+pub struct LastWelcome {
+    /// Messages making up the welcome (media and text may be separate)
+    pub message_ids: Vec<MessageId>,
+    /// Member the welcome was for
+    pub user_id: u64,
 }
 
 impl WelcomeRepository {
-    pub fn new(db: &Database, cache: &CacheRegistry) -> Self {
+    pub fn new(
+        db: &Database,
+        cache: &CacheRegistry,
+        defaults: Arc<GlobalDefaultsRepository>,
+    ) -> Self {
         let welcome_cache = cache.get_or_create(
             "welcome_settings",
             CacheConfig::with_capacity(2_000)
                 .ttl(Duration::from_secs(300)), // 5 minutes (lazy load)
         );
 
+        let recent_welcomes = cache.get_or_create(
+            "welcome_cooldown",
+            CacheConfig::with_capacity(50_000)
+                .no_ttl()
+                .tti(Duration::from_secs(MAX_WELCOME_COOLDOWN_SECS)),
+        );
+
+        let pending_verifications = cache.get_or_create(
+            "welcome_pending_verify",
+            CacheConfig::with_capacity(50_000)
+                .ttl(Duration::from_secs(3600)), // Outlives the verify timeout
+        );
+
+        let last_welcomes = cache.get_or_create(
+            "welcome_last_sent",
+            CacheConfig::with_capacity(10_000)
+                .ttl(Duration::from_secs(48 * 3600)), // Bots can't delete older messages
+        );
+
         Self {
             collection: db.collection("welcome"),
             cache: welcome_cache,
+            recent_welcomes,
+            pending_collection: db.collection("welcome_pending"),
+            pending_verifications,
+            last_welcomes,
+            defaults,
         }
     }
 
@@ -50,14 +102,42 @@ impl WelcomeRepository {
     }
 
     /// Get or create welcome settings with defaults.
+    ///
+    /// Concurrent misses for the same chat share one load.
     pub async fn get_or_create(&self, chat_id: i64) -> Result<WelcomeSettings> {
+        self.cache
+            .get_or_insert_with_async(chat_id, || async {
+                if let Some(settings) = self.get(chat_id).await? {
+                    return Ok(settings);
+                }
+
+                let settings = match self.defaults.load().await.welcome {
+                    Some(template) => WelcomeSettings {
+                        id: None,
+                        chat_id,
+                        ..template
+                    },
+                    None => WelcomeSettings::new(chat_id),
+                };
+                self.save(&settings).await?;
+                Ok(settings)
+            })
+            .await
+    }
+
+    /// Get welcome settings, creating them only if a global welcome template exists.
+    ///
+    /// Lets new chats inherit the operator's welcome without an admin touching /welcome.
+    pub async fn get_or_seed(&self, chat_id: i64) -> Result<Option<WelcomeSettings>> {
         if let Some(settings) = self.get(chat_id).await? {
-            return Ok(settings);
+            return Ok(Some(settings));
+        }
+
+        if self.defaults.load().await.welcome.is_none() {
+            return Ok(None);
         }
 
-        let settings = WelcomeSettings::new(chat_id);
-        self.save(&settings).await?;
-        Ok(settings)
+        self.get_or_create(chat_id).await.map(Some)
     }
 
     /// Save welcome settings (upsert).
@@ -77,4 +157,84 @@ impl WelcomeRepository {
 
         Ok(())
     }
+
+    /// Check whether a member was welcomed in this chat less than
+    /// `cooldown_secs` before `now`. A cooldown of 0 never applies.
+    pub fn in_welcome_cooldown(&self, chat_id: i64, user_id: u64, cooldown_secs: u64, now: i64) -> bool {
+        if cooldown_secs == 0 {
+            return false;
+        }
+
+        let active = self
+            .recent_welcomes
+            .get(&(chat_id, user_id))
+            .is_some_and(|last| now - last < cooldown_secs as i64);
+        if active {
+            debug!("Welcome cooldown active for user {} in chat {}", user_id, chat_id);
+        }
+        active
+    }
+
+    /// Record that a member was welcomed at `now`, starting their cooldown.
+    pub fn mark_welcomed(&self, chat_id: i64, user_id: u64, now: i64) {
+        self.recent_welcomes.insert((chat_id, user_id), now);
+    }
+
+    /// Record that a member was muted on join and must verify by `kick_at`.
+    pub async fn mark_pending_verification(&self, chat_id: i64, user_id: u64, kick_at: i64) -> Result<()> {
+        self.pending_verifications.insert((chat_id, user_id), ());
+
+        let filter = doc! { "chat_id": chat_id, "user_id": user_id as i64 };
+        let pending = PendingVerification { chat_id, user_id, kick_at };
+        self.pending_collection
+            .replace_one(filter, pending)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    /// Clear a member's pending verification, returning its kick time.
+    ///
+    /// Returns `None` if the member wasn't pending, so the verify button and
+    /// the timeout kick can't both act on the same member.
+    pub async fn take_pending_verification(&self, chat_id: i64, user_id: u64) -> Result<Option<i64>> {
+        self.pending_verifications.remove(&(chat_id, user_id));
+
+        let filter = doc! { "chat_id": chat_id, "user_id": user_id as i64 };
+        let taken = self.pending_collection.find_one_and_delete(filter).await?;
+        Ok(taken.map(|pending| pending.kick_at))
+    }
+
+    /// Load every stored pending verification, e.g. to re-arm timeouts at startup.
+    pub async fn load_pending_verifications(&self) -> Result<Vec<PendingVerification>> {
+        let mut cursor = self.pending_collection.find(doc! {}).await?;
+        let mut pending = Vec::new();
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(entry) => {
+                    self.pending_verifications.insert((entry.chat_id, entry.user_id), ());
+                    pending.push(entry);
+                }
+                Err(e) => debug!("Skipping unreadable pending verification: {}", e),
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Whether a member still has to press the verify button.
+    pub fn is_pending_verification(&self, chat_id: i64, user_id: u64) -> bool {
+        self.pending_verifications.contains(&(chat_id, user_id))
+    }
+
+    /// Record the welcome just sent in a chat, returning the one before it.
+    pub fn replace_last_welcome(&self, chat_id: i64, welcome: LastWelcome) -> Option<LastWelcome> {
+        let previous = self.last_welcomes.remove(&chat_id);
+        self.last_welcomes.insert(chat_id, welcome);
+        previous
+    }
+
+    /// Drop the cached settings for a chat so the next access reads MongoDB.
+    pub fn invalidate_chat(&self, chat_id: i64) {
+        self.cache.invalidate(&chat_id);
+    }
 }
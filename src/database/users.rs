@@ -81,11 +81,10 @@ impl UserRepo {
             existing.update_from_telegram(user);
 
             // Invalidate old username cache if changed
-            if let Some(old) = &old_username {
-                if existing.username.as_ref() != Some(old) {
+            if let Some(old) = &old_username
+                && existing.username.as_ref() != Some(old) {
                     self.cache_by_username.invalidate(old);
                 }
-            }
 
             // Update caches
             self.cache_by_id.insert(user_id, existing.clone());
@@ -140,17 +139,39 @@ impl UserRepo {
     }
 
     /// Set AFK status.
-    pub async fn set_afk(&self, user_id: u64, reason: Option<String>) -> Result<()> {
+    ///
+    /// `media` is an optional `(file_id, media_type)` shown with AFK notices.
+    pub async fn set_afk(
+        &self,
+        user_id: u64,
+        reason: Option<String>,
+        media: Option<(String, String)>,
+    ) -> Result<()> {
         let now = chrono::Utc::now().timestamp();
         let reason_val = reason.unwrap_or_else(|| "ㅤ".to_string());
 
         // Update DB
         let filter = doc! { "user_id": user_id as i64 };
-        let update = doc! { 
-            "$set": { 
-                "afk_reason": &reason_val,
-                "afk_time": now
-            }
+        let update = match &media {
+            Some((file_id, media_type)) => doc! {
+                "$set": {
+                    "afk_reason": &reason_val,
+                    "afk_time": now,
+                    "afk_media_file_id": file_id,
+                    "afk_media_type": media_type
+                }
+            },
+            // Media from an earlier AFK must not carry over
+            None => doc! {
+                "$set": {
+                    "afk_reason": &reason_val,
+                    "afk_time": now
+                },
+                "$unset": {
+                    "afk_media_file_id": "",
+                    "afk_media_type": ""
+                }
+            },
         };
         self.collection.update_one(filter, update).await?;
 
@@ -158,6 +179,9 @@ impl UserRepo {
         if let Some(mut user) = self.cache_by_id.get(&user_id) {
             user.afk_reason = Some(reason_val);
             user.afk_time = Some(now);
+            let (file_id, media_type) = media.unzip();
+            user.afk_media_file_id = file_id;
+            user.afk_media_type = media_type;
             self.cache_by_id.insert(user_id, user);
         } else {
             // Force reload
@@ -174,7 +198,9 @@ impl UserRepo {
         let update = doc! { 
             "$unset": { 
                 "afk_reason": "",
-                "afk_time": ""
+                "afk_time": "",
+                "afk_media_file_id": "",
+                "afk_media_type": ""
             }
         };
         self.collection.update_one(filter, update).await?;
@@ -183,6 +209,8 @@ impl UserRepo {
         if let Some(mut user) = self.cache_by_id.get(&user_id) {
             user.afk_reason = None;
             user.afk_time = None;
+            user.afk_media_file_id = None;
+            user.afk_media_type = None;
             self.cache_by_id.insert(user_id, user);
         }
 
@@ -231,6 +259,46 @@ impl UserRepo {
         Ok(result)
     }
 
+    /// Latest profile photo file ID, if it was looked up within `max_age_secs`.
+    ///
+    /// Returns `None` when it has to be looked up again, and `Some(None)`
+    /// for a user known to have no photo.
+    pub async fn profile_photo(&self, user_id: u64, max_age_secs: i64) -> Result<Option<Option<String>>> {
+        let Some(user) = self.get_by_id(user_id).await? else {
+            return Ok(None);
+        };
+        let now = chrono::Utc::now().timestamp();
+        Ok(user
+            .photo_checked_at
+            .filter(|checked| now - checked < max_age_secs)
+            .map(|_| user.photo_file_id))
+    }
+
+    /// Store the result of a profile photo lookup (`None` = no photo).
+    pub async fn set_profile_photo(&self, user_id: u64, file_id: Option<String>) -> Result<()> {
+        let Some(mut user) = self.get_by_id(user_id).await? else {
+            return Ok(()); // Unknown user, nothing to attach it to
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        let filter = doc! { "user_id": user_id as i64 };
+        let update = match &file_id {
+            Some(file_id) => doc! {
+                "$set": { "photo_file_id": file_id, "photo_checked_at": now }
+            },
+            None => doc! {
+                "$set": { "photo_checked_at": now },
+                "$unset": { "photo_file_id": "" }
+            },
+        };
+        self.collection.update_one(filter, update).await?;
+
+        user.photo_file_id = file_id;
+        user.photo_checked_at = Some(now);
+        self.cache_by_id.insert(user_id, user);
+        Ok(())
+    }
+
     /// Set user language.
     pub async fn set_lang(&self, user_id: u64, lang: String) -> Result<()> {
         let mut user = match self.get_by_id(user_id).await? {
@@ -2,36 +2,58 @@
 //!
 //! Monitors messages and applies penalties for flooding.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 use teloxide::prelude::*;
-use teloxide::types::{ChatPermissions, ParseMode};
+use teloxide::types::{ChatMemberUpdated, ChatPermissions, InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
 use tracing::{debug, info, warn};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::FloodPenalty;
-use crate::utils::{html_escape, format_duration_full};
+use crate::database::{AntifloodConfig, FloodPenalty};
+use crate::database::models::MessageContext;
+use crate::constants::{MAX_JOIN_GRACE_SECS, MIN_TEMP_RESTRICTION, PERMANENT_RESTRICTION_MARKER};
+use crate::utils::{html_escape, format_duration_full, restrict_until};
 use crate::i18n::get_text;
 
+/// How often idle flood state is reaped.
+const REAP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Users (and then chats) quiet for this long are dropped from the tracker.
+/// Kept above the longest flood window (300s) so live windows are never cut.
+const IDLE_THRESHOLD: Duration = Duration::from_secs(600);
+
+/// Limit multiplier for users still within the join grace period.
+const JOIN_GRACE_MULTIPLIER: u32 = 2;
+
 /// User's flood tracking data
 #[derive(Debug, Clone)]
 struct UserFloodData {
-    message_times: Vec<Instant>,
+    /// Time and weight of each message in the current window
+    message_times: Vec<(Instant, u32)>,
     warnings: u32,
+    /// Whether a warning message was already sent in the current window
+    warned: bool,
+    /// Last message from this user, for idle cleanup
+    last_seen: Instant,
 }
 
 impl UserFloodData {
-    fn new() -> Self {
+    fn new(now: Instant) -> Self {
         Self {
             message_times: Vec::new(),
             warnings: 0,
+            warned: false,
+            last_seen: now,
         }
     }
 }
 
+/// Tracker key: chat ID plus forum topic (None = whole chat).
+type FloodKey = (i64, Option<i32>);
+
 /// Per-chat tracking: last user who spoke
 #[derive(Debug, Clone, Default)]
 struct ChatFloodState {
@@ -44,31 +66,68 @@ struct ChatFloodState {
 /// Global flood tracker (in-memory, lock-free).
 #[derive(Clone)]
 pub struct FloodTracker {
-    /// Per-chat flood state using DashMap for lock-free access
-    data: Arc<DashMap<i64, ChatFloodState>>,
+    /// Per-chat (or per-topic) flood state using DashMap for lock-free access
+    data: Arc<DashMap<FloodKey, ChatFloodState>>,
+    /// When each member joined, per (chat, user), for the join grace period.
+    /// Kept apart from `data` so reaping idle users doesn't forget it.
+    joins: Arc<DashMap<(i64, u64), Instant>>,
 }
 
 impl FloodTracker {
     pub fn new() -> Self {
         Self {
             data: Arc::new(DashMap::new()),
+            joins: Arc::new(DashMap::new()),
         }
     }
 
     /// Record a message and check if user is flooding.
-    /// If a different user sends a message, reset all other users' counters in that chat.
+    /// If a different user sends a message, reset all other users' counters in that chat
+    /// (or topic, when `thread_id` is set).
+    /// The message counts `weight` times toward `max_messages` (see [`message_weight`]).
     /// Returns (is_flooding, warning_count)
     pub fn record_message(
         &self,
         chat_id: i64,
+        thread_id: Option<i32>,
+        user_id: u64,
+        max_messages: u32,
+        window_secs: u32,
+        weight: u32,
+    ) -> (bool, u32) {
+        self.record_weighted_at((chat_id, thread_id), user_id, max_messages, window_secs, weight, Instant::now())
+    }
+
+    /// Same as [`Self::record_message`] for a plain message, with the
+    /// message time passed in.
+    #[cfg(test)]
+    fn record_message_at(
+        &self,
+        chat_id: i64,
+        thread_id: Option<i32>,
         user_id: u64,
         max_messages: u32,
         window_secs: u32,
+        now: Instant,
+    ) -> (bool, u32) {
+        self.record_weighted_at((chat_id, thread_id), user_id, max_messages, window_secs, 1, now)
+    }
+
+    /// Same as [`Self::record_message`], with the message time passed in.
+    ///
+    /// Keeps the window logic deterministic for tests.
+    fn record_weighted_at(
+        &self,
+        key: FloodKey,
+        user_id: u64,
+        max_messages: u32,
+        window_secs: u32,
+        weight: u32,
+        now: Instant,
     ) -> (bool, u32) {
-        let now = Instant::now();
         let window = Duration::from_secs(window_secs as u64);
 
-        let mut chat_state = self.data.entry(chat_id).or_default();
+        let mut chat_state = self.data.entry(key).or_default();
 
         // If a different user spoke, reset all other users' counters (conversation interrupt)
         if let Some(last_user) = chat_state.last_user_id
@@ -77,6 +136,7 @@ impl FloodTracker {
                 for (uid, user_data) in chat_state.users.iter_mut() {
                     if *uid != user_id {
                         user_data.message_times.clear();
+                        user_data.warned = false;
                         // Keep warnings, only reset message counter
                     }
                 }
@@ -86,16 +146,22 @@ impl FloodTracker {
         chat_state.last_user_id = Some(user_id);
 
         // Get or create user flood data
-        let entry = chat_state.users.entry(user_id).or_insert_with(UserFloodData::new);
+        let entry = chat_state.users.entry(user_id).or_insert_with(|| UserFloodData::new(now));
+        entry.last_seen = now;
 
         // Clean old messages outside window
-        entry.message_times.retain(|&t| now.duration_since(t) < window);
+        entry.message_times.retain(|&(t, _)| now.duration_since(t) < window);
+        if entry.message_times.is_empty() {
+            // Fresh window: the user may be warned again
+            entry.warned = false;
+        }
 
         // Add current message
-        entry.message_times.push(now);
+        entry.message_times.push((now, weight.max(1)));
 
         // Check if flooding
-        let is_flooding = entry.message_times.len() > max_messages as usize;
+        let counted: u32 = entry.message_times.iter().map(|&(_, w)| w).sum();
+        let is_flooding = counted > max_messages;
 
         if is_flooding {
             entry.warnings += 1;
@@ -104,10 +170,109 @@ impl FloodTracker {
         (is_flooding, entry.warnings)
     }
 
-    /// Reset all data for a user in a chat
-    pub fn reset_user(&self, chat_id: i64, user_id: u64) {
-        if let Some(mut chat_state) = self.data.get_mut(&chat_id) {
-            chat_state.users.remove(&user_id);
+    /// Remember that a user just joined a chat, starting their join grace.
+    pub fn record_join(&self, chat_id: i64, user_id: u64) {
+        self.record_join_at(chat_id, user_id, Instant::now());
+    }
+
+    /// Same as [`Self::record_join`], with the join time passed in.
+    fn record_join_at(&self, chat_id: i64, user_id: u64, now: Instant) {
+        self.joins.insert((chat_id, user_id), now);
+    }
+
+    /// Whether a user joined the chat less than `grace` ago.
+    ///
+    /// Only joins seen by the bot count, so members who were here before it
+    /// started never get the grace. The join time covers every topic.
+    pub fn in_grace(&self, chat_id: i64, user_id: u64, grace: Duration) -> bool {
+        self.in_grace_at(chat_id, user_id, grace, Instant::now())
+    }
+
+    /// Same as [`Self::in_grace`], with the current time passed in.
+    fn in_grace_at(&self, chat_id: i64, user_id: u64, grace: Duration, now: Instant) -> bool {
+        self.joins
+            .get(&(chat_id, user_id))
+            .is_some_and(|joined| now.saturating_duration_since(*joined) < grace)
+    }
+
+    /// Claim the single warning message allowed per window.
+    ///
+    /// Returns true the first time it's called for a user since their
+    /// window last reset (or since a penalty cleared them), false after.
+    pub fn claim_warning(&self, chat_id: i64, thread_id: Option<i32>, user_id: u64) -> bool {
+        let Some(mut chat_state) = self.data.get_mut(&(chat_id, thread_id)) else {
+            return false;
+        };
+        match chat_state.users.get_mut(&user_id) {
+            Some(user) if !user.warned => {
+                user.warned = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drop users idle for `idle_threshold`, and chats left with no users.
+    ///
+    /// Their warnings are forgotten too; a user quiet that long starts over.
+    /// Join times are kept until no grace period can still cover them.
+    pub fn cleanup(&self, idle_threshold: Duration) {
+        self.cleanup_at(idle_threshold, Instant::now());
+    }
+
+    /// Same as [`Self::cleanup`], with the current time passed in.
+    fn cleanup_at(&self, idle_threshold: Duration, now: Instant) {
+        self.data.retain(|_, chat_state| {
+            chat_state
+                .users
+                .retain(|_, user| now.saturating_duration_since(user.last_seen) < idle_threshold);
+            !chat_state.users.is_empty()
+        });
+        let max_grace = Duration::from_secs(MAX_JOIN_GRACE_SECS);
+        self.joins
+            .retain(|_, joined| now.saturating_duration_since(*joined) < max_grace);
+    }
+
+    /// Live stats for a chat across all its topics.
+    ///
+    /// Returns (users currently tracked, users with flood warnings). A user
+    /// active in several topics counts once.
+    pub fn chat_stats(&self, chat_id: i64) -> (usize, usize) {
+        let mut tracked = HashSet::new();
+        let mut warned = HashSet::new();
+        for entry in self.data.iter().filter(|entry| entry.key().0 == chat_id) {
+            for (&user_id, user) in &entry.value().users {
+                tracked.insert(user_id);
+                if user.warnings > 0 {
+                    warned.insert(user_id);
+                }
+            }
+        }
+        (tracked.len(), warned.len())
+    }
+
+    /// Spawn the background task that periodically reaps idle state.
+    pub fn spawn_reaper(&self) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                tracker.cleanup(IDLE_THRESHOLD);
+                debug!("Flood tracker reaped, {} chats tracked", tracker.data.len());
+            }
+        });
+    }
+
+    /// Reset all data for a user in a chat (or topic)
+    ///
+    /// Their join time is kept so a penalty doesn't restart the join grace
+    /// period.
+    pub fn reset_user(&self, chat_id: i64, thread_id: Option<i32>, user_id: u64) {
+        if let Some(mut chat_state) = self.data.get_mut(&(chat_id, thread_id))
+            && let Some(user) = chat_state.users.get_mut(&user_id)
+        {
+            *user = UserFloodData::new(user.last_seen);
         }
     }
 }
@@ -119,6 +284,15 @@ impl Default for FloodTracker {
 }
 
 /// Check if this is a group message (not a command)
+/// Record member joins so new members get the antiflood join grace.
+///
+/// Runs for every `chat_member` update, whatever the welcome settings.
+pub fn track_join(update: ChatMemberUpdated, flood_tracker: FloodTracker) {
+    if !update.old_chat_member.is_present() && update.new_chat_member.is_present() {
+        flood_tracker.record_join(update.chat.id.0, update.new_chat_member.user.id.0);
+    }
+}
+
 fn is_group_message(msg: Message) -> bool {
     // Only process in groups
     if !msg.chat.is_group() && !msg.chat.is_supergroup() {
@@ -139,11 +313,33 @@ fn is_group_message(msg: Message) -> bool {
     true
 }
 
+/// How many messages `msg` counts as toward the flood limit.
+///
+/// Sticker and GIF floods bury a chat faster than text, so groups can make
+/// them (and other media) count more.
+fn message_weight(config: &AntifloodConfig, msg: &Message) -> u32 {
+    let weight = if msg.sticker().is_some() || msg.animation().is_some() {
+        config.sticker_weight
+    } else if msg.photo().is_some()
+        || msg.video().is_some()
+        || msg.video_note().is_some()
+        || msg.voice().is_some()
+        || msg.audio().is_some()
+        || msg.document().is_some()
+    {
+        config.media_weight
+    } else {
+        1
+    };
+    weight.max(1)
+}
+
 /// Public function to check antiflood - called from unified handler.
 pub async fn check_antiflood(
     bot: &ThrottledBot,
     msg: &Message,
     state: &AppState,
+    ctx: &MessageContext,
     flood_tracker: &FloodTracker,
 ) -> anyhow::Result<()> {
     // Skip if not group message
@@ -152,7 +348,7 @@ pub async fn check_antiflood(
     }
     
     // Call the internal handler logic
-    antiflood_check_impl(bot, msg, state, flood_tracker).await
+    antiflood_check_impl(bot, msg, state, ctx, flood_tracker).await
 }
 
 
@@ -162,6 +358,7 @@ async fn antiflood_check_impl(
     bot: &ThrottledBot,
     msg: &Message,
     state: &AppState,
+    ctx: &MessageContext,
     flood_tracker: &FloodTracker,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
@@ -177,39 +374,48 @@ async fn antiflood_check_impl(
 
     let user_id = user.id;
 
-    // Get message context (lightweight, cached)
-    let ctx = state.message_context.get_or_default(chat_id.0).await?;
-
     // Check if antiflood is enabled
     if !ctx.antiflood.enabled {
         return Ok(());
     }
 
-    // Bot owners bypass all restrictions
-    if state.is_owner(user_id.0) {
-        debug!("User {} is owner, bypassing antiflood", user_id);
+    // Owners, approved users and admins bypass antiflood
+    if state.is_exempt(ctx, user_id).await {
+        debug!("User {} is exempt, bypassing antiflood", user_id);
         return Ok(());
     }
 
-    // Check if user is approved (bypass antiflood) - O(n) but small list
-    if ctx.is_approved(user_id.0) {
-        debug!("User {} is approved, bypassing antiflood", user_id);
-        return Ok(());
-    }
+    // Resolve locale
+    // Announcements go out in the group language
+    let locale = AppState::locale_for_group_in(ctx);
+
+    // In forum groups, track each topic as its own stream
+    let thread_id = if ctx.antiflood.per_topic && msg.is_topic_message {
+        msg.thread_id.map(|t| t.0.0)
+    } else {
+        None
+    };
 
-    // Check if user is admin (admins bypass antiflood)
-    if state.permissions.is_admin(chat_id, user_id).await.unwrap_or(false) {
-        return Ok(());
+    // Trusted regulars get a raised limit rather than a full bypass, and so
+    // do members who joined recently
+    let mut limit = ctx.antiflood.limit_for(user_id.0);
+    if ctx.antiflood.join_grace_secs > 0
+        && flood_tracker.in_grace(
+            chat_id.0,
+            user_id.0,
+            Duration::from_secs(ctx.antiflood.join_grace_secs),
+        )
+    {
+        limit = limit.saturating_mul(JOIN_GRACE_MULTIPLIER);
     }
 
-    // Resolve locale
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-
     let (is_flooding, warnings) = flood_tracker.record_message(
         chat_id.0,
+        thread_id,
         user_id.0,
-        ctx.antiflood.max_messages,
+        limit,
         ctx.antiflood.time_window_secs,
+        message_weight(&ctx.antiflood, msg),
     );
 
     if !is_flooding {
@@ -221,54 +427,78 @@ async fn antiflood_check_impl(
         user_id, chat_id, warnings
     );
 
-    // Check if we should apply penalty or just warn
-    if warnings <= ctx.antiflood.warnings_before_penalty {
-        // Send warning
-        let remaining = ctx.antiflood.warnings_before_penalty - warnings + 1;
-        
-        let warning_msg = get_text(&locale, "antiflood.flood_warning")
-            .replace("{id}", &user_id.to_string())
-            .replace("{name}", &html_escape(&user.first_name))
-            .replace("{count}", &remaining.to_string());
-
-        bot.send_message(chat_id, warning_msg)
-            .parse_mode(ParseMode::Html)
-            .await?;
-        return Ok(());
-    }
+    let penalty = match decide_antiflood_action(&ctx.antiflood, warnings) {
+        AntifloodAction::Warn { remaining } => {
+            // One warning per window, so the bot doesn't add to the flood
+            if !flood_tracker.claim_warning(chat_id.0, thread_id, user_id.0) {
+                debug!("Suppressing repeat flood warning for user {} in chat {}", user_id, chat_id);
+                return Ok(());
+            }
+
+            let warning_msg = get_text(&locale, "antiflood.flood_warning")
+                .replace("{id}", &user_id.to_string())
+                .replace("{name}", &html_escape(&user.first_name))
+                .replace("{count}", &remaining.to_string());
+
+            state
+                .circuit
+                .send(bot.send_message(chat_id, warning_msg).parse_mode(ParseMode::Html))
+                .await?;
+            return Ok(());
+        }
+        AntifloodAction::NotifyOnly => {
+            if let Err(e) = notify_admins(bot, state, chat_id, user, &locale).await {
+                warn!("Failed to send flood alert in chat {}: {}", chat_id, e);
+            }
+            flood_tracker.reset_user(chat_id.0, thread_id, user_id.0);
+            return Ok(());
+        }
+        AntifloodAction::Penalize { penalty, notify } => {
+            // Human-in-the-loop: alert admins alongside the penalty
+            if notify && let Err(e) = notify_admins(bot, state, chat_id, user, &locale).await {
+                warn!("Failed to send flood alert in chat {}: {}", chat_id, e);
+            }
+            penalty
+        }
+    };
 
     info!(
         "Applying flood penalty {:?} to user {} in chat {}",
-        ctx.antiflood.penalty, user_id, chat_id
+        penalty, user_id, chat_id
     );
 
-    match ctx.antiflood.penalty {
+    if matches!(penalty, FloodPenalty::Kick | FloodPenalty::TempBan | FloodPenalty::Ban) {
+        state.bye.mark_removed(chat_id.0, user_id.0);
+    }
+
+    match penalty {
         FloodPenalty::Warn => {
-            bot.send_message(
-                chat_id,
-                get_text(&locale, "antiflood.penalty_warn_msg")
-                    .replace("{id}", &user_id.to_string())
-                    .replace("{name}", &html_escape(&user.first_name)),
-            )
-            .parse_mode(ParseMode::Html)
-            .await?;
+            state
+                .circuit
+                .send(
+                    bot.send_message(
+                        chat_id,
+                        get_text(&locale, "antiflood.penalty_warn_msg")
+                            .replace("{id}", &user_id.to_string())
+                            .replace("{name}", &html_escape(&user.first_name)),
+                    )
+                    .parse_mode(ParseMode::Html),
+                )
+                .await?;
         }
         FloodPenalty::Mute => {
-            let until = if ctx.antiflood.penalty_duration_secs > 0 {
-                chrono::Utc::now()
-                    + chrono::Duration::seconds(ctx.antiflood.penalty_duration_secs as i64)
-            } else {
-                // Permanent mute (use a far future date)
-                chrono::Utc::now() + chrono::Duration::days(366)
-            };
-
             let perms = ChatPermissions::empty(); // No permissions = muted
 
-            match bot
-                .restrict_chat_member(chat_id, user_id, perms)
-                .until_date(until)
-                .await
-            {
+            // A permanent mute is sent without an until_date
+            let mut request = bot.restrict_chat_member(chat_id, user_id, perms);
+            if ctx.antiflood.penalty_duration_secs != PERMANENT_RESTRICTION_MARKER {
+                request = request.until_date(restrict_until(
+                    state.clock.as_ref(),
+                    Duration::from_secs(ctx.antiflood.penalty_duration_secs).max(MIN_TEMP_RESTRICTION),
+                ));
+            }
+
+            match request.await {
                 Ok(_) => {
                     let duration_str = format!("selama {}", format_duration_full(ctx.antiflood.penalty_duration_secs));
                     // Ideally format_duration_full should also be localized later, but sticking to English/Simple for now
@@ -276,15 +506,19 @@ async fn antiflood_check_impl(
                     // For now, let's keep it simple as part of the message replacement or just inject it.
                     // The i18n key expects `{duration}`.
                     
-                    bot.send_message(
-                        chat_id,
-                        get_text(&locale, "antiflood.penalty_mute_msg")
-                            .replace("{id}", &user_id.to_string())
-                            .replace("{name}", &html_escape(&user.first_name))
-                            .replace("{duration}", &duration_str),
-                    )
-                    .parse_mode(ParseMode::Html)
-                    .await?;
+                    state
+                        .circuit
+                        .send(
+                            bot.send_message(
+                                chat_id,
+                                get_text(&locale, "antiflood.penalty_mute_msg")
+                                    .replace("{id}", &user_id.to_string())
+                                    .replace("{name}", &html_escape(&user.first_name))
+                                    .replace("{duration}", &duration_str),
+                            )
+                            .parse_mode(ParseMode::Html),
+                        )
+                        .await?;
                 }
                 Err(e) => {
                     warn!("Failed to mute user {}: {}", user_id, e);
@@ -296,14 +530,18 @@ async fn antiflood_check_impl(
                 Ok(_) => {
                     // Unban immediately so they can rejoin
                     let _ = bot.unban_chat_member(chat_id, user_id).await;
-                    bot.send_message(
-                        chat_id,
-                        get_text(&locale, "antiflood.penalty_kick_msg")
-                            .replace("{id}", &user_id.to_string())
-                            .replace("{name}", &html_escape(&user.first_name)),
-                    )
-                    .parse_mode(ParseMode::Html)
-                    .await?;
+                    state
+                        .circuit
+                        .send(
+                            bot.send_message(
+                                chat_id,
+                                get_text(&locale, "antiflood.penalty_kick_msg")
+                                    .replace("{id}", &user_id.to_string())
+                                    .replace("{name}", &html_escape(&user.first_name)),
+                            )
+                            .parse_mode(ParseMode::Html),
+                        )
+                        .await?;
                 }
                 Err(e) => {
                     warn!("Failed to kick user {}: {}", user_id, e);
@@ -311,8 +549,10 @@ async fn antiflood_check_impl(
             }
         }
         FloodPenalty::TempBan => {
-            let until = chrono::Utc::now()
-                + chrono::Duration::seconds(ctx.antiflood.penalty_duration_secs as i64);
+            let until = restrict_until(
+                state.clock.as_ref(),
+                Duration::from_secs(ctx.antiflood.penalty_duration_secs).max(MIN_TEMP_RESTRICTION),
+            );
 
             match bot
                 .ban_chat_member(chat_id, user_id)
@@ -321,15 +561,19 @@ async fn antiflood_check_impl(
             {
                 Ok(_) => {
                     let duration_str = format!("selama {}", format_duration_full(ctx.antiflood.penalty_duration_secs));
-                    bot.send_message(
-                        chat_id,
-                        get_text(&locale, "antiflood.penalty_tban_msg")
-                            .replace("{id}", &user_id.to_string())
-                            .replace("{name}", &html_escape(&user.first_name))
-                            .replace("{duration}", &duration_str),
-                    )
-                    .parse_mode(ParseMode::Html)
-                    .await?;
+                    state
+                        .circuit
+                        .send(
+                            bot.send_message(
+                                chat_id,
+                                get_text(&locale, "antiflood.penalty_tban_msg")
+                                    .replace("{id}", &user_id.to_string())
+                                    .replace("{name}", &html_escape(&user.first_name))
+                                    .replace("{duration}", &duration_str),
+                            )
+                            .parse_mode(ParseMode::Html),
+                        )
+                        .await?;
                 }
                 Err(e) => {
                     warn!("Failed to tempban user {}: {}", user_id, e);
@@ -339,14 +583,18 @@ async fn antiflood_check_impl(
         FloodPenalty::Ban => {
             match bot.ban_chat_member(chat_id, user_id).await {
                 Ok(_) => {
-                    bot.send_message(
-                        chat_id,
-                        get_text(&locale, "antiflood.penalty_ban_msg")
-                            .replace("{id}", &user_id.to_string())
-                            .replace("{name}", &html_escape(&user.first_name)),
-                    )
-                    .parse_mode(ParseMode::Html)
-                    .await?;
+                    state
+                        .circuit
+                        .send(
+                            bot.send_message(
+                                chat_id,
+                                get_text(&locale, "antiflood.penalty_ban_msg")
+                                    .replace("{id}", &user_id.to_string())
+                                    .replace("{name}", &html_escape(&user.first_name)),
+                            )
+                            .parse_mode(ParseMode::Html),
+                        )
+                        .await?;
                 }
                 Err(e) => {
                     warn!("Failed to ban user {}: {}", user_id, e);
@@ -356,9 +604,407 @@ async fn antiflood_check_impl(
     }
 
     // Reset user's flood tracking after penalty
-    flood_tracker.reset_user(chat_id.0, user_id.0);
+    flood_tracker.reset_user(chat_id.0, thread_id, user_id.0);
+
+    Ok(())
+}
+
+/// What to do about a user who just went over the flood limit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AntifloodAction {
+    /// Warn them; `remaining` more floods before the penalty
+    Warn { remaining: u32 },
+    /// Alert admins and leave the decision to them
+    NotifyOnly,
+    /// Apply the penalty, alerting admins too when `notify` is set
+    Penalize { penalty: FloodPenalty, notify: bool },
+}
+
+/// Decide the response to a flood, given the user's warning count from
+/// [`FloodTracker::record_message`].
+///
+/// Kept free of Telegram calls so the moderation progression can be tested.
+pub fn decide_antiflood_action(config: &AntifloodConfig, warnings: u32) -> AntifloodAction {
+    if warnings <= config.warnings_before_penalty {
+        return AntifloodAction::Warn {
+            remaining: config.warnings_before_penalty - warnings + 1,
+        };
+    }
+    if config.notify_admins && config.notify_only {
+        return AntifloodAction::NotifyOnly;
+    }
+    AntifloodAction::Penalize {
+        penalty: config.penalty.clone(),
+        notify: config.notify_admins,
+    }
+}
+
+/// Post a flood alert that silently pings the chat's admins.
+///
+/// The alert carries mute/ignore buttons handled by
+/// `plugins::antiflood::flood_callback_handler`.
+async fn notify_admins(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    user: &teloxide::types::User,
+    locale: &str,
+) -> anyhow::Result<()> {
+    // Invisible mentions notify admins without cluttering the alert
+    let admins: String = state
+        .permissions
+        .chat_administrators(chat_id)
+        .await?
+        .iter()
+        .filter(|m| !m.user.is_bot)
+        .map(|m| format!("<a href=\"tg://user?id={}\">\u{200b}</a>", m.user.id))
+        .collect();
+
+    let text = get_text(locale, "antiflood.notify_alert")
+        .replace("{admins}", &admins)
+        .replace("{id}", &user.id.to_string())
+        .replace("{name}", &html_escape(&user.first_name));
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            get_text(locale, "antiflood.notify_button_mute"),
+            format!("flood:mute:{}:{}", chat_id.0, user.id.0),
+        ),
+        InlineKeyboardButton::callback(
+            get_text(locale, "antiflood.notify_button_ignore"),
+            format!("flood:ignore:{}:{}", chat_id.0, user.id.0),
+        ),
+    ]]);
+
+    let alert = bot
+        .send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(keyboard);
+    state.circuit.send(alert).await?;
 
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAT: i64 = -100;
+
+    /// Send `count` messages from `user` one millisecond apart, starting at `start`.
+    fn burst(tracker: &FloodTracker, user: u64, count: u32, start: Instant) -> (bool, u32) {
+        let mut result = (false, 0);
+        for i in 0..count {
+            result = tracker.record_message_at(
+                CHAT,
+                None,
+                user,
+                3,
+                5,
+                start + Duration::from_millis(i as u64),
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn test_limit_is_inclusive() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        // Exactly max_messages is allowed
+        assert_eq!(burst(&tracker, 1, 3, start), (false, 0));
+
+        // One more within the window floods
+        let result = tracker.record_message_at(CHAT, None, 1, 3, 5, start + Duration::from_millis(10));
+        assert_eq!(result, (true, 1));
+
+        // Every further message keeps counting warnings
+        let result = tracker.record_message_at(CHAT, None, 1, 3, 5, start + Duration::from_millis(11));
+        assert_eq!(result, (true, 2));
+    }
+
+    #[test]
+    fn test_weighted_messages() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        // A sticker worth 2 plus a plain message fill the limit of 3 exactly
+        assert_eq!(tracker.record_weighted_at((CHAT, None), 1, 3, 5, 2, start), (false, 0));
+        let t = start + Duration::from_millis(1);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, t), (false, 0));
+
+        // The next sticker pushes it over
+        let t = start + Duration::from_millis(2);
+        assert_eq!(tracker.record_weighted_at((CHAT, None), 1, 3, 5, 2, t), (true, 1));
+
+        // A weight of 0 still counts once
+        let later = start + Duration::from_secs(10);
+        for i in 0..3 {
+            let t = later + Duration::from_millis(i);
+            assert_eq!(tracker.record_weighted_at((CHAT, None), 2, 3, 5, 0, t), (false, 0));
+        }
+        let t = later + Duration::from_millis(3);
+        assert_eq!(tracker.record_weighted_at((CHAT, None), 2, 3, 5, 0, t), (true, 1));
+    }
+
+    #[test]
+    fn test_window_expiry() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        burst(&tracker, 1, 3, start);
+
+        // After the window has passed, old messages no longer count
+        let later = start + Duration::from_secs(6);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, later), (false, 0));
+
+        // Messages exactly one window old have already dropped out
+        let tracker = FloodTracker::new();
+        burst(&tracker, 1, 3, start);
+        let edge = start + Duration::from_secs(5) + Duration::from_millis(1);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, edge), (false, 0));
+    }
+
+    #[test]
+    fn test_other_user_resets_counter() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        burst(&tracker, 1, 3, start);
+
+        // Another user interrupts the stream
+        let t = start + Duration::from_millis(10);
+        assert_eq!(tracker.record_message_at(CHAT, None, 2, 3, 5, t), (false, 0));
+
+        // User 1 starts from an empty window again
+        let t = start + Duration::from_millis(20);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, t), (false, 0));
+    }
+
+    #[test]
+    fn test_reset_keeps_warnings() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(burst(&tracker, 1, 4, start), (true, 1));
 
+        // Interruption clears the message counter but not the warnings
+        tracker.record_message_at(CHAT, None, 2, 3, 5, start + Duration::from_millis(10));
+        let result = burst(&tracker, 1, 4, start + Duration::from_millis(20));
+        assert_eq!(result, (true, 2));
+    }
+
+    #[test]
+    fn test_topics_and_chats_are_separate() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        burst(&tracker, 1, 3, start);
+
+        // Same user in another topic or chat has a fresh window
+        let t = start + Duration::from_millis(10);
+        assert_eq!(tracker.record_message_at(CHAT, Some(7), 1, 3, 5, t), (false, 0));
+        assert_eq!(tracker.record_message_at(CHAT - 1, None, 1, 3, 5, t), (false, 0));
+
+        // And does not count as an interruption in the original stream
+        let t = start + Duration::from_millis(11);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, t), (true, 1));
+    }
+
+    #[test]
+    fn test_cleanup_drops_idle_state() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        burst(&tracker, 1, 2, start);
+        tracker.record_message_at(CHAT - 1, None, 3, 3, 5, start);
+        let later = start + Duration::from_secs(50);
+        tracker.record_message_at(CHAT, None, 2, 3, 5, later);
+
+        tracker.cleanup_at(Duration::from_secs(60), start + Duration::from_secs(70));
+
+        // The idle user and the now-empty chat are gone, the active user stays
+        assert!(tracker.data.get(&(CHAT - 1, None)).is_none());
+        let chat_state = tracker.data.get(&(CHAT, None)).unwrap();
+        assert!(!chat_state.users.contains_key(&1));
+        assert!(chat_state.users.contains_key(&2));
+    }
+
+    #[test]
+    fn test_one_warning_per_window() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        // Nothing to claim before the user is tracked
+        assert!(!tracker.claim_warning(CHAT, None, 1));
+
+        assert_eq!(burst(&tracker, 1, 4, start), (true, 1));
+        assert!(tracker.claim_warning(CHAT, None, 1));
+
+        // Further flooding in the same window still counts, but stays quiet
+        let t = start + Duration::from_millis(10);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, t), (true, 2));
+        assert!(!tracker.claim_warning(CHAT, None, 1));
+
+        // Once the window has reset, a new warning may go out
+        let later = start + Duration::from_secs(6);
+        burst(&tracker, 1, 4, later);
+        assert!(tracker.claim_warning(CHAT, None, 1));
+
+        // An interruption resets the window too
+        tracker.record_message_at(CHAT, None, 2, 3, 5, later + Duration::from_millis(10));
+        burst(&tracker, 1, 4, later + Duration::from_millis(20));
+        assert!(tracker.claim_warning(CHAT, None, 1));
+
+        // As does a penalty
+        tracker.reset_user(CHAT, None, 1);
+        burst(&tracker, 1, 4, later + Duration::from_millis(30));
+        assert!(tracker.claim_warning(CHAT, None, 1));
+    }
+
+    #[test]
+    fn test_reset_user_clears_state() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(burst(&tracker, 1, 4, start), (true, 1));
+        tracker.reset_user(CHAT, None, 1);
+
+        let t = start + Duration::from_millis(10);
+        assert_eq!(tracker.record_message_at(CHAT, None, 1, 3, 5, t), (false, 0));
+    }
+
+    #[test]
+    fn test_chat_stats() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(burst(&tracker, 1, 4, start), (true, 1));
+        tracker.record_message_at(CHAT, None, 2, 3, 5, start);
+        // Same user in a topic counts once; other chats don't count
+        tracker.record_message_at(CHAT, Some(7), 2, 3, 5, start);
+        tracker.record_message_at(CHAT - 1, None, 3, 3, 5, start);
+
+        assert_eq!(tracker.chat_stats(CHAT), (2, 1));
+        assert_eq!(tracker.chat_stats(1), (0, 0));
+    }
+
+    #[test]
+    fn test_trusted_limit() {
+        let mut config = AntifloodConfig { max_messages: 3, trusted_multiplier: 2, ..Default::default() };
+        assert!(config.toggle_trusted(2));
+        assert_eq!(config.limit_for(1), 3);
+        assert_eq!(config.limit_for(2), 6);
+
+        // A trusted user can send past the normal limit
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+        for i in 0..6 {
+            let t = start + Duration::from_millis(i);
+            assert_eq!(tracker.record_message_at(CHAT, None, 2, config.limit_for(2), 5, t), (false, 0));
+        }
+
+        // Toggling again removes the trust
+        assert!(!config.toggle_trusted(2));
+        assert_eq!(config.limit_for(2), 3);
+    }
+
+    #[test]
+    fn test_join_grace() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+        let grace = Duration::from_secs(60);
+
+        // Members whose join the bot never saw get no grace
+        assert!(!tracker.in_grace_at(CHAT, 1, grace, start));
+
+        tracker.record_join_at(CHAT, 1, start);
+        burst(&tracker, 1, 2, start);
+        assert!(tracker.in_grace_at(CHAT, 1, grace, start + Duration::from_secs(59)));
+        assert!(!tracker.in_grace_at(CHAT, 1, grace, start + Duration::from_secs(60)));
+        assert!(!tracker.in_grace_at(CHAT - 1, 1, grace, start));
+
+        // A penalty doesn't restart the grace period
+        tracker.reset_user(CHAT, None, 1);
+        assert!(!tracker.in_grace_at(CHAT, 1, grace, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_reaped_user_gets_no_new_grace() {
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+        let grace = Duration::from_secs(60);
+
+        tracker.record_join_at(CHAT, 1, start);
+        burst(&tracker, 1, 2, start);
+
+        // Reaping the idle user doesn't hand the grace out again
+        let later = start + Duration::from_secs(700);
+        tracker.cleanup_at(IDLE_THRESHOLD, later);
+        assert!(!tracker.data.contains_key(&(CHAT, None)));
+        assert!(!tracker.in_grace_at(CHAT, 1, grace, later));
+
+        // Nor does coming back in a topic the tracker hasn't seen
+        tracker.record_message_at(CHAT, Some(7), 1, 3, 5, later);
+        assert!(!tracker.in_grace_at(CHAT, 1, grace, later));
+
+        // Join times outlive reaping only as long as the longest grace
+        assert!(tracker.joins.contains_key(&(CHAT, 1)));
+        tracker.cleanup_at(IDLE_THRESHOLD, start + Duration::from_secs(MAX_JOIN_GRACE_SECS));
+        assert!(!tracker.joins.contains_key(&(CHAT, 1)));
+    }
+
+    #[test]
+    fn test_antiflood_progression() {
+        let config = AntifloodConfig {
+            enabled: true,
+            max_messages: 3,
+            time_window_secs: 5,
+            penalty: FloodPenalty::TempBan,
+            warnings_before_penalty: 2,
+            ..Default::default()
+        };
+        let tracker = FloodTracker::new();
+        let start = Instant::now();
+
+        // Drive the tracker the way the handler does and collect its decisions
+        let mut actions = Vec::new();
+        for i in 0..10 {
+            let t = start + Duration::from_millis(i);
+            let (flooding, warnings) =
+                tracker.record_message_at(CHAT, None, 1, config.limit_for(1), config.time_window_secs, t);
+            if !flooding {
+                continue;
+            }
+            let action = decide_antiflood_action(&config, warnings);
+            if matches!(action, AntifloodAction::Penalize { .. }) {
+                tracker.reset_user(CHAT, None, 1);
+            }
+            actions.push(action);
+        }
+
+        let penalty = AntifloodAction::Penalize { penalty: FloodPenalty::TempBan, notify: false };
+        assert_eq!(
+            actions,
+            vec![
+                AntifloodAction::Warn { remaining: 2 },
+                AntifloodAction::Warn { remaining: 1 },
+                penalty.clone(),
+                // After the penalty the count starts over: three more are fine
+                AntifloodAction::Warn { remaining: 2 },
+            ]
+        );
+
+        // Admin alerts ride along with the penalty, or replace it
+        let notify = AntifloodConfig { notify_admins: true, ..config.clone() };
+        assert_eq!(
+            decide_antiflood_action(&notify, 3),
+            AntifloodAction::Penalize { penalty: FloodPenalty::TempBan, notify: true }
+        );
+        let notify_only = AntifloodConfig { notify_only: true, ..notify };
+        assert_eq!(decide_antiflood_action(&notify_only, 3), AntifloodAction::NotifyOnly);
+        assert_eq!(decide_antiflood_action(&notify_only, 2), AntifloodAction::Warn { remaining: 1 });
+        assert_eq!(decide_antiflood_action(&config, 3), penalty);
+    }
+}
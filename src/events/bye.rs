@@ -4,12 +4,13 @@
 
 use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
-use teloxide::types::{ChatMemberUpdated, InputFile, ParseMode};
+use teloxide::types::ChatMemberUpdated;
 use tracing::{debug, info};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
 use crate::plugins::bye::{build_bye_keyboard, format_bye_text};
 use crate::i18n::get_text;
+use crate::utils::{merge_filling_buttons, send_content, Content};
 
 /// Returns the handler for member leave events.
 pub fn handler() -> UpdateHandler<anyhow::Error> {
@@ -40,8 +41,14 @@ async fn bye_handler(
 
     debug!("Member {} left chat {}", user.id, chat.id);
 
+    // Skip non-critical sends while Telegram is failing
+    if !state.circuit.allow_request() {
+        debug!("Circuit open, skipping goodbye in chat {}", chat.id);
+        return Ok(());
+    }
+
     // Resolve locale for this chat
-    let locale = state.get_locale(Some(chat.id.0), Some(user.id.0)).await;
+    let locale = state.locale_for_group(chat.id.0).await;
 
     // Get bye settings (lazy loaded, 5min TTL)
     let settings = match state.bye.get(chat.id.0).await? {
@@ -58,6 +65,12 @@ async fn bye_handler(
         return Ok(());
     }
 
+    // Members we banned or kicked already got a penalty message
+    if settings.skip_on_ban && state.bye.take_removed(chat.id.0, user.id.0) {
+        debug!("Skipping goodbye for removed member {} in chat {}", user.id, chat.id);
+        return Ok(());
+    }
+
     // Get goodbye message text
     let default_msg = get_text(&locale, "bye.default_message");
     let template = settings
@@ -68,59 +81,19 @@ async fn bye_handler(
     let chat_title = chat.title().unwrap_or("Grup");
 
     // Format the goodbye text with placeholders
-    let formatted_text = format_bye_text(template, user, chat_title);
+    let (formatted_text, format, rule_buttons) =
+        format_bye_text(template, user, chat_title, chat.id.0, &state.bot_username);
 
-    // Build keyboard if buttons are configured
-    let keyboard = build_bye_keyboard(&settings.buttons);
+    // Build keyboard from the configured buttons plus any {rules} button
+    let mut buttons = settings.buttons.clone();
+    merge_filling_buttons(&mut buttons, rule_buttons);
+    let keyboard = build_bye_keyboard(&buttons);
 
     // Send goodbye message (with or without media)
-    if let Some(ref file_id) = settings.media_file_id {
-        match settings.media_type.as_deref() {
-            Some("photo") => {
-                bot.send_photo(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("video") => {
-                bot.send_video(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("animation") => {
-                bot.send_animation(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("sticker") => {
-                bot.send_sticker(chat.id, InputFile::file_id(file_id))
-                    .await?;
-                if !formatted_text.is_empty() {
-                    bot.send_message(chat.id, formatted_text)
-                        .parse_mode(ParseMode::Html)
-                        .reply_markup(keyboard)
-                        .await?;
-                }
-            }
-            _ => {
-                bot.send_message(chat.id, formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-        }
-    } else {
-        // Text only goodbye
-        bot.send_message(chat.id, formatted_text)
-            .parse_mode(ParseMode::Html)
-            .reply_markup(keyboard)
-            .await?;
-    }
+    let content = Content::new(formatted_text, format.parse_mode())
+        .media(settings.media_type.as_deref(), settings.media_file_id.as_deref())
+        .keyboard(keyboard);
+    send_content(&bot, chat.id, content).await?;
 
     info!(
         "Sent goodbye message for {} in chat {}",
@@ -0,0 +1,30 @@
+//! Join/leave service message cleanup.
+//!
+//! With `clean_service` on, Telegram's "X joined the group" and "X left the
+//! group" messages are deleted. Only those service messages are touched: the
+//! bot's welcome and goodbye are ordinary messages and always stay.
+
+use teloxide::prelude::*;
+use tracing::debug;
+
+use crate::bot::dispatcher::ThrottledBot;
+use crate::database::models::MessageContext;
+
+/// Delete `msg` if it's a join/leave service message and the chat asked for it.
+///
+/// Without delete rights the deletion fails and the message stays; that's
+/// logged at debug level only, since it would otherwise repeat on every join.
+pub async fn clean_service(bot: &ThrottledBot, msg: &Message, ctx: &MessageContext) {
+    if !ctx.clean_service || !is_join_or_leave(msg) {
+        return;
+    }
+
+    if let Err(e) = bot.delete_message(msg.chat.id, msg.id).await {
+        debug!("Can't delete service message in chat {}: {}", msg.chat.id, e);
+    }
+}
+
+/// Whether `msg` is a member join or leave service message.
+fn is_join_or_leave(msg: &Message) -> bool {
+    msg.new_chat_members().is_some() || msg.left_chat_member().is_some()
+}
@@ -3,25 +3,24 @@
 //! Handles incoming messages and checks for filter triggers.
 //! Optimized for decentralized architecture (L1/L2 Caching).
 
+use regex::Regex;
 use teloxide::prelude::*;
-use teloxide::types::{
-    InlineKeyboardButton, InlineKeyboardMarkup, InputFile, MessageId, ParseMode,
-    ReplyParameters,
-};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
 use tracing::debug;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::models::DbFilter;
-use crate::utils::apply_fillings_new;
+use crate::database::models::{DbFilter, MessageContext};
+use crate::utils::{apply_fillings_new, link_preview_options, send_content, Content};
 
 /// Public function to check filters - called from unified handler.
 pub async fn check_filters(
     bot: &ThrottledBot,
     msg: &Message,
     state: &AppState,
+    ctx: &MessageContext,
 ) -> anyhow::Result<()> {
     // Clone values needed for the internal handler
-    filter_check_impl(bot, msg, state).await
+    filter_check_impl(bot, msg, state, ctx).await
 }
 
 /// Internal filter check implementation.
@@ -29,7 +28,13 @@ async fn filter_check_impl(
     bot: &ThrottledBot,
     msg: &Message,
     state: &AppState,
+    ctx: &MessageContext,
 ) -> anyhow::Result<()> {
+    // Paused with /filters off
+    if !ctx.filters_enabled {
+        return Ok(());
+    }
+
     // Only process in groups
     if !msg.chat.is_group() && !msg.chat.is_supergroup() {
         return Ok(());
@@ -61,42 +66,43 @@ async fn filter_check_impl(
         return Ok(());
     }
 
-    // Find first matching trigger
-    // We check against the keys in memory (Fast!)
-    let mut matched_trigger = None;
+    // Any trigger the text contains is a candidate; its MatchType decides.
+    // Longer triggers first, so the most specific filter wins
     let text_lower = text.to_lowercase();
-
-    for trigger in triggers {
-        // Word-based matching for L1 check (consistent with default MatchType::Word)
-        // Strip punctuation and check whole words
-        let matches = text_lower.split_whitespace().any(|word| {
-            let cleaned = word.trim_matches(|c: char| !c.is_alphanumeric());
-            cleaned == trigger || word == trigger
-        });
-        if matches {
-            matched_trigger = Some(trigger);
+    let mut candidates: Vec<(String, Option<Regex>)> = triggers
+        .into_iter()
+        .filter(|trigger| text_lower.contains(trigger.as_str()))
+        .map(|trigger| (trigger, None))
+        .collect();
+    candidates.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+    // Regex triggers can't be found by substring, so they come last, along
+    // with their compiled pattern
+    let regexes = state.filters.get_regex_triggers(chat_id.0).await?;
+    candidates.extend(
+        regexes
+            .into_iter()
+            .filter(|(_, re)| re.is_match(text))
+            .map(|(trigger, re)| (trigger, Some(re))),
+    );
+
+    // L2 Cache: Fetch each candidate's content and check its MatchType
+    let mut matched = None;
+    for (trigger, regex) in candidates {
+        debug!("L1 Filter Match: '{}' in chat {}", trigger, chat_id);
+        if let Some(filter) = state.filters.get_filter(chat_id.0, &trigger).await?
+            && filter.matches(text, regex.as_ref())
+        {
+            matched = Some(filter);
             break;
         }
     }
 
-    let trigger = match matched_trigger {
-        Some(t) => t,
-        None => return Ok(()),
-    };
-    
-    debug!("L1 Filter Match: '{}' in chat {}", trigger, chat_id);
-
-    // L2 Cache: Fetch the actual filter content match
-    let filter = match state.filters.get_filter(chat_id.0, &trigger).await? {
+    let filter = match matched {
         Some(f) => f,
-        None => return Ok(()), // Should not happen if L1 and DB are synced
+        None => return Ok(()),
     };
 
-    // Verify MatchType (Double Check)
-    if !filter.matches(text) {
-        return Ok(());
-    }
-
     // Check user permissions
     let is_admin = state.permissions.is_admin(chat_id, user.id).await.unwrap_or(false);
 
@@ -110,25 +116,32 @@ async fn filter_check_impl(
     debug!("Executing Filter '{}'", filter.trigger);
 
     // Determine reply target
-    let reply_to = if filter.replytag {
-        msg.reply_to_message()
-            .and_then(|m| m.from.as_ref())
-            .map(|_| msg.reply_to_message().unwrap().id)
-            .unwrap_or(msg.id)
-    } else {
-        msg.id
-    };
+    let reply_to = reply_target(msg.id, msg.reply_to_message().map(|m| m.id), filter.replytag);
 
     // Send filter response
     send_filter_response(bot, state, chat_id, user, &filter, reply_to).await?;
 
+    // Count the trigger (buffered)
+    state.filters.record_trigger(chat_id.0, &filter.trigger);
+
     Ok(())
 }
 
+/// Pick the message a filter response replies to.
+///
+/// With `{replytag}` the response goes to the message the trigger replied to,
+/// falling back to the trigger itself when it was not a reply.
+fn reply_target(trigger: MessageId, replied: Option<MessageId>, replytag: bool) -> MessageId {
+    match replied {
+        Some(id) if replytag => id,
+        _ => trigger,
+    }
+}
+
 /// Send the filter response.
 async fn send_filter_response(
     bot: &ThrottledBot,
-    _state: &AppState,
+    state: &AppState,
     chat_id: ChatId,
     user: &teloxide::types::User,
     filter: &DbFilter,
@@ -166,92 +179,34 @@ async fn send_filter_response(
     };
 
     // Send based on media type
-    match (&filter.media_file_id, &filter.media_type) {
-        (Some(file_id), Some(media_type)) => {
-            match media_type.as_str() {
-                "photo" => {
-                    let mut req = bot.send_photo(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() {
-                        req = req.caption(&text).parse_mode(ParseMode::Html);
-                    }
-                    if let Some(kb) = keyboard {
-                        req = req.reply_markup(kb);
-                    }
-                    if filter.protect {
-                        req = req.protect_content(true);
-                    }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "video" => {
-                    let mut req = bot.send_video(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() {
-                        req = req.caption(&text).parse_mode(ParseMode::Html);
-                    }
-                    if let Some(kb) = keyboard {
-                        req = req.reply_markup(kb);
-                    }
-                    if filter.protect {
-                        req = req.protect_content(true);
-                    }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "animation" => {
-                    let mut req = bot.send_animation(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() {
-                        req = req.caption(&text).parse_mode(ParseMode::Html);
-                    }
-                    if let Some(kb) = keyboard {
-                        req = req.reply_markup(kb);
-                    }
-                    if filter.protect {
-                        req = req.protect_content(true);
-                    }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "document" => {
-                    let mut req = bot.send_document(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() {
-                        req = req.caption(&text).parse_mode(ParseMode::Html);
-                    }
-                    if let Some(kb) = keyboard {
-                        req = req.reply_markup(kb);
-                    }
-                    if filter.protect {
-                        req = req.protect_content(true);
-                    }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "sticker" => {
-                    let mut req = bot.send_sticker(chat_id, InputFile::file_id(file_id));
-                    if filter.protect {
-                        req = req.protect_content(true);
-                    }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                _ => {}
-            }
-        }
-        _ => {
-            // Text-only response
-            if !text.is_empty() {
-                let mut req = bot.send_message(chat_id, &text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_parameters(ReplyParameters::new(reply_to));
-                if let Some(kb) = keyboard {
-                    req = req.reply_markup(kb);
-                }
-                if filter.protect {
-                    req = req.protect_content(true);
-                }
-                req.await?;
-            }
-        }
-    }
+    let content = Content::new(text, ParseMode::Html)
+        .media(filter.media_type.as_deref(), filter.media_file_id.as_deref())
+        .keyboard(keyboard)
+        .reply_to(reply_to)
+        .preview(link_preview_options(filter.preview))
+        .protect(filter.protect);
+    state.circuit.send(send_content(bot, chat_id, content)).await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_target() {
+        let trigger = MessageId(10);
+        let replied = Some(MessageId(7));
+
+        // Default: reply to the trigger message
+        assert_eq!(reply_target(trigger, replied, false), trigger);
+        assert_eq!(reply_target(trigger, None, false), trigger);
+
+        // {replytag}: reply to the message the trigger replied to
+        assert_eq!(reply_target(trigger, replied, true), MessageId(7));
+
+        // {replytag} without a reply falls back to the trigger
+        assert_eq!(reply_target(trigger, None, true), trigger);
+    }
+}
@@ -0,0 +1,60 @@
+//! Join request event handler.
+//!
+//! Auto-approves `ChatJoinRequest` updates in groups that opted in with
+//! `/joinrequests auto`, declining requests that look like spam accounts.
+
+use teloxide::dispatching::UpdateHandler;
+use teloxide::prelude::*;
+use teloxide::types::ChatJoinRequest;
+use tracing::{debug, info, warn};
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::database::models::JoinRequestMode;
+use crate::utils::links::contains_link;
+
+/// Build the join request handler.
+pub fn handler() -> UpdateHandler<anyhow::Error> {
+    Update::filter_chat_join_request().endpoint(handle_join_request)
+}
+
+/// Approve or decline a join request according to the chat's mode.
+async fn handle_join_request(
+    bot: ThrottledBot,
+    request: ChatJoinRequest,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = request.chat.id;
+    let user = &request.from;
+
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+    if ctx.join_requests == JoinRequestMode::Manual {
+        debug!("Join request from {} in chat {} left to admins", user.id, chat_id);
+        return Ok(());
+    }
+
+    // Spam accounts commonly advertise links in their display name
+    let name = match &user.last_name {
+        Some(last) => format!("{} {}", user.first_name, last),
+        None => user.first_name.clone(),
+    };
+    let suspicious = contains_link(&name);
+
+    let result = if suspicious {
+        bot.decline_chat_join_request(chat_id, user.id).await
+    } else {
+        bot.approve_chat_join_request(chat_id, user.id).await
+    };
+
+    match result {
+        Ok(_) => info!(
+            "Join request from {} in chat {} {}",
+            user.id,
+            chat_id,
+            if suspicious { "declined" } else { "approved" }
+        ),
+        // Usually the bot lacks CanInviteUsers or the request was already handled
+        Err(e) => warn!("Failed to handle join request from {} in chat {}: {}", user.id, chat_id, e),
+    }
+
+    Ok(())
+}
@@ -7,7 +7,10 @@
 
 pub mod antiflood;
 pub mod bye;
+pub mod clean_service;
 pub mod filters;
+pub mod join_request;
+pub mod recent_messages;
 pub mod welcome;
 
 use teloxide::dispatching::UpdateHandler;
@@ -15,14 +18,17 @@ use teloxide::prelude::*;
 use tracing::{debug, error};
 
 pub use antiflood::FloodTracker;
+pub use recent_messages::RecentMessages;
 
 // Import handlers from plugins
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::database::models::MessageContext;
 use crate::plugins::afk;
 
 /// Build the combined event handler for chat member updates.
 pub fn event_handler() -> UpdateHandler<anyhow::Error> {
     dptree::entry()
+        .inspect(antiflood::track_join)
         .branch(welcome::handler())
         .branch(bye::handler())
 }
@@ -40,6 +46,9 @@ pub fn message_event_handler() -> UpdateHandler<anyhow::Error> {
 }
 
 /// Unified message handler that runs all sub-handlers.
+///
+/// The chat's `MessageContext` is loaded once here and shared by every
+/// sub-handler, so a message costs a single settings lookup.
 async fn unified_message_handler(
     bot: ThrottledBot,
     msg: Message,
@@ -52,21 +61,43 @@ async fn unified_message_handler(
     debug!("unified_message_handler: chat={}, text='{}', is_command={}", 
            msg.chat.id, text.chars().take(30).collect::<String>(), is_command);
 
+    // Remember the message for user-targeted cleanup (/ban -d)
+    if let Some(user) = msg.from.as_ref() {
+        state
+            .recent_messages
+            .record(msg.chat.id.0, msg.id, user.id.0, msg.date.timestamp());
+    }
+
+    // One settings read per message, shared by all sub-handlers
+    let ctx = match state.message_context.get_or_default(msg.chat.id.0).await {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            error!("Failed to load message context for chat {}: {}", msg.chat.id, e);
+            MessageContext::new(msg.chat.id.0)
+        }
+    };
+
+    // Drop join/leave service messages if the chat asked for it
+    clean_service::clean_service(&bot, &msg, &ctx).await;
+
     // Run antiflood (for non-commands)
     if !is_command
-        && let Err(e) = antiflood::check_antiflood(&bot, &msg, &state, &flood_tracker).await {
+        && let Err(e) = antiflood::check_antiflood(&bot, &msg, &state, &ctx, &flood_tracker).await {
             error!("Antiflood error: {}", e);
+            state.circuit.record_error(&e);
         }
 
     // Run filters (for non-commands)
     if !is_command && !text.is_empty()
-        && let Err(e) = filters::check_filters(&bot, &msg, &state).await {
+        && let Err(e) = filters::check_filters(&bot, &msg, &state, &ctx).await {
             error!("Filters error: {}", e);
+            state.circuit.record_error(&e);
         }
 
     // Run AFK handler (for all messages - welcome back + reply detection)
-    if let Err(e) = afk::afk_handler(bot.clone(), msg.clone(), state.clone()).await {
+    if let Err(e) = afk::afk_handler(&bot, &msg, &state, &ctx).await {
         error!("AFK handler error: {}", e);
+        state.circuit.record_error(&e);
     }
 
     Ok(())
@@ -0,0 +1,135 @@
+//! Recently seen messages per chat.
+//!
+//! The Bot API can't list chat history, so commands that act on a user's
+//! past messages (e.g. `/ban -d`) rely on this in-memory index. It only
+//! covers messages seen since the bot started, at most [`MAX_PER_CHAT`]
+//! per chat, and chats that go quiet for an hour are dropped.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use teloxide::types::MessageId;
+
+use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
+
+/// Messages remembered per chat; older ones are evicted first.
+pub const MAX_PER_CHAT: usize = 200;
+
+/// A chat's buffer is dropped after this long without new messages.
+const CHAT_IDLE: Duration = Duration::from_secs(3600);
+
+/// A message seen in a group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecentMessage {
+    pub message_id: MessageId,
+    pub user_id: u64,
+    /// Send time (unix seconds).
+    pub timestamp: i64,
+}
+
+type ChatBuffer = Arc<Mutex<VecDeque<RecentMessage>>>;
+
+/// Bounded per-chat index of recent messages.
+#[derive(Clone)]
+pub struct RecentMessages {
+    chats: TypedCache<i64, ChatBuffer>,
+    per_chat: usize,
+}
+
+impl RecentMessages {
+    /// Create the index, backed by a registry cache.
+    pub fn new(cache: &CacheRegistry) -> Self {
+        Self::with_capacity(cache, MAX_PER_CHAT)
+    }
+
+    fn with_capacity(cache: &CacheRegistry, per_chat: usize) -> Self {
+        let chats = cache.get_or_create(
+            "recent_messages",
+            CacheConfig::with_capacity(10_000).tti(CHAT_IDLE),
+        );
+        Self { chats, per_chat }
+    }
+
+    /// Remember a message, evicting the chat's oldest one when full.
+    pub fn record(&self, chat_id: i64, message_id: MessageId, user_id: u64, timestamp: i64) {
+        let buffer = self
+            .chats
+            .get_or_insert_with(chat_id, || Arc::new(Mutex::new(VecDeque::with_capacity(self.per_chat))));
+        let mut buffer = buffer.lock();
+        if buffer.len() >= self.per_chat {
+            buffer.pop_front();
+        }
+        buffer.push_back(RecentMessage { message_id, user_id, timestamp });
+    }
+
+    /// IDs of a user's remembered messages in a chat, oldest first.
+    pub fn user_messages(&self, chat_id: i64, user_id: u64) -> Vec<MessageId> {
+        self.chats
+            .get(&chat_id)
+            .map(|buffer| {
+                buffer
+                    .lock()
+                    .iter()
+                    .filter(|m| m.user_id == user_id)
+                    .map(|m| m.message_id)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Newest remembered message in a chat sent before `cutoff` (unix seconds).
+    pub fn newest_before(&self, chat_id: i64, cutoff: i64) -> Option<MessageId> {
+        let buffer = self.chats.get(&chat_id)?;
+        let buffer = buffer.lock();
+        buffer
+            .iter()
+            .filter(|m| m.timestamp < cutoff)
+            .map(|m| m.message_id)
+            .max_by_key(|id| id.0)
+    }
+
+    /// Drop messages that no longer exist (e.g. after deleting them).
+    pub fn forget(&self, chat_id: i64, message_ids: &[MessageId]) {
+        if let Some(buffer) = self.chats.get(&chat_id) {
+            buffer.lock().retain(|m| !message_ids.contains(&m.message_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAT: i64 = -100;
+
+    #[test]
+    fn test_user_messages() {
+        let recent = RecentMessages::new(&CacheRegistry::new());
+        recent.record(CHAT, MessageId(1), 7, 0);
+        recent.record(CHAT, MessageId(2), 8, 0);
+        recent.record(CHAT, MessageId(3), 7, 0);
+        recent.record(CHAT - 1, MessageId(4), 7, 0);
+
+        assert_eq!(recent.user_messages(CHAT, 7), vec![MessageId(1), MessageId(3)]);
+
+        recent.forget(CHAT, &[MessageId(1)]);
+        assert_eq!(recent.user_messages(CHAT, 7), vec![MessageId(3)]);
+        assert!(recent.user_messages(CHAT, 9).is_empty());
+    }
+
+    #[test]
+    fn test_per_chat_eviction() {
+        let recent = RecentMessages::with_capacity(&CacheRegistry::new(), 3);
+        for id in 1..=5 {
+            recent.record(CHAT, MessageId(id), 7, 0);
+        }
+
+        // Only the newest three survive
+        assert_eq!(
+            recent.user_messages(CHAT, 7),
+            vec![MessageId(3), MessageId(4), MessageId(5)]
+        );
+    }
+}
@@ -1,16 +1,35 @@
 //! Welcome event handler.
 //!
 //! Handles new member joins and sends customizable welcome messages.
+//!
+//! Welcomes are driven by `chat_member` updates, not by Telegram's
+//! "X joined the group" service message. The two arrive independently and
+//! in no fixed order, so the welcome never replies to the service message:
+//! whatever happens to that message (deleted by an admin or a clean-service
+//! setting), the welcome stays.
+//!
+//! With `welcome_mute` on, new members are muted and the welcome carries an
+//! "I'm human" button. Members who don't press it within [`VERIFY_TIMEOUT`]
+//! are kicked. Pending members are stored, and [`rearm_verify_timeouts`]
+//! restarts their timers after a restart.
+
+use std::time::Duration;
 
 use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
-use teloxide::types::{ChatMemberUpdated, InputFile, ParseMode};
-use tracing::{debug, info};
+use teloxide::types::{ChatMemberUpdated, ChatPermissions, InlineKeyboardButton, User};
+use tracing::{debug, info, warn};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::plugins::welcome::{build_welcome_keyboard, format_welcome_text};
+use crate::database::LastWelcome;
+use crate::plugins::ban::kick_member;
+use crate::plugins::welcome::{build_welcome_keyboard, format_welcome_text, variant_roll, VERIFY_CALLBACK_PREFIX};
+use crate::utils::{apply_notelink_filling, is_invalid_media, merge_filling_buttons, send_content, Content};
 use crate::i18n::get_text;
 
+/// How long a muted member has to press the verify button before being kicked.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Returns the handler for new member events.
 pub fn handler() -> UpdateHandler<anyhow::Error> {
     dptree::filter(is_new_member).endpoint(welcome_handler)
@@ -29,22 +48,29 @@ fn is_new_member(update: ChatMemberUpdated) -> bool {
     is_joining && is_not_bot
 }
 
+/// Resolve who added the new member.
+///
+/// Joins through a link (or by the user themselves) have no distinct inviter.
+fn inviter<'a>(actor: &'a User, member: &User, via_link: bool) -> Option<&'a User> {
+    (actor.id != member.id && !via_link).then_some(actor)
+}
+
 /// Handle new member join event.
 async fn welcome_handler(
     bot: ThrottledBot,
     update: ChatMemberUpdated,
     state: AppState,
 ) -> anyhow::Result<()> {
-    let chat = update.chat;
+    let chat = &update.chat;
     let user = &update.new_chat_member.user;
 
     debug!("New member {} joined chat {}", user.id, chat.id);
 
     // Resolve locale for this chat (using group config first)
-    let locale = state.get_locale(Some(chat.id.0), Some(user.id.0)).await;
+    let locale = state.locale_for_group(chat.id.0).await;
 
-    // Get welcome settings (lazy loaded, 5min TTL)
-    let settings = match state.welcome.get(chat.id.0).await? {
+    // Get welcome settings (lazy loaded, 5min TTL, seeded from global defaults)
+    let settings = match state.welcome.get_or_seed(chat.id.0).await? {
         Some(s) => s,
         None => {
             debug!("No welcome settings for chat {}", chat.id);
@@ -58,9 +84,24 @@ async fn welcome_handler(
         return Ok(());
     }
 
+    // Skip users who were welcomed recently (leave/rejoin spam). Gated
+    // chats welcome every join: the verify button lives on the welcome, and
+    // a kicked member rejoining must get a fresh one.
+    let now = state.clock.now().timestamp();
+    if !settings.welcome_mute
+        && state
+            .welcome
+            .in_welcome_cooldown(chat.id.0, user.id.0, settings.cooldown_secs, now)
+    {
+        return Ok(());
+    }
+
+    // Pick one of the welcome variants (or the single message)
+    let welcome = settings.pick_variant(variant_roll());
+
     // Get welcome message text
     let default_msg = get_text(&locale, "welcome.default_message");
-    let template = settings
+    let template = welcome
         .message
         .as_deref()
         .unwrap_or(&default_msg);
@@ -74,60 +115,87 @@ async fn welcome_handler(
         .unwrap_or(0) as u64;
 
     // Format the welcome text with placeholders
-    let formatted_text = format_welcome_text(template, user, chat_title, member_count);
+    let (formatted_text, format, rule_buttons) = format_welcome_text(
+        template,
+        user,
+        inviter(&update.from, user, update.invite_link.is_some()),
+        chat_title,
+        member_count,
+        chat.id.0,
+        &state.bot_username,
+    );
+
+    // {notelink:name} becomes a deep link button under the configured ones
+    let (formatted_text, note_links) =
+        apply_notelink_filling(&formatted_text, chat.id.0, &state.bot_username);
+    let mut buttons = welcome.buttons;
+    merge_filling_buttons(&mut buttons, rule_buttons);
+    buttons.extend(note_links.into_iter().map(|btn| vec![btn]));
 
     // Build keyboard if buttons are configured
-    let keyboard = build_welcome_keyboard(&settings.buttons);
-
-    // Send welcome message (with or without media)
-    if let Some(ref file_id) = settings.media_file_id {
-        match settings.media_type.as_deref() {
-            Some("photo") => {
-                bot.send_photo(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("video") => {
-                bot.send_video(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("animation") => {
-                bot.send_animation(chat.id, InputFile::file_id(file_id))
-                    .caption(formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
-            Some("sticker") => {
-                // Send sticker first, then the message
-                bot.send_sticker(chat.id, InputFile::file_id(file_id))
-                    .await?;
-                if !formatted_text.is_empty() {
-                    bot.send_message(chat.id, formatted_text)
-                        .parse_mode(ParseMode::Html)
-                        .reply_markup(keyboard)
-                        .await?;
-                }
-            }
-            _ => {
-                // Unknown media type, send as document or just text
-                bot.send_message(chat.id, formatted_text)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .await?;
-            }
+    let mut keyboard = build_welcome_keyboard(&buttons);
+
+    // Mute until the member proves they're human. Without the rights to
+    // restrict, the welcome goes out ungated. Telegram keeps restrictions
+    // across a leave and rejoin, so a member an admin muted is left as they
+    // are: the verify button would lift the admin's mute.
+    let admin_restricted = update.new_chat_member.is_restricted()
+        && !state.welcome.is_pending_verification(chat.id.0, user.id.0);
+    let gated = settings.welcome_mute
+        && !admin_restricted
+        && bot
+            .restrict_chat_member(chat.id, user.id, ChatPermissions::empty())
+            .await
+            .is_ok();
+    if gated {
+        keyboard = keyboard.append_row(vec![InlineKeyboardButton::callback(
+            get_text(&locale, "welcome.verify_button"),
+            format!("{}{}:{}", VERIFY_CALLBACK_PREFIX, chat.id, user.id),
+        )]);
+        let kick_at = now + VERIFY_TIMEOUT.as_secs() as i64;
+        if let Err(e) = state.welcome.mark_pending_verification(chat.id.0, user.id.0, kick_at).await {
+            warn!("Failed to store pending verification for {} in chat {}: {}", user.id, chat.id, e);
         }
-    } else {
-        // Text only welcome
-        bot.send_message(chat.id, formatted_text)
-            .parse_mode(ParseMode::Html)
-            .reply_markup(keyboard)
-            .await?;
+    } else if settings.welcome_mute && admin_restricted {
+        debug!("Member {} is already restricted in chat {}, not gating", user.id, chat.id);
+    } else if settings.welcome_mute {
+        debug!("Can't restrict in chat {}, sending welcome without verification", chat.id);
+    }
+
+    // Send welcome message (with or without media). Deliberately not a
+    // reply: the join service message may already be gone, or be deleted
+    // right after, and the welcome must survive either way.
+    let content = Content::new(formatted_text.clone(), format.parse_mode())
+        .media(welcome.media_type.as_deref(), welcome.media_file_id.as_deref())
+        .keyboard(keyboard.clone());
+    let send = send_content(&bot, chat.id, content);
+    // A plain welcome waits out an outage, but a gated member needs the
+    // verify button, and the restrict just went through anyway
+    let sent = if gated { send.await.map(Some) } else { state.circuit.send(send).await };
+    let sent = match sent {
+        Ok(Some(sent)) => sent,
+        Ok(None) => {
+            debug!("Circuit open, skipping welcome in chat {}", chat.id);
+            return Ok(());
+        }
+        // A stale media file ID shouldn't leave the member ungreeted
+        Err(e) if welcome.media_file_id.is_some() && is_invalid_media(&e) => {
+            warn!("Welcome media in chat {} is no longer valid, sending text only: {}", chat.id, e);
+            let content = Content::new(formatted_text, format.parse_mode()).keyboard(keyboard);
+            send_content(&bot, chat.id, content).await?
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only a welcome that actually went out starts the cooldown
+    state.welcome.mark_welcomed(chat.id.0, user.id.0, now);
+
+    if settings.clean_welcome {
+        clean_previous_welcome(&bot, &state, chat.id, LastWelcome { message_ids: sent, user_id: user.id.0 }).await;
+    }
+
+    if gated {
+        spawn_verify_timeout(bot.clone(), state.clone(), chat.id, user.id, VERIFY_TIMEOUT);
     }
 
     info!(
@@ -138,3 +206,69 @@ async fn welcome_handler(
     Ok(())
 }
 
+/// Delete the chat's previous welcome now that `welcome` replaced it.
+///
+/// A welcome whose member still has to press its verify button is kept, or
+/// they'd have no way to verify before the timeout.
+async fn clean_previous_welcome(bot: &ThrottledBot, state: &AppState, chat_id: ChatId, welcome: LastWelcome) {
+    let Some(previous) = state.welcome.replace_last_welcome(chat_id.0, welcome) else {
+        return;
+    };
+    if state.welcome.is_pending_verification(chat_id.0, previous.user_id) {
+        return;
+    }
+
+    // Missing delete rights or an already-deleted message aren't worth failing over
+    if let Err(e) = bot.delete_messages(chat_id, previous.message_ids).await {
+        debug!("Failed to delete previous welcome in chat {}: {}", chat_id, e);
+    }
+}
+
+/// Kick a muted member who still hasn't verified after `delay`.
+fn spawn_verify_timeout(bot: ThrottledBot, state: AppState, chat_id: ChatId, user_id: UserId, delay: Duration) {
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+
+        // Verified (or already handled) in the meantime
+        match state.welcome.take_pending_verification(chat_id.0, user_id.0).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Failed to check pending verification for {} in chat {}: {}", user_id, chat_id, e);
+                return;
+            }
+        }
+
+        state.bye.mark_removed(chat_id.0, user_id.0);
+        match kick_member(&bot, chat_id, user_id).await {
+            Ok(()) => info!("Kicked unverified member {} from chat {}", user_id, chat_id),
+            Err(e) => warn!("Failed to kick unverified member {} from chat {}: {}", user_id, chat_id, e),
+        }
+    });
+}
+
+/// Restart the timeout of every member still pending verification.
+///
+/// Called once at startup; members whose time ran out while the bot was
+/// down are kicked right away.
+pub fn rearm_verify_timeouts(bot: ThrottledBot, state: AppState) {
+    tokio::spawn(async move {
+        let pending = match state.welcome.load_pending_verifications().await {
+            Ok(pending) => pending,
+            Err(e) => {
+                warn!("Failed to load pending verifications: {}", e);
+                return;
+            }
+        };
+        if !pending.is_empty() {
+            info!("Re-arming {} pending verification timeouts", pending.len());
+        }
+
+        let now = state.clock.now().timestamp();
+        for entry in pending {
+            let delay = Duration::from_secs(entry.kick_at.saturating_sub(now).max(0) as u64);
+            spawn_verify_timeout(bot.clone(), state.clone(), ChatId(entry.chat_id), UserId(entry.user_id), delay);
+        }
+    });
+}
+
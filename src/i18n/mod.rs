@@ -41,25 +41,44 @@ pub fn get_text(lang: &str, key: &str) -> String {
     let store = store.unwrap();
 
     // Try requested language
-    if let Some(val) = store.get(lang) {
-        if let Some(text) = resolve_key(val, key) {
+    if let Some(val) = store.get(lang)
+        && let Some(text) = resolve_key(val, key) {
             return text;
         }
-    }
 
     // Fallback to "en"
-    if lang != "en" {
-        if let Some(val) = store.get("en") {
-            if let Some(text) = resolve_key(val, key) {
-                return text;
-            }
+    if lang != "en"
+        && let Some(val) = store.get("en")
+        && let Some(text) = resolve_key(val, key) {
+            return text;
         }
-    }
 
     // Key not found
     key.to_string()
 }
 
+/// Codes of the loaded languages, sorted.
+pub fn available_locales() -> Vec<String> {
+    let mut langs: Vec<String> = TRANSLATIONS
+        .get()
+        .map(|store| store.keys().cloned().collect())
+        .unwrap_or_default();
+    langs.sort();
+    langs
+}
+
+/// Whether `lang` is one of the loaded languages.
+pub fn is_available(lang: &str) -> bool {
+    TRANSLATIONS.get().is_some_and(|store| store.contains_key(lang))
+}
+
+/// Loaded language for a Telegram `language_code` (IETF tag such as
+/// "en-US"), matched on the primary subtag.
+pub fn locale_from_language_code(code: &str) -> Option<String> {
+    let primary = code.split(['-', '_']).next()?.to_lowercase();
+    is_available(&primary).then_some(primary)
+}
+
 fn resolve_key(val: &Value, key: &str) -> Option<String> {
     let mut current = val;
     for part in key.split('.') {
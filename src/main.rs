@@ -5,6 +5,7 @@
 //! ## Architecture
 //!
 //! - `config` - Environment configuration
+//! - `constants` - Shared defaults and limits
 //! - `database` - MongoDB integration
 //! - `cache` - LRU-based caching with Moka
 //! - `permissions` - Admin checking with caching
@@ -16,6 +17,7 @@
 mod bot;
 mod cache;
 mod config;
+mod constants;
 mod database;
 mod events;
 mod permissions;
@@ -24,10 +26,11 @@ mod utils;
 pub mod i18n;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use teloxide::adaptors::throttle::Limits;
 use teloxide::prelude::*;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
 use cache::CacheRegistry;
@@ -69,7 +72,13 @@ async fn main() -> anyhow::Result<()> {
 
     // Connect to MongoDB
     info!("Connecting to MongoDB...");
-    let db = Database::connect(&config.mongodb_uri, &config.mongodb_database).await?;
+    let db = Database::connect(
+        &config.mongodb_uri,
+        &config.mongodb_database,
+        config.mongodb_connect_attempts,
+        Duration::from_millis(config.mongodb_retry_base_ms),
+    )
+    .await?;
     let db = Arc::new(db);
     info!("Database connected");
 
@@ -102,10 +111,22 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Build dispatcher
-    let dispatcher = bot::build_dispatcher(bot.clone(), db, cache, config.owner_ids.clone(), bot_username);
+    let (dispatcher, state) = bot::build_dispatcher(
+        bot.clone(),
+        db.clone(),
+        cache,
+        config.owner_ids.clone(),
+        bot_username,
+        Duration::from_secs(config.afk_cooldown_secs),
+    );
 
     // Run the bot
-    bot::run(&config, dispatcher, bot).await;
+    bot::run(&config, dispatcher, bot, db).await;
+
+    // Write out trigger counts still buffered since the last periodic flush
+    if let Err(e) = state.filters.flush_hits().await {
+        warn!("Failed to flush filter hits on shutdown: {}", e);
+    }
 
     Ok(())
 }
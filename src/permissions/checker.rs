@@ -3,11 +3,44 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use dashmap::DashMap;
 use teloxide::prelude::*;
 use teloxide::types::{ChatId, ChatMember, ChatMemberKind, UserId};
 use tracing::debug;
 
 use crate::cache::{CacheConfig, CacheRegistry, TypedCache};
+use crate::constants::{CACHE_ADMIN_LIST_TTL_SECS, CACHE_ADMIN_TTI_SECS, CACHE_ADMIN_TTL_SECS};
+
+/// A right a command can require of its caller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// Any admin
+    Admin,
+    /// The group's creator
+    GroupOwner,
+    DeleteMessages,
+    RestrictMembers,
+    PromoteMembers,
+    ChangeInfo,
+    InviteUsers,
+    PinMessages,
+}
+
+impl Permission {
+    /// Name shown in the missing-permission error.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Admin => "Admin",
+            Self::GroupOwner => "GroupOwner",
+            Self::DeleteMessages => "CanDeleteMessages",
+            Self::RestrictMembers => "CanRestrictMembers",
+            Self::PromoteMembers => "CanPromoteMembers",
+            Self::ChangeInfo => "CanChangeInfo",
+            Self::InviteUsers => "CanInviteUsers",
+            Self::PinMessages => "CanPinMessages",
+        }
+    }
+}
 
 /// Cached admin information.
 #[derive(Clone, Debug)]
@@ -19,10 +52,8 @@ pub struct AdminInfo {
     pub can_restrict_members: bool,
     pub can_promote_members: bool,
     pub can_change_info: bool,
-    #[allow(dead_code)]
     pub can_invite_users: bool,
     pub can_pin_messages: bool,
-    #[allow(dead_code)]
     pub can_manage_chat: bool,
 }
 
@@ -82,8 +113,12 @@ type AdminCacheKey = (i64, u64); // (chat_id, user_id)
 pub struct Permissions {
     bot: Bot,
     cache: TypedCache<AdminCacheKey, Option<AdminInfo>>,
+    /// Full administrator lists, keyed by chat ID.
+    admin_lists: TypedCache<i64, Arc<Vec<ChatMember>>>,
     /// Bot owner IDs - these users have all permissions in all chats.
     owner_ids: Vec<u64>,
+    /// Lookups already made while handling the current update (see [`Self::scoped`]).
+    memo: Option<Arc<DashMap<AdminCacheKey, Option<AdminInfo>>>>,
 }
 
 impl Permissions {
@@ -94,11 +129,26 @@ impl Permissions {
         let cache = cache_registry.get_or_create(
             "admin_permissions",
             CacheConfig::with_capacity(10_000)
-                .ttl(Duration::from_secs(300)) // 5 minutes
-                .tti(Duration::from_secs(120)), // 2 minutes idle
+                .ttl(Duration::from_secs(CACHE_ADMIN_TTL_SECS))
+                .tti(Duration::from_secs(CACHE_ADMIN_TTI_SECS)),
         );
+        let admin_lists = cache_registry.get_or_create(
+            "admin_lists",
+            CacheConfig::with_capacity(1_000).ttl(Duration::from_secs(CACHE_ADMIN_LIST_TTL_SECS)),
+        );
+
+        Self { bot, cache, admin_lists, owner_ids, memo: None }
+    }
 
-        Self { bot, cache, owner_ids }
+    /// Create a checker for handling a single update.
+    ///
+    /// Repeated checks for the same user (e.g. actor and target in /warn)
+    /// reuse the first result without touching the shared cache again.
+    pub fn scoped(&self) -> Self {
+        Self {
+            memo: Some(Arc::new(DashMap::new())),
+            ..self.clone()
+        }
     }
 
     /// Check if a user is a bot owner.
@@ -124,19 +174,31 @@ impl Permissions {
 
         let cache_key = (chat_id.0, user_id.0);
 
+        // Already looked up while handling this update
+        if let Some(memo) = &self.memo
+            && let Some(known) = memo.get(&cache_key)
+        {
+            return Ok(known.clone());
+        }
+
         // Check cache first
-        if let Some(cached) = self.cache.get(&cache_key) {
+        let result = if let Some(cached) = self.cache.get(&cache_key) {
             debug!("Admin cache hit for user {} in chat {}", user_id, chat_id);
-            return Ok(cached);
-        }
+            cached
+        } else {
+            debug!("Admin cache miss for user {} in chat {}", user_id, chat_id);
 
-        debug!("Admin cache miss for user {} in chat {}", user_id, chat_id);
+            // Fetch from Telegram API
+            let result = self.fetch_admin_info(chat_id, user_id).await?;
 
-        // Fetch from Telegram API
-        let result = self.fetch_admin_info(chat_id, user_id).await?;
+            // Cache the result (including None for non-admins)
+            self.cache.insert(cache_key, result.clone());
+            result
+        };
 
-        // Cache the result (including None for non-admins)
-        self.cache.insert(cache_key, result.clone());
+        if let Some(memo) = &self.memo {
+            memo.insert(cache_key, result.clone());
+        }
 
         Ok(result)
     }
@@ -258,12 +320,83 @@ impl Permissions {
             .unwrap_or(false))
     }
 
+    /// Check if a user can invite users (create invite links).
+    /// Bot owners always return true.
+    pub async fn can_invite_users(
+        &self,
+        chat_id: ChatId,
+        user_id: UserId,
+    ) -> anyhow::Result<bool> {
+        if self.is_bot_owner(user_id) {
+            return Ok(true);
+        }
+        Ok(self
+            .get_admin_info(chat_id, user_id)
+            .await?
+            .map(|a| a.can_invite_users)
+            .unwrap_or(false))
+    }
+
+    /// Check if a user can manage the chat.
+    /// Bot owners always return true.
+    #[allow(dead_code)]
+    pub async fn can_manage_chat(
+        &self,
+        chat_id: ChatId,
+        user_id: UserId,
+    ) -> anyhow::Result<bool> {
+        if self.is_bot_owner(user_id) {
+            return Ok(true);
+        }
+        Ok(self
+            .get_admin_info(chat_id, user_id)
+            .await?
+            .map(|a| a.can_manage_chat)
+            .unwrap_or(false))
+    }
+
+    /// Check if a user has `permission` in a chat.
+    pub async fn has(
+        &self,
+        chat_id: ChatId,
+        user_id: UserId,
+        permission: Permission,
+    ) -> anyhow::Result<bool> {
+        match permission {
+            Permission::Admin => self.is_admin(chat_id, user_id).await,
+            Permission::GroupOwner => self.is_owner(chat_id, user_id).await,
+            Permission::DeleteMessages => self.can_delete_messages(chat_id, user_id).await,
+            Permission::RestrictMembers => self.can_restrict_members(chat_id, user_id).await,
+            Permission::PromoteMembers => self.can_promote_members(chat_id, user_id).await,
+            Permission::ChangeInfo => self.can_change_info(chat_id, user_id).await,
+            Permission::InviteUsers => self.can_invite_users(chat_id, user_id).await,
+            Permission::PinMessages => self.can_pin_messages(chat_id, user_id).await,
+        }
+    }
+
+    /// Get every administrator of a chat, including the bot if it is one.
+    ///
+    /// Cached briefly so repeated /admins calls don't hit the API.
+    pub async fn chat_administrators(&self, chat_id: ChatId) -> anyhow::Result<Arc<Vec<ChatMember>>> {
+        if let Some(admins) = self.admin_lists.get(&chat_id.0) {
+            return Ok(admins);
+        }
+
+        let admins = Arc::new(self.bot.get_chat_administrators(chat_id).await?);
+        self.admin_lists.insert(chat_id.0, admins.clone());
+        Ok(admins)
+    }
+
     /// Invalidate cached admin info for a user.
     ///
     /// Call this when admin status might have changed.
     pub fn invalidate(&self, chat_id: ChatId, user_id: UserId) {
         let cache_key = (chat_id.0, user_id.0);
         self.cache.invalidate(&cache_key);
+        self.admin_lists.invalidate(&chat_id.0);
+        if let Some(memo) = &self.memo {
+            memo.remove(&cache_key);
+        }
         debug!(
             "Invalidated admin cache for user {} in chat {}",
             user_id, chat_id
@@ -271,13 +404,13 @@ impl Permissions {
     }
 
     /// Invalidate all cached admin info for a chat.
-    ///
-    /// Note: This is expensive, use sparingly.
-    pub fn _invalidate_chat(&self, _chat_id: ChatId) {
-        // Note: Moka doesn't support prefix invalidation easily
-        // For now, we rely on TTL. In production, you might want
-        // to track keys per chat for targeted invalidation.
-        self.cache.invalidate_all();
-        debug!("Invalidated all admin cache");
+    pub fn invalidate_chat(&self, chat_id: ChatId) {
+        let chat = chat_id.0;
+        self.cache.invalidate_if(move |key| key.0 == chat);
+        self.admin_lists.invalidate(&chat);
+        if let Some(memo) = &self.memo {
+            memo.retain(|key, _| key.0 != chat);
+        }
+        debug!("Invalidated admin cache for chat {}", chat_id);
     }
 }
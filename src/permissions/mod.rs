@@ -27,4 +27,4 @@
 
 mod checker;
 
-pub use checker::Permissions;
+pub use checker::{Permission, Permissions};
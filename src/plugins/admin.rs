@@ -1,14 +1,15 @@
 //! Admin management commands.
 //!
-//! Commands for promoting and demoting group administrators.
+//! Commands for listing, promoting and demoting group administrators.
 
 use teloxide::prelude::*;
-use teloxide::types::{ChatAdministratorRights, ParseMode, ReplyParameters, UserId};
+use teloxide::types::{ChatAdministratorRights, ChatMember, ChatMemberKind, ParseMode, ReplyParameters, UserId};
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::utils::html_escape;
+use crate::utils::{get_target_from_msg, html_escape, require_admin};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
 
 /// Handle /promote command - promote a user to admin.
 ///
@@ -19,45 +20,18 @@ pub async fn promote_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, get_text(&locale, "admin.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
 
     // Check permission: can_promote_members
-    if !state.permissions.can_promote_members(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPromoteMembers"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PromoteMembers).await? else {
         return Ok(());
-    }
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Get target user (reply or @username/ID)
     let text = msg.text().unwrap_or("");
     let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
 
-    let (target_user_id, custom_title) = if let Some(reply) = msg.reply_to_message() {
-        // From reply
-        let target_id = reply.from.as_ref().map(|u| u.id);
-        let title = if parts.is_empty() { None } else { Some(parts.join(" ")) };
-        (target_id, title)
-    } else if !parts.is_empty() {
-        // From argument
-        let first_arg = parts[0];
-        let target_id = parse_user_id(first_arg);
-        let title = if parts.len() > 1 { Some(parts[1..].join(" ")) } else { None };
-        (target_id, title)
-    } else {
+    if msg.reply_to_message().is_none() && parts.is_empty() {
         bot.send_message(
             chat_id,
             get_text(&locale, "admin.promote_usage"),
@@ -66,12 +40,19 @@ pub async fn promote_command(
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
-    };
+    }
 
-    let target_user_id = match target_user_id {
-        Some(id) => id,
+    let (target_user_id, custom_title) = match get_target_from_msg(&bot, &msg, &state).await {
+        // Whatever follows the target is the custom title
+        Some((id, _, skip)) => {
+            let title = parts.get(skip..).filter(|rest| !rest.is_empty()).map(|rest| rest.join(" "));
+            (id, title)
+        }
         None => {
-            bot.send_message(chat_id, get_text(&locale, "admin.error_user_not_found"))
+            // Only the argument form can fail here; replies always carry a sender
+            let arg = msg.reply_to_message().is_none().then(|| parts.first().copied()).flatten();
+            bot.send_message(chat_id, user_not_found_text(&locale, arg))
+                .parse_mode(ParseMode::Html)
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
             return Ok(());
@@ -116,6 +97,9 @@ pub async fn promote_command(
                 let _ = bot.set_chat_administrator_custom_title(chat_id, target_user_id, &title).await;
             }
 
+            // Invalidate permissions cache
+            state.permissions.invalidate(chat_id, target_user_id);
+
             let title_msg = custom_title
                 .map(|t| format!(" ({})", html_escape(&t)))
                 .unwrap_or_default();
@@ -152,38 +136,18 @@ pub async fn demote_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, get_text(&locale, "admin.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
 
     // Check permission: can_promote_members
-    if !state.permissions.can_promote_members(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPromoteMembers"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PromoteMembers).await? else {
         return Ok(());
-    }
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Get target user
     let text = msg.text().unwrap_or("");
     let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
 
-    let target_user_id = if let Some(reply) = msg.reply_to_message() {
-        reply.from.as_ref().map(|u| u.id)
-    } else if !parts.is_empty() {
-        parse_user_id(parts[0])
-    } else {
+    if msg.reply_to_message().is_none() && parts.is_empty() {
         bot.send_message(
             chat_id,
             get_text(&locale, "admin.demote_usage"),
@@ -192,12 +156,15 @@ pub async fn demote_command(
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
-    };
+    }
 
-    let target_user_id = match target_user_id {
-        Some(id) => id,
+    let target_user_id = match get_target_from_msg(&bot, &msg, &state).await {
+        Some((id, _, _)) => id,
         None => {
-            bot.send_message(chat_id, get_text(&locale, "admin.error_user_not_found"))
+            // Only the argument form can fail here; replies always carry a sender
+            let arg = msg.reply_to_message().is_none().then(|| parts.first().copied()).flatten();
+            bot.send_message(chat_id, user_not_found_text(&locale, arg))
+                .parse_mode(ParseMode::Html)
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
             return Ok(());
@@ -243,14 +210,108 @@ pub async fn demote_command(
     Ok(())
 }
 
-/// Parse user ID from string (@username or numeric ID).
-fn parse_user_id(input: &str) -> Option<UserId> {
-    // Try numeric ID first
-    if let Ok(id) = input.parse::<u64>() {
-        return Some(UserId(id));
+/// Handle /admins command - list the chat's current administrators.
+///
+/// Anonymous admins are listed separately; 🔨 and ⭐ mark who can
+/// restrict members and who can promote.
+pub async fn admins_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        bot.send_message(chat_id, get_text(&locale, "admin.error_group_only"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let admins = state.permissions.chat_administrators(chat_id).await?;
+
+    let mut visible = Vec::new();
+    let mut anonymous = Vec::new();
+    // Owner first, then admins in the order Telegram returns them
+    for member in admins.iter().filter(|m| m.is_owner()).chain(admins.iter().filter(|m| !m.is_owner())) {
+        let line = format_admin_line(member);
+        if is_anonymous_admin(member) {
+            anonymous.push(line);
+        } else {
+            visible.push(line);
+        }
+    }
+
+    let mut text = get_text(&locale, "admin.admins_header")
+        .replace("{title}", &html_escape(msg.chat.title().unwrap_or("Grup")));
+    text.push_str("\n\n");
+    text.push_str(&visible.join("\n"));
+    if !anonymous.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(&get_text(&locale, "admin.admins_anonymous"));
+        text.push('\n');
+        text.push_str(&anonymous.join("\n"));
+    }
+    text.push_str("\n\n");
+    text.push_str(&get_text(&locale, "admin.admins_legend"));
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Whether an admin posts anonymously as the group.
+fn is_anonymous_admin(member: &ChatMember) -> bool {
+    match &member.kind {
+        ChatMemberKind::Owner(owner) => owner.is_anonymous,
+        ChatMemberKind::Administrator(admin) => admin.is_anonymous,
+        _ => false,
     }
+}
+
+/// Format one /admins entry: role marker, mention, custom title and rights.
+fn format_admin_line(member: &ChatMember) -> String {
+    let (marker, title, flags) = match &member.kind {
+        ChatMemberKind::Owner(owner) => ("👑 ", owner.custom_title.as_deref(), String::new()),
+        ChatMemberKind::Administrator(admin) => {
+            let mut flags = String::new();
+            if admin.can_restrict_members {
+                flags.push_str(" 🔨");
+            }
+            if admin.can_promote_members {
+                flags.push_str(" ⭐");
+            }
+            let marker = if member.user.is_bot { "🤖 " } else { "" };
+            (marker, admin.custom_title.as_deref(), flags)
+        }
+        _ => ("", None, String::new()),
+    };
+
+    let title = title
+        .filter(|t| !t.is_empty())
+        .map(|t| format!(" — <i>{}</i>", html_escape(t)))
+        .unwrap_or_default();
 
-    // @username - can't resolve directly without getChat/getChatMember
-    // For now, only support numeric IDs and reply
-    None
+    format!(
+        "• {}<a href=\"tg://user?id={}\">{}</a>{}{}",
+        marker,
+        member.user.id,
+        html_escape(&member.user.full_name()),
+        title,
+        flags
+    )
+}
+
+/// Error text for a target that couldn't be resolved.
+fn user_not_found_text(locale: &str, arg: Option<&str>) -> String {
+    match arg.filter(|a| a.starts_with('@')) {
+        Some(username) => get_text(locale, "admin.error_username_unresolved")
+            .replace("{username}", &html_escape(username)),
+        None => get_text(locale, "admin.error_user_not_found"),
+    }
 }
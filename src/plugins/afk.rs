@@ -5,17 +5,18 @@
 
 use teloxide::prelude::*;
 use teloxide::types::{
-    MessageId, ParseMode, ReplyParameters, MessageEntityKind,
+    ParseMode, ReplyParameters, MessageEntityKind,
 };
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::utils::{format_duration_full, html_escape};
+use crate::database::models::MessageContext;
+use crate::utils::{apply_fillings_new, format_duration_full, html_escape, send_content, Content};
 use crate::i18n::get_text;
 
 /// Handle /afk command - set AFK status.
 ///
-/// Usage: /afk [reason]
+/// Usage: /afk [reason] (reply to media to show it with AFK notices)
 pub async fn afk_command(
     bot: ThrottledBot,
     msg: Message,
@@ -34,16 +35,21 @@ pub async fn afk_command(
         .split_once(char::is_whitespace)
         .map(|(_, rest)| rest.trim().to_string())
         .filter(|s| !s.is_empty());
+    let media = msg.reply_to_message().and_then(extract_media);
 
     // Save AFK status (Updates Cache & DB)
-    state.users.set_afk(user_id, reason.clone()).await?;
+    state.users.set_afk(user_id, reason.clone(), media).await?;
 
     info!("User {} went AFK in chat {}", user_id, chat_id);
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id)).await;
-    
+    let chat_name = msg.chat.title().unwrap_or("");
+
     let reason_text = reason
-        .map(|r| get_text(&locale, "afk.reason").replace("{reason}", &html_escape(&r)))
+        .map(|r| {
+            get_text(&locale, "afk.reason")
+                .replace("{reason}", &apply_fillings_new(&html_escape(&r), user, chat_name, None))
+        })
         .unwrap_or_default();
 
     bot.send_message(
@@ -69,11 +75,30 @@ pub async fn brb_command(
     afk_command(bot, msg, state).await
 }
 
+/// Extract a media `(file_id, media_type)` to show with AFK notices.
+fn extract_media(msg: &Message) -> Option<(String, String)> {
+    if let Some(photo) = msg.photo() {
+        let largest = photo.iter().max_by_key(|p| p.width * p.height)?;
+        Some((largest.file.id.clone(), "photo".to_string()))
+    } else if let Some(video) = msg.video() {
+        Some((video.file.id.clone(), "video".to_string()))
+    } else if let Some(animation) = msg.animation() {
+        Some((animation.file.id.clone(), "animation".to_string()))
+    } else if let Some(document) = msg.document() {
+        Some((document.file.id.clone(), "document".to_string()))
+    } else {
+        msg.sticker().map(|sticker| (sticker.file.id.clone(), "sticker".to_string()))
+    }
+}
+
 /// AFK handler - detect replies/mentions to AFK users and auto-remove AFK.
+///
+/// Called from the unified message handler with the chat's preloaded context.
 pub async fn afk_handler(
-    bot: ThrottledBot,
-    msg: Message,
-    state: AppState,
+    bot: &ThrottledBot,
+    msg: &Message,
+    state: &AppState,
+    ctx: &MessageContext,
 ) -> anyhow::Result<()> {
     // Only process in groups
     if !msg.chat.is_group() && !msg.chat.is_supergroup() {
@@ -90,33 +115,34 @@ pub async fn afk_handler(
     // 1. Check if current user is AFK (Auto-Remove)
     // We check cache first
     let current_user_data = state.users.get_by_id(user_id).await?;
-    if let Some(data) = current_user_data {
-        if let Some(reason) = &data.afk_reason {
+    if let Some(data) = current_user_data
+        && let Some(reason) = &data.afk_reason {
             // User is AFK, remove it
             let duration_secs = data.afk_time.map(|t| chrono::Utc::now().timestamp() - t).unwrap_or(0) as u64;
             let duration = format_duration_full(duration_secs);
             
             state.users.remove_afk(user_id).await?;
 
-            let locale = state.get_locale(Some(chat_id.0), Some(user_id)).await;
+            let locale = state.get_locale_in(ctx, Some(user_id)).await;
 
+            let chat_name = msg.chat.title().unwrap_or("");
             let reason_text = get_text(&locale, "afk.reason")
-                .replace("{reason}", &html_escape(reason));
-
-            bot.send_message(
-                chat_id,
-                get_text(&locale, "afk.returned_afk")
-                    .replace("{id}", &user_id.to_string())
-                    .replace("{name}", &html_escape(&user.first_name))
-                    .replace("{reason}", &reason_text)
-                    .replace("{duration}", &duration),
-            )
-            .parse_mode(ParseMode::Html)
-            .disable_notification(true)
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+                .replace("{reason}", &apply_fillings_new(&html_escape(reason), user, chat_name, None));
+
+            let notice = bot
+                .send_message(
+                    chat_id,
+                    get_text(&locale, "afk.returned_afk")
+                        .replace("{id}", &user_id.to_string())
+                        .replace("{name}", &html_escape(&user.first_name))
+                        .replace("{reason}", &reason_text)
+                        .replace("{duration}", &duration),
+                )
+                .parse_mode(ParseMode::Html)
+                .disable_notification(true)
+                .reply_parameters(ReplyParameters::new(msg.id));
+            state.circuit.send(notice).await?;
         }
-    }
 
     // Track which user IDs we've already notified about (to avoid duplicates)
     let mut notified_users: std::collections::HashSet<u64> = std::collections::HashSet::new();
@@ -126,12 +152,11 @@ pub async fn afk_handler(
         && let Some(reply_user) = &reply.from {
             let reply_user_id = reply_user.id.0;
             // Fetch replied user data
-                    if let Ok(Some(target)) = state.users.get_by_id(reply_user_id).await {
-                if target.afk_reason.is_some() && !notified_users.contains(&reply_user_id) {
-                    send_afk_notification(&bot, chat_id, msg.id, &target, &state).await?;
+            if let Ok(Some(target)) = state.users.get_by_id(reply_user_id).await
+                && target.afk_reason.is_some() && !notified_users.contains(&reply_user_id) {
+                    send_afk_notification(bot, msg, &target, state, ctx).await?;
                     notified_users.insert(reply_user_id);
                 }
-            }
         }
 
     // 3. Check Mentions in message text
@@ -143,12 +168,11 @@ pub async fn afk_handler(
                 // TextMention (Clickable Name)
                 MessageEntityKind::TextMention { user: mentioned_user } => {
                     let mentioned_user_id = mentioned_user.id.0;
-                    if let Ok(Some(target)) = state.users.get_by_id(mentioned_user_id).await {
-                        if target.afk_reason.is_some() && !notified_users.contains(&mentioned_user_id) {
-                            send_afk_notification(&bot, chat_id, msg.id, &target, &state).await?;
+                    if let Ok(Some(target)) = state.users.get_by_id(mentioned_user_id).await
+                        && target.afk_reason.is_some() && !notified_users.contains(&mentioned_user_id) {
+                            send_afk_notification(bot, msg, &target, state, ctx).await?;
                             notified_users.insert(mentioned_user_id);
                         }
-                    }
                 },
                 // @username Mention
                 MessageEntityKind::Mention => {
@@ -158,12 +182,11 @@ pub async fn afk_handler(
                         let username = mention_text.trim_start_matches('@');
                         
                         // Resolve username -> UserData (Includes AFK status!)
-                        if let Ok(Some(target)) = state.users.get_by_username(username).await {
-                             if target.afk_reason.is_some() && !notified_users.contains(&target.user_id) {
-                                send_afk_notification(&bot, chat_id, msg.id, &target, &state).await?;
+                        if let Ok(Some(target)) = state.users.get_by_username(username).await
+                            && target.afk_reason.is_some() && !notified_users.contains(&target.user_id) {
+                                send_afk_notification(bot, msg, &target, state, ctx).await?;
                                 notified_users.insert(target.user_id);
                             }
-                        }
                     }
                 },
                 _ => {}
@@ -174,36 +197,53 @@ pub async fn afk_handler(
     Ok(())
 }
 
+/// Tell the chat that `user` is AFK, with their reason and media.
 async fn send_afk_notification(
     bot: &ThrottledBot,
-    chat_id: teloxide::types::ChatId,
-    reply_to_msg_id: MessageId,
+    msg: &Message,
     user: &crate::database::CachedUser,
     state: &AppState,
+    ctx: &MessageContext,
 ) -> anyhow::Result<()> {
+    let chat_id = ChatId(ctx.chat_id);
+
+    // Many mentions in a row would otherwise get a notice each
+    if let Some(notices) = &state.afk_notices {
+        let key = (ctx.chat_id, user.user_id);
+        if notices.contains(&key) {
+            return Ok(());
+        }
+        notices.insert(key, ());
+    }
+
     let duration_secs = user.afk_time.map(|t| chrono::Utc::now().timestamp() - t).unwrap_or(0) as u64;
     let duration = format_duration_full(duration_secs);
     
-    // Resolve locale - we can use chat_id default because we are notifying the group
-    let locale = state.get_locale(Some(chat_id.0), None).await;
+    // Resolve locale - we can use the group default because we are notifying the group
+    let locale = state.get_locale_in(ctx, None).await;
 
+    // Placeholders in the reason refer to the AFK user
+    let chat_name = msg.chat.title().unwrap_or("");
     let reason_text = user.afk_reason
         .as_ref()
-        .map(|r| get_text(&locale, "afk.reason").replace("{reason}", &html_escape(r)))
+        .map(|r| {
+            get_text(&locale, "afk.reason").replace(
+                "{reason}",
+                &apply_fillings_new(&html_escape(r), &user.to_telegram(), chat_name, None),
+            )
+        })
         .unwrap_or_default();
 
-    bot.send_message(
-        chat_id,
-        get_text(&locale, "afk.is_afk")
-            .replace("{id}", &user.user_id.to_string())
-            .replace("{name}", &html_escape(&user.first_name))
-            .replace("{reason}", &reason_text)
-            .replace("{duration}", &duration),
-    )
-    .parse_mode(ParseMode::Html)
-    .disable_notification(true)
-    .reply_parameters(ReplyParameters::new(reply_to_msg_id))
-    .await?;
+    let text = get_text(&locale, "afk.is_afk")
+        .replace("{id}", &user.user_id.to_string())
+        .replace("{name}", &html_escape(&user.first_name))
+        .replace("{reason}", &reason_text)
+        .replace("{duration}", &duration);
+
+    let content = Content::new(text, ParseMode::Html)
+        .media(user.afk_media_type.as_deref(), user.afk_media_file_id.as_deref())
+        .reply_to(msg.id);
+    state.circuit.send(send_content(bot, chat_id, content)).await?;
 
     Ok(())
 }
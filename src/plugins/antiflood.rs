@@ -2,14 +2,22 @@
 //!
 //! Commands for configuring antiflood protection in groups.
 
+use std::time::Duration;
 
 use teloxide::prelude::*;
-use teloxide::types::{ParseMode, ReplyParameters};
+use teloxide::types::{ChatPermissions, ParseMode, ReplyParameters};
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::FloodPenalty;
+use crate::constants::{DEFAULT_FLOOD_PENALTY_SECS, MAX_JOIN_GRACE_SECS, MIN_TEMP_RESTRICTION, PERMANENT_RESTRICTION_MARKER};
+use crate::database::{AntifloodConfig, FloodPenalty};
+use crate::events::FloodTracker;
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{get_target_from_msg, html_escape, parse_duration, require_admin, restrict_until};
+
+/// Highest weight a sticker or media message can be given.
+const MAX_FLOOD_WEIGHT: u32 = 10;
 
 /// Handle /antiflood command - show or toggle antiflood.
 pub async fn antiflood_command(
@@ -18,36 +26,15 @@ pub async fn antiflood_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
-    };
 
-    // Check if in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "antiflood.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    // Check admin permission (can_change_info)
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
-    
+    };
+
     // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    // Check admin permission (can_change_info)
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(chat_id, get_text(&locale, "antiflood.error_permission"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
-
     let mut ctx = state.message_context.get_or_default(chat_id.0).await?;
 
     // Parse arguments
@@ -56,16 +43,7 @@ pub async fn antiflood_command(
 
     if args.is_empty() {
         // Show current status
-        let status = if ctx.antiflood.enabled {
-            get_text(&locale, "antiflood.status_enabled")
-                .replace("{limit}", &ctx.antiflood.max_messages.to_string())
-                .replace("{seconds}", &ctx.antiflood.time_window_secs.to_string())
-                .replace("{warns}", &ctx.antiflood.warnings_before_penalty.to_string())
-                .replace("{penalty}", &penalty_to_string(&ctx.antiflood.penalty, &locale))
-                .replace("{duration}", &duration_to_string(ctx.antiflood.penalty_duration_secs, &locale))
-        } else {
-            get_text(&locale, "antiflood.status_disabled")
-        };
+        let status = format_status(&ctx.antiflood, &locale);
 
         bot.send_message(chat_id, status)
             .parse_mode(ParseMode::Html)
@@ -91,6 +69,104 @@ pub async fn antiflood_command(
                 .await?;
             info!("Antiflood disabled in chat {}", chat_id);
         }
+        "topics" => {
+            // Toggle per-topic tracking for forum groups
+            let per_topic = match args.get(1).map(|a| a.to_lowercase()) {
+                Some(a) if a == "on" => true,
+                Some(a) if a == "off" => false,
+                _ => !ctx.antiflood.per_topic,
+            };
+            ctx.antiflood.per_topic = per_topic;
+            state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+            let key = if per_topic { "antiflood.topics_enabled" } else { "antiflood.topics_disabled" };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+        "grace" => {
+            // Raised limit for members who just joined
+            let grace = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+                Some("off" | "0") => Some(0),
+                Some(arg) => parse_duration(arg)
+                    .map(|d| d.as_secs())
+                    .filter(|&secs| secs > 0 && secs <= MAX_JOIN_GRACE_SECS),
+                None => None,
+            };
+            let Some(grace) = grace else {
+                bot.send_message(chat_id, get_text(&locale, "antiflood.grace_usage"))
+                    .parse_mode(ParseMode::Html)
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                return Ok(());
+            };
+            ctx.antiflood.join_grace_secs = grace;
+            state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+            let text = if grace == 0 {
+                get_text(&locale, "antiflood.grace_off")
+            } else {
+                get_text(&locale, "antiflood.grace_on").replace("{duration}", &duration_to_string(grace, &locale))
+            };
+            bot.send_message(chat_id, text)
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+        "weight" => {
+            // How much stickers/GIFs and other media count: /antiflood weight <sticker|media> <n>
+            let weight = args
+                .get(2)
+                .and_then(|a| a.parse::<u32>().ok())
+                .filter(|n| (1..=MAX_FLOOD_WEIGHT).contains(n));
+            let target = match (args.get(1).map(|a| a.to_lowercase()).as_deref(), weight) {
+                (Some("sticker" | "stickers" | "gif"), Some(weight)) => {
+                    Some((&mut ctx.antiflood.sticker_weight, weight, "antiflood.weight_sticker_set"))
+                }
+                (Some("media"), Some(weight)) => {
+                    Some((&mut ctx.antiflood.media_weight, weight, "antiflood.weight_media_set"))
+                }
+                _ => None,
+            };
+            let Some((field, weight, key)) = target else {
+                bot.send_message(
+                    chat_id,
+                    get_text(&locale, "antiflood.weight_usage")
+                        .replace("{sticker}", &ctx.antiflood.sticker_weight.to_string())
+                        .replace("{media}", &ctx.antiflood.media_weight.to_string())
+                        .replace("{max}", &MAX_FLOOD_WEIGHT.to_string()),
+                )
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+                return Ok(());
+            };
+            *field = weight;
+            state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+            bot.send_message(chat_id, get_text(&locale, key).replace("{weight}", &weight.to_string()))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+        "notify" => {
+            // Alert admins alongside the penalty ("on") or instead of it ("only")
+            let (notify, only, key) = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+                Some("on") => (true, false, "antiflood.notify_on"),
+                Some("only") => (true, true, "antiflood.notify_only"),
+                Some("off") => (false, false, "antiflood.notify_off"),
+                _ => {
+                    bot.send_message(chat_id, get_text(&locale, "antiflood.usage"))
+                        .parse_mode(ParseMode::Html)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    return Ok(());
+                }
+            };
+            ctx.antiflood.notify_admins = notify;
+            ctx.antiflood.notify_only = only;
+            state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+            bot.send_message(chat_id, get_text(&locale, key))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
         _ => {
             bot.send_message(
                 chat_id,
@@ -112,30 +188,12 @@ pub async fn setflood_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
-    };
-
-    // Check permissions
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(chat_id, get_text(&locale, "antiflood.error_permission"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
-
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().skip(1).collect();
 
@@ -188,33 +246,116 @@ pub async fn setflood_command(
     Ok(())
 }
 
-/// Handle /setfloodpenalty command.
-pub async fn setfloodpenalty_command(
+/// Handle /floodtrust command - raise the flood limit for trusted users.
+///
+/// Usage:
+/// - /floodtrust - list trusted users
+/// - /floodtrust <reply|user_id|@username> - trust or untrust a user
+/// - /floodtrust multiplier <n> - set the limit multiplier
+pub async fn floodtrust_command(
     bot: ThrottledBot,
     msg: Message,
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
-    // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(chat_id, get_text(&locale, "antiflood.error_permission"))
+    let mut ctx = state.message_context.get_or_default(chat_id.0).await?;
+    let text = msg.text().unwrap_or("");
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+
+    if args.first().is_some_and(|a| a.eq_ignore_ascii_case("multiplier")) {
+        let multiplier: u32 = match args.get(1).map(|a| a.parse()) {
+            Some(Ok(n)) if (2..=10).contains(&n) => n,
+            _ => {
+                bot.send_message(chat_id, get_text(&locale, "antiflood.error_trust_multiplier"))
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                return Ok(());
+            }
+        };
+        ctx.antiflood.trusted_multiplier = multiplier;
+        state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+        bot.send_message(
+            chat_id,
+            get_text(&locale, "antiflood.trust_multiplier_set").replace("{multiplier}", &multiplier.to_string()),
+        )
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+        return Ok(());
+    }
+
+    if args.is_empty() && msg.reply_to_message().is_none() {
+        // List trusted users
+        let mut list = if ctx.antiflood.trusted_users.is_empty() {
+            get_text(&locale, "antiflood.trust_list_empty")
+        } else {
+            let mut list = get_text(&locale, "antiflood.trust_list_header");
+            for (i, id) in ctx.antiflood.trusted_users.iter().enumerate() {
+                list.push_str(&format!("{}. <a href=\"tg://user?id={}\">{}</a>\n", i + 1, id, id));
+            }
+            list
+        };
+        list.push_str(
+            &get_text(&locale, "antiflood.trust_list_footer")
+                .replace("{multiplier}", &ctx.antiflood.trusted_multiplier.to_string())
+                .replace("{limit}", &ctx.antiflood.max_messages.to_string())
+                .replace("{trusted_limit}", &ctx.antiflood.trusted_limit().to_string()),
+        );
+        bot.send_message(chat_id, list)
+            .parse_mode(ParseMode::Html)
             .reply_parameters(ReplyParameters::new(msg.id))
             .await?;
         return Ok(());
     }
 
+    let Some((target_id, target_name, _)) = get_target_from_msg(&bot, &msg, &state).await else {
+        bot.send_message(chat_id, get_text(&locale, "antiflood.trust_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    let trusted = ctx.antiflood.toggle_trusted(target_id.0);
+    let limit = ctx.antiflood.limit_for(target_id.0);
+    state.message_context.update_antiflood(chat_id.0, ctx.antiflood).await?;
+
+    let key = if trusted { "antiflood.trusted" } else { "antiflood.untrusted" };
+    bot.send_message(
+        chat_id,
+        get_text(&locale, key)
+            .replace("{id}", &target_id.to_string())
+            .replace("{name}", &html_escape(&target_name))
+            .replace("{limit}", &limit.to_string()),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
+    info!("Flood trust for {} in chat {} set to {}", target_id, chat_id, trusted);
+
+    Ok(())
+}
+
+/// Handle /setfloodpenalty command.
+pub async fn setfloodpenalty_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().skip(1).collect();
 
@@ -243,10 +384,19 @@ pub async fn setfloodpenalty_command(
         }
     };
 
-    let duration_secs = if args.len() > 1 {
-        parse_duration(args[1]).unwrap_or(300)
-    } else {
-        300 // Default 5 minutes
+    // 0 keeps a mute permanent; anything else shorter than Telegram's
+    // minimum would silently become permanent, so it's raised to it
+    let duration_secs = match args.get(1).map(|arg| parse_duration(arg)) {
+        None => DEFAULT_FLOOD_PENALTY_SECS,
+        Some(Some(d)) if d.is_zero() && penalty == FloodPenalty::Mute => PERMANENT_RESTRICTION_MARKER,
+        Some(Some(d)) => d.max(MIN_TEMP_RESTRICTION).as_secs(),
+        Some(None) => {
+            bot.send_message(chat_id, get_text(&locale, "antiflood.setpenalty_usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
     };
 
     let mut ctx = state.message_context.get_or_default(chat_id.0).await?;
@@ -267,22 +417,187 @@ pub async fn setfloodpenalty_command(
     Ok(())
 }
 
-/// Parse duration string like "5m", "1h", "30s" to seconds.
-fn parse_duration(s: &str) -> Option<u64> {
-    let s = s.to_lowercase();
-    if let Some(num) = s.strip_suffix('s') {
-        return num.parse().ok();
+/// Handle /floodstatus command - settings plus live flood tracking.
+///
+/// Shows what antiflood is configured to do next to what the in-memory
+/// tracker currently sees, to explain why it is or isn't acting.
+pub async fn floodstatus_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+    flood_tracker: FloodTracker,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+
+    let (tracked, warned) = flood_tracker.chat_stats(chat_id.0);
+    let text = format!(
+        "{}{}",
+        format_status(&ctx.antiflood, &locale),
+        get_text(&locale, "antiflood.runtime_stats")
+            .replace("{tracked}", &tracked.to_string())
+            .replace("{warned}", &warned.to_string()),
+    );
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Handle the mute/ignore buttons on a flood alert.
+///
+/// Callback data: `flood:<mute|ignore>:<chat_id>:<user_id>`.
+pub async fn flood_callback_handler(
+    bot: ThrottledBot,
+    q: CallbackQuery,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let Some(data) = q.data.as_deref() else {
+        return Ok(());
+    };
+
+    let parts: Vec<&str> = data.split(':').collect();
+    let (action, chat_id, target_id) = match parts.as_slice() {
+        ["flood", action, chat, user] => (
+            *action,
+            chat.parse::<i64>().unwrap_or(0),
+            user.parse::<u64>().unwrap_or(0),
+        ),
+        _ => ("", 0, 0),
+    };
+
+    let locale = state.get_locale(Some(chat_id), Some(q.from.id.0)).await;
+
+    if chat_id == 0 || target_id == 0 {
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "warn.callback_invalid_data"))
+            .await?;
+        return Ok(());
     }
-    if let Some(num) = s.strip_suffix('m') {
-        return num.parse::<u64>().ok().map(|n| n * 60);
+
+    let chat_id = ChatId(chat_id);
+    if !state
+        .permissions
+        .can_restrict_members(chat_id, q.from.id)
+        .await
+        .unwrap_or(false)
+    {
+        bot.answer_callback_query(&q.id)
+            .text(
+                get_text(&locale, "common.error_missing_permission")
+                    .replace("{permission}", "CanRestrictMembers"),
+            )
+            .show_alert(true)
+            .await?;
+        return Ok(());
     }
-    if let Some(num) = s.strip_suffix('h') {
-        return num.parse::<u64>().ok().map(|n| n * 3600);
+
+    let key = match action {
+        "mute" => {
+            let ctx = state.message_context.get_or_default(chat_id.0).await?;
+            let duration = ctx.antiflood.penalty_duration_secs;
+            let mut request = bot.restrict_chat_member(chat_id, UserId(target_id), ChatPermissions::empty());
+            if duration != PERMANENT_RESTRICTION_MARKER {
+                let duration = Duration::from_secs(duration).max(MIN_TEMP_RESTRICTION);
+                request = request.until_date(restrict_until(state.clock.as_ref(), duration));
+            }
+            if request.await.is_err() {
+                bot.answer_callback_query(&q.id)
+                    .text(get_text(&locale, "antiflood.notify_mute_failed"))
+                    .show_alert(true)
+                    .await?;
+                return Ok(());
+            }
+            info!("Flood alert: {} muted {} in chat {}", q.from.id, target_id, chat_id);
+            "antiflood.notify_muted"
+        }
+        "ignore" => "antiflood.notify_ignored",
+        _ => {
+            bot.answer_callback_query(&q.id)
+                .text(get_text(&locale, "warn.callback_invalid_data"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let target_name = match state.users.get_by_id(target_id).await {
+        Ok(Some(user)) => user.first_name,
+        _ => format!("User {}", target_id),
+    };
+    let text = get_text(&locale, key)
+        .replace("{admin}", &format!(
+            "<a href=\"tg://user?id={}\">{}</a>",
+            q.from.id,
+            html_escape(&q.from.first_name)
+        ))
+        .replace("{target}", &format!(
+            "<a href=\"tg://user?id={}\">{}</a>",
+            target_id,
+            html_escape(&target_name)
+        ));
+
+    // Replacing the text also drops the buttons, so the alert is handled once
+    if let Some(message) = &q.message {
+        let _ = bot
+            .edit_message_text(message.chat().id, message.id(), text)
+            .parse_mode(ParseMode::Html)
+            .await;
     }
-    if let Some(num) = s.strip_suffix('d') {
-        return num.parse::<u64>().ok().map(|n| n * 86400);
+
+    bot.answer_callback_query(&q.id).await?;
+
+    Ok(())
+}
+
+/// Format the chat's antiflood settings.
+fn format_status(config: &AntifloodConfig, locale: &str) -> String {
+    if config.enabled {
+        let mut status = get_text(locale, "antiflood.status_enabled")
+            .replace("{limit}", &config.max_messages.to_string())
+            .replace("{seconds}", &config.time_window_secs.to_string())
+            .replace("{warns}", &config.warnings_before_penalty.to_string())
+            .replace("{penalty}", &penalty_to_string(&config.penalty, locale))
+            .replace("{duration}", &duration_to_string(config.penalty_duration_secs, locale));
+        if config.notify_admins {
+            let key = if config.notify_only {
+                "antiflood.notify_status_only"
+            } else {
+                "antiflood.notify_status_on"
+            };
+            status.push_str(&get_text(locale, key));
+        }
+        if config.join_grace_secs > 0 {
+            status.push_str(
+                &get_text(locale, "antiflood.grace_status")
+                    .replace("{duration}", &duration_to_string(config.join_grace_secs, locale)),
+            );
+        }
+        if config.sticker_weight > 1 || config.media_weight > 1 {
+            status.push_str(
+                &get_text(locale, "antiflood.weight_status")
+                    .replace("{sticker}", &config.sticker_weight.to_string())
+                    .replace("{media}", &config.media_weight.to_string()),
+            );
+        }
+        if !config.trusted_users.is_empty() {
+            status.push_str(
+                &get_text(locale, "antiflood.trust_status")
+                    .replace("{count}", &config.trusted_users.len().to_string())
+                    .replace("{multiplier}", &config.trusted_multiplier.to_string()),
+            );
+        }
+        status
+    } else {
+        get_text(locale, "antiflood.status_disabled")
     }
-    s.parse().ok()
 }
 
 fn penalty_to_string(penalty: &FloodPenalty, locale: &str) -> String {
@@ -297,7 +612,7 @@ fn penalty_to_string(penalty: &FloodPenalty, locale: &str) -> String {
 }
 
 fn duration_to_string(secs: u64, locale: &str) -> String {
-    if secs == 0 {
+    if secs == PERMANENT_RESTRICTION_MARKER {
         return get_text(locale, "antiflood.duration_permanent");
     }
     if secs < 60 {
@@ -8,6 +8,8 @@ use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::require_admin;
 
 
 /// Handle /approve command - approve a user.
@@ -17,33 +19,15 @@ pub async fn approve_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id);
 
-    if user_id.is_none() {
-        return Ok(());
-    }
-    let admin_id = user_id.unwrap();
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        // Resolve locale (fall back to user lang as we don't have group context effectively if not a group, but safe to use 0)
-        let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "approval.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    // Check if user is admin
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::Admin).await? else {
         return Ok(());
-    }
+    };
 
     // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
-    // Check if user is admin
-    if !state.permissions.is_admin(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, get_text(&locale, "approval.error_admin_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
-
     // Get target user from reply or args
     let target_user = get_target_user(&msg, &state, &bot).await?;
 
@@ -94,25 +78,13 @@ pub async fn unapprove_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = match msg.from.as_ref() {
-        Some(u) => u.id,
-        None => return Ok(()),
-    };
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::Admin).await? else {
         return Ok(());
-    }
+    };
 
     // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
-    if !state.permissions.is_admin(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, get_text(&locale, "approval.error_admin_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
-
     let target_user = get_target_user(&msg, &state, &bot).await?;
 
     let (target_id, target_name, _) = match target_user {
@@ -158,29 +130,15 @@ pub async fn unapproveall_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = match msg.from.as_ref() {
-        Some(u) => u.id,
-        None => return Ok(()),
-    };
 
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    // Requires can_promote_members (higher level admin)
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::PromoteMembers).await? else {
         return Ok(());
-    }
+    };
 
     // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
-    // Requires can_promote_members (higher level admin)
-    if !state.permissions.can_promote_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "approval.error_promote_permission"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let count = state.message_context.unapprove_all(chat_id.0).await?;
 
     bot.send_message(
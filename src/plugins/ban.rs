@@ -2,13 +2,17 @@
 //!
 //! Commands for banning, unbanning, and kicking users.
 
+use chrono::{DateTime, Utc};
 use teloxide::prelude::*;
-use teloxide::types::{ParseMode, ReplyParameters, UserId};
-use std::time::{SystemTime, UNIX_EPOCH};
+use teloxide::types::{ParseMode, ReplyParameters, UntilDate, UserId};
+use tracing::warn;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::utils::{html_escape, parse_duration, get_target_from_msg};
+use crate::utils::{format_duration_full, html_escape, is_flag, parse_duration, get_target_from_msg, get_target_ids, mention_user_id, restrict_until, require_admin, extends};
+use crate::constants::{MAX_TARGET_IDS, MIN_TEMP_RESTRICTION};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::plugins::purge::sweep_recent_messages;
 
 /// Handle /ban command.
 pub async fn ban_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
@@ -65,10 +69,9 @@ pub async fn kickme_command(bot: ThrottledBot, msg: Message, state: AppState) ->
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    // Ban then unban = kick
-    match bot.ban_chat_member(chat_id, user_id).await {
+    match kick_member(&bot, chat_id, user_id).await {
         Ok(_) => {
-            let _ = bot.unban_chat_member(chat_id, user_id).await;
+            state.bye.mark_removed(chat_id.0, user_id.0);
             bot.send_message(chat_id, get_text(&locale, "ban.kickme_goodbye"))
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
@@ -83,6 +86,39 @@ pub async fn kickme_command(bot: ThrottledBot, msg: Message, state: AppState) ->
     Ok(())
 }
 
+/// Kick a member: ban, then lift the ban so they can rejoin.
+///
+/// The unban uses `only_if_banned`, so it only lifts our own ban. Without it
+/// Telegram removes the user from the chat again if they somehow got back in
+/// (e.g. a join request approved right after the ban), which would turn a
+/// rejoin into a second silent kick.
+pub(crate) async fn kick_member(bot: &ThrottledBot, chat_id: ChatId, user_id: UserId) -> anyhow::Result<()> {
+    bot.ban_chat_member(chat_id, user_id).await?;
+    bot.unban_chat_member(chat_id, user_id)
+        .only_if_banned(true)
+        .await?;
+    Ok(())
+}
+
+/// Check whether a user is currently in the chat.
+///
+/// Users Telegram doesn't know in this chat count as absent.
+async fn is_chat_member(bot: &ThrottledBot, chat_id: ChatId, user_id: UserId) -> bool {
+    bot.get_chat_member(chat_id, user_id)
+        .await
+        .map(|member| member.is_present())
+        .unwrap_or(false)
+}
+
+/// When a user's current ban ends, if they are banned.
+async fn current_ban(bot: &ThrottledBot, chat_id: ChatId, user_id: UserId) -> Option<UntilDate> {
+    bot.get_chat_member(chat_id, user_id)
+        .await
+        .ok()
+        .filter(|member| member.is_banned())
+        .and_then(|member| member.kind.until_date())
+}
+
 #[derive(PartialEq, Clone, Copy)]
 enum BanMode {
     Forever,
@@ -102,24 +138,30 @@ async fn ban_action(
     mode: BanMode,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
 
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    // Check permission: can_restrict_members
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
 
-    // Check permission: can_restrict_members
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-    if !state.permissions.can_restrict_members(chat_id, user_id).await.unwrap_or(false) {
+    // Errors go to the admin in their language, announcements to the group
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
+    let group_locale = state.locale_for_group(chat_id.0).await;
+
+    // `/ban 123 456 789 reason` acts on every listed ID
+    let ids = get_target_ids(&msg, mode == BanMode::Temporary);
+    if ids.len() > MAX_TARGET_IDS {
         bot.send_message(
             chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanRestrictMembers"),
+            get_text(&locale, "common.error_too_many_targets").replace("{max}", &MAX_TARGET_IDS.to_string()),
         )
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
     }
+    if ids.len() > 1 {
+        return ban_many(&bot, &msg, &state, mode, &ids, &locale, &group_locale).await;
+    }
 
     // Use shared target resolver
     let (target_id, target_name, skip_words) = match get_target_from_msg(&bot, &msg, &state).await {
@@ -137,30 +179,11 @@ async fn ban_action(
     let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
     let reason_start_idx = skip_words;
 
-    // Anti-Admin Check (except for Unban)
-    if mode != BanMode::Unban
-        && state.permissions.is_admin(chat_id, target_id).await.unwrap_or(false) {
-            let action_text = match mode {
-                BanMode::Forever | BanMode::Temporary | BanMode::DeleteAndBan | BanMode::SilentBan => {
-                    get_text(&locale, "ban.action_ban")
-                }
-                BanMode::Kick | BanMode::DeleteKick | BanMode::SilentKick => get_text(&locale, "ban.action_kick"),
-                BanMode::Unban => unreachable!(),
-            };
-            bot.send_message(
-                chat_id,
-                get_text(&locale, "ban.anti_admin").replace("{action}", &action_text)
-            )
-                .reply_parameters(ReplyParameters::new(msg.id))
-                .await?;
-            return Ok(());
-        }
-
     // Handle Time for Tban
     let (duration, reason_idx) = if mode == BanMode::Temporary {
         if parts.len() > reason_start_idx {
             if let Some(d) = parse_duration(parts[reason_start_idx]) {
-                (Some(d), reason_start_idx + 1)
+                (Some(d.max(MIN_TEMP_RESTRICTION)), reason_start_idx + 1)
             } else {
                 bot.send_message(chat_id, get_text(&locale, "ban.error_time_format"))
                     .reply_parameters(ReplyParameters::new(msg.id))
@@ -186,97 +209,260 @@ async fn ban_action(
     };
 
     // Format reason line - empty if no reason
-    let reason_line = reason.as_ref()
-        .map(|r| get_text(&locale, "ban.reason").replace("{reason}", &html_escape(r)))
+    let mut reason_line = reason.as_ref()
+        .map(|r| get_text(&group_locale, "ban.reason").replace("{reason}", &html_escape(r)))
         .unwrap_or_default();
 
-    // For silent modes, delete command message first
+    // `-d` on a ban also sweeps the target's recent messages
+    let is_ban = matches!(mode, BanMode::Forever | BanMode::Temporary | BanMode::DeleteAndBan | BanMode::SilentBan);
+    let sweep = is_ban && parts.iter().take_while(|a| is_flag(a)).any(|a| *a == "-d");
+    if sweep && require_admin(&bot, &msg, &state, Permission::DeleteMessages).await?.is_none() {
+        return Ok(());
+    }
+
+    // Re-banning replaces the old end date, so say which way it moved
+    let until_dt = duration.map(|d| restrict_until(state.clock.as_ref(), d));
+    let tban_key = match until_dt {
+        Some(dt) => match current_ban(&bot, chat_id, target_id).await {
+            Some(existing) if extends(existing, dt) => "ban.tban_extended",
+            Some(_) => "ban.tban_shortened",
+            None => "ban.tban",
+        },
+        None => "ban.tban",
+    };
+
+    // Execute
+    let applied = ban_target(&bot, &state, chat_id, target_id, mode, until_dt).await;
+
+    // For silent modes, delete the command; refusing an admin still replies to it
     let is_silent = matches!(mode, BanMode::SilentBan | BanMode::SilentKick);
-    if is_silent {
+    if is_silent && !matches!(applied, Err(Skipped::Admin)) {
         let _ = bot.delete_message(chat_id, msg.id).await;
     }
 
-    // Execute
-    match mode {
-        BanMode::Forever => {
-            bot.ban_chat_member(chat_id, target_id)
+    match applied {
+        Ok(()) => {}
+        Err(Skipped::Admin) => {
+            let action_text = if is_ban {
+                get_text(&locale, "ban.action_ban")
+            } else {
+                get_text(&locale, "ban.action_kick")
+            };
+            bot.send_message(
+                chat_id,
+                get_text(&locale, "ban.anti_admin").replace("{action}", &action_text)
+            )
+                .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
-            bot.send_message(chat_id, get_text(&locale, "ban.banned")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-                .replace("{reason}", &reason_line)
-            ).parse_mode(ParseMode::Html).await?;
-        },
-        BanMode::Temporary => {
-            let d = duration.unwrap();
-            let until = SystemTime::now() + d;
-            let until_date = until.duration_since(UNIX_EPOCH)?.as_secs();
-            let until_dt = chrono::DateTime::from_timestamp(until_date as i64, 0).unwrap_or_default();
-            
-            bot.ban_chat_member(chat_id, target_id)
-                .until_date(until_dt)
+            return Ok(());
+        }
+        Err(Skipped::NotInGroup) => {
+            // Kicking someone who already left would just ban+unban a non-member
+            if !is_silent {
+                bot.send_message(chat_id, get_text(&locale, "ban.error_not_in_group")
+                    .replace("{id}", &target_id.to_string())
+                    .replace("{name}", &html_escape(&target_name))
+                )
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
+            }
+            return Ok(());
+        }
+        Err(Skipped::Failed(e)) => return Err(e),
+    }
 
-            bot.send_message(chat_id, get_text(&locale, "ban.tban")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-                .replace("{duration}", &format!("{:?}", d)) // Ideally format_duration
-                .replace("{reason}", &reason_line)
-            ).parse_mode(ParseMode::Html).await?;
-        },
-        BanMode::DeleteAndBan => {
-            if let Some(reply) = msg.reply_to_message() {
-                 let _ = bot.delete_message(chat_id, reply.id).await;
+    if sweep {
+        let swept = sweep_recent_messages(&bot, &state, chat_id, target_id).await;
+        reason_line.push_str(&get_text(&group_locale, "ban.swept").replace("{count}", &swept.to_string()));
+    }
+
+    if matches!(mode, BanMode::DeleteAndBan | BanMode::DeleteKick)
+        && let Some(reply) = msg.reply_to_message() {
+            let _ = bot.delete_message(chat_id, reply.id).await;
+        }
+
+    let key = match mode {
+        BanMode::Forever => "ban.banned",
+        BanMode::Temporary => tban_key,
+        BanMode::DeleteAndBan => "ban.dban",
+        BanMode::Kick => "ban.kicked",
+        BanMode::DeleteKick => "ban.dkick",
+        BanMode::Unban => "ban.unbanned",
+        // Silent - no message, command already deleted
+        BanMode::SilentBan | BanMode::SilentKick => return Ok(()),
+    };
+    bot.send_message(chat_id, get_text(&group_locale, key)
+        .replace("{id}", &target_id.to_string())
+        .replace("{name}", &html_escape(&target_name))
+        .replace("{duration}", &duration.map(|d| format_duration_full(d.as_secs())).unwrap_or_default())
+        .replace("{reason}", &reason_line)
+    ).parse_mode(ParseMode::Html).await?;
+
+    Ok(())
+}
+
+/// Why a ban-family action was not applied to a target.
+enum Skipped {
+    /// Admins can't be banned or kicked.
+    Admin,
+    /// Kicks skip users who already left.
+    NotInGroup,
+    /// The Telegram call failed.
+    Failed(anyhow::Error),
+}
+
+/// Check and apply a ban-family action to one target.
+///
+/// Shared by single and multi-target commands. The target is marked as
+/// removed, so no goodbye goes out, only once the call succeeded. `until`
+/// only applies to bans.
+async fn ban_target(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    target_id: UserId,
+    mode: BanMode,
+    until: Option<DateTime<Utc>>,
+) -> Result<(), Skipped> {
+    let is_kick = matches!(mode, BanMode::Kick | BanMode::DeleteKick | BanMode::SilentKick);
+    if mode != BanMode::Unban && state.permissions.is_admin(chat_id, target_id).await.unwrap_or(false) {
+        return Err(Skipped::Admin);
+    }
+    if is_kick && !is_chat_member(bot, chat_id, target_id).await {
+        return Err(Skipped::NotInGroup);
+    }
+
+    let applied = if is_kick {
+        kick_member(bot, chat_id, target_id).await
+    } else if mode == BanMode::Unban {
+        bot.unban_chat_member(chat_id, target_id).await.map(|_| ()).map_err(Into::into)
+    } else {
+        let mut req = bot.ban_chat_member(chat_id, target_id);
+        if let Some(dt) = until {
+            req = req.until_date(dt);
+        }
+        req.await.map(|_| ()).map_err(Into::into)
+    };
+    applied.map_err(Skipped::Failed)?;
+
+    if mode != BanMode::Unban {
+        state.bye.mark_removed(chat_id.0, target_id.0);
+    }
+    Ok(())
+}
+
+/// Apply a ban-family action to several user IDs and post one summary.
+///
+/// Every target gets its own anti-admin and membership check, so an admin
+/// in the list doesn't stop the others. There's no replied message here, so
+/// /dban and /dkick act like /ban and /kick.
+async fn ban_many(
+    bot: &ThrottledBot,
+    msg: &Message,
+    state: &AppState,
+    mode: BanMode,
+    targets: &[UserId],
+    locale: &str,
+    group_locale: &str,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    let text = msg.text().unwrap_or("");
+    let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
+    let flags = parts.iter().take_while(|a| is_flag(a)).count();
+    let mut reason_idx = flags + targets.len();
+
+    // /tban takes its duration right after the IDs
+    let duration = if mode == BanMode::Temporary {
+        match parts.get(reason_idx).and_then(|a| parse_duration(a)) {
+            Some(d) => {
+                reason_idx += 1;
+                Some(d.max(MIN_TEMP_RESTRICTION))
             }
-            bot.ban_chat_member(chat_id, target_id).await?;
-            
-            bot.send_message(chat_id, get_text(&locale, "ban.dban")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-                .replace("{reason}", &reason_line)
-            ).parse_mode(ParseMode::Html).await?;
-        },
-        BanMode::SilentBan => {
-            // Silent - no message, command already deleted
-            bot.ban_chat_member(chat_id, target_id).await?;
-        },
-        BanMode::Kick => {
-            // Ban then Unban
-            bot.ban_chat_member(chat_id, target_id).await?;
-            bot.unban_chat_member(chat_id, target_id).await?;
-            
-            bot.send_message(chat_id, get_text(&locale, "ban.kicked")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-                .replace("{reason}", &reason_line)
-            ).parse_mode(ParseMode::Html).await?;
-        },
-        BanMode::DeleteKick => {
-            if let Some(reply) = msg.reply_to_message() {
-                let _ = bot.delete_message(chat_id, reply.id).await;
+            None => {
+                bot.send_message(chat_id, get_text(locale, "ban.error_time_format"))
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                return Ok(());
             }
-            bot.ban_chat_member(chat_id, target_id).await?;
-            bot.unban_chat_member(chat_id, target_id).await?;
-            
-            bot.send_message(chat_id, get_text(&locale, "ban.dkick")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-                .replace("{reason}", &reason_line)
-            ).parse_mode(ParseMode::Html).await?;
-        },
-        BanMode::SilentKick => {
-            // Silent - no message, command already deleted
-            bot.ban_chat_member(chat_id, target_id).await?;
-            bot.unban_chat_member(chat_id, target_id).await?;
-        },
-        BanMode::Unban => {
-             bot.unban_chat_member(chat_id, target_id).await?;
-             bot.send_message(chat_id, get_text(&locale, "ban.unbanned")
-                .replace("{id}", &target_id.to_string())
-                .replace("{name}", &html_escape(&target_name))
-            ).parse_mode(ParseMode::Html).await?;
-        },
+        }
+    } else {
+        None
+    };
+
+    let is_ban = matches!(mode, BanMode::Forever | BanMode::Temporary | BanMode::DeleteAndBan | BanMode::SilentBan);
+    let is_kick = matches!(mode, BanMode::Kick | BanMode::DeleteKick | BanMode::SilentKick);
+    let sweep = is_ban && parts[..flags].contains(&"-d");
+    if sweep && require_admin(bot, msg, state, Permission::DeleteMessages).await?.is_none() {
+        return Ok(());
+    }
+
+    let is_silent = matches!(mode, BanMode::SilentBan | BanMode::SilentKick);
+    if is_silent {
+        let _ = bot.delete_message(chat_id, msg.id).await;
+    }
+
+    let until_dt = duration.map(|d| restrict_until(state.clock.as_ref(), d));
+    let mut lines = String::new();
+    let mut done = 0;
+    let mut swept = 0;
+    for &target_id in targets {
+        let result = ban_target(bot, state, chat_id, target_id, mode, until_dt).await.map_err(|skipped| match skipped {
+            Skipped::Admin => get_text(group_locale, "ban.multi_admin"),
+            Skipped::NotInGroup => get_text(group_locale, "ban.multi_not_in_group"),
+            Skipped::Failed(e) => {
+                warn!("Failed to act on {} in chat {}: {}", target_id, chat_id, e);
+                get_text(group_locale, "ban.multi_failed")
+            }
+        });
+
+        let user = mention_user_id(state, target_id.0).await;
+        match result {
+            Ok(()) => {
+                done += 1;
+                if sweep {
+                    swept += sweep_recent_messages(bot, state, chat_id, target_id).await;
+                }
+                lines.push_str(&get_text(group_locale, "ban.multi_ok").replace("{user}", &user));
+            }
+            Err(error) => lines.push_str(
+                &get_text(group_locale, "ban.multi_skipped")
+                    .replace("{user}", &user)
+                    .replace("{error}", &error),
+            ),
+        }
+    }
+
+    if is_silent {
+        return Ok(());
     }
 
+    let mut reason_line = parts
+        .get(reason_idx..)
+        .map(|r| r.join(" "))
+        .filter(|r| !r.is_empty())
+        .map(|r| get_text(group_locale, "ban.reason").replace("{reason}", &html_escape(&r)))
+        .unwrap_or_default();
+    if sweep {
+        reason_line.push_str(&get_text(group_locale, "ban.swept").replace("{count}", &swept.to_string()));
+    }
+
+    let key = match mode {
+        BanMode::Temporary => "ban.multi_tban",
+        BanMode::Unban => "ban.multi_unbanned",
+        _ if is_kick => "ban.multi_kicked",
+        _ => "ban.multi_banned",
+    };
+    let summary = get_text(group_locale, key)
+        .replace("{done}", &done.to_string())
+        .replace("{total}", &targets.len().to_string())
+        .replace("{duration}", &duration.map(|d| format_duration_full(d.as_secs())).unwrap_or_default())
+        .replace("{reason}", &reason_line);
+    bot.send_message(chat_id, summary + &lines)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
     Ok(())
 }
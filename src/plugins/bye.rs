@@ -5,13 +5,16 @@
 
 use teloxide::prelude::*;
 use teloxide::types::{
-    InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode, ReplyParameters,
+    InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, ReplyParameters,
 };
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::{ByeSettings, InlineButton};
+use crate::database::{ByeSettings, ContentFormat, InlineButton};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{apply_rules_filling, format_template_source, merge_filling_buttons, require_admin, send_content, Content};
+use crate::utils::parser::parse_format_tag;
 
 /// Handle /bye command - show or toggle goodbye.
 pub async fn bye_command(
@@ -20,37 +23,12 @@ pub async fn bye_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
-    };
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "bye.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let settings = state.bye.get_or_create(chat_id.0).await?;
 
     let text = msg.text().unwrap_or("");
@@ -85,7 +63,29 @@ pub async fn bye_command(
         }
         "preview" => {
             // Show preview of goodbye message
-            send_bye_preview(&bot, chat_id, &settings, &msg).await?;
+            send_bye_preview(&bot, &state, chat_id, &settings, &msg).await?;
+        }
+        "skipban" => {
+            // Stay quiet when the bot removed the member: /bye skipban <on|off>
+            let skip_on_ban = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+                Some("on" | "yes") => true,
+                Some("off" | "no") => false,
+                _ => {
+                    bot.send_message(chat_id, get_text(&locale, "bye.skipban_usage"))
+                        .parse_mode(ParseMode::Html)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let mut new_settings = settings.clone();
+            new_settings.skip_on_ban = skip_on_ban;
+            state.bye.save(&new_settings).await?;
+            let key = if skip_on_ban { "bye.skipban_on" } else { "bye.skipban_off" };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
         }
         _ => {
             bot.send_message(
@@ -108,29 +108,12 @@ pub async fn setbye_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let mut settings = state.bye.get_or_create(chat_id.0).await?;
 
     // Check if replying to a message
@@ -139,13 +122,14 @@ pub async fn setbye_command(
     let args_text = text_content
         .split_once(char::is_whitespace)
         .map(|(_, rest)| rest.trim())
-        .filter(|s| !s.is_empty());
+        .filter(|s| !s.is_empty())
+        .map(|args| crate::utils::tail_html(text_content, msg.entities(), args));
 
     if let Some(reply) = replied {
         // Get message content from reply
         let (message_text, media_file_id, media_type) = extract_message_content(reply);
 
-        if let Some(text) = message_text.or_else(|| args_text.map(String::from)) {
+        if let Some(text) = message_text.or(args_text) {
             settings.message = Some(text);
         }
 
@@ -188,29 +172,12 @@ pub async fn setbyebuttons_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let text = msg.text().unwrap_or("");
     let args = text
         .split_once(char::is_whitespace)
@@ -266,33 +233,48 @@ pub async fn resetbye_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
+    let settings = ByeSettings::new(chat_id.0); // Reset to default
+    state.bye.save(&settings).await?;
+
+    bot.send_message(chat_id, get_text(&locale, "bye.reset_success"))
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
+
+    Ok(())
+}
+
+/// Handle /dumpbye command - show the raw goodbye template.
+pub async fn dumpbye_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
-    let settings = ByeSettings::new(chat_id.0); // Reset to default
-    state.bye.save(&settings).await?;
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    bot.send_message(chat_id, get_text(&locale, "bye.reset_success"))
+    let settings = state.bye.get_or_create(chat_id.0).await?;
+
+    let text = format_template_source(
+        &locale,
+        &get_text(&locale, "bye.dump_header"),
+        settings.message.as_deref(),
+        &settings.buttons,
+        settings.media_file_id.as_ref().and(settings.media_type.as_deref()),
+        "setbyebuttons",
+    );
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
 
@@ -301,7 +283,8 @@ pub async fn resetbye_command(
 
 /// Extract message content (text, media file_id, media type).
 fn extract_message_content(msg: &Message) -> (Option<String>, Option<String>, Option<String>) {
-    let text = msg.text().or_else(|| msg.caption()).map(String::from);
+    // Keep the message's formatting (bold, links, ...) as HTML
+    let text = crate::utils::message_html(msg);
 
     let (file_id, media_type) = if let Some(photo) = msg.photo() {
         let largest = photo.iter().max_by_key(|p| p.width * p.height);
@@ -316,6 +299,10 @@ fn extract_message_content(msg: &Message) -> (Option<String>, Option<String>, Op
             Some(animation.file.id.clone()),
             Some("animation".to_string()),
         )
+    } else if let Some(voice) = msg.voice() {
+        (Some(voice.file.id.clone()), Some("voice".to_string()))
+    } else if let Some(audio) = msg.audio() {
+        (Some(audio.file.id.clone()), Some("audio".to_string()))
     } else if let Some(sticker) = msg.sticker() {
         (Some(sticker.file.id.clone()), Some("sticker".to_string()))
     } else if let Some(document) = msg.document() {
@@ -413,6 +400,12 @@ fn format_bye_status(settings: &ByeSettings, locale: &str) -> String {
     } else {
         get_text(locale, "bye.status_inactive")
     };
+
+    let skip_on_ban = if settings.skip_on_ban {
+        get_text(locale, "bye.status_active")
+    } else {
+        get_text(locale, "bye.status_inactive")
+    };
     
     let default_msg = get_text(locale, "bye.status_none");
     let message = settings
@@ -437,6 +430,7 @@ fn format_bye_status(settings: &ByeSettings, locale: &str) -> String {
 
     get_text(locale, "bye.status_header")
         .replace("{status}", &status)
+        .replace("{skipban}", &skip_on_ban)
         .replace("{media}", &media)
         .replace("{buttons}", &buttons)
         .replace("{message}", message)
@@ -444,84 +438,63 @@ fn format_bye_status(settings: &ByeSettings, locale: &str) -> String {
 
 async fn send_bye_preview(
     bot: &ThrottledBot,
+    state: &AppState,
     chat_id: ChatId,
     settings: &ByeSettings,
     msg: &Message,
 ) -> anyhow::Result<()> {
     let user = msg.from.as_ref().unwrap();
-    let formatted = format_bye_text(
+    let (formatted, format, rule_buttons) = format_bye_text(
         settings.message.as_deref().unwrap_or("Selamat tinggal!"),
         user,
         msg.chat.title().unwrap_or("Grup"),
+        chat_id.0,
+        &state.bot_username,
     );
 
-    let keyboard = build_bye_keyboard(&settings.buttons);
-
-    if let Some(ref file_id) = settings.media_file_id {
-        match settings.media_type.as_deref() {
-            Some("photo") => {
-                bot.send_photo(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            Some("video") => {
-                bot.send_video(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            Some("animation") => {
-                bot.send_animation(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            _ => {
-                bot.send_message(chat_id, formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-        }
-    } else {
-        bot.send_message(chat_id, formatted)
-            .parse_mode(ParseMode::Html)
-            .reply_markup(keyboard)
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-    }
+    let mut buttons = settings.buttons.clone();
+    merge_filling_buttons(&mut buttons, rule_buttons);
+    let keyboard = build_bye_keyboard(&buttons);
+
+    let content = Content::new(formatted, format.parse_mode())
+        .media(settings.media_type.as_deref(), settings.media_file_id.as_deref())
+        .keyboard(keyboard)
+        .reply_to(msg.id);
+    send_content(bot, chat_id, content).await?;
 
     Ok(())
 }
 
 /// Format goodbye text with placeholders.
-pub fn format_bye_text(template: &str, user: &teloxide::types::User, group: &str) -> String {
+///
+/// Returns the text, the format chosen by a `{markdown}` / `{noformat}` tag
+/// and the buttons for any `{rules}` / `{rules:same}` filling.
+pub fn format_bye_text(
+    template: &str,
+    user: &teloxide::types::User,
+    group: &str,
+    chat_id: i64,
+    bot_username: &str,
+) -> (String, ContentFormat, Vec<(InlineButton, bool)>) {
+    let (template, format) = parse_format_tag(template);
+    let template = format.prepare(&template);
+    let (template, rule_buttons) = apply_rules_filling(&template, chat_id, bot_username);
+
     let name = user.first_name.clone();
     let username = user
         .username
         .as_ref()
         .map(|u| format!("@{}", u))
         .unwrap_or_else(|| name.clone());
-    let mention = format!(
-        "<a href=\"tg://user?id={}\">{}</a>",
-        user.id,
-        html_escape(&name)
-    );
+    let mention = format.mention(user.id, &name);
 
-    template
-        .replace("{name}", &html_escape(&name))
-        .replace("{username}", &html_escape(&username))
+    let text = template
+        .replace("{name}", &format.escape(&name))
+        .replace("{username}", &format.escape(&username))
         .replace("{mention}", &mention)
         .replace("{id}", &user.id.to_string())
-        .replace("{group}", &html_escape(group))
+        .replace("{group}", &format.escape(group));
+    (text, format, rule_buttons)
 }
 
 pub fn build_bye_keyboard(buttons: &[Vec<InlineButton>]) -> InlineKeyboardMarkup {
@@ -540,9 +513,3 @@ pub fn build_bye_keyboard(buttons: &[Vec<InlineButton>]) -> InlineKeyboardMarkup
 
     InlineKeyboardMarkup::new(keyboard)
 }
-
-fn html_escape(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-}
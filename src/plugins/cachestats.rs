@@ -0,0 +1,46 @@
+//! Cache stats plugin.
+//!
+//! /cachestats shows hit/miss counts for every cache since the bot started,
+//! so owners can tune TTLs. Owner-only: the numbers span all groups.
+
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::utils::{html_escape, send_content, Content};
+
+/// Handle /cachestats command.
+pub async fn cachestats_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    // Not an owner: act as if the command doesn't exist
+    if !state.is_owner(user.id.0) {
+        return Ok(());
+    }
+
+    let chat_id = msg.chat.id;
+    let locale = state.get_locale(Some(chat_id.0), Some(user.id.0)).await;
+
+    let mut text = get_text(&locale, "cachestats.header");
+    for (name, stats) in state.cache.stats() {
+        let hit_rate = stats
+            .hit_rate()
+            .map_or_else(|| "-".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+        text.push_str(
+            &get_text(&locale, "cachestats.entry")
+                .replace("{name}", &html_escape(&name))
+                .replace("{entries}", &stats.entries.to_string())
+                .replace("{hits}", &stats.hits.to_string())
+                .replace("{misses}", &stats.misses.to_string())
+                .replace("{rate}", &hit_rate)
+                .replace("{inserts}", &stats.inserts.to_string()),
+        );
+    }
+
+    // Truncated rather than failing if the cache list outgrows a message
+    send_content(&bot, chat_id, Content::new(text, ParseMode::Html).reply_to(msg.id)).await?;
+
+    Ok(())
+}
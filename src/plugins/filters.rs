@@ -3,13 +3,14 @@
 //! Commands for managing auto-reply filters in groups.
 
 use teloxide::prelude::*;
-use teloxide::types::{ParseMode, ReplyParameters, UserId};
+use teloxide::types::{ParseMode, ReplyParameters};
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::{DbFilter, MatchType};
-use crate::utils::{html_escape, parse_content};
+use crate::database::{build_regex, filter_key, DbFilter, MatchType};
+use crate::utils::{format_audit, html_escape, parse_content, require_admin, send_content, Content};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
 
 /// Handle /filter command - add a new filter.
 ///
@@ -23,28 +24,12 @@ pub async fn filter_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, "⚠️ Perintah ini hanya untuk grup.")
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     // Check permission: can_change_info
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-    if !state.permissions.can_change_info(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
 
     let text = msg.text().unwrap_or("");
     let args = text.split_once(char::is_whitespace)
@@ -80,16 +65,18 @@ pub async fn filter_command(
         (None, None)
     };
 
-    // Parse the reply content
-    let mut final_reply = reply.clone();
+    // Parse the reply content, keeping its formatting
+    let mut final_reply = if reply.is_empty() {
+        reply.clone()
+    } else {
+        crate::utils::tail_html(text, msg.entities(), &reply)
+    };
     
     // If no reply text but replying to message, use that message's text
     if final_reply.is_empty()
         && let Some(reply_msg) = msg.reply_to_message() {
-            final_reply = reply_msg.text()
-                .or_else(|| reply_msg.caption())
-                .map(String::from)
-                .unwrap_or_default();
+            // Keep the replied message's formatting as HTML
+            final_reply = crate::utils::message_html(reply_msg).unwrap_or_default();
         }
 
     if final_reply.is_empty() && media_file_id.is_none() {
@@ -102,14 +89,32 @@ pub async fn filter_command(
     // Determine match type from trigger prefix
     let (clean_trigger, match_type) = parse_trigger_type(&trigger);
 
+    // Reject regex triggers that don't compile (or are too large)
+    if match_type == MatchType::Regex
+        && let Err(e) = build_regex(&clean_trigger) {
+            bot.send_message(
+                chat_id,
+                get_text(&locale, "filters.error_invalid_regex")
+                    .replace("{error}", &html_escape(&e.to_string())),
+            )
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+            return Ok(());
+        }
+
     // Parse content for tags and buttons
     let parsed = parse_content(&final_reply);
 
     // Create filter using DbFilter
-    let filter = DbFilter {
+    let mut filter = DbFilter {
         id: None,
         chat_id: chat_id.0,
-        trigger: clean_trigger.to_lowercase(),
+        trigger: match match_type {
+            MatchType::Regex => format!("regex:{}", clean_trigger),
+            _ => clean_trigger.to_lowercase(),
+        },
+        pattern: (match_type == MatchType::Regex).then(|| clean_trigger.clone()),
         match_type,
         reply: parsed.text.clone(),
         buttons: parsed.buttons,
@@ -119,8 +124,20 @@ pub async fn filter_command(
         user_only: parsed.tags.user_only,
         protect: parsed.tags.protect,
         replytag: parsed.tags.replytag,
+        preview: parsed.tags.preview,
+        hits: 0,
+        created_by: None,
+        created_at: None,
+        updated_by: None,
+        updated_at: None,
     };
 
+    // Keep creation info when editing an existing filter. The hit count is
+    // only carried over for the cached copy; the stored one isn't touched.
+    let previous = state.filters.get_filter(chat_id.0, &filter.trigger).await?;
+    filter.hits = previous.as_ref().map(|f| f.hits).unwrap_or(0);
+    filter.stamp_audit(previous.as_ref(), user_id.0);
+
     // Save filter using FilterRepository
     state.filters.save_filter(&filter).await?;
 
@@ -129,7 +146,7 @@ pub async fn filter_command(
     bot.send_message(
         chat_id,
         get_text(&locale, "filters.added")
-            .replace("{trigger}", &html_escape(&clean_trigger)),
+            .replace("{trigger}", &html_escape(&filter.trigger)),
     )
     .parse_mode(ParseMode::Html)
     .reply_parameters(ReplyParameters::new(msg.id))
@@ -138,7 +155,117 @@ pub async fn filter_command(
     Ok(())
 }
 
-/// Handle /filters command - list all filters.
+/// Handle /filterinfo command - show a filter's stored content and who edited it.
+pub async fn filterinfo_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::Admin).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let trigger = text
+        .split_once(char::is_whitespace)
+        .map(|(_, rest)| filter_key(rest.trim().trim_matches('"')))
+        .unwrap_or_default();
+
+    if trigger.is_empty() {
+        bot.send_message(chat_id, get_text(&locale, "filters.info_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let Some(filter) = state.filters.get_filter(chat_id.0, &trigger).await? else {
+        bot.send_message(
+            chat_id,
+            get_text(&locale, "filters.not_found").replace("{trigger}", &html_escape(&trigger)),
+        )
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+        return Ok(());
+    };
+
+    let mut info = get_text(&locale, "filters.info_header")
+        .replace("{trigger}", &html_escape(&filter.trigger))
+        .replace("{hits}", &filter.hits.to_string());
+    info.push_str(&describe_filter(&locale, &filter));
+    info.push_str(
+        &format_audit(&state, &locale, filter.created_by, filter.created_at, filter.updated_by, filter.updated_at).await,
+    );
+    if !filter.reply.is_empty() {
+        info.push_str(&get_text(&locale, "filters.info_reply").replace("{reply}", &html_escape(&filter.reply)));
+    }
+
+    // Long replies are truncated to fit
+    send_content(&bot, chat_id, Content::new(info, ParseMode::Html).reply_to(msg.id)).await?;
+
+    Ok(())
+}
+
+/// Render a filter's match type, tags, media and buttons for /filterinfo.
+fn describe_filter(locale: &str, filter: &DbFilter) -> String {
+    let match_type = match filter.match_type {
+        MatchType::Word => get_text(locale, "filters.match_word"),
+        MatchType::Keyword => get_text(locale, "filters.match_keyword"),
+        MatchType::Exact => get_text(locale, "filters.match_exact"),
+        MatchType::Prefix => get_text(locale, "filters.match_prefix"),
+        MatchType::Regex => get_text(locale, "filters.match_regex").replace(
+            "{pattern}",
+            &html_escape(filter.pattern.as_deref().unwrap_or(&filter.trigger)),
+        ),
+    };
+    let mut out = get_text(locale, "filters.info_match").replace("{match}", &match_type);
+
+    let tags: Vec<&str> = [
+        (filter.admin_only, "{admin}"),
+        (filter.user_only, "{user}"),
+        (filter.protect, "{protect}"),
+        (filter.replytag, "{replytag}"),
+        (filter.preview, "{preview}"),
+    ]
+    .into_iter()
+    .filter_map(|(set, tag)| set.then_some(tag))
+    .collect();
+    let tags = if tags.is_empty() {
+        get_text(locale, "filters.info_no_tags")
+    } else {
+        format!("<code>{}</code>", tags.join(" "))
+    };
+    out.push_str(&get_text(locale, "filters.info_tags").replace("{tags}", &tags));
+
+    if let Some(media_type) = &filter.media_type {
+        out.push_str(&get_text(locale, "filters.info_media").replace("{type}", &html_escape(media_type)));
+    }
+
+    if !filter.buttons.is_empty() {
+        // One line per row, buttons in a row separated by a bar
+        let rows: Vec<String> = filter
+            .buttons
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|b| format!("{} → <code>{}</code>", html_escape(&b.text), html_escape(&b.url)))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect();
+        out.push_str(&get_text(locale, "filters.info_buttons").replace("{buttons}", &rows.join("\n")));
+    }
+
+    out
+}
+
+/// Handle /filters command - list all filters, or pause/resume them.
+///
+/// Usage: /filters [on|off]
 pub async fn filters_command(
     bot: ThrottledBot,
     msg: Message,
@@ -154,8 +281,31 @@ pub async fn filters_command(
         return Ok(());
     }
 
-    // Get triggers from FilterRepository (L1 cache)
-    let triggers = state.filters.get_triggers(chat_id.0).await?;
+    let text = msg.text().unwrap_or("");
+    let toggle = match text.split_whitespace().nth(1).map(|a| a.to_lowercase()).as_deref() {
+        Some("on" | "yes" | "true") => Some(true),
+        Some("off" | "no" | "false") => Some(false),
+        _ => None,
+    };
+    if let Some(enabled) = toggle {
+        // Unlike /stopall, pausing keeps every filter
+        let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+            return Ok(());
+        };
+        state.message_context.update_filters_enabled(chat_id.0, enabled).await?;
+        info!("Filters set to {} in chat {} by {}", enabled, chat_id, user_id);
+
+        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+        let key = if enabled { "filters.resumed" } else { "filters.paused" };
+        bot.send_message(chat_id, get_text(&locale, key))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    // Get triggers with hit counts, most-triggered first
+    let triggers = state.filters.get_hit_counts(chat_id.0).await?;
 
     let locale = state.get_locale(Some(chat_id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
 
@@ -174,10 +324,19 @@ pub async fn filters_command(
         .replace("{title}", &html_escape(group_name))
         .replace("{count}", &triggers.len().to_string());
 
-    for trigger in triggers {
-        text.push_str(&format!("• <code>{}</code>\n", html_escape(&trigger)));
+    for (trigger, hits) in triggers {
+        text.push_str(
+            &get_text(&locale, "filters.list_item")
+                .replace("{trigger}", &html_escape(&trigger))
+                .replace("{hits}", &hits.to_string()),
+        );
+        text.push('\n');
     }
     text.push_str(&get_text(&locale, "filters.list_footer"));
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+    if !ctx.filters_enabled {
+        text.push_str(&get_text(&locale, "filters.list_paused"));
+    }
 
     bot.send_message(chat_id, text)
         .parse_mode(ParseMode::Html)
@@ -194,28 +353,12 @@ pub async fn stop_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, "⚠️ Perintah ini hanya untuk grup.")
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     // Check permission
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-    if !state.permissions.can_change_info(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
 
     let text = msg.text().unwrap_or("");
     let trigger = text.split_once(char::is_whitespace)
@@ -263,28 +406,12 @@ pub async fn stopall_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, "⚠️ Perintah ini hanya untuk grup.")
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::GroupOwner).await? else {
         return Ok(());
-    }
+    };
 
     // Check permission: must be owner
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-    if !state.permissions.is_owner(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "GroupOwner"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
 
     // Get current trigger count
     let triggers = state.filters.get_triggers(chat_id.0).await?;
@@ -334,6 +461,8 @@ fn parse_trigger_type(trigger: &str) -> (String, MatchType) {
         (trigger.strip_prefix("exact:").unwrap().to_string(), MatchType::Exact)
     } else if trigger.starts_with("prefix:") {
         (trigger.strip_prefix("prefix:").unwrap().to_string(), MatchType::Prefix)
+    } else if trigger.starts_with("regex:") {
+        (trigger.strip_prefix("regex:").unwrap().to_string(), MatchType::Regex)
     } else {
         (trigger.to_string(), MatchType::Keyword)
     }
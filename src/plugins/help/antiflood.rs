@@ -1,14 +1,16 @@
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
+use super::HelpScope;
+
 pub fn get_text(locale: &str) -> String {
     crate::i18n::get_text(locale, "help.antiflood_text")
 }
 
-pub fn get_keyboard(locale: &str) -> InlineKeyboardMarkup {
+pub fn get_keyboard(locale: &str, scope: HelpScope) -> InlineKeyboardMarkup {
     let back_text = crate::i18n::get_text(locale, "help.back");
     let back_text = if back_text == "help.back" { "🔙 Back".to_string() } else { format!("🔙 {}", back_text) };
 
     InlineKeyboardMarkup::new(vec![
-        vec![InlineKeyboardButton::callback(back_text, "help:back")],
+        vec![InlineKeyboardButton::callback(back_text, scope.data("back"))],
     ])
 }
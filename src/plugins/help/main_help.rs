@@ -1,12 +1,14 @@
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 
+use super::HelpScope;
+
 pub fn get_text(locale: &str) -> String {
     let title = crate::i18n::get_text(locale, "help.title");
     let intro = crate::i18n::get_text(locale, "help.intro");
     format!("{}\n\n{}", title, intro)
 }
 
-pub fn get_keyboard(locale: &str) -> InlineKeyboardMarkup {
+pub fn get_keyboard(locale: &str, scope: HelpScope) -> InlineKeyboardMarkup {
     // For now, hardcode button labels or use i18n if keys exist.
     // Ideally buttons should be translated too.
     // I'll stick to English for buttons unless requested, OR use keys if available.
@@ -19,29 +21,25 @@ pub fn get_keyboard(locale: &str) -> InlineKeyboardMarkup {
         if text == key { default.to_string() } else { text }
     };
 
-    InlineKeyboardMarkup::new(vec![
-        vec![
-            InlineKeyboardButton::callback(l("help.notes", "📝 Notes"), "help:notes"),
-            InlineKeyboardButton::callback(l("help.filters", "⚙️ Filters"), "help:filters"),
-        ],
-        vec![
-            InlineKeyboardButton::callback(l("help.welcome", "👋 Welcome"), "help:welcome"),
-            InlineKeyboardButton::callback(l("help.bye", "👋 Goodbye"), "help:bye"),
-        ],
-        vec![
-            InlineKeyboardButton::callback(l("help.admin", "🛡️ Admin"), "help:admin"),
-            InlineKeyboardButton::callback(l("help.warns", "⚠️ Warns"), "help:warns"),
-        ],
-        vec![
-            InlineKeyboardButton::callback(l("help.antiflood", "🌊 Antiflood"), "help:antiflood"),
-            InlineKeyboardButton::callback(l("help.approval", "✅ Approval"), "help:approval"),
-        ],
-        vec![
-            InlineKeyboardButton::callback(l("help.afk", "💤 AFK"), "help:afk"),
-            InlineKeyboardButton::callback(l("help.purge", "🗑️ Purge"), "help:purge"),
-        ],
-        vec![
-            InlineKeyboardButton::callback(l("help.rules", "📜 Rules"), "help:rules"),
-        ],
-    ])
+    let sections = [
+        ("help.notes", "📝 Notes", "notes"),
+        ("help.filters", "⚙️ Filters", "filters"),
+        ("help.welcome", "👋 Welcome", "welcome"),
+        ("help.bye", "👋 Goodbye", "bye"),
+        ("help.admin", "🛡️ Admin", "admin"),
+        ("help.warns", "⚠️ Warns", "warns"),
+        ("help.antiflood", "🌊 Antiflood", "antiflood"),
+        ("help.approval", "✅ Approval", "approval"),
+        ("help.afk", "💤 AFK", "afk"),
+        ("help.purge", "🗑️ Purge", "purge"),
+        ("help.rules", "📜 Rules", "rules"),
+    ];
+
+    // Two buttons per row, leaving out sections the reader can't use
+    let buttons: Vec<InlineKeyboardButton> = sections
+        .iter()
+        .filter(|(_, _, section)| scope.shows(section))
+        .map(|(key, default, section)| InlineKeyboardButton::callback(l(key, default), scope.data(section)))
+        .collect();
+    InlineKeyboardMarkup::new(buttons.chunks(2).map(|row| row.to_vec()).collect::<Vec<_>>())
 }
@@ -1,11 +1,15 @@
 //! Help command module.
 //!
 //! Handles /help command and callback queries for the interactive help system.
+//!
+//! Members who ask for help in a group only see the sections they can use;
+//! the scope rides along in the deep link and in every callback's data.
 
 use teloxide::prelude::*;
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::permissions::Permission;
 
 mod main_help;
 mod notes;
@@ -20,6 +24,52 @@ mod approval;
 mod purge;
 mod rules;
 
+/// Sections only useful to admins, hidden from members.
+const ADMIN_SECTIONS: &[&str] = &[
+    "admin", "filters", "welcome", "bye", "warns", "antiflood", "approval", "purge",
+];
+
+/// Which help sections a reader sees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpScope {
+    /// Every section: admins, and /help sent straight to PM
+    Full,
+    /// Only sections usable without admin rights
+    Member,
+}
+
+impl HelpScope {
+    /// Scope for a `/start` deep link payload, if it opens the help menu.
+    pub fn from_start_payload(payload: &str) -> Option<Self> {
+        match payload {
+            "help" => Some(Self::Full),
+            "help_member" => Some(Self::Member),
+            _ => None,
+        }
+    }
+
+    /// `/start` deep link payload that opens the help menu in this scope.
+    fn start_payload(self) -> &'static str {
+        match self {
+            Self::Full => "help",
+            Self::Member => "help_member",
+        }
+    }
+
+    /// Callback data for a help `section`, keeping this scope.
+    fn data(self, section: &str) -> String {
+        match self {
+            Self::Full => format!("help:{}", section),
+            Self::Member => format!("help:{}:member", section),
+        }
+    }
+
+    /// Whether `section` is shown in this scope.
+    fn shows(self, section: &str) -> bool {
+        self == Self::Full || !ADMIN_SECTIONS.contains(&section)
+    }
+}
+
 /// Handle /help command.
 pub async fn help_handler(
     bot: ThrottledBot,
@@ -32,13 +82,32 @@ pub async fn help_handler(
 
     // In groups, redirect to PM
     if msg.chat.is_group() || msg.chat.is_supergroup() {
-        let pm_url = format!("https://t.me/{}?start=help", state.bot_username);
+        // Members get a menu without the admin-only sections
+        let is_admin = match msg.from.as_ref() {
+            Some(user) => state
+                .permissions
+                .has(chat_id, user.id, Permission::Admin)
+                .await
+                .unwrap_or(false),
+            None => false,
+        };
+        let scope = if is_admin { HelpScope::Full } else { HelpScope::Member };
+        let pm_url = format!("https://t.me/{}?start={}", state.bot_username, scope.start_payload());
         let btn_text = crate::i18n::get_text(&locale, "common.help_btn"); 
         let btn_text = if btn_text == "common.help_btn" { "📚 Help / Bantuan".to_string() } else { btn_text }; // Fallback temp
         
         // Use a simple localized message
         let msg_text = crate::i18n::get_text(&locale, "help.redirect_pm");
         let msg_text = if msg_text == "help.redirect_pm" { "Contact me in PM." } else { &msg_text };
+        // Quick overview of what this group has set up
+        let msg_text = match crate::plugins::settings::content_counts(&state, chat_id.0).await {
+            Ok(counts) => format!(
+                "{}\n\n{}",
+                msg_text,
+                crate::plugins::settings::format_content_counts(&locale, &counts)
+            ),
+            Err(_) => msg_text.to_string(),
+        };
 
         let keyboard = InlineKeyboardMarkup::new(vec![
             vec![InlineKeyboardButton::url(btn_text, pm_url.parse().unwrap())],
@@ -50,13 +119,18 @@ pub async fn help_handler(
     }
 
     // In PM, show help directly
-    send_help_menu(&bot, chat_id, &locale).await
+    send_help_menu(&bot, chat_id, &locale, HelpScope::Full).await
 }
 
-/// Send the main help menu.
-pub async fn send_help_menu(bot: &ThrottledBot, chat_id: ChatId, locale: &str) -> anyhow::Result<()> {
+/// Send the main help menu, showing the sections in `scope`.
+pub async fn send_help_menu(
+    bot: &ThrottledBot,
+    chat_id: ChatId,
+    locale: &str,
+    scope: HelpScope,
+) -> anyhow::Result<()> {
     let text = main_help::get_text(locale);
-    let keyboard = main_help::get_keyboard(locale);
+    let keyboard = main_help::get_keyboard(locale, scope);
 
     bot.send_message(chat_id, text)
         .parse_mode(ParseMode::Html)
@@ -106,19 +180,28 @@ pub async fn callback_handler(
     let locale = state.get_locale(chat_id, Some(user_id)).await;
 
     let part = data.strip_prefix("help:").unwrap_or("");
+    let (part, scope) = match part.strip_suffix(":member") {
+        Some(part) => (part, HelpScope::Member),
+        None => (part, HelpScope::Full),
+    };
+    if !scope.shows(part) {
+        bot.answer_callback_query(q.id).await?;
+        return Ok(());
+    }
+
     let (text, keyboard) = match part {
-        "main" | "back" => (main_help::get_text(&locale), main_help::get_keyboard(&locale)),
-        "notes" => (notes::get_text(&locale), notes::get_keyboard(&locale)),
-        "afk" => (afk::get_text(&locale), afk::get_keyboard(&locale)),
-        "admin" => (admin::get_text(&locale), admin::get_keyboard(&locale)),
-        "filters" => (filters::get_text(&locale), filters::get_keyboard(&locale)),
-        "welcome" => (welcome::get_text(&locale), welcome::get_keyboard(&locale)),
-        "bye" => (bye::get_text(&locale), bye::get_keyboard(&locale)),
-        "warns" => (warns::get_text(&locale), warns::get_keyboard(&locale)),
-        "antiflood" => (antiflood::get_text(&locale), antiflood::get_keyboard(&locale)),
-        "approval" => (approval::get_text(&locale), approval::get_keyboard(&locale)),
-        "purge" => (purge::get_text(&locale), purge::get_keyboard(&locale)),
-        "rules" => (rules::get_text(&locale), rules::get_keyboard(&locale)),
+        "main" | "back" => (main_help::get_text(&locale), main_help::get_keyboard(&locale, scope)),
+        "notes" => (notes::get_text(&locale), notes::get_keyboard(&locale, scope)),
+        "afk" => (afk::get_text(&locale), afk::get_keyboard(&locale, scope)),
+        "admin" => (admin::get_text(&locale), admin::get_keyboard(&locale, scope)),
+        "filters" => (filters::get_text(&locale), filters::get_keyboard(&locale, scope)),
+        "welcome" => (welcome::get_text(&locale), welcome::get_keyboard(&locale, scope)),
+        "bye" => (bye::get_text(&locale), bye::get_keyboard(&locale, scope)),
+        "warns" => (warns::get_text(&locale), warns::get_keyboard(&locale, scope)),
+        "antiflood" => (antiflood::get_text(&locale), antiflood::get_keyboard(&locale, scope)),
+        "approval" => (approval::get_text(&locale), approval::get_keyboard(&locale, scope)),
+        "purge" => (purge::get_text(&locale), purge::get_keyboard(&locale, scope)),
+        "rules" => (rules::get_text(&locale), rules::get_keyboard(&locale, scope)),
         _ => return Ok(()),
     };
 
@@ -132,3 +215,23 @@ pub async fn callback_handler(
     bot.answer_callback_query(q.id).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_help_scope() {
+        assert!(HelpScope::Full.shows("purge"));
+        assert!(!HelpScope::Member.shows("purge"));
+        assert!(HelpScope::Member.shows("notes"));
+
+        assert_eq!(HelpScope::Full.data("back"), "help:back");
+        assert_eq!(HelpScope::Member.data("back"), "help:back:member");
+
+        for scope in [HelpScope::Full, HelpScope::Member] {
+            assert_eq!(HelpScope::from_start_payload(scope.start_payload()), Some(scope));
+        }
+        assert_eq!(HelpScope::from_start_payload("rules_1"), None);
+    }
+}
@@ -0,0 +1,162 @@
+//! User info plugin.
+//!
+//! /info shows a snapshot of a user's role, warnings and approval state,
+//! with their profile photo when they have one.
+
+use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters, UserId};
+use tracing::debug;
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::utils::{get_target_from_msg, html_escape, is_invalid_media, send_content, Content};
+
+/// How long a looked-up profile photo is reused before asking Telegram again.
+const PROFILE_PHOTO_MAX_AGE_SECS: i64 = 86_400;
+
+/// Handle /info command.
+///
+/// Usage: /info [reply|user_id|@username] (defaults to the sender)
+pub async fn info_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(sender) = msg.from.as_ref() else {
+        return Ok(());
+    };
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return Ok(());
+    }
+
+    let locale = state.get_locale(Some(chat_id.0), Some(sender.id.0)).await;
+
+    let has_target = msg.reply_to_message().is_some()
+        || msg.text().is_some_and(|t| t.split_whitespace().nth(1).is_some());
+    let (target_id, target_name) = if has_target {
+        match get_target_from_msg(&bot, &msg, &state).await {
+            Some((id, name, _)) => (id, name),
+            None => {
+                bot.send_message(chat_id, get_text(&locale, "info.error_user_not_found"))
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                return Ok(());
+            }
+        }
+    } else {
+        (sender.id, sender.first_name.clone())
+    };
+
+    let text = build_info_card(&state, chat_id, target_id, &target_name, &locale).await;
+    let photo = profile_photo(&bot, &state, target_id).await;
+
+    let content = Content::new(text.clone(), ParseMode::Html)
+        .media(photo.is_some().then_some("photo"), photo.as_deref())
+        .reply_to(msg.id);
+    match send_content(&bot, chat_id, content).await {
+        Ok(_) => {}
+        // The stored photo went stale: forget it and send the card alone
+        Err(e) if photo.is_some() && is_invalid_media(&e) => {
+            let _ = state.users.set_profile_photo(target_id.0, None).await;
+            send_content(&bot, chat_id, Content::new(text, ParseMode::Html).reply_to(msg.id)).await?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(())
+}
+
+/// File ID of a user's current profile photo, or `None` if they have none.
+///
+/// Looked up once per [`PROFILE_PHOTO_MAX_AGE_SECS`] and stored on the
+/// user's `CachedUser`, so repeated /info calls don't hit the API. Users we
+/// have no record of are looked up every time.
+pub(crate) async fn profile_photo(bot: &ThrottledBot, state: &AppState, user_id: UserId) -> Option<String> {
+    if let Ok(Some(cached)) = state.users.profile_photo(user_id.0, PROFILE_PHOTO_MAX_AGE_SECS).await {
+        return cached;
+    }
+
+    let photos = match bot.get_user_profile_photos(user_id).limit(1).await {
+        Ok(photos) => photos,
+        Err(e) => {
+            // Not cached: the next call tries again
+            debug!("Failed to get profile photos of {}: {}", user_id, e);
+            return None;
+        }
+    };
+    let file_id = photos
+        .photos
+        .first()
+        .and_then(|sizes| sizes.iter().max_by_key(|p| p.width * p.height))
+        .map(|p| p.file.id.clone());
+
+    if let Err(e) = state.users.set_profile_photo(user_id.0, file_id.clone()).await {
+        debug!("Failed to store profile photo of {}: {}", user_id, e);
+    }
+    file_id
+}
+
+/// Render the info card, showing "unknown" for anything we can't look up.
+async fn build_info_card(
+    state: &AppState,
+    chat_id: ChatId,
+    user_id: UserId,
+    fallback_name: &str,
+    locale: &str,
+) -> String {
+    let unknown = get_text(locale, "common.audit_unknown");
+    let cached = state.users.get_by_id(user_id.0).await.ok().flatten();
+
+    let name = match &cached {
+        Some(user) => match &user.last_name {
+            Some(last) => format!("{} {}", user.first_name, last),
+            None => user.first_name.clone(),
+        },
+        None => fallback_name.to_string(),
+    };
+    let username = cached
+        .as_ref()
+        .and_then(|u| u.username_display.as_ref())
+        .map(|u| format!("@{}", html_escape(u)))
+        .unwrap_or_else(|| unknown.clone());
+
+    // Bot owners pass every admin check, so report them separately
+    let role = if state.is_owner(user_id.0) {
+        get_text(locale, "info.role_bot_owner")
+    } else {
+        match state.permissions.get_admin_info(chat_id, user_id).await {
+            Ok(Some(admin)) if admin.is_owner => get_text(locale, "info.role_owner"),
+            Ok(Some(_)) => get_text(locale, "info.role_admin"),
+            Ok(None) => get_text(locale, "info.role_member"),
+            Err(_) => unknown.clone(),
+        }
+    };
+
+    let warns = match state.warns.get(chat_id.0).await {
+        Ok(Some(data)) => {
+            let count = data
+                .get_user(user_id.0)
+                .map(|u| u.active_count(data.config.warn_time_secs))
+                .unwrap_or(0);
+            format!("{}/{}", count, data.config.limit)
+        }
+        // No warns stored for this chat yet: nobody has any
+        Ok(None) => {
+            let config = state.defaults.load().await.warn.unwrap_or_default();
+            format!("0/{}", config.limit)
+        }
+        Err(_) => unknown.clone(),
+    };
+
+    let approved = match state.message_context.get_or_default(chat_id.0).await {
+        Ok(ctx) if ctx.is_approved(user_id.0) => get_text(locale, "common.yes"),
+        Ok(_) => get_text(locale, "common.no"),
+        Err(_) => unknown,
+    };
+
+    get_text(locale, "info.card")
+        .replace("{id}", &user_id.to_string())
+        .replace("{name}", &html_escape(&name))
+        .replace("{username}", &username)
+        .replace("{role}", &role)
+        .replace("{warns}", &warns)
+        .replace("{approved}", &approved)
+}
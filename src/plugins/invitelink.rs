@@ -0,0 +1,127 @@
+//! Invite link plugin.
+//!
+//! /invitelink creates or revokes invite links. Links are delivered to the
+//! admin's PM so they don't leak into the group.
+
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, ReplyParameters};
+use tracing::info;
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{html_escape, is_pm_unreachable, parse_duration, require_admin};
+
+/// Largest member limit Telegram accepts for an invite link.
+const MAX_MEMBER_LIMIT: u32 = 99_999;
+
+/// Handle /invitelink command.
+///
+/// Usage:
+/// - /invitelink [member_limit] [expiry] - create a new link
+/// - /invitelink revoke [link] - revoke a link, or reset the primary link
+pub async fn invitelink_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::InviteUsers).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+
+    let result = if args.first().is_some_and(|a| a.eq_ignore_ascii_case("revoke")) {
+        match args.get(1) {
+            Some(link) => bot
+                .revoke_chat_invite_link(chat_id, *link)
+                .await
+                .map(|link| get_text(&locale, "invitelink.revoked").replace("{link}", &html_escape(&link))),
+            // Exporting a new primary link revokes the old one
+            None => bot
+                .export_chat_invite_link(chat_id)
+                .await
+                .map(|link| get_text(&locale, "invitelink.primary_reset").replace("{link}", &html_escape(&link))),
+        }
+    } else {
+        let mut member_limit = None;
+        let mut expiry = None;
+        for arg in &args {
+            // Bare numbers are member limits; durations need a unit
+            match arg.parse::<u32>() {
+                Ok(n) if (1..=MAX_MEMBER_LIMIT).contains(&n) => member_limit = Some(n),
+                Err(_) if parse_duration(arg).is_some() => expiry = parse_duration(arg),
+                _ => {
+                    bot.send_message(chat_id, get_text(&locale, "invitelink.usage"))
+                        .parse_mode(ParseMode::Html)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut req = bot.create_chat_invite_link(chat_id);
+        if let Some(limit) = member_limit {
+            req = req.member_limit(limit);
+        }
+        if let Some(expiry) = expiry {
+            req = req.expire_date(state.clock.now() + expiry);
+        }
+        req.await.map(|link| {
+            let mut text = get_text(&locale, "invitelink.created").replace("{link}", &html_escape(&link.invite_link));
+            if let Some(limit) = member_limit {
+                text.push_str(&get_text(&locale, "invitelink.member_limit").replace("{count}", &limit.to_string()));
+            }
+            if let Some(expire) = link.expire_date {
+                text.push_str(
+                    &get_text(&locale, "invitelink.expires")
+                        .replace("{date}", &expire.format("%Y-%m-%d %H:%M UTC").to_string()),
+                );
+            }
+            text
+        })
+    };
+
+    let link_text = match result {
+        Ok(text) => text,
+        Err(e) => {
+            info!("Invite link request failed in chat {}: {}", chat_id, e);
+            bot.send_message(chat_id, get_text(&locale, "invitelink.error_failed"))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Deliver to PM so the link isn't posted in the group
+    let group_title = html_escape(msg.chat.title().unwrap_or("Grup"));
+    let pm_text = get_text(&locale, "invitelink.pm_header").replace("{title}", &group_title) + &link_text;
+    let (text, button) = match bot
+        .send_message(user_id, pm_text)
+        .parse_mode(ParseMode::Html)
+        .await
+    {
+        Ok(_) => (
+            get_text(&locale, "invitelink.sent_pm"),
+            get_text(&locale, "invitelink.open_button"),
+        ),
+        Err(e) if is_pm_unreachable(&e) => (
+            get_text(&locale, "invitelink.start_first"),
+            get_text(&locale, "invitelink.start_button"),
+        ),
+        Err(e) => return Err(e.into()),
+    };
+
+    let pm_link = format!("https://t.me/{}", state.bot_username);
+    bot.send_message(chat_id, text)
+        .reply_markup(InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::url(
+            button,
+            pm_link.parse()?,
+        )]]))
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    info!("Invite link command by {} in chat {}", user_id, chat_id);
+    Ok(())
+}
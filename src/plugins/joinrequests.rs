@@ -0,0 +1,67 @@
+//! Join request command handlers.
+//!
+//! Commands for choosing how join requests are handled in groups that
+//! require admin approval for new members.
+
+use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters};
+use tracing::info;
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::database::models::JoinRequestMode;
+use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::require_admin;
+
+/// Handle /joinrequests command - show or set join request handling.
+///
+/// Usage: /joinrequests [auto|manual]
+pub async fn joinrequests_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let arg = text.split_whitespace().nth(1).map(|a| a.to_lowercase());
+
+    let (mode, key) = match arg.as_deref() {
+        Some("auto" | "on") => (JoinRequestMode::Auto, "joinrequests.set_auto"),
+        Some("manual" | "off") => (JoinRequestMode::Manual, "joinrequests.set_manual"),
+        Some(_) => {
+            bot.send_message(chat_id, get_text(&locale, "joinrequests.usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+        None => {
+            // Show current mode
+            let ctx = state.message_context.get_or_default(chat_id.0).await?;
+            let key = match ctx.join_requests {
+                JoinRequestMode::Auto => "joinrequests.status_auto",
+                JoinRequestMode::Manual => "joinrequests.status_manual",
+            };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    state.message_context.update_join_requests(chat_id.0, mode).await?;
+    info!("Join requests set to {:?} in chat {} by {}", mode, chat_id, user_id);
+
+    bot.send_message(chat_id, get_text(&locale, key))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
@@ -11,18 +11,24 @@ pub mod antiflood;
 pub mod approval;
 pub mod ban;
 pub mod bye;
+pub mod cachestats;
 pub mod filters;
 pub mod help;
+pub mod info;
+pub mod invitelink;
+pub mod joinrequests;
 pub mod mute;
 pub mod notes;
 pub mod ping;
 pub mod pin;
 pub mod purge;
+pub mod report;
 pub mod rules;
 pub mod start;
 pub mod warn;
 pub mod welcome;
 pub mod settings;
+pub mod usage;
 
 use teloxide::dispatching::UpdateHandler;
 use teloxide::prelude::*;
@@ -30,6 +36,7 @@ use teloxide::types::Me;
 use teloxide::utils::command::BotCommands;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::database::CommandScope;
 
 /// All bot commands.
 #[derive(BotCommands, Clone)]
@@ -51,6 +58,12 @@ pub enum Command {
     #[command(description = "Atur hukuman flood")]
     Setfloodpenalty,
 
+    #[command(description = "Naikin batas flood user terpercaya")]
+    Floodtrust,
+
+    #[command(description = "Liat pengaturan & status flood sekarang")]
+    Floodstatus,
+
     // Approval commands
     #[command(description = "Approve user (bypass antiflood)")]
     Approve,
@@ -67,6 +80,10 @@ pub enum Command {
     #[command(description = "Lihat daftar user approved")]
     Approved,
 
+    // Join request commands
+    #[command(description = "Atur persetujuan join request")]
+    Joinrequests,
+
     // Notes commands
     #[command(description = "Simpan note")]
     Save,
@@ -74,15 +91,18 @@ pub enum Command {
     #[command(description = "Ambil note")]
     Get,
 
-    #[command(description = "Lihat semua notes")]
+    #[command(description = "Lihat semua notes, atau on/off buat shortcut #nama")]
     Notes,
 
-    #[command(description = "Lihat semua notes")]
+    #[command(description = "Lihat semua notes, atau on/off buat shortcut #nama")]
     Saved,
 
     #[command(description = "Hapus note")]
     Clear,
 
+    #[command(description = "Lihat info pembuat note")]
+    Noteinfo,
+
     #[command(description = "Hapus semua notes")]
     Clearall,
 
@@ -102,6 +122,21 @@ pub enum Command {
     #[command(description = "Reset welcome")]
     Resetwelcome,
 
+    #[command(description = "Lihat sumber template welcome")]
+    Dumpwelcome,
+
+    #[command(description = "Tambah variasi welcome (dipilih acak)")]
+    Addwelcome,
+
+    #[command(description = "Daftar variasi welcome")]
+    Welcomevariants,
+
+    #[command(description = "Hapus variasi welcome")]
+    Rmwelcome,
+
+    #[command(description = "Hapus welcome lama tiap ada member baru")]
+    Cleanwelcome,
+
     // Rules commands
     #[command(description = "Lihat peraturan grup")]
     Rules,
@@ -115,7 +150,13 @@ pub enum Command {
     #[command(description = "Atur tampilan rules di PM/grup")]
     Setrulesprivate,
 
+    #[command(description = "Lihat sumber teks peraturan")]
+    Dumprules,
+
     // Admin commands
+    #[command(description = "Lihat daftar admin grup")]
+    Admins,
+
     #[command(description = "Promosikan user menjadi admin")]
     Promote,
 
@@ -126,12 +167,15 @@ pub enum Command {
     #[command(description = "Tambah filter auto-reply")]
     Filter,
 
-    #[command(description = "Lihat semua filter")]
+    #[command(description = "Lihat semua filter, atau on/off buat jeda")]
     Filters,
 
     #[command(description = "Hapus filter")]
     Stop,
 
+    #[command(description = "Liat isi dan info filter")]
+    Filterinfo,
+
     #[command(description = "Hapus semua filter")]
     Stopall,
 
@@ -152,6 +196,9 @@ pub enum Command {
     #[command(description = "Pin pesan (silent)")]
     Permapin,
 
+    #[command(description = "Lihat pengumuman grup")]
+    Announcement,
+
     // Ban commands
     #[command(description = "Ban user")]
     Ban,
@@ -219,6 +266,12 @@ pub enum Command {
     #[command(description = "Hapus range dari purgefrom")]
     Purgeto,
 
+    #[command(description = "Hapus pesan terbaru dari user")]
+    Purgeuser,
+
+    #[command(description = "Batalin purge yang lagi jalan")]
+    Cancelpurge,
+
     // Bye commands
     #[command(description = "Pengaturan goodbye")]
     Bye,
@@ -232,6 +285,9 @@ pub enum Command {
     #[command(description = "Reset goodbye")]
     Resetbye,
 
+    #[command(description = "Lihat sumber template goodbye")]
+    Dumpbye,
+
     // Warning commands
     #[command(description = "Beri peringatan user")]
     Warn,
@@ -244,9 +300,15 @@ pub enum Command {
     
     #[command(description = "Lihat peringatan user")]
     Warns,
+
+    #[command(description = "Liat semua user yang kena peringatan")]
+    Warnlist,
     
-    #[command(description = "Hapus peringatan terakhir")]
+    #[command(description = "Hapus peringatan terakhir, atau yang ke-N")]
     Rmwarn,
+
+    #[command(description = "Hapus peringatan (alias /rmwarn)")]
+    Unwarn,
     
     #[command(description = "Reset semua peringatan user")]
     Resetwarn,
@@ -266,11 +328,56 @@ pub enum Command {
     #[command(description = "Ubah masa berlaku peringatan")]
     Warntime,
 
+    #[command(description = "Tambah alasan peringatan cepat")]
+    Addwarnreason,
+
+    #[command(description = "Hapus alasan peringatan cepat")]
+    Rmwarnreason,
+
+    #[command(description = "Lihat alasan peringatan cepat")]
+    Warnreasons,
+
     #[command(description = "Set language (en/id)")]
     Setlang,
 
+    #[command(description = "Atur bahasa pribadi kamu")]
+    Lang,
+
+    #[command(description = "Batasi perintah ke satu topik")]
+    Cmdrestrict,
+
+    #[command(description = "Hapus pesan servis join/keluar")]
+    Cleanservice,
+
+    #[command(description = "Muat ulang pengaturan grup dari database")]
+    Reload,
+
+    #[command(description = "Lihat pengaturan yang beda dari default")]
+    Config,
+
+    #[command(description = "Jadikan pengaturan grup ini default untuk grup baru")]
+    Setglobaldefault,
+
     #[command(description = "Cek latency API Telegram")]
     Ping,
+
+    #[command(description = "Lihat info user: role, warn, dan status approve")]
+    Info,
+
+    #[command(description = "Laporin pesan ke admin")]
+    Report,
+
+    #[command(description = "Nyalain/matiin /report di grup")]
+    Reports,
+
+    #[command(description = "Lihat perintah yang paling sering dipakai di grup")]
+    Usage,
+
+    #[command(description = "Bikin atau cabut link undangan")]
+    Invitelink,
+
+    #[command(description = "Statistik cache bot (khusus owner)")]
+    Cachestats,
 }
 
 /// Build the combined command handler.
@@ -280,24 +387,37 @@ pub fn command_handler() -> UpdateHandler<anyhow::Error> {
     
 
     teloxide::filter_command::<Command, _>()
+        // Drop commands sent outside the allowed topic
+        .branch(dptree::filter_async(is_command_restricted).endpoint(drop_command))
+        // Count what's left for /usage
+        .chain(dptree::inspect(|msg: Message, state: AppState| {
+            if let Some(text) = msg.text() {
+                state.usage.record(msg.chat.id.0, text);
+            }
+        }))
         .branch(case![Command::Start(args)].endpoint(handle_start))
         .branch(case![Command::Help].endpoint(handle_help))
         // Antiflood
         .branch(case![Command::Antiflood].endpoint(antiflood::antiflood_command))
         .branch(case![Command::Setflood].endpoint(antiflood::setflood_command))
         .branch(case![Command::Setfloodpenalty].endpoint(antiflood::setfloodpenalty_command))
+        .branch(case![Command::Floodtrust].endpoint(antiflood::floodtrust_command))
+        .branch(case![Command::Floodstatus].endpoint(antiflood::floodstatus_command))
         // Approval
         .branch(case![Command::Approve].endpoint(approval::approve_command))
         .branch(case![Command::Unapprove].endpoint(approval::unapprove_command))
         .branch(case![Command::Unapproveall].endpoint(approval::unapproveall_command))
         .branch(case![Command::Approval].endpoint(approval::approval_command))
         .branch(case![Command::Approved].endpoint(approval::approved_command))
+        // Join requests
+        .branch(case![Command::Joinrequests].endpoint(joinrequests::joinrequests_command))
         // Notes
         .branch(case![Command::Save].endpoint(notes::save_command))
         .branch(case![Command::Get].endpoint(handle_get))
         .branch(case![Command::Notes].endpoint(notes::notes_command))
         .branch(case![Command::Saved].endpoint(notes::notes_command))
         .branch(case![Command::Clear].endpoint(notes::clear_command))
+        .branch(case![Command::Noteinfo].endpoint(notes::noteinfo_command))
         .branch(case![Command::Clearall].endpoint(notes::clearall_command))
         .branch(case![Command::Privatenotes].endpoint(notes::privatenotes_command))
         // Welcome
@@ -305,18 +425,26 @@ pub fn command_handler() -> UpdateHandler<anyhow::Error> {
         .branch(case![Command::Setwelcome].endpoint(welcome::setwelcome_command))
         .branch(case![Command::Setwelcomebuttons].endpoint(welcome::setwelcomebuttons_command))
         .branch(case![Command::Resetwelcome].endpoint(welcome::resetwelcome_command))
+        .branch(case![Command::Dumpwelcome].endpoint(welcome::dumpwelcome_command))
+        .branch(case![Command::Addwelcome].endpoint(welcome::addwelcome_command))
+        .branch(case![Command::Welcomevariants].endpoint(welcome::welcomevariants_command))
+        .branch(case![Command::Rmwelcome].endpoint(welcome::rmwelcome_command))
+        .branch(case![Command::Cleanwelcome].endpoint(welcome::cleanwelcome_command))
         // Rules
         .branch(case![Command::Rules].endpoint(handle_rules))
         .branch(case![Command::Setrules].endpoint(rules::setrules_command))
         .branch(case![Command::Clearrules].endpoint(rules::clearrules_command))
         .branch(case![Command::Setrulesprivate].endpoint(rules::setrulesprivate_command))
+        .branch(case![Command::Dumprules].endpoint(rules::dumprules_command))
         // Admin
+        .branch(case![Command::Admins].endpoint(admin::admins_command))
         .branch(case![Command::Promote].endpoint(admin::promote_command))
         .branch(case![Command::Demote].endpoint(admin::demote_command))
         // Filters
         .branch(case![Command::Filter].endpoint(filters::filter_command))
         .branch(case![Command::Filters].endpoint(filters::filters_command))
         .branch(case![Command::Stop].endpoint(filters::stop_command))
+        .branch(case![Command::Filterinfo].endpoint(filters::filterinfo_command))
         .branch(case![Command::Stopall].endpoint(filters::stopall_command))
         // AFK
         .branch(case![Command::Afk].endpoint(afk::afk_command))
@@ -325,6 +453,7 @@ pub fn command_handler() -> UpdateHandler<anyhow::Error> {
         .branch(case![Command::Pin].endpoint(pin::pin_command))
         .branch(case![Command::Unpin].endpoint(pin::unpin_command))
         .branch(case![Command::Permapin].endpoint(pin::permapin_command))
+        .branch(case![Command::Announcement].endpoint(pin::announcement_command))
         // Ban
         .branch(case![Command::Ban].endpoint(ban::ban_command))
         .branch(case![Command::Unban].endpoint(ban::unban_command))
@@ -350,27 +479,91 @@ pub fn command_handler() -> UpdateHandler<anyhow::Error> {
         .branch(case![Command::Del].endpoint(purge::del_command))
         .branch(case![Command::Purgefrom].endpoint(purge::purgefrom_command))
         .branch(case![Command::Purgeto].endpoint(purge::purgeto_command))
+        .branch(case![Command::Purgeuser].endpoint(purge::purgeuser_command))
+        .branch(case![Command::Cancelpurge].endpoint(purge::cancelpurge_command))
         // Bye
         .branch(case![Command::Bye].endpoint(bye::bye_command))
         .branch(case![Command::Setbye].endpoint(bye::setbye_command))
         .branch(case![Command::Setbyebuttons].endpoint(bye::setbyebuttons_command))
         .branch(case![Command::Resetbye].endpoint(bye::resetbye_command))
+        .branch(case![Command::Dumpbye].endpoint(bye::dumpbye_command))
         // Warning
         .branch(case![Command::Warn].endpoint(warn::warn_command))
         .branch(case![Command::Dwarn].endpoint(warn::dwarn_command))
         .branch(case![Command::Swarn].endpoint(warn::swarn_command))
         .branch(case![Command::Warns].endpoint(warn::warns_command))
+        .branch(case![Command::Warnlist].endpoint(warn::warnlist_command))
         .branch(case![Command::Rmwarn].endpoint(warn::rmwarn_command))
+        .branch(case![Command::Unwarn].endpoint(warn::unwarn_command))
         .branch(case![Command::Resetwarn].endpoint(warn::resetwarn_command))
         .branch(case![Command::Resetallwarns].endpoint(warn::resetallwarns_command))
         .branch(case![Command::Warnings].endpoint(warn::warnings_command))
         .branch(case![Command::Warnmode].endpoint(warn::warnmode_command))
         .branch(case![Command::Warnlimit].endpoint(warn::warnlimit_command))
         .branch(case![Command::Warntime].endpoint(warn::warntime_command))
+        .branch(case![Command::Addwarnreason].endpoint(warn::addwarnreason_command))
+        .branch(case![Command::Rmwarnreason].endpoint(warn::rmwarnreason_command))
+        .branch(case![Command::Warnreasons].endpoint(warn::warnreasons_command))
         // Settings
         .branch(case![Command::Setlang].endpoint(settings::setlang_command))
+        .branch(case![Command::Lang].endpoint(settings::lang_command))
+        .branch(case![Command::Cmdrestrict].endpoint(settings::cmdrestrict_command))
+        .branch(case![Command::Cleanservice].endpoint(settings::cleanservice_command))
+        .branch(case![Command::Reload].endpoint(settings::reload_command))
+        .branch(case![Command::Config].endpoint(settings::config_command))
+        .branch(case![Command::Setglobaldefault].endpoint(settings::setglobaldefault_command))
         // Ping
         .branch(case![Command::Ping].endpoint(ping::ping_command))
+        // Info
+        .branch(case![Command::Info].endpoint(info::info_command))
+        // Reports
+        .branch(case![Command::Report].endpoint(report::report_command))
+        .branch(case![Command::Reports].endpoint(report::reports_command))
+        // Usage
+        .branch(case![Command::Usage].endpoint(usage::usage_command))
+        .branch(case![Command::Cachestats].endpoint(cachestats::cachestats_command))
+        // Invite links
+        .branch(case![Command::Invitelink].endpoint(invitelink::invitelink_command))
+}
+
+/// Check if a command was sent outside the chat's allowed command topic.
+async fn is_command_restricted(msg: Message, cmd: Command, state: AppState) -> bool {
+    // Always allow lifting the restriction
+    if matches!(cmd, Command::Cmdrestrict) {
+        return false;
+    }
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return false;
+    }
+
+    let ctx = match state.message_context.get_or_default(msg.chat.id.0).await {
+        Ok(ctx) => ctx,
+        Err(_) => return false,
+    };
+
+    let allowed = match ctx.command_thread {
+        Some(thread) => thread,
+        None => return false,
+    };
+
+    if settings::message_thread(&msg) == allowed {
+        return false;
+    }
+
+    match ctx.command_scope {
+        CommandScope::All => true,
+        CommandScope::Users => {
+            let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
+            !state.is_owner(user_id.0)
+                && !state.permissions.is_admin(msg.chat.id, user_id).await.unwrap_or(false)
+        }
+    }
+}
+
+/// Silently ignore a restricted command.
+async fn drop_command() -> anyhow::Result<()> {
+    Ok(())
 }
 
 /// Build hashtag handler for notes.
@@ -404,11 +597,14 @@ async fn handle_start(
         let chat_id_str = args.strip_prefix("rules_").unwrap();
         return rules::handle_rules_deeplink(bot, msg, state, chat_id_str).await;
     }
+    if let Some(payload) = args.strip_prefix("note_") {
+        return notes::handle_note_deeplink(bot, msg, state, payload).await;
+    }
 
     // Help deep link
-    if args == "help" {
+    if let Some(scope) = help::HelpScope::from_start_payload(&args) {
         let locale = state.get_locale(Some(msg.chat.id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
-        return help::send_help_menu(&bot, msg.chat.id, &locale).await;
+        return help::send_help_menu(&bot, msg.chat.id, &locale, scope).await;
     }
 
     // Default start message
@@ -426,6 +622,18 @@ pub fn callback_handler() -> UpdateHandler<anyhow::Error> {
         .branch(dptree::filter(|q: CallbackQuery| {
             q.data.as_ref().map(|d| d.starts_with("warn_remove:")).unwrap_or(false)
         }).endpoint(warn::warn_callback_handler))
+        .branch(dptree::filter(|q: CallbackQuery| {
+            q.data.as_ref().map(|d| d.starts_with("warnlist_page:")).unwrap_or(false)
+        }).endpoint(warn::warnlist_callback_handler))
+        .branch(dptree::filter(|q: CallbackQuery| {
+            q.data.as_ref().map(|d| d.starts_with("flood:")).unwrap_or(false)
+        }).endpoint(antiflood::flood_callback_handler))
+        .branch(dptree::filter(|q: CallbackQuery| {
+            q.data.as_ref().map(|d| d.starts_with("notes_clearall:")).unwrap_or(false)
+        }).endpoint(notes::clearall_callback_handler))
+        .branch(dptree::filter(|q: CallbackQuery| {
+            q.data.as_ref().map(|d| d.starts_with(welcome::VERIFY_CALLBACK_PREFIX)).unwrap_or(false)
+        }).endpoint(welcome::verify_callback_handler))
         .branch(dptree::endpoint(help::callback_handler))
 }
 
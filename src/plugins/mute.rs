@@ -2,13 +2,16 @@
 //!
 //! Commands for muting and unmuting users.
 
+use chrono::{DateTime, Utc};
 use teloxide::prelude::*;
-use teloxide::types::{ChatPermissions, ParseMode, ReplyParameters, UserId};
-use std::time::{SystemTime, UNIX_EPOCH};
+use teloxide::types::{ChatMemberKind, ChatPermissions, ParseMode, ReplyParameters, UntilDate, UserId};
+use tracing::warn;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::utils::{html_escape, parse_duration, get_target_from_msg};
+use crate::utils::{format_duration_full, html_escape, is_flag, parse_duration, get_target_from_msg, get_target_ids, mention_user_id, restrict_until, require_admin, extends};
+use crate::constants::{MAX_TARGET_IDS, MIN_TEMP_RESTRICTION};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
 
 /// Handle /mute command - now supports optional duration.
 /// /mute @user = mute forever
@@ -37,6 +40,34 @@ pub async fn unmute_command(bot: ThrottledBot, msg: Message, state: AppState) ->
     mute_action(bot, msg, state, MuteMode::Unmute).await
 }
 
+/// When a user's current mute ends, if they are muted.
+async fn current_mute(bot: &ThrottledBot, chat_id: ChatId, user_id: UserId) -> Option<UntilDate> {
+    let member = bot.get_chat_member(chat_id, user_id).await.ok()?;
+    match member.kind {
+        ChatMemberKind::Restricted(r) if !r.can_send_messages => Some(r.until_date),
+        _ => None,
+    }
+}
+
+/// Permissions restored by /unmute.
+pub(crate) fn unmuted_permissions() -> ChatPermissions {
+    ChatPermissions::empty()
+        | ChatPermissions::SEND_MESSAGES
+        | ChatPermissions::SEND_AUDIOS
+        | ChatPermissions::SEND_DOCUMENTS
+        | ChatPermissions::SEND_PHOTOS
+        | ChatPermissions::SEND_VIDEOS
+        | ChatPermissions::SEND_VIDEO_NOTES
+        | ChatPermissions::SEND_VOICE_NOTES
+        | ChatPermissions::SEND_POLLS
+        | ChatPermissions::SEND_OTHER_MESSAGES
+        | ChatPermissions::ADD_WEB_PAGE_PREVIEWS
+        | ChatPermissions::CHANGE_INFO
+        | ChatPermissions::INVITE_USERS
+        | ChatPermissions::PIN_MESSAGES
+        | ChatPermissions::MANAGE_TOPICS
+}
+
 #[derive(PartialEq, Clone, Copy)]
 enum MuteMode {
     Normal,     // /mute - optional duration (default forever)
@@ -53,25 +84,30 @@ async fn mute_action(
     mode: MuteMode,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
 
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    // Permission check
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
 
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    // Errors go to the admin in their language, announcements to the group
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
+    let group_locale = state.locale_for_group(chat_id.0).await;
 
-    // Permission check
-    if !state.permissions.can_restrict_members(chat_id, user_id).await.unwrap_or(false) {
+    // `/mute 123 456 789 reason` acts on every listed ID
+    let ids = get_target_ids(&msg, mode == MuteMode::Temporary);
+    if ids.len() > MAX_TARGET_IDS {
         bot.send_message(
             chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanRestrictMembers"),
+            get_text(&locale, "common.error_too_many_targets").replace("{max}", &MAX_TARGET_IDS.to_string()),
         )
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
     }
+    if ids.len() > 1 {
+        return mute_many(&bot, &msg, &state, mode, &ids, &locale, &group_locale).await;
+    }
 
     // Use shared target resolver
     let (target_id, target_name, skip_words) = match get_target_from_msg(&bot, &msg, &state).await {
@@ -89,9 +125,69 @@ async fn mute_action(
     let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
     let reason_start_idx = skip_words;
 
-    // Anti-Admin
-    if mode != MuteMode::Unmute
-        && state.permissions.is_admin(chat_id, target_id).await.unwrap_or(false) {
+    // Mutes take an optional duration (/tmute requires one); unmutes take none
+    let requires_duration = mode == MuteMode::Temporary;
+    let (until_dt, display_duration, reason_idx) = if mode == MuteMode::Unmute {
+        (None, None, reason_start_idx)
+    } else if parts.len() > reason_start_idx {
+        // Try to parse duration from first available arg. Bare numbers only
+        // count as seconds for /tmute, otherwise they start the reason.
+        let arg = parts[reason_start_idx];
+        let is_bare_number = arg.chars().all(|c| c.is_ascii_digit());
+        let parsed = if is_bare_number && !requires_duration {
+            None
+        } else {
+            parse_duration(arg).map(|d| d.max(MIN_TEMP_RESTRICTION))
+        };
+        if let Some(d) = parsed {
+            let dt = restrict_until(state.clock.as_ref(), d);
+            (Some(dt), Some(d), reason_start_idx + 1)
+        } else if requires_duration {
+            // /tmute requires duration but got invalid format
+            bot.send_message(chat_id, get_text(&locale, "mute.error_time_format"))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        } else {
+            // /mute, /dmute, /smute - no duration = forever, treat as reason
+            (None, None, reason_start_idx)
+        }
+    } else if requires_duration {
+        // /tmute without any args after target
+        bot.send_message(chat_id, get_text(&locale, "mute.error_duration_missing"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    } else {
+        (None, None, reason_start_idx)
+    };
+
+    // Extract reason - None if not provided
+    let reason = if parts.len() > reason_idx {
+        let r = parts[reason_idx..].join(" ");
+        if r.is_empty() { None } else { Some(r) }
+    } else {
+        None
+    };
+
+    // A timed re-mute replaces the old end date; note which way it moved
+    let existing = match until_dt {
+        Some(dt) => current_mute(&bot, chat_id, target_id)
+            .await
+            .map(|existing| extends(existing, dt)),
+        None => None,
+    };
+
+    let applied = mute_target(&bot, &state, chat_id, target_id, mode, until_dt).await;
+
+    // For silent modes, delete the command; refusing an admin still replies to it
+    if mode == MuteMode::SilentMute && !matches!(applied, Err(Skipped::Admin)) {
+        let _ = bot.delete_message(chat_id, msg.id).await;
+    }
+
+    match applied {
+        Ok(()) => {}
+        Err(Skipped::Admin) => {
             bot.send_message(
                 chat_id,
                 get_text(&locale, "mute.anti_admin")
@@ -100,122 +196,181 @@ async fn mute_action(
                 .await?;
             return Ok(());
         }
-
-    // For silent modes, delete command message first
-    if mode == MuteMode::SilentMute {
-        let _ = bot.delete_message(chat_id, msg.id).await;
+        Err(Skipped::Failed(e)) => return Err(e),
     }
 
-    // Mode handling
     match mode {
-        MuteMode::Normal | MuteMode::Temporary | MuteMode::DeleteMute | MuteMode::SilentMute => {
-            // For Normal mode: duration is optional (default forever)
-            // For Temporary mode: duration is required
-            // For DeleteMute/SilentMute: behavior like Normal (optional duration)
-            
-            let requires_duration = mode == MuteMode::Temporary;
-            
-            let (until_dt, display_duration, reason_idx) = if parts.len() > reason_start_idx {
-                // Try to parse duration from first available arg
-                if let Some(d) = parse_duration(parts[reason_start_idx]) {
-                    let until = SystemTime::now() + d;
-                    let until_date = until.duration_since(UNIX_EPOCH)?.as_secs();
-                    let dt = chrono::DateTime::from_timestamp(until_date as i64, 0).unwrap_or_default();
-                    (Some(dt), Some(d), reason_start_idx + 1)
-                } else if requires_duration {
-                    // /tmute requires duration but got invalid format
-                    bot.send_message(chat_id, get_text(&locale, "mute.error_time_format"))
-                        .reply_parameters(ReplyParameters::new(msg.id))
-                        .await?;
-                    return Ok(());
-                } else {
-                    // /mute, /dmute, /smute - no duration = forever, treat as reason
-                    (None, None, reason_start_idx)
-                }
-            } else if requires_duration {
-                // /tmute without any args after target
-                bot.send_message(chat_id, get_text(&locale, "mute.error_duration_missing"))
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-                return Ok(());
-            } else {
-                (None, None, reason_start_idx)
-            };
-
-            // Extract reason - None if not provided
-            let reason = if parts.len() > reason_idx {
-                let r = parts[reason_idx..].join(" ");
-                if r.is_empty() { None } else { Some(r) }
-            } else {
-                None
-            };
-
+        MuteMode::Unmute => {
+            bot.send_message(chat_id, get_text(&group_locale, "mute.unmuted")
+                .replace("{id}", &target_id.to_string())
+                .replace("{name}", &html_escape(&target_name))
+            ).parse_mode(ParseMode::Html).await?;
+        }
+        // Don't send message for silent mode
+        MuteMode::SilentMute => {}
+        MuteMode::Normal | MuteMode::Temporary | MuteMode::DeleteMute => {
             // Delete replied message for DeleteMute
             if mode == MuteMode::DeleteMute
                 && let Some(reply) = msg.reply_to_message() {
                     let _ = bot.delete_message(chat_id, reply.id).await;
                 }
 
-            // Mute permissions
-            let permissions = ChatPermissions::empty(); // No rights = Muted
+            let duration_msg = display_duration
+                .map(|d| get_text(&group_locale, "mute.duration").replace("{duration}", &format_duration_full(d.as_secs())))
+                .unwrap_or_default();
+            let reason_line = reason.as_ref()
+                .map(|r| get_text(&group_locale, "mute.reason").replace("{reason}", &html_escape(r)))
+                .unwrap_or_default();
 
-            let req = bot.restrict_chat_member(chat_id, target_id, permissions);
-            let req = if let Some(dt) = until_dt {
-                req.until_date(dt)
+            let action_text = if let Some(extended) = existing {
+                get_text(&group_locale, if extended { "mute.action_extended" } else { "mute.action_shortened" })
+            } else if mode == MuteMode::DeleteMute {
+                get_text(&group_locale, "mute.action_muted_and_deleted")
             } else {
-                req
+                get_text(&group_locale, "mute.action_muted")
             };
 
-            req.await?;
-
-            // Don't send message for silent mode
-            if mode != MuteMode::SilentMute {
-                let duration_msg = display_duration.map(|d| format!("\nDurasi: {:?}", d)).unwrap_or_default();
-                let reason_line = reason.as_ref()
-                    .map(|r| get_text(&locale, "mute.reason").replace("{reason}", &html_escape(r)))
-                    .unwrap_or_default();
-                
-                let action_text = if mode == MuteMode::DeleteMute {
-                    get_text(&locale, "mute.action_muted_and_deleted")
-                } else {
-                    get_text(&locale, "mute.action_muted")
-                };
-
-                bot.send_message(chat_id, get_text(&locale, "mute.muted")
-                    .replace("{id}", &target_id.to_string())
-                    .replace("{name}", &html_escape(&target_name))
-                    .replace("{action}", &action_text)
-                    .replace("{duration}", &duration_msg)
-                    .replace("{reason}", &reason_line)
-                ).parse_mode(ParseMode::Html).await?;
-            }
-        },
-        MuteMode::Unmute => {
-            // Lift restrictions = Unmute
-            let permissions = ChatPermissions::empty()
-                | ChatPermissions::SEND_MESSAGES
-                | ChatPermissions::SEND_AUDIOS
-                | ChatPermissions::SEND_DOCUMENTS
-                | ChatPermissions::SEND_PHOTOS
-                | ChatPermissions::SEND_VIDEOS
-                | ChatPermissions::SEND_VIDEO_NOTES
-                | ChatPermissions::SEND_VOICE_NOTES
-                | ChatPermissions::SEND_POLLS
-                | ChatPermissions::SEND_OTHER_MESSAGES
-                | ChatPermissions::ADD_WEB_PAGE_PREVIEWS
-                | ChatPermissions::CHANGE_INFO
-                | ChatPermissions::INVITE_USERS
-                | ChatPermissions::PIN_MESSAGES
-                | ChatPermissions::MANAGE_TOPICS;
-            
-            bot.restrict_chat_member(chat_id, target_id, permissions).await?;
-
-            bot.send_message(chat_id, get_text(&locale, "mute.unmuted")
+            bot.send_message(chat_id, get_text(&group_locale, "mute.muted")
                 .replace("{id}", &target_id.to_string())
                 .replace("{name}", &html_escape(&target_name))
+                .replace("{action}", &action_text)
+                .replace("{duration}", &duration_msg)
+                .replace("{reason}", &reason_line)
             ).parse_mode(ParseMode::Html).await?;
         }
     }
 
     Ok(())
 }
+
+/// Why a mute-family action was not applied to a target.
+enum Skipped {
+    /// Admins can't be muted.
+    Admin,
+    /// The Telegram call failed.
+    Failed(anyhow::Error),
+}
+
+/// Check and apply a mute-family action to one target.
+///
+/// Shared by single and multi-target commands. `until` only applies to mutes.
+async fn mute_target(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    target_id: UserId,
+    mode: MuteMode,
+    until: Option<DateTime<Utc>>,
+) -> Result<(), Skipped> {
+    if mode != MuteMode::Unmute && state.permissions.is_admin(chat_id, target_id).await.unwrap_or(false) {
+        return Err(Skipped::Admin);
+    }
+
+    let permissions = if mode == MuteMode::Unmute {
+        unmuted_permissions()
+    } else {
+        ChatPermissions::empty() // No rights = Muted
+    };
+    let mut req = bot.restrict_chat_member(chat_id, target_id, permissions);
+    if let Some(dt) = until {
+        req = req.until_date(dt);
+    }
+    req.await.map_err(|e| Skipped::Failed(e.into()))?;
+    Ok(())
+}
+
+/// Apply a mute-family action to several user IDs and post one summary.
+///
+/// Every target gets its own anti-admin check, so an admin in the list
+/// doesn't stop the others. There's no replied message here, so /dmute acts
+/// like /mute.
+async fn mute_many(
+    bot: &ThrottledBot,
+    msg: &Message,
+    state: &AppState,
+    mode: MuteMode,
+    targets: &[UserId],
+    locale: &str,
+    group_locale: &str,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    let text = msg.text().unwrap_or("");
+    let parts: Vec<&str> = text.split_whitespace().skip(1).collect();
+    let flags = parts.iter().take_while(|a| is_flag(a)).count();
+    let mut reason_idx = flags + targets.len();
+
+    // A duration may follow the IDs; /tmute requires one
+    let duration = if mode == MuteMode::Unmute {
+        None
+    } else {
+        parts.get(reason_idx).and_then(|a| parse_duration(a))
+    };
+    match duration {
+        Some(_) => reason_idx += 1,
+        None if mode == MuteMode::Temporary => {
+            bot.send_message(chat_id, get_text(locale, "mute.error_time_format"))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+        None => {}
+    }
+    let duration = duration.map(|d| d.max(MIN_TEMP_RESTRICTION));
+
+    if mode == MuteMode::SilentMute {
+        let _ = bot.delete_message(chat_id, msg.id).await;
+    }
+
+    let until_dt = duration.map(|d| restrict_until(state.clock.as_ref(), d));
+    let mut lines = String::new();
+    let mut done = 0;
+    for &target_id in targets {
+        let result = mute_target(bot, state, chat_id, target_id, mode, until_dt).await.map_err(|skipped| match skipped {
+            Skipped::Admin => get_text(group_locale, "mute.multi_admin"),
+            Skipped::Failed(e) => {
+                warn!("Failed to act on {} in chat {}: {}", target_id, chat_id, e);
+                get_text(group_locale, "mute.multi_failed")
+            }
+        });
+
+        let user = mention_user_id(state, target_id.0).await;
+        match result {
+            Ok(()) => {
+                done += 1;
+                lines.push_str(&get_text(group_locale, "mute.multi_ok").replace("{user}", &user));
+            }
+            Err(error) => lines.push_str(
+                &get_text(group_locale, "mute.multi_skipped")
+                    .replace("{user}", &user)
+                    .replace("{error}", &error),
+            ),
+        }
+    }
+
+    if mode == MuteMode::SilentMute {
+        return Ok(());
+    }
+
+    let key = if mode == MuteMode::Unmute { "mute.multi_unmuted" } else { "mute.multi_muted" };
+    let duration_msg = duration
+        .map(|d| get_text(group_locale, "mute.duration").replace("{duration}", &format_duration_full(d.as_secs())))
+        .unwrap_or_default();
+    let reason_line = parts
+        .get(reason_idx..)
+        .map(|r| r.join(" "))
+        .filter(|r| !r.is_empty())
+        .map(|r| get_text(group_locale, "mute.reason").replace("{reason}", &html_escape(&r)))
+        .unwrap_or_default();
+    let summary = get_text(group_locale, key)
+        .replace("{done}", &done.to_string())
+        .replace("{total}", &targets.len().to_string())
+        .replace("{duration}", &duration_msg)
+        .replace("{reason}", &reason_line);
+    bot.send_message(chat_id, summary + &lines)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
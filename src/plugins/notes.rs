@@ -3,12 +3,15 @@
 //! Handles saving and retrieving notes using decentralized repository.
 
 use teloxide::prelude::*;
-use teloxide::types::{ParseMode, ReplyParameters, InlineKeyboardMarkup, InlineKeyboardButton, InputFile};
+use teloxide::types::{ParseMode, ReplyParameters, InlineKeyboardMarkup, InlineKeyboardButton, MessageId, User};
+use teloxide::RequestError;
+use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
 use crate::database::models::DbNote;
-use crate::utils::{apply_fillings_new, html_escape, parser::parse_buttons};
+use crate::utils::{apply_fillings_as, apply_notelink_filling, format_audit, html_escape, is_pm_unreachable, link_preview_options, send_content, require_admin, Content, parser::{parse_buttons, parse_tags}};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
 
 async fn save_note(
     bot: ThrottledBot,
@@ -27,6 +30,11 @@ async fn save_note(
         return Ok(());
     }
 
+    // Only admins who can change group info may create or edit notes
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
     let name = args[0].to_lowercase();
     
     // Determine content and media from multiple sources:
@@ -44,13 +52,10 @@ async fn save_note(
     
     // Get content: prefer inline, then reply text/caption
     let content = if !inline_content.is_empty() {
-        inline_content.to_string()
+        crate::utils::tail_html(full_text, msg.entities(), inline_content)
     } else if let Some(reply_msg) = reply {
-        // Try text first, then caption
-        reply_msg.text()
-            .or_else(|| reply_msg.caption())
-            .map(String::from)
-            .unwrap_or_default()
+        // Text or caption, with its formatting kept as HTML
+        crate::utils::message_html(reply_msg).unwrap_or_default()
     } else {
         String::new()
     };
@@ -82,14 +87,30 @@ async fn save_note(
         return Ok(());
     }
     
-    // Parse buttons if any
-    let (clean_content, buttons) = parse_buttons(&content);
+    // Parse buttons and tags ({admin}, {private}, {preview}, {markdown}...) if any
+    let (content, buttons) = parse_buttons(&content);
+    let (clean_content, tags) = parse_tags(&content);
     
-    let mut note = DbNote::new(msg.chat.id.0, &name, &clean_content);
+    let mut note = DbNote::new(msg.chat.id.0, &name, clean_content.trim());
     note.buttons = buttons;
+    note.admin_only = tags.admin_only;
+    note.user_only = tags.user_only;
+    note.protect = tags.protect;
+    note.private = if tags.is_private {
+        Some(true)
+    } else if tags.no_private {
+        Some(false)
+    } else {
+        None
+    };
+    note.preview = tags.preview;
+    note.format = tags.format;
     note.file_id = file_id;
     note.file_type = file_type;
 
+    let previous = state.notes.get_note(msg.chat.id.0, &name).await?;
+    note.stamp_audit(previous.as_ref(), user_id.0);
+
     state.notes.save_note(&note).await?;
 
     bot.send_message(msg.chat.id, get_text(&locale, "notes.saved").replace("{name}", &html_escape(&name)))
@@ -123,7 +144,11 @@ async fn list_notes(
         .collect::<Vec<_>>()
         .join("\n");
 
-    let notes_footer = get_text(&locale, "notes.list_footer");
+    let mut notes_footer = get_text(&locale, "notes.list_footer");
+    let ctx = state.message_context.get_or_default(msg.chat.id.0).await?;
+    if !ctx.hashtag_notes {
+        notes_footer.push_str(&get_text(&locale, "notes.hashtag_paused"));
+    }
 
     bot.send_message(msg.chat.id, format!("{}\n\n{}\n\n{}", get_text(&locale, "notes.list_header"), notes_list, notes_footer))
         .parse_mode(ParseMode::Html)
@@ -147,7 +172,7 @@ async fn get_note(
     
     // Uses L2 Cache (Content)
     if let Some(note) = state.notes.get_note(msg.chat.id.0, name_clean).await? {
-        send_note_response(&bot, &msg, &note).await?;
+        send_note_response(&bot, &msg, &state, &note).await?;
     } else {
         let locale = state.get_locale(Some(msg.chat.id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
         bot.send_message(msg.chat.id, get_text(&locale, "notes.not_found").replace("{name}", &html_escape(name_clean)))
@@ -172,6 +197,10 @@ async fn clear_note(
     // Resolve locale
     let locale = state.get_locale(Some(msg.chat.id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
 
+    if require_admin(&bot, &msg, &state, Permission::ChangeInfo).await?.is_none() {
+        return Ok(());
+    }
+
     if state.notes.delete_note(msg.chat.id.0, &name).await? {
         bot.send_message(msg.chat.id, get_text(&locale, "notes.deleted").replace("{name}", &html_escape(&name)))
             .parse_mode(ParseMode::Html)
@@ -186,80 +215,108 @@ async fn clear_note(
     Ok(())
 }
 
+/// Send a note where it was requested, or to the requester's PM when the
+/// chat has private notes enabled (a `{private}` / `{noprivate}` tag on the
+/// note overrides that).
 async fn send_note_response(
     bot: &ThrottledBot,
     msg: &Message,
+    state: &AppState,
     note: &DbNote,
 ) -> anyhow::Result<()> {
-    let user = msg.from.as_ref().unwrap();
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let chat_id = msg.chat.id;
+    let reply_to = msg.reply_to_message().map(|m| m.id).unwrap_or(msg.id);
+
+    // {admin} / {user} notes stay silent for everyone else, like filters
+    if (note.admin_only || note.user_only)
+        && !note.visible_to(state.permissions.is_admin(chat_id, user.id).await.unwrap_or(false))
+    {
+        return Ok(());
+    }
+
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+    if !note.private.unwrap_or(ctx.private_notes) {
+        deliver_note(bot, state, chat_id, Some(reply_to), user, note).await?;
+        return Ok(());
+    }
+
+    let locale = state.get_locale_in(&ctx, Some(user.id.0)).await;
+    let pm_link = format!("https://t.me/{}", state.bot_username);
+
+    let (text, button) = match deliver_note(bot, state, ChatId(user.id.0 as i64), None, user, note).await {
+        Ok(()) => (
+            get_text(&locale, "notes.private_sent").replace("{name}", &html_escape(&note.name)),
+            get_text(&locale, "notes.private_open_button"),
+        ),
+        Err(e) if is_pm_unreachable(&e) => (
+            get_text(&locale, "notes.private_start_first"),
+            get_text(&locale, "notes.private_start_button"),
+        ),
+        Err(e) => return Err(e.into()),
+    };
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::url(
+        button,
+        pm_link.parse()?,
+    )]]);
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_markup(keyboard)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Send the note content to `chat_id`, optionally as a reply.
+async fn deliver_note(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    reply_to: Option<MessageId>,
+    user: &User,
+    note: &DbNote,
+) -> Result<(), RequestError> {
      // Apply fillings if needed
-    let text = apply_fillings_new(&note.content, user, "Grup", None);
+    let content = note.format.prepare(&note.content);
+    let text = apply_fillings_as(&content, user, "Grup", None, note.format);
+    let (text, note_links) = apply_notelink_filling(&text, note.chat_id, &state.bot_username);
 
     // Build keyboard
-    let keyboard = if !note.buttons.is_empty() {
-        let rows: Vec<Vec<InlineKeyboardButton>> = note
-            .buttons
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .filter_map(|btn| {
-                        btn.url.parse().ok().map(|url| {
-                            InlineKeyboardButton::url(&btn.text, url)
-                        })
+    let rows: Vec<Vec<InlineKeyboardButton>> = note
+        .buttons
+        .iter()
+        .cloned()
+        .chain(note_links.into_iter().map(|btn| vec![btn]))
+        .map(|row| {
+            row.iter()
+                .filter_map(|btn| {
+                    btn.url.parse().ok().map(|url| {
+                        InlineKeyboardButton::url(&btn.text, url)
                     })
-                    .collect()
-            })
-            .filter(|row: &Vec<_>| !row.is_empty())
-            .collect();
-        
-        if rows.is_empty() {
-            None
-        } else {
-            Some(InlineKeyboardMarkup::new(rows))
-        }
-    } else {
+                })
+                .collect()
+        })
+        .filter(|row: &Vec<_>| !row.is_empty())
+        .collect();
+    let keyboard = if rows.is_empty() {
         None
+    } else {
+        Some(InlineKeyboardMarkup::new(rows))
     };
 
-    let chat_id = msg.chat.id;
-    let reply_to = msg.reply_to_message().map(|m| m.id).unwrap_or(msg.id);
-
     // Send based on media
-     match (&note.file_id, &note.file_type) {
-        (Some(file_id), Some(media_type)) => {
-             match media_type.as_str() {
-                "photo" => {
-                    let mut req = bot.send_photo(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() { req = req.caption(&text).parse_mode(ParseMode::Html); }
-                    if let Some(kb) = keyboard { req = req.reply_markup(kb); }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "video" => {
-                    let mut req = bot.send_video(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() { req = req.caption(&text).parse_mode(ParseMode::Html); }
-                    if let Some(kb) = keyboard { req = req.reply_markup(kb); }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                "document" => {
-                    let mut req = bot.send_document(chat_id, InputFile::file_id(file_id));
-                    if !text.is_empty() { req = req.caption(&text).parse_mode(ParseMode::Html); }
-                    if let Some(kb) = keyboard { req = req.reply_markup(kb); }
-                    req = req.reply_parameters(ReplyParameters::new(reply_to));
-                    req.await?;
-                }
-                _ => {}
-            }
-        }
-        _ => {
-            let mut req = bot.send_message(chat_id, &text).parse_mode(ParseMode::Html);
-            if let Some(kb) = keyboard { req = req.reply_markup(kb); }
-            req = req.reply_parameters(ReplyParameters::new(reply_to));
-            req.await?;
-        }
-    }
-    
+    let content = Content::new(text, note.format.parse_mode())
+        .media(note.file_type.as_deref(), note.file_id.as_deref())
+        .keyboard(keyboard)
+        .reply_to(reply_to)
+        .preview(link_preview_options(note.preview))
+        .protect(note.protect);
+    send_content(bot, chat_id, content).await?;
+
     Ok(())
 }
 
@@ -269,21 +326,74 @@ async fn send_note_response(
 
 /// Save note command.
 pub async fn save_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
     let text = msg.text().unwrap_or("").to_string();
     let parts: Vec<String> = text.split_whitespace().map(|s| s.to_string()).collect();
     let args: Vec<&str> = parts.iter().skip(1).map(|s| s.as_str()).collect();
     save_note(bot, msg, state, &args).await
 }
 
+/// Handle /noteinfo command - show who created and last edited a note.
+pub async fn noteinfo_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::Admin).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(msg.chat.id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let Some(name) = text.split_whitespace().nth(1).map(|n| n.trim_start_matches('#').to_lowercase()) else {
+        bot.send_message(msg.chat.id, get_text(&locale, "notes.info_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    let Some(note) = state.notes.get_note(msg.chat.id.0, &name).await? else {
+        bot.send_message(msg.chat.id, get_text(&locale, "notes.not_found").replace("{name}", &html_escape(&name)))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    let info = get_text(&locale, "notes.info_header").replace("{name}", &html_escape(&name))
+        + &format_audit(&state, &locale, note.created_by, note.created_at, note.updated_by, note.updated_at).await;
+
+    bot.send_message(msg.chat.id, info)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+    Ok(())
+}
+
 /// List notes command.
 pub async fn notes_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     if !msg.chat.is_group() && !msg.chat.is_supergroup() {
         return Ok(());
     }
-    list_notes(bot, msg, state).await
+
+    let text = msg.text().unwrap_or("");
+    let enabled = match text.split_whitespace().nth(1).map(|a| a.to_lowercase()).as_deref() {
+        Some("on" | "yes" | "true") => true,
+        Some("off" | "no" | "false") => false,
+        _ => return list_notes(bot, msg, state).await,
+    };
+
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let chat_id = msg.chat.id;
+    state.message_context.update_hashtag_notes(chat_id.0, enabled).await?;
+    info!("Hashtag notes set to {} in chat {} by {}", enabled, chat_id, user_id);
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    let key = if enabled { "notes.hashtag_on" } else { "notes.hashtag_off" };
+    bot.send_message(chat_id, get_text(&locale, key))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+    Ok(())
 }
 
 /// Clear note command.
@@ -298,25 +408,148 @@ pub async fn clear_command(bot: ThrottledBot, msg: Message, state: AppState) ->
 }
 
 /// Clear all notes command (placeholder - requires admin check).
+///
+/// Asks for confirmation with inline buttons before deleting anything.
 pub async fn clearall_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let count = state.notes.get_names(chat_id.0).await?.len();
+    if count == 0 {
+        bot.send_message(chat_id, get_text(&locale, "notes.list_empty"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
         return Ok(());
     }
-    // TODO: Implement clearall with admin permission check
-    let locale = state.get_locale(Some(msg.chat.id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
-    bot.send_message(msg.chat.id, get_text(&locale, "notes.error_clearall_impl"))
-        .await?;
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            get_text(&locale, "notes.clearall_confirm_button"),
+            format!("notes_clearall:confirm:{}", chat_id.0),
+        ),
+        InlineKeyboardButton::callback(
+            get_text(&locale, "notes.clearall_cancel_button"),
+            "notes_clearall:cancel",
+        ),
+    ]]);
+
+    bot.send_message(
+        chat_id,
+        get_text(&locale, "notes.clearall_confirm").replace("{count}", &count.to_string()),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_markup(keyboard)
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
+
     Ok(())
 }
 
-/// Toggle private notes command (placeholder).
+/// Handle the /clearall confirmation buttons.
+///
+/// Callback data: `notes_clearall:confirm:<chat_id>` or `notes_clearall:cancel`.
+pub async fn clearall_callback_handler(
+    bot: ThrottledBot,
+    q: CallbackQuery,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let (Some(data), Some(message)) = (q.data.as_deref(), q.message.as_ref()) else {
+        return Ok(());
+    };
+    let chat_id = message.chat().id;
+    let locale = state.get_locale(Some(chat_id.0), Some(q.from.id.0)).await;
+
+    // Only admins who could run /clearall may answer the prompt
+    if !state.permissions.can_change_info(chat_id, q.from.id).await.unwrap_or(false) {
+        bot.answer_callback_query(&q.id)
+            .text(
+                get_text(&locale, "common.error_missing_permission")
+                    .replace("{permission}", "CanChangeInfo"),
+            )
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    let text = match data.split(':').collect::<Vec<_>>().as_slice() {
+        ["notes_clearall", "confirm", target] if target.parse::<i64>().ok() == Some(chat_id.0) => {
+            let deleted = state.notes.clear_all(chat_id.0).await?;
+            info!("Cleared {} notes from chat {} by {}", deleted, chat_id, q.from.id);
+            get_text(&locale, "notes.clearall_done").replace("{count}", &deleted.to_string())
+        }
+        ["notes_clearall", "cancel"] => get_text(&locale, "notes.clearall_cancelled"),
+        _ => {
+            bot.answer_callback_query(&q.id)
+                .text(get_text(&locale, "warn.callback_invalid_data"))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    // Replacing the text drops the buttons so the prompt can't be reused
+    let _ = bot
+        .edit_message_text(chat_id, message.id(), text)
+        .parse_mode(ParseMode::Html)
+        .await;
+    bot.answer_callback_query(&q.id).await?;
+
+    Ok(())
+}
+
+/// Handle /privatenotes command - show or toggle delivering notes to PM.
+///
+/// Usage: /privatenotes [on|off]
 pub async fn privatenotes_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     if !msg.chat.is_group() && !msg.chat.is_supergroup() {
         return Ok(());
     }
-    // TODO: Implement private notes toggle
-    let locale = state.get_locale(Some(msg.chat.id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
-    bot.send_message(msg.chat.id, get_text(&locale, "notes.error_privatenotes_impl"))
+    let chat_id = msg.chat.id;
+    let locale = state.get_locale(Some(chat_id.0), msg.from.as_ref().map(|u| u.id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let arg = text.split_whitespace().nth(1).map(|a| a.to_lowercase());
+
+    let enabled = match arg.as_deref() {
+        Some("on" | "yes" | "true") => true,
+        Some("off" | "no" | "false") => false,
+        Some(_) => {
+            bot.send_message(chat_id, get_text(&locale, "notes.private_usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+        None => {
+            // Show current state
+            let ctx = state.message_context.get_or_default(chat_id.0).await?;
+            let key = if ctx.private_notes {
+                "notes.private_status_on"
+            } else {
+                "notes.private_status_off"
+            };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    state.message_context.update_private_notes(chat_id.0, enabled).await?;
+    info!("Private notes set to {} in chat {} by {}", enabled, chat_id, user_id);
+
+    let key = if enabled { "notes.private_on" } else { "notes.private_off" };
+    bot.send_message(chat_id, get_text(&locale, key))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
     Ok(())
 }
@@ -357,12 +590,67 @@ pub async fn handle_hashtag_note(
         return Ok(());
     }
 
+    // Paused with /notes off
+    let ctx = state.message_context.get_or_default(msg.chat.id.0).await?;
+    if !ctx.hashtag_notes {
+        return Ok(());
+    }
+
     // Get and send note
     if let Some(note) = state.notes.get_note(msg.chat.id.0, &note_name).await? {
-        send_note_response(&bot, &msg, &note).await?;
+        send_note_response(&bot, &msg, &state, &note).await?;
     }
 
     Ok(())
 }
 
+/// Handle deep link for notes: /start note_CHATID_NAME
+///
+/// Only members of the note's chat can fetch it.
+pub async fn handle_note_deeplink(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+    payload: &str,
+) -> anyhow::Result<()> {
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let private_chat_id = msg.chat.id;
+    let locale = state.get_locale(Some(private_chat_id.0), Some(user.id.0)).await;
+
+    let parsed = payload
+        .split_once('_')
+        .and_then(|(chat, name)| Some((chat.parse::<i64>().ok()?, name.to_lowercase())));
+    let Some((group_chat_id, name)) = parsed else {
+        bot.send_message(private_chat_id, get_text(&locale, "notes.error_link"))
+            .await?;
+        return Ok(());
+    };
+
+    let is_member = bot
+        .get_chat_member(ChatId(group_chat_id), user.id)
+        .await
+        .map(|m| m.is_present())
+        .unwrap_or(false);
+    if !is_member {
+        bot.send_message(private_chat_id, get_text(&locale, "notes.error_link_not_member"))
+            .await?;
+        return Ok(());
+    }
 
+    let note = state.notes.get_note(group_chat_id, &name).await?;
+    let is_admin = state.permissions.is_admin(ChatId(group_chat_id), user.id).await.unwrap_or(false);
+    match note.filter(|note| note.visible_to(is_admin)) {
+        Some(note) => {
+            deliver_note(&bot, &state, private_chat_id, None, user, &note).await?;
+        }
+        None => {
+            bot.send_message(private_chat_id, get_text(&locale, "notes.not_found").replace("{name}", &html_escape(&name)))
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -7,7 +7,10 @@ use teloxide::types::{ParseMode, ReplyParameters, UserId};
 use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::database::Announcement;
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::require_admin;
 
 /// Handle /pin command - pin a message.
 /// 
@@ -19,28 +22,12 @@ pub async fn pin_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        bot.send_message(chat_id, get_text(&locale, "pin.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
-        return Ok(());
-    }
 
     // Check permission: can_pin_messages
-    if !state.permissions.can_pin_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPinMessages"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PinMessages).await? else {
         return Ok(());
-    }
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Check if reply
     let reply = match msg.reply_to_message() {
@@ -87,45 +74,35 @@ pub async fn pin_command(
 
 /// Handle /permapin command - send and pin a custom message.
 ///
-/// Usage: /permapin <text> - Bot will send the text and pin it.
-/// Supports markdown formatting.
+/// Usage: /permapin [-a] <text> - Bot will send the text and pin it.
+/// Supports HTML formatting. With `-a` the text is also saved as the group
+/// announcement, which /announcement shows again after the pin is gone.
 pub async fn permapin_command(
     bot: ThrottledBot,
     msg: Message,
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
 
-    // Must be in group
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "pin.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    // Check permission: can_pin_messages
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PinMessages).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    // Check permission: can_pin_messages
-    if !state.permissions.can_pin_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPinMessages"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     // Extract text to pin
     let text = msg.text().unwrap_or("");
     let content = text.strip_prefix("/permapin").unwrap_or("").trim();
+    let (save, content) = match content.split_once(char::is_whitespace) {
+        Some(("-a", rest)) => (true, rest.trim()),
+        _ if content == "-a" => (true, ""),
+        _ => (false, content),
+    };
 
     if content.is_empty() {
         bot.send_message(chat_id, get_text(&locale, "pin.permapin_missing_content"))
+            .parse_mode(ParseMode::Html)
             .reply_parameters(ReplyParameters::new(msg.id))
             .await?;
         return Ok(());
@@ -143,6 +120,13 @@ pub async fn permapin_command(
     {
         Ok(_) => {
             info!("Permapin message {} in chat {}", sent.id, chat_id);
+            if save {
+                let announcement = Announcement {
+                    text: content.to_string(),
+                    set_at: state.clock.now().timestamp(),
+                };
+                state.message_context.set_announcement(chat_id.0, Some(announcement)).await?;
+            }
             // Delete the command message
             let _ = bot.delete_message(chat_id, msg.id).await;
         }
@@ -156,6 +140,59 @@ pub async fn permapin_command(
     Ok(())
 }
 
+/// Handle /announcement command - show the saved group announcement.
+///
+/// Usage: /announcement, or /announcement clear (admins) to remove it.
+/// Clearing leaves the pinned message alone.
+pub async fn announcement_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        bot.send_message(chat_id, get_text(&locale, "pin.error_group_only"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let text = msg.text().unwrap_or("");
+    if text.split_whitespace().nth(1).is_some_and(|a| a.eq_ignore_ascii_case("clear")) {
+        if require_admin(&bot, &msg, &state, Permission::PinMessages).await?.is_none() {
+            return Ok(());
+        }
+        state.message_context.set_announcement(chat_id.0, None).await?;
+        bot.send_message(chat_id, get_text(&locale, "pin.announcement_cleared"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+    let text = match ctx.announcement {
+        Some(announcement) => {
+            let date = chrono::DateTime::from_timestamp(announcement.set_at, 0)
+                .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_default();
+            get_text(&locale, "pin.announcement_header")
+                .replace("{date}", &date)
+                .replace("{text}", &announcement.text)
+        }
+        None => get_text(&locale, "pin.announcement_none"),
+    };
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
 /// Handle /pinned command - get the current pinned message.
 pub async fn pinned_command(
     bot: ThrottledBot,
@@ -231,25 +268,12 @@ pub async fn unpin_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PinMessages).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state.permissions.can_pin_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPinMessages"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let message_id = msg.reply_to_message().map(|m| m.id);
 
     if let Some(mid) = message_id {
@@ -291,25 +315,12 @@ pub async fn unpinall_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::PinMessages).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state.permissions.can_pin_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPinMessages"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     match bot.unpin_all_chat_messages(chat_id).await {
         Ok(_) => {
             bot.send_message(chat_id, get_text(&locale, "pin.unpin_all_success"))
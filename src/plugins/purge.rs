@@ -3,22 +3,45 @@
 //! Commands for deleting multiple messages at once.
 
 use std::collections::HashMap;
-use std::sync::{LazyLock, Mutex};
+use std::sync::{LazyLock, Mutex, PoisonError};
 
 use teloxide::prelude::*;
-use teloxide::types::{MessageId, ReplyParameters, UserId};
+use teloxide::types::{MessageId, ParseMode, ReplyParameters, UserId};
+use tokio_util::sync::CancellationToken;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::constants::MAX_PURGE_RANGE;
+use crate::database::PurgeMarker;
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{get_target_from_msg, html_escape, require_admin};
 
-/// Global cache for purgefrom markers: chat_id -> message_id
-static PURGE_MARKERS: LazyLock<Mutex<HashMap<i64, MessageId>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+/// Write-through cache for purgefrom markers: chat_id -> marker.
+/// The markers are persisted in the message context so they survive a restart.
+static PURGE_MARKERS: LazyLock<Mutex<HashMap<i64, PurgeMarker>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Purges running per chat, so /cancelpurge can stop them.
+static ACTIVE_PURGES: LazyLock<Mutex<HashMap<i64, CancellationToken>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// How long a /purgefrom marker stays valid (seconds).
+const PURGE_MARKER_TTL_SECS: i64 = 3600;
+
+/// Bots can't delete messages older than 48 hours (seconds).
+const DELETE_AGE_LIMIT_SECS: i64 = 48 * 3600;
 
 /// Handle /purge command - delete messages from reply to now.
 /// 
 /// Usage:
 /// - /purge - delete from replied message to current
-/// - /purge <N> - delete N messages after replied message
+/// - /purge <N> - delete N messages starting at the replied message
+/// - /purge -<N> - delete N messages ending at the replied message (backwards)
+///
+/// Counts cover a contiguous range of message IDs. IDs of messages that are
+/// already gone (or never existed in this chat) are skipped without
+/// aborting the purge; see [`delete_messages_batch`] for how they're counted.
+/// Messages known to be past Telegram's 48-hour limit aren't attempted at
+/// all; see [`too_old_boundary`]. The deletion runs in the background and
+/// can be stopped with /cancelpurge.
 pub async fn purge_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     purge_action(bot, msg, state, false).await
 }
@@ -41,7 +64,7 @@ async fn purge_action(
         return Ok(());
     }
 
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
 
     // Check permission: can_delete_messages
     if !state.permissions.can_delete_messages(chat_id, user_id).await.unwrap_or(false) {
@@ -73,70 +96,40 @@ async fn purge_action(
     let start_id = reply.id.0;
     let end_id = msg.id.0;
 
-    // Parse optional count argument
+    // Parse optional count argument (0 means no count)
     let text = msg.text().unwrap_or("");
-    let parts: Vec<&str> = text.split_whitespace().collect();
-    
-    let count = if parts.len() > 1 {
-        parts[1].parse::<i32>().ok()
-    } else {
-        None
-    };
-
-    // Collect message IDs to delete
-    let mut to_delete: Vec<MessageId> = Vec::new();
-    
-    if let Some(n) = count {
-        // Delete N messages after the replied message
-        for i in 0..=n {
-            to_delete.push(MessageId(start_id + i));
-        }
-        // Also delete the command message
-        to_delete.push(msg.id);
-    } else {
-        // Delete all from start to end (inclusive)
-        for id in start_id..=end_id {
-            to_delete.push(MessageId(id));
+    let count = text
+        .split_whitespace()
+        .nth(1)
+        .and_then(|arg| arg.parse::<i32>().ok())
+        .filter(|n| *n != 0);
+
+    let range = purge_range(start_id, end_id, count);
+    if !within_purge_cap(&range) {
+        if !silent {
+            send_range_too_large(&bot, &msg, &locale, range.len()).await?;
         }
+        return Ok(());
     }
+    let to_delete: Vec<MessageId> = range.map(MessageId).collect();
 
-    // Delete messages in batches (Telegram allows max 100 per call)
-    let deleted_count = delete_messages_batch(&bot, chat_id, &to_delete).await;
-
-    if !silent && deleted_count > 0 {
-        let confirm = bot.send_message(
-            chat_id, 
-            get_text(&locale, "purge.purge_success").replace("{count}", &deleted_count.to_string())
-        ).await?;
-        
-        // Auto-delete confirmation after 3 seconds
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-            let _ = bot.delete_message(chat_id, confirm.id).await;
-        });
-    }
-
-    Ok(())
+    // Skip what's known to be too old, then delete the rest; ones that
+    // are gone or turn out too old are skipped
+    let boundary = too_old_boundary(&state, chat_id, reply);
+    let (to_delete, too_old) = skip_too_old(to_delete, boundary);
+    spawn_purge(bot, state, &msg, &locale, to_delete, too_old, silent).await
 }
 
 /// Handle /del command - delete the replied message.
 pub async fn del_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
-
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Check permission
-    if !state.permissions.can_delete_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, get_text(&locale, "purge.error_permission"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::DeleteMessages).await? else {
         return Ok(());
-    }
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
 
     // Must be a reply
     let reply = match msg.reply_to_message() {
@@ -159,21 +152,13 @@ pub async fn del_command(bot: ThrottledBot, msg: Message, state: AppState) -> an
 /// Handle /purgefrom command - mark starting point for range purge.
 pub async fn purgefrom_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
-
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Check permission
-    if !state.permissions.can_delete_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, get_text(&locale, "purge.error_permission"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::DeleteMessages).await? else {
         return Ok(());
-    }
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
 
     // Must be a reply
     let reply = match msg.reply_to_message() {
@@ -187,8 +172,15 @@ pub async fn purgefrom_command(bot: ThrottledBot, msg: Message, state: AppState)
     };
 
     // Store the marker
+    let marker = PurgeMarker {
+        message_id: reply.id.0,
+        set_at: state.clock.now().timestamp(),
+    };
     if let Ok(mut markers) = PURGE_MARKERS.lock() {
-        markers.insert(chat_id.0, reply.id);
+        markers.insert(chat_id.0, marker);
+    }
+    if let Err(e) = state.message_context.set_purge_marker(chat_id.0, Some(marker)).await {
+        tracing::warn!("Failed to persist purge marker for {}: {}", chat_id, e);
     }
 
     // Delete command message
@@ -211,21 +203,13 @@ pub async fn purgefrom_command(bot: ThrottledBot, msg: Message, state: AppState)
 /// Handle /purgeto command - delete from marked purgefrom to this reply.
 pub async fn purgeto_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
-
-    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     // Check permission
-    if !state.permissions.can_delete_messages(chat_id, user_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, get_text(&locale, "purge.error_permission"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::DeleteMessages).await? else {
         return Ok(());
-    }
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
 
     // Must be a reply
     let reply = match msg.reply_to_message() {
@@ -238,24 +222,26 @@ pub async fn purgeto_command(bot: ThrottledBot, msg: Message, state: AppState) -
         }
     };
 
-    // Get the purgefrom marker
-    let start_id_result: Result<Option<i32>, ()> = {
-        match PURGE_MARKERS.lock() {
-            Ok(mut markers) => Ok(markers.remove(&chat_id.0).map(|id| id.0)),
-            Err(_) => Err(()),
-        }
-    };
-    
-    let start_id = match start_id_result {
-        Ok(Some(id)) => id,
-        Ok(None) => {
-            bot.send_message(chat_id, get_text(&locale, "purge.purgeto_no_start"))
+    // Get the purgefrom marker, falling back to the stored one after a restart.
+    // Both copies are cleared so a marker is only used once.
+    let cached = PURGE_MARKERS.lock().ok().and_then(|mut markers| markers.remove(&chat_id.0));
+    let stored = match state.message_context.take_purge_marker(chat_id.0).await {
+        Ok(stored) => stored,
+        Err(e) if cached.is_none() => {
+            tracing::error!("Failed to load purge marker for {}: {}", chat_id, e);
+            bot.send_message(chat_id, get_text(&locale, "purge.error_internal"))
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
             return Ok(());
         }
-        Err(_) => {
-            bot.send_message(chat_id, get_text(&locale, "purge.error_internal"))
+        Err(_) => None,
+    };
+
+    let now = state.clock.now().timestamp();
+    let start_id = match cached.or(stored).filter(|m| marker_is_fresh(m, now)) {
+        Some(marker) => marker.message_id,
+        None => {
+            bot.send_message(chat_id, get_text(&locale, "purge.purgeto_no_start"))
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
             return Ok(());
@@ -271,45 +257,395 @@ pub async fn purgeto_command(bot: ThrottledBot, msg: Message, state: AppState) -
         (end_id, start_id)
     };
 
-    // Collect message IDs
-    let mut to_delete: Vec<MessageId> = Vec::new();
-    for id in actual_start..=actual_end {
-        to_delete.push(MessageId(id));
+    let range = actual_start..actual_end.saturating_add(1);
+    if !within_purge_cap(&range) {
+        return send_range_too_large(&bot, &msg, &locale, range.len()).await;
+    }
+    let to_delete: Vec<MessageId> = range.map(MessageId).collect();
+
+    let boundary = too_old_boundary(&state, chat_id, reply);
+    let (to_delete, too_old) = skip_too_old(to_delete, boundary);
+    spawn_purge(bot, state, &msg, &locale, to_delete, too_old, false).await
+}
+
+/// Handle /cancelpurge command - stop the purge running in this chat.
+///
+/// The purge stops before its next batch of [`DELETE_BATCH_SIZE`] and
+/// reports how many messages it processed.
+pub async fn cancelpurge_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    // Check permission
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::DeleteMessages).await? else {
+        return Ok(());
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
+
+    let token = ACTIVE_PURGES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&chat_id.0)
+        .cloned();
+    match token {
+        // The purge's own report confirms the cancellation
+        Some(token) => {
+            token.cancel();
+            let _ = bot.delete_message(chat_id, msg.id).await;
+        }
+        None => {
+            bot.send_message(chat_id, get_text(&locale, "purge.cancel_none"))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A chat's entry in [`ACTIVE_PURGES`], removed again when dropped.
+struct ActivePurge {
+    chat_id: i64,
+    token: CancellationToken,
+}
+
+impl ActivePurge {
+    /// Register a purge in `chat_id`, or `None` if one is already running.
+    fn start(chat_id: i64) -> Option<Self> {
+        let mut purges = ACTIVE_PURGES.lock().unwrap_or_else(PoisonError::into_inner);
+        if purges.contains_key(&chat_id) {
+            return None;
+        }
+        let token = CancellationToken::new();
+        purges.insert(chat_id, token.clone());
+        Some(Self { chat_id, token })
+    }
+}
+
+impl Drop for ActivePurge {
+    fn drop(&mut self) {
+        ACTIVE_PURGES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.chat_id);
+    }
+}
+
+/// Delete `to_delete` in a background task, then the command message.
+///
+/// Only one purge runs per chat; while it does, further purges are refused
+/// and /cancelpurge can stop it between batches. Silent purges only report
+/// when cancelled, so the admin learns how far it got.
+async fn spawn_purge(
+    bot: ThrottledBot,
+    state: AppState,
+    msg: &Message,
+    locale: &str,
+    to_delete: Vec<MessageId>,
+    too_old: usize,
+    silent: bool,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(purge) = ActivePurge::start(chat_id.0) else {
+        if !silent {
+            bot.send_message(chat_id, get_text(locale, "purge.already_running"))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+        }
+        return Ok(());
+    };
+
+    let command_id = msg.id;
+    tokio::spawn(async move {
+        let (processed, failed) = delete_messages_batch(&bot, chat_id, &to_delete, &purge.token).await;
+        let cancelled = purge.token.is_cancelled();
+        drop(purge);
+        // The command itself isn't part of the reported count
+        let _ = bot.delete_message(chat_id, command_id).await;
+
+        if (!silent || cancelled)
+            && let Err(e) = report_purge(&bot, &state, chat_id, processed, too_old, failed, cancelled).await
+        {
+            tracing::warn!("Failed to report purge in {}: {}", chat_id, e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Handle /purgeuser command - delete a user's recent messages.
+///
+/// Usage: /purgeuser (reply), /purgeuser <@user/ID>
+///
+/// The Bot API can't search chat history, so this only reaches messages in
+/// the recent-message index; see [`sweep_recent_messages`].
+pub async fn purgeuser_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    // Check permission
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::DeleteMessages).await? else {
+        return Ok(());
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user_id.0).await;
+
+    let Some((target_id, target_name, _)) = get_target_from_msg(&bot, &msg, &state).await else {
+        bot.send_message(chat_id, get_text(&locale, "purge.purgeuser_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    let processed = sweep_recent_messages(&bot, &state, chat_id, target_id).await;
+    let _ = bot.delete_message(chat_id, msg.id).await;
+
+    let group_locale = state.locale_for_group(chat_id.0).await;
+    let key = if processed > 0 { "purge.purgeuser_done" } else { "purge.purgeuser_none" };
+    let text = get_text(&group_locale, key)
+        .replace("{count}", &processed.to_string())
+        .replace("{name}", &html_escape(&target_name));
+    let confirm = bot.send_message(chat_id, text).parse_mode(ParseMode::Html).await?;
+
+    // Auto-delete confirmation
+    tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        let _ = bot.delete_message(chat_id, confirm.id).await;
+    });
+
+    Ok(())
+}
+
+/// Delete a user's messages that the bot saw recently.
+///
+/// Only the recent-message index is covered (see
+/// `events::recent_messages`): messages from before the bot started or
+/// pushed out by newer chat traffic stay. Returns how many were processed;
+/// like [`delete_messages_batch`], that's an upper bound on real deletions.
+pub(crate) async fn sweep_recent_messages(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    user_id: UserId,
+) -> usize {
+    let ids = state.recent_messages.user_messages(chat_id.0, user_id.0);
+    let mut processed = 0;
+    // deleteMessages takes at most 100 IDs per call
+    for chunk in ids.chunks(100) {
+        if bot.delete_messages(chat_id, chunk.to_vec()).await.is_ok() {
+            processed += chunk.len();
+        }
+    }
+    state.recent_messages.forget(chat_id.0, &ids);
+    processed
+}
+
+
+/// Post the purge result, deleting it again after a few seconds.
+///
+/// The confirmation is for the group, not just the admin. Nothing is sent
+/// when nothing was processed or skipped, unless the purge was cancelled.
+async fn report_purge(
+    bot: &ThrottledBot,
+    state: &AppState,
+    chat_id: ChatId,
+    processed: usize,
+    too_old: usize,
+    failed: usize,
+    cancelled: bool,
+) -> anyhow::Result<()> {
+    if processed == 0 && too_old == 0 && failed == 0 && !cancelled {
+        return Ok(());
+    }
+
+    let group_locale = state.locale_for_group(chat_id.0).await;
+    let mut text = if cancelled {
+        get_text(&group_locale, "purge.cancelled")
+    } else {
+        String::new()
+    };
+    text.push_str(&get_text(&group_locale, "purge.purge_success").replace("{count}", &processed.to_string()));
+    if too_old > 0 {
+        text.push_str(&get_text(&group_locale, "purge.too_old").replace("{count}", &too_old.to_string()));
+    }
+    if failed > 0 {
+        text.push_str(&get_text(&group_locale, "purge.failed").replace("{count}", &failed.to_string()));
     }
-    // Also delete command message
-    to_delete.push(msg.id);
+    let confirm = bot.send_message(chat_id, text).await?;
+
+    // Skipped messages take longer to read about
+    let delay = if too_old > 0 || failed > 0 || cancelled { 8 } else { 3 };
+    let bot = bot.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+        let _ = bot.delete_message(chat_id, confirm.id).await;
+    });
+
+    Ok(())
+}
+
+/// Newest message ID known to be past the deletion age limit.
+///
+/// Message IDs grow with time, so everything up to it is too old as well.
+/// Ages are known for the replied message and for messages in the
+/// recent-message index; with neither old enough, nothing is ruled out.
+fn too_old_boundary(state: &AppState, chat_id: ChatId, reply: &Message) -> Option<i32> {
+    let cutoff = state.clock.now().timestamp() - DELETE_AGE_LIMIT_SECS;
+    let reply_id = (reply.date.timestamp() < cutoff).then_some(reply.id.0);
+    let recent_id = state.recent_messages.newest_before(chat_id.0, cutoff).map(|id| id.0);
+    reply_id.max(recent_id)
+}
 
-    let deleted_count = delete_messages_batch(&bot, chat_id, &to_delete).await;
+/// Split off the IDs at or below the too-old `boundary`.
+///
+/// Returns the IDs still worth attempting and how many were skipped.
+fn skip_too_old(ids: Vec<MessageId>, boundary: Option<i32>) -> (Vec<MessageId>, usize) {
+    let Some(boundary) = boundary else {
+        return (ids, 0);
+    };
+    let total = ids.len();
+    let fresh: Vec<MessageId> = ids.into_iter().filter(|id| id.0 > boundary).collect();
+    let skipped = total - fresh.len();
+    (fresh, skipped)
+}
 
-    if deleted_count > 0 {
-        let confirm = bot.send_message(
-            chat_id,
-            get_text(&locale, "purge.purge_success").replace("{count}", &deleted_count.to_string())
-        ).await?;
+/// Whether a /purgefrom marker is still within its TTL at `now` (unix seconds).
+fn marker_is_fresh(marker: &PurgeMarker, now: i64) -> bool {
+    now - marker.set_at <= PURGE_MARKER_TTL_SECS
+}
 
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-            let _ = bot.delete_message(chat_id, confirm.id).await;
-        });
+/// Message IDs a purge covers, never reaching the command message itself.
+///
+/// - no count: from the replied message up to the command
+/// - `n > 0`: `n` IDs starting at the replied message
+/// - `n < 0`: `-n` IDs ending at the replied message (IDs start at 1)
+fn purge_range(start_id: i32, end_id: i32, count: Option<i32>) -> std::ops::Range<i32> {
+    match count {
+        Some(n) if n < 0 => start_id.saturating_add(n).saturating_add(1).max(1)..start_id + 1,
+        Some(n) => start_id..start_id.saturating_add(n).min(end_id),
+        None => start_id..end_id,
     }
+}
+
+/// Whether a purge range stays within [`MAX_PURGE_RANGE`].
+fn within_purge_cap(range: &std::ops::Range<i32>) -> bool {
+    range.len() <= MAX_PURGE_RANGE
+}
 
+/// Tell the admin a purge range is over [`MAX_PURGE_RANGE`].
+async fn send_range_too_large(bot: &ThrottledBot, msg: &Message, locale: &str, count: usize) -> anyhow::Result<()> {
+    let text = get_text(locale, "purge.range_too_large")
+        .replace("{count}", &count.to_string())
+        .replace("{max}", &MAX_PURGE_RANGE.to_string());
+    bot.send_message(msg.chat.id, text)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
     Ok(())
 }
 
-/// Delete messages in batches (Telegram limit is typically handled server-side,
-/// but we process one by one for reliability).
+/// Telegram's `deleteMessages` limit per call.
+const DELETE_BATCH_SIZE: usize = 100;
+
+/// Delete messages in batches of [`DELETE_BATCH_SIZE`].
+///
+/// A chunk the bulk call rejects is retried one message at a time. Bulk
+/// calls silently skip messages that are gone, so the first count is how
+/// many IDs were processed, an upper bound on what was really deleted.
+/// Stops before the next chunk once `cancel` fires. Returns that count and
+/// how many failed when retried one by one.
 async fn delete_messages_batch(
     bot: &ThrottledBot,
-    chat_id: teloxide::types::ChatId,
+    chat_id: ChatId,
     message_ids: &[MessageId],
-) -> usize {
-    let mut deleted = 0;
-    
-    for &msg_id in message_ids {
-        if bot.delete_message(chat_id, msg_id).await.is_ok() {
-            deleted += 1;
+    cancel: &CancellationToken,
+) -> (usize, usize) {
+    let mut processed = 0;
+    let mut failed = 0;
+
+    for chunk in message_ids.chunks(DELETE_BATCH_SIZE) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        if bot.delete_messages(chat_id, chunk.to_vec()).await.is_ok() {
+            processed += chunk.len();
+            continue;
         }
+        for &msg_id in chunk {
+            if bot.delete_message(chat_id, msg_id).await.is_ok() {
+                processed += 1;
+            } else {
+                failed += 1;
+            }
+        }
+    }
+
+    (processed, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_purge_range() {
+        // Whole range up to (not including) the command
+        assert_eq!(purge_range(10, 20, None), 10..20);
+
+        // Exactly N messages, starting at the replied one
+        assert_eq!(purge_range(10, 20, Some(3)), 10..13);
+        assert_eq!(purge_range(10, 20, Some(3)).count(), 3);
+
+        // Never past the command message
+        assert_eq!(purge_range(10, 20, Some(50)), 10..20);
+
+        // Backwards, ending at the replied message
+        assert_eq!(purge_range(10, 20, Some(-3)), 8..11);
+        assert_eq!(purge_range(2, 20, Some(-5)), 1..3);
+    }
+
+    #[test]
+    fn test_purge_range_cap() {
+        let max = MAX_PURGE_RANGE as i32;
+        assert!(within_purge_cap(&purge_range(1, 1 + max, None)));
+        assert!(!within_purge_cap(&purge_range(1, 2 + max, None)));
+
+        // Counts are capped too, in both directions
+        assert!(within_purge_cap(&purge_range(10, 10 * max, Some(max))));
+        assert!(!within_purge_cap(&purge_range(10, 10 * max, Some(max + 1))));
+        assert!(!within_purge_cap(&purge_range(10 * max, 20 * max, Some(-max - 1))));
+    }
+
+    #[test]
+    fn test_one_active_purge_per_chat() {
+        let purge = ActivePurge::start(-100).unwrap();
+        assert!(ActivePurge::start(-100).is_none());
+        assert!(ActivePurge::start(-200).is_some());
+
+        // Finishing frees the chat for the next purge
+        drop(purge);
+        assert!(ActivePurge::start(-100).is_some());
+    }
+
+    #[test]
+    fn test_marker_expiry() {
+        let marker = PurgeMarker { message_id: 10, set_at: 1_000 };
+        assert!(marker_is_fresh(&marker, 1_000));
+        assert!(marker_is_fresh(&marker, 1_000 + PURGE_MARKER_TTL_SECS));
+        assert!(!marker_is_fresh(&marker, 1_001 + PURGE_MARKER_TTL_SECS));
+    }
+
+    #[test]
+    fn test_skip_too_old() {
+        let ids: Vec<MessageId> = (10..15).map(MessageId).collect();
+
+        // Unknown ages are all attempted
+        assert_eq!(skip_too_old(ids.clone(), None), (ids.clone(), 0));
+
+        // Everything up to the newest too-old message is skipped
+        let (fresh, skipped) = skip_too_old(ids.clone(), Some(12));
+        assert_eq!(fresh, vec![MessageId(13), MessageId(14)]);
+        assert_eq!(skipped, 3);
+
+        assert_eq!(skip_too_old(ids, Some(20)), (Vec::new(), 5));
     }
-    
-    deleted
 }
@@ -0,0 +1,165 @@
+//! Report plugin.
+//!
+//! /report lets members flag a message to the group's admins, once
+//! enabled by an admin with /reports on.
+
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode, ReplyParameters};
+use tracing::info;
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{html_escape, require_admin};
+
+/// Handle /report command.
+///
+/// Usage: /report [reason] (reply to the offending message)
+pub async fn report_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(reporter) = msg.from.as_ref() else {
+        return Ok(());
+    };
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return Ok(());
+    }
+
+    let locale = state.locale_for_user(chat_id.0, reporter.id.0).await;
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+
+    if !ctx.reports_enabled {
+        bot.send_message(chat_id, get_text(&locale, "report.disabled"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let reported = msg.reply_to_message();
+    let target = reported.and_then(|r| r.from.as_ref());
+
+    // Reports are for admins, so admins themselves can't be reported
+    if let Some(target) = target
+        && state.permissions.is_admin(chat_id, target.id).await.unwrap_or(false)
+    {
+        bot.send_message(chat_id, get_text(&locale, "report.error_admin"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    // One report per member per minute
+    let key = (chat_id.0, reporter.id.0);
+    if state.recent_reports.contains(&key) {
+        bot.send_message(chat_id, get_text(&locale, "report.cooldown"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+    state.recent_reports.insert(key, ());
+
+    // Invisible mentions notify admins without cluttering the report
+    let admins: String = state
+        .permissions
+        .chat_administrators(chat_id)
+        .await?
+        .iter()
+        .filter(|m| !m.user.is_bot)
+        .map(|m| format!("<a href=\"tg://user?id={}\">\u{200b}</a>", m.user.id))
+        .collect();
+
+    // The report is for everyone, so it goes out in the group language
+    let group_locale = AppState::locale_for_group_in(&ctx);
+    let mut text = get_text(&group_locale, "report.reported")
+        .replace("{admins}", &admins)
+        .replace("{reporter_id}", &reporter.id.to_string())
+        .replace("{reporter}", &html_escape(&reporter.first_name));
+    if let Some(target) = target {
+        text.push_str(
+            &get_text(&group_locale, "report.target")
+                .replace("{id}", &target.id.to_string())
+                .replace("{name}", &html_escape(&target.first_name)),
+        );
+    }
+    let reason = msg.text().and_then(|t| t.split_once(char::is_whitespace)).map(|(_, r)| r.trim());
+    if let Some(reason) = reason.filter(|r| !r.is_empty()) {
+        text.push_str(&get_text(&group_locale, "report.reason").replace("{reason}", &html_escape(reason)));
+    }
+
+    // Answer the reported message itself so admins land right on it
+    let reply_to = reported.map_or(msg.id, |r| r.id);
+    let mut req = bot
+        .send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(reply_to));
+    if let Some(url) = reported.and_then(|r| r.url()) {
+        req = req.reply_markup(InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::url(
+            get_text(&group_locale, "report.button_view"),
+            url,
+        )]]));
+    }
+    req.await?;
+
+    info!(
+        "User {} reported {:?} in chat {}",
+        reporter.id,
+        target.map(|t| t.id),
+        chat_id
+    );
+
+    Ok(())
+}
+
+/// Handle /reports command - show or toggle member reports.
+///
+/// Usage: /reports [on|off]
+pub async fn reports_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return Ok(());
+    }
+    let chat_id = msg.chat.id;
+    let locale = state.get_locale(Some(chat_id.0), msg.from.as_ref().map(|u| u.id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let arg = text.split_whitespace().nth(1).map(|a| a.to_lowercase());
+
+    let enabled = match arg.as_deref() {
+        Some("on" | "yes" | "true") => true,
+        Some("off" | "no" | "false") => false,
+        Some(_) => {
+            bot.send_message(chat_id, get_text(&locale, "report.usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+        None => {
+            // Show current state
+            let ctx = state.message_context.get_or_default(chat_id.0).await?;
+            let key = if ctx.reports_enabled {
+                "report.status_on"
+            } else {
+                "report.status_off"
+            };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    state.message_context.update_reports(chat_id.0, enabled).await?;
+    info!("Reports set to {} in chat {} by {}", enabled, chat_id, user_id);
+
+    let key = if enabled { "report.enabled" } else { "report.disabled_set" };
+    bot.send_message(chat_id, get_text(&locale, key))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+    Ok(())
+}
@@ -9,6 +9,8 @@ use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{format_template_source, require_admin, send_content, Content};
 
 /// Handle /rules command - show group rules.
 pub async fn rules_command(
@@ -63,10 +65,8 @@ pub async fn rules_command(
             .replace("{title}", &html_escape(title))
             .replace("{text}", rules_text);
 
-        bot.send_message(chat_id, formatted)
-            .parse_mode(ParseMode::Html)
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+        let content = Content::new(formatted, ParseMode::Html).reply_to(msg.id);
+        send_content(&bot, chat_id, content).await?;
     }
 
     Ok(())
@@ -79,37 +79,12 @@ pub async fn setrules_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
-    };
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "rules.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     // Get rules text from reply or command args
     let rules_text = get_rules_text(&msg);
 
@@ -144,29 +119,12 @@ pub async fn clearrules_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     // Use RulesRepository specific method
     state.rules.clear_rules(chat_id.0).await?;
 
@@ -184,29 +142,12 @@ pub async fn setrulesprivate_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().skip(1).collect();
 
@@ -268,7 +209,7 @@ pub async fn handle_rules_deeplink(
 
     let locale = state.get_locale(Some(private_chat_id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
 
-    let settings = match state.rules.get(group_chat_id).await? {
+    let settings = match state.rules.get_for_deeplink(group_chat_id).await? {
         Some(s) => s,
         None => {
             bot.send_message(private_chat_id, get_text(&locale, "rules.error_group_not_found"))
@@ -305,8 +246,38 @@ pub async fn handle_rules_deeplink(
         .replace("{title}", &html_escape(&group_name))
         .replace("{text}", rules_text);
 
-    bot.send_message(private_chat_id, formatted)
+    send_content(&bot, private_chat_id, Content::new(formatted, ParseMode::Html)).await?;
+
+    Ok(())
+}
+
+/// Handle /dumprules command - show the raw rules text.
+pub async fn dumprules_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let settings = state.rules.get_or_create(chat_id.0).await?;
+
+    let text = format_template_source(
+        &locale,
+        &get_text(&locale, "rules.dump_header"),
+        settings.text.as_deref(),
+        &[],
+        None,
+        "",
+    );
+
+    bot.send_message(chat_id, text)
         .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
 
     Ok(())
@@ -315,9 +286,10 @@ pub async fn handle_rules_deeplink(
 /// Get rules text from message (reply or args).
 fn get_rules_text(msg: &Message) -> Option<String> {
     // Check if replying to a message
+    // Formatting is kept as HTML in both cases
     if let Some(reply) = msg.reply_to_message()
-        && let Some(text) = reply.text().or_else(|| reply.caption()) {
-            return Some(text.to_string());
+        && let Some(text) = crate::utils::message_html(reply) {
+            return Some(text);
         }
 
     // Check command args
@@ -327,7 +299,7 @@ fn get_rules_text(msg: &Message) -> Option<String> {
         .map(|(_, rest)| rest.trim())
         .filter(|s| !s.is_empty());
 
-    args.map(String::from)
+    args.map(|args| crate::utils::tail_html(text, msg.entities(), args))
 }
 
 /// Escape HTML special characters.
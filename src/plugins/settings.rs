@@ -1,13 +1,42 @@
 //! Settings plugin.
 //!
-//! Handles configuration commands like /setlang.
+//! Handles configuration commands like /setlang, /lang, /cmdrestrict,
+//! /cleanservice, /reload, /config and /setglobaldefault.
 
+use std::time::Duration;
+
+use mongodb::bson::{self, Bson};
+use serde::Serialize;
 use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters};
+use tracing::info;
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::cache::CacheConfig;
+use crate::database::{ByeSettings, CommandScope, MessageContext, RulesSettings, WelcomeSettings};
 use crate::database::models::message_context::GroupInfo;
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::{html_escape, require_admin};
+
+/// How long a group's content counts are reused before recounting.
+const CONTENT_COUNTS_TTL: Duration = Duration::from_secs(60);
+
+/// Thread ID of the General topic (messages without a topic thread).
+const GENERAL_THREAD_ID: i32 = 1;
 
+/// Resolve the topic a message was sent in (General if none).
+pub fn message_thread(msg: &Message) -> i32 {
+    if msg.is_topic_message {
+        msg.thread_id.map(|t| t.0.0).unwrap_or(GENERAL_THREAD_ID)
+    } else {
+        GENERAL_THREAD_ID
+    }
+}
+
+/// Handle /setlang command - set the group language (or the user's in PM).
+///
+/// Usage: /setlang <lang>
 pub async fn setlang_command(
     bot: ThrottledBot,
     msg: Message,
@@ -23,9 +52,12 @@ pub async fn setlang_command(
     
     // /setlang <lang>
     if args.len() < 2 {
-        bot.send_message(msg.chat.id, get_text(&locale, "settings.usage"))
-            .reply_parameters(teloxide::types::ReplyParameters::new(msg.id))
-            .await?;
+        bot.send_message(
+            msg.chat.id,
+            get_text(&locale, "settings.usage").replace("{langs}", &available_langs("/")),
+        )
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
         return Ok(());
     }
     
@@ -33,6 +65,91 @@ pub async fn setlang_command(
     set_lang(bot, msg, state, lang, locale).await
 }
 
+/// Handle /lang command - set the user's personal language.
+///
+/// Group languages set with /setlang take priority over it.
+///
+/// Usage: /lang [lang]
+pub async fn lang_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user.id.0)).await;
+
+    let group_lang = if msg.chat.is_private() {
+        None
+    } else {
+        state
+            .message_context
+            .get_or_default(chat_id.0)
+            .await
+            .ok()
+            .and_then(|ctx| ctx.group_info)
+            .and_then(|info| info.lang)
+    };
+
+    let text = msg.text().unwrap_or("");
+    let Some(lang) = text.split_whitespace().nth(1).map(str::to_lowercase) else {
+        // No argument: show the current preference
+        let cached = state.users.get_by_id(user.id.0).await.ok().flatten();
+        let mut response = match cached.as_ref().map(|u| (u.lang.clone(), u.client_lang())) {
+            Some((Some(lang), _)) => get_text(&locale, "settings.lang_current").replace("{lang}", &lang),
+            Some((None, Some(client))) => {
+                get_text(&locale, "settings.lang_current_client").replace("{lang}", &client)
+            }
+            _ => get_text(&locale, "settings.lang_current_none"),
+        };
+        response.push_str(
+            &get_text(&locale, "settings.lang_usage").replace("{langs}", &available_langs("/")),
+        );
+        bot.send_message(chat_id, response)
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    if !crate::i18n::is_available(&lang) {
+        bot.send_message(chat_id, invalid_lang_text(&locale))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    state.users.set_lang(user.id.0, lang.clone()).await?;
+
+    // Answer in the language the user will actually see here
+    let mut response = get_text(group_lang.as_deref().unwrap_or(&lang), "settings.success_user");
+    if let Some(group_lang) = &group_lang {
+        response.push_str(
+            &get_text(group_lang, "settings.lang_group_priority").replace("{lang}", group_lang),
+        );
+    }
+    bot.send_message(chat_id, response)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Loaded language codes joined with `sep`.
+fn available_langs(sep: &str) -> String {
+    crate::i18n::available_locales().join(sep)
+}
+
+/// Error listing the languages that can be picked.
+fn invalid_lang_text(locale: &str) -> String {
+    let langs = crate::i18n::available_locales()
+        .iter()
+        .map(|l| format!("<code>{}</code>", l))
+        .collect::<Vec<_>>()
+        .join(", ");
+    get_text(locale, "settings.invalid_lang").replace("{langs}", &langs)
+}
+
 async fn set_lang(
     bot: ThrottledBot,
     msg: Message,
@@ -43,10 +160,10 @@ async fn set_lang(
     let chat_id = msg.chat.id;
     let user_id = msg.from.as_ref().map(|u| u.id.0).unwrap_or(0);
     
-    // Validate lang
-    if lang != "en" && lang != "id" {
-        bot.send_message(chat_id, get_text(&locale, "settings.invalid_lang"))
-            .parse_mode(teloxide::types::ParseMode::Html)
+    // Validate lang against the loaded translations
+    if !crate::i18n::is_available(&lang) {
+        bot.send_message(chat_id, invalid_lang_text(&locale))
+            .parse_mode(ParseMode::Html)
             .await?;
         return Ok(());
     }
@@ -59,13 +176,7 @@ async fn set_lang(
     } else {
         // Set Group Lang (Admin Only)
         // Check permission using can_change_info
-        if !state.permissions.can_change_info(chat_id, msg.from.as_ref().unwrap().id).await.unwrap_or(false) {
-            bot.send_message(
-                chat_id,
-                get_text(&locale, "common.error_missing_permission")
-                    .replace("{permission}", "CanChangeInfo"),
-            )
-            .await?;
+        if require_admin(&bot, &msg, &state, Permission::ChangeInfo).await?.is_none() {
             return Ok(());
         }
 
@@ -82,9 +193,489 @@ async fn set_lang(
         
         // Use new lang for success message
         bot.send_message(chat_id, get_text(&lang, "settings.success_group"))
-            .parse_mode(teloxide::types::ParseMode::Html)
+            .parse_mode(ParseMode::Html)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Handle /cmdrestrict command - only allow commands in one topic.
+///
+/// Usage: /cmdrestrict <thread_id|here|off> [users|all]
+pub async fn cmdrestrict_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
+
+    let Some(first) = args.first().map(|a| a.to_lowercase()) else {
+        let ctx = state.message_context.get_or_default(chat_id.0).await?;
+        let status = match ctx.command_thread {
+            Some(thread) => get_text(&locale, "settings.cmdrestrict_status")
+                .replace("{thread}", &thread.to_string())
+                .replace("{scope}", scope_to_str(ctx.command_scope)),
+            None => get_text(&locale, "settings.cmdrestrict_status_off"),
+        };
+        bot.send_message(chat_id, status)
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    // Parse <thread_id|here|off> [users|all]
+    let parsed = match first.as_str() {
+        "off" => Some((None, CommandScope::default())),
+        _ => {
+            let thread = if first == "here" {
+                Some(message_thread(&msg))
+            } else {
+                first.parse::<i32>().ok().filter(|id| *id > 0)
+            };
+            let scope = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+                None | Some("users") => Some(CommandScope::Users),
+                Some("all") => Some(CommandScope::All),
+                Some(_) => None,
+            };
+            thread.zip(scope).map(|(thread, scope)| (Some(thread), scope))
+        }
+    };
+
+    let Some((thread, scope)) = parsed else {
+        bot.send_message(chat_id, get_text(&locale, "settings.cmdrestrict_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    state
+        .message_context
+        .update_command_restriction(chat_id.0, thread, scope)
+        .await?;
+
+    let reply = match thread {
+        Some(thread) => get_text(&locale, "settings.cmdrestrict_set")
+            .replace("{thread}", &thread.to_string())
+            .replace("{scope}", scope_to_str(scope)),
+        None => get_text(&locale, "settings.cmdrestrict_off"),
+    };
+
+    bot.send_message(chat_id, reply)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    info!("Command restriction in chat {} set to {:?} ({:?})", chat_id, thread, scope);
+    Ok(())
+}
+
+fn scope_to_str(scope: CommandScope) -> &'static str {
+    match scope {
+        CommandScope::Users => "users",
+        CommandScope::All => "all",
+    }
+}
+
+/// Handle /cleanservice command - delete join/leave service messages.
+///
+/// Usage: /cleanservice [on|off]
+pub async fn cleanservice_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let enabled = match text.split_whitespace().nth(1).map(|a| a.to_lowercase()).as_deref() {
+        Some("on" | "yes") => true,
+        Some("off" | "no") => false,
+        Some(_) => {
+            bot.send_message(chat_id, get_text(&locale, "settings.cleanservice_usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+        None => {
+            let ctx = state.message_context.get_or_default(chat_id.0).await?;
+            let key = if ctx.clean_service {
+                "settings.cleanservice_status_on"
+            } else {
+                "settings.cleanservice_status_off"
+            };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    state.message_context.update_clean_service(chat_id.0, enabled).await?;
+    info!("Clean service set to {} in chat {} by {}", enabled, chat_id, user_id);
+
+    let mut reply = get_text(&locale, if enabled { "settings.cleanservice_on" } else { "settings.cleanservice_off" });
+    // Deletes fail quietly without the right, so say so up front
+    if enabled {
+        let me = bot.get_me().await?;
+        if !state.permissions.can_delete_messages(chat_id, me.id).await.unwrap_or(false) {
+            reply.push_str(&get_text(&locale, "settings.cleanservice_no_rights"));
+        }
+    }
+
+    bot.send_message(chat_id, reply)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+    Ok(())
+}
+
+/// Handle /reload command - drop this chat's cached settings.
+///
+/// Forces a fresh MongoDB read after out-of-band edits.
+pub async fn reload_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    state.invalidate_chat(chat_id);
+    info!("Reloaded cached settings for chat {}", chat_id);
+
+    // Re-resolve locale in case the group language changed in the database
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    bot.send_message(chat_id, get_text(&locale, "settings.reload_done"))
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Fields that identify a document or hold data rather than settings.
+const CONFIG_SKIP_FIELDS: &[&str] = &[
+    "_id",
+    "chat_id",
+    "title",
+    "group_info",
+    "approved_users",
+    "antiflood",
+    "purge_marker",
+    "announcement",
+];
+
+/// Longest value shown in /config before truncation.
+const CONFIG_VALUE_MAX_CHARS: usize = 40;
+
+/// List the top-level fields of `current` that differ from `default`.
+///
+/// Returns `(field, value)` pairs with the value rendered for display.
+fn config_diff<T: Serialize>(current: &T, default: &T) -> Vec<(String, String)> {
+    let (Ok(current), Ok(default)) = (bson::to_document(current), bson::to_document(default)) else {
+        return Vec::new();
+    };
+
+    current
+        .iter()
+        .filter(|(key, _)| !CONFIG_SKIP_FIELDS.contains(&key.as_str()))
+        .filter(|(key, value)| default.get(key.as_str()).unwrap_or(&Bson::Null) != *value)
+        .map(|(key, value)| (key.clone(), config_value(value)))
+        .collect()
+}
+
+/// Render a BSON value compactly for /config.
+fn config_value(value: &Bson) -> String {
+    let text = match value {
+        Bson::String(s) => s.clone(),
+        Bson::Null => "none".to_string(),
+        Bson::Array(items) => format!("[{} items]", items.len()),
+        Bson::Document(doc) => format!("{{{} entries}}", doc.len()),
+        other => other.to_string(),
+    };
+
+    if text.chars().count() > CONFIG_VALUE_MAX_CHARS {
+        let truncated: String = text.chars().take(CONFIG_VALUE_MAX_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        text
+    }
+}
+
+/// Handle /config command - show settings that differ from the defaults.
+///
+/// Sections with global defaults (/setglobaldefault) are compared against
+/// those, since that's what the chat started from.
+pub async fn config_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let defaults = state.defaults.load().await;
+    let default_antiflood = defaults.antiflood.map(|a| a.as_template()).unwrap_or_default();
+    let default_warn = defaults.warn.unwrap_or_default();
+    let default_welcome = match defaults.welcome {
+        Some(template) => WelcomeSettings { id: None, chat_id: chat_id.0, ..template },
+        None => WelcomeSettings::new(chat_id.0),
+    };
+
+    // Only read what exists; missing documents are at their defaults
+    let ctx = state.message_context.get_or_default(chat_id.0).await?;
+    let mut sections = vec![
+        ("settings.config_section_chat", config_diff(&ctx, &MessageContext::new(chat_id.0))),
+        ("settings.config_section_antiflood", config_diff(&ctx.antiflood, &default_antiflood)),
+    ];
+    if let Some(warns) = state.warns.get(chat_id.0).await? {
+        sections.push(("settings.config_section_warns", config_diff(&warns.config, &default_warn)));
+    }
+    if let Some(welcome) = state.welcome.get(chat_id.0).await? {
+        sections.push(("settings.config_section_welcome", config_diff(&welcome, &default_welcome)));
+    }
+    if let Some(bye) = state.bye.get(chat_id.0).await? {
+        sections.push(("settings.config_section_bye", config_diff(&bye, &ByeSettings::new(chat_id.0))));
+    }
+    if let Some(rules) = state.rules.get(chat_id.0).await? {
+        sections.push(("settings.config_section_rules", config_diff(&rules, &RulesSettings::new(chat_id.0))));
+    }
+
+    let mut body = String::new();
+    for (title_key, fields) in sections.iter().filter(|(_, fields)| !fields.is_empty()) {
+        body.push_str(&format!("\n\n<b>{}</b>", get_text(&locale, title_key)));
+        for (field, value) in fields {
+            body.push_str(&format!(
+                "\n• <code>{}</code>: <code>{}</code>",
+                html_escape(field),
+                html_escape(value)
+            ));
+        }
+    }
+
+    let mut reply = if body.is_empty() {
+        get_text(&locale, "settings.config_all_default")
+    } else {
+        get_text(&locale, "settings.config_header") + &body
+    };
+    if let Ok(counts) = content_counts(&state, chat_id.0).await {
+        reply.push_str("\n\n");
+        reply.push_str(&format_content_counts(&locale, &counts));
+    }
+
+    bot.send_message(chat_id, reply)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// How much content a group has configured.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentCounts {
+    pub notes: usize,
+    pub filters: usize,
+    /// Users with at least one active warning
+    pub warned_users: usize,
+}
+
+/// Count a group's notes, filters and warned users, cached briefly.
+pub async fn content_counts(state: &AppState, chat_id: i64) -> anyhow::Result<ContentCounts> {
+    let cache = state.cache.get_or_create::<i64, ContentCounts>(
+        "content_counts",
+        CacheConfig::with_capacity(10_000).ttl(CONTENT_COUNTS_TTL),
+    );
+    if let Some(counts) = cache.get(&chat_id) {
+        return Ok(counts);
+    }
+
+    let warned_users = match state.warns.get(chat_id).await? {
+        Some(data) => data
+            .user_warns
+            .iter()
+            .filter(|u| u.active_count(data.config.warn_time_secs) > 0)
+            .count(),
+        None => 0,
+    };
+    let counts = ContentCounts {
+        notes: state.notes.get_names(chat_id).await?.len(),
+        filters: state.filters.get_triggers(chat_id).await?.len(),
+        warned_users,
+    };
+
+    cache.insert(chat_id, counts);
+    Ok(counts)
+}
+
+/// One-line summary of a group's content counts.
+pub fn format_content_counts(locale: &str, counts: &ContentCounts) -> String {
+    get_text(locale, "settings.content_counts")
+        .replace("{notes}", &counts.notes.to_string())
+        .replace("{filters}", &counts.filters.to_string())
+        .replace("{warned}", &counts.warned_users.to_string())
+}
+
+/// Handle /setglobaldefault command - use this group's settings as defaults for new groups.
+///
+/// Usage: /setglobaldefault <antiflood|warn|welcome> [reset]
+pub async fn setglobaldefault_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let user_id = match msg.from.as_ref() {
+        Some(user) => user.id,
+        None => return Ok(()),
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    if !state.is_owner(user_id.0) {
+        bot.send_message(chat_id, get_text(&locale, "settings.error_owner_only"))
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let text = msg.text().unwrap_or("");
+    let args: Vec<String> = text.split_whitespace().skip(1).map(|a| a.to_lowercase()).collect();
+    let mut defaults = state.defaults.get().await?;
+
+    let Some(section) = args.first().filter(|s| matches!(s.as_str(), "antiflood" | "warn" | "welcome")) else {
+        let status = |set: bool| {
+            if set {
+                get_text(&locale, "settings.globaldefault_custom")
+            } else {
+                get_text(&locale, "settings.globaldefault_builtin")
+            }
+        };
+        bot.send_message(
+            chat_id,
+            get_text(&locale, "settings.globaldefault_usage")
+                .replace("{antiflood}", &status(defaults.antiflood.is_some()))
+                .replace("{warn}", &status(defaults.warn.is_some()))
+                .replace("{welcome}", &status(defaults.welcome.is_some())),
+        )
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+        return Ok(());
+    };
+
+    let reset = args.get(1).is_some_and(|a| a == "reset");
+
+    if !reset && !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        bot.send_message(chat_id, get_text(&locale, "settings.error_group_only"))
+            .reply_parameters(ReplyParameters::new(msg.id))
             .await?;
+        return Ok(());
     }
 
+    match section.as_str() {
+        "antiflood" => {
+            defaults.antiflood = if reset {
+                None
+            } else {
+                Some(state.message_context.get_or_default(chat_id.0).await?.antiflood.as_template())
+            };
+        }
+        "warn" => {
+            defaults.warn = if reset {
+                None
+            } else {
+                Some(state.warns.get_or_create(chat_id.0).await?.config)
+            };
+        }
+        _ => {
+            defaults.welcome = if reset {
+                None
+            } else {
+                let mut template = state.welcome.get_or_create(chat_id.0).await?;
+                template.id = None;
+                template.chat_id = 0;
+                Some(template)
+            };
+        }
+    }
+
+    state.defaults.save(&defaults).await?;
+    info!("Global {} defaults {} by {}", section, if reset { "reset" } else { "set" }, user_id);
+
+    let key = if reset { "settings.globaldefault_reset" } else { "settings.globaldefault_set" };
+    bot.send_message(chat_id, get_text(&locale, key).replace("{section}", section))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{AntifloodConfig, FloodPenalty};
+
+    #[test]
+    fn test_config_diff() {
+        let default = AntifloodConfig::default();
+        assert!(config_diff(&default, &default).is_empty());
+
+        let mut custom = default.clone();
+        custom.enabled = true;
+        custom.penalty = FloodPenalty::Ban;
+        let diff = config_diff(&custom, &default);
+        assert_eq!(
+            diff,
+            vec![
+                ("enabled".to_string(), "true".to_string()),
+                ("penalty".to_string(), "ban".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_config_diff_skips_identity_and_truncates() {
+        let mut rules = RulesSettings::new(42);
+        rules.text = Some("x".repeat(100));
+        let diff = config_diff(&rules, &RulesSettings::new(1));
+
+        // chat_id differs but is not a setting
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].0, "text");
+        assert_eq!(diff[0].1.chars().count(), CONFIG_VALUE_MAX_CHARS + 1);
+    }
+
+    #[test]
+    fn test_antiflood_template_drops_trusted_users() {
+        let mut config = AntifloodConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config.toggle_trusted(42);
+
+        let template = config.as_template();
+        assert!(template.trusted_users.is_empty());
+        assert!(template.enabled);
+    }
+}
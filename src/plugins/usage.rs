@@ -0,0 +1,44 @@
+//! Command usage plugin.
+//!
+//! /usage lists the commands run in a chat since the bot started, so admins
+//! can see which features their group actually uses.
+
+use teloxide::prelude::*;
+use teloxide::types::{ParseMode, ReplyParameters};
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::utils::require_admin;
+
+/// Handle /usage command.
+pub async fn usage_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let since = state.usage.since().format("%Y-%m-%d %H:%M UTC").to_string();
+    let mut text = get_text(&locale, "usage.header").replace("{since}", &since);
+    let counts = state.usage.for_chat(chat_id.0);
+    if counts.is_empty() {
+        text.push_str(&get_text(&locale, "usage.empty"));
+    }
+    for (i, (command, count)) in counts.iter().enumerate() {
+        text.push_str(
+            &get_text(&locale, "usage.entry")
+                .replace("{rank}", &(i + 1).to_string())
+                .replace("{command}", command)
+                .replace("{count}", &count.to_string()),
+        );
+    }
+    text.push_str(&get_text(&locale, "usage.footer"));
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
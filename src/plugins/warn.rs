@@ -2,6 +2,9 @@
 //!
 //! Commands for managing user warnings in groups.
 
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use teloxide::prelude::*;
 use teloxide::types::{
     ChatId, ChatPermissions, InlineKeyboardButton, InlineKeyboardMarkup, MessageEntityKind, ParseMode,
@@ -9,12 +12,18 @@ use teloxide::types::{
 };
 use tracing::info;
 
+use crate::database::warn::WarnConfig;
 use crate::database::WarnMode;
 use crate::utils::parser::format_duration_full as format_duration;
-use crate::utils::{html_escape, parse_duration};
+use crate::constants::MIN_TEMP_RESTRICTION;
+use crate::utils::{html_escape, mention_user_id, parse_duration, require_admin, restrict_until, Clock};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+
+/// Warned users shown per /warnlist page.
+const WARNLIST_PAGE_SIZE: usize = 20;
 
 /// Handle /warn command.
 pub async fn warn_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
@@ -51,8 +60,11 @@ async fn warn_action(
         return Ok(());
     }
 
+    // Errors go to the admin in their language, announcements to the group
+    let locale = state.locale_for_user(chat_id.0, admin_id.0).await;
+    let group_locale = state.locale_for_group(chat_id.0).await;
+
     // Permission check
-    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
     if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
         if action != WarnAction::Silent {
             bot.send_message(
@@ -99,15 +111,15 @@ async fn warn_action(
     let reason = if reason.is_empty() {
         None
     } else {
-        Some(reason)
+        let config = state.warns.get_or_create(chat_id.0).await?.config;
+        Some(expand_warn_reason(&reason, &config.reasons))
     };
 
     // Delete messages based on action
-    if action == WarnAction::DeleteMsg {
-        if let Some(reply) = msg.reply_to_message() {
+    if action == WarnAction::DeleteMsg
+        && let Some(reply) = msg.reply_to_message() {
             let _ = bot.delete_message(chat_id, reply.id).await;
         }
-    }
     if action == WarnAction::Silent {
         let _ = bot.delete_message(chat_id, msg.id).await;
     }
@@ -127,15 +139,19 @@ async fn warn_action(
     let limit = warns_data.config.limit;
 
     if warn_count as u32 >= limit {
+        if matches!(warns_data.config.mode, WarnMode::Ban | WarnMode::Kick | WarnMode::TBan) {
+            state.bye.mark_removed(chat_id.0, target_id.0);
+        }
+
         // Apply penalty
         let penalty_result = apply_warn_penalty(
             &bot,
             chat_id,
             target_id,
             &target_name,
-            &warns_data.config.mode,
-            warns_data.config.action_duration_secs,
-            &locale,
+            &warns_data.config,
+            state.clock.as_ref(),
+            &group_locale,
         ).await;
 
         // Clear user warnings after penalty
@@ -144,9 +160,9 @@ async fn warn_action(
         if action != WarnAction::Silent {
             let penalty_msg = match penalty_result {
                 Ok(msg) => msg,
-                Err(_) => get_text(&locale, "warn.error_penalty_failed"),
+                Err(_) => get_text(&group_locale, "warn.error_penalty_failed"),
             };
-            bot.send_message(chat_id, get_text(&locale, "warn.limit_reached")
+            bot.send_message(chat_id, get_text(&group_locale, "warn.limit_reached")
                 .replace("{id}", &target_id.to_string())
                 .replace("{name}", &html_escape(&target_name))
                 .replace("{count}", &warn_count.to_string())
@@ -161,18 +177,18 @@ async fn warn_action(
     } else {
         // Just warning
         if action != WarnAction::Silent {
-            let reason_text_default = get_text(&locale, "warn.no_reason");
+            let reason_text_default = get_text(&group_locale, "warn.no_reason");
             let reason_text = reason.as_deref().unwrap_or(&reason_text_default);
             let keyboard = InlineKeyboardMarkup::new(vec![vec![
                 InlineKeyboardButton::callback(
-                    get_text(&locale, "warn.button_remove"),
+                    get_text(&group_locale, "warn.button_remove"),
                     format!("warn_remove:{}:{}", chat_id.0, target_id.0),
                 ),
             ]]);
 
             bot.send_message(
                 chat_id,
-                get_text(&locale, "warn.warning_header")
+                get_text(&group_locale, "warn.warning_header")
                     .replace("{id}", &target_id.to_string())
                     .replace("{name}", &html_escape(&target_name))
                     .replace("{count}", &warn_count.to_string())
@@ -190,27 +206,42 @@ async fn warn_action(
     Ok(())
 }
 
+/// Expand `:name` tokens in a warn reason to the stored quick-reason text.
+///
+/// Unknown tokens are left untouched.
+fn expand_warn_reason(reason: &str, reasons: &BTreeMap<String, String>) -> String {
+    reason
+        .split(' ')
+        .map(|word| {
+            word.strip_prefix(':')
+                .and_then(|name| reasons.get(&name.to_lowercase()))
+                .map(String::as_str)
+                .unwrap_or(word)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Apply penalty based on warn mode.
 async fn apply_warn_penalty(
     bot: &ThrottledBot,
     chat_id: ChatId,
     user_id: UserId,
     user_name: &str,
-    mode: &WarnMode,
-    duration_secs: u64,
+    config: &WarnConfig,
+    clock: &dyn Clock,
     locale: &str,
 ) -> anyhow::Result<String> {
-    match mode {
+    let duration_secs = config.action_duration_secs;
+    match config.mode {
         WarnMode::Ban => {
             bot.ban_chat_member(chat_id, user_id).await?;
             Ok(get_text(locale, "warn.penalty_ban")
                 .replace("{name}", &html_escape(user_name)))
         }
         WarnMode::Mute => {
-            let perms = ChatPermissions::empty();
-            let until = chrono::Utc::now() + chrono::Duration::days(366);
-            bot.restrict_chat_member(chat_id, user_id, perms)
-                .until_date(until)
+            // No until_date: the mute lasts until lifted
+            bot.restrict_chat_member(chat_id, user_id, ChatPermissions::empty())
                 .await?;
             Ok(get_text(locale, "warn.penalty_mute")
                 .replace("{name}", &html_escape(user_name)))
@@ -222,7 +253,7 @@ async fn apply_warn_penalty(
                 .replace("{name}", &html_escape(user_name)))
         }
         WarnMode::TBan => {
-            let until = chrono::Utc::now() + chrono::Duration::seconds(duration_secs as i64);
+            let until = restrict_until(clock, Duration::from_secs(duration_secs));
             bot.ban_chat_member(chat_id, user_id)
                 .until_date(until)
                 .await?;
@@ -233,7 +264,7 @@ async fn apply_warn_penalty(
         }
         WarnMode::TMute => {
             let perms = ChatPermissions::empty();
-            let until = chrono::Utc::now() + chrono::Duration::seconds(duration_secs as i64);
+            let until = restrict_until(clock, Duration::from_secs(duration_secs));
             bot.restrict_chat_member(chat_id, user_id, perms)
                 .until_date(until)
                 .await?;
@@ -305,29 +336,17 @@ pub async fn warns_command(bot: ThrottledBot, msg: Message, state: AppState) ->
 /// Handle /rmwarn command - remove latest warning.
 pub async fn rmwarn_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-    
-    // Resolve locale
-    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
 
     // Permission check
-    if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanRestrictMembers"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
     // Get target
-    let (target_id, target_name, _) = match get_target_from_msg(&bot, &msg, &state).await {
+    let (target_id, target_name, skip_words) = match get_target_from_msg(&bot, &msg, &state).await {
         Some(t) => t,
         None => {
             bot.send_message(chat_id, get_text(&locale, "warn.error_no_target_remove"))
@@ -337,17 +356,48 @@ pub async fn rmwarn_command(bot: ThrottledBot, msg: Message, state: AppState) ->
         }
     };
 
-    // Use Repo to remove
-    let removed = state.warns.remove_warning(chat_id.0, target_id.0).await?;
+    // A number after the target picks a warning as numbered by /warns
+    let index = msg
+        .text()
+        .and_then(|t| t.split_whitespace().nth(1 + skip_words))
+        .and_then(|arg| arg.parse::<usize>().ok());
+
+    let removed = match index {
+        Some(index) => {
+            let removed = match index.checked_sub(1) {
+                Some(i) => state.warns.remove_warning_at(chat_id.0, target_id.0, i).await?,
+                None => None,
+            };
+            if removed.is_none() {
+                let count = state.warns.get_warning_count(chat_id.0, target_id.0).await?;
+                let key = if count == 0 { "warn.user_no_warnings_simple" } else { "warn.error_index_range" };
+                bot.send_message(
+                    chat_id,
+                    get_text(&locale, key)
+                        .replace("{index}", &index.to_string())
+                        .replace("{count}", &count.to_string()),
+                )
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+                return Ok(());
+            }
+            true
+        }
+        None => state.warns.remove_warning(chat_id.0, target_id.0).await?,
+    };
 
     if removed {
         let count = state.warns.get_warning_count(chat_id.0, target_id.0).await?;
         let data = state.warns.get_or_create(chat_id.0).await?;
-        
+
+        let text = match index {
+            Some(index) => get_text(&locale, "warn.removed_index").replace("{index}", &index.to_string()),
+            None => get_text(&locale, "warn.removed_last"),
+        };
         bot.send_message(
             chat_id,
-            get_text(&locale, "warn.removed_last")
-                .replace("{id}", &target_id.to_string())
+            text.replace("{id}", &target_id.to_string())
                 .replace("{name}", &html_escape(&target_name))
                 .replace("{count}", &count.to_string())
                 .replace("{limit}", &data.config.limit.to_string()),
@@ -364,25 +414,22 @@ pub async fn rmwarn_command(bot: ThrottledBot, msg: Message, state: AppState) ->
     Ok(())
 }
 
+/// Handle /unwarn command - alias for /rmwarn.
+pub async fn unwarn_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    rmwarn_command(bot, msg, state).await
+}
+
 /// Handle /resetwarn command - reset user's warnings.
 pub async fn resetwarn_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-    
-    // Resolve locale
-    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
 
     // Permission check
-    if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, "❌ Anda tidak memiliki izin untuk menghapus peringatan.")
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
     // Get target
     let (target_id, target_name, _) = match get_target_from_msg(&bot, &msg, &state).await {
@@ -419,24 +466,12 @@ pub async fn resetwarn_command(bot: ThrottledBot, msg: Message, state: AppState)
 /// Handle /resetallwarns command - reset ALL warnings.
 pub async fn resetallwarns_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::PromoteMembers).await? else {
         return Ok(());
-    }
+    };
 
     // Requires can_promote_members
     let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-    if !state.permissions.can_promote_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanPromoteMembers"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
 
     // Manual access needed to clear everything
     let mut data = state.warns.get_or_create(chat_id.0).await?;
@@ -474,11 +509,18 @@ pub async fn warnings_command(bot: ThrottledBot, msg: Message, state: AppState)
         None => get_text(&locale, "warn.permanent_no_expire"),
     };
 
+    // The duration only applies to tban/tmute
+    let duration = if data.config.mode.is_temporary() {
+        format_duration(data.config.action_duration_secs)
+    } else {
+        get_text(&locale, "warn.duration_not_used")
+    };
+
     let message = get_text(&locale, "warn.settings_header")
         .replace("{limit}", &data.config.limit.to_string())
-        .replace("{mode}", &data.config.mode.as_str())
-        .replace("{desc}", &data.config.mode.description()) // Ideally description should be localized too
-        .replace("{duration}", &format_duration(data.config.action_duration_secs))
+        .replace("{mode}", data.config.mode.as_str())
+        .replace("{desc}", &get_text(&locale, data.config.mode.description_key()))
+        .replace("{duration}", &duration)
         .replace("{validity}", &warn_time);
 
     bot.send_message(chat_id, message)
@@ -492,95 +534,104 @@ pub async fn warnings_command(bot: ThrottledBot, msg: Message, state: AppState)
 /// Handle /warnmode command.
 pub async fn warnmode_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
+
+    // Permission check
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
+        return Ok(());
+    };
 
     // Resolve locale
     let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
+    let text = msg.text().unwrap_or("");
+    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
 
-    // Permission check
-    if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
+    if args.is_empty() {
+        // Show current
+        let data = state.warns.get_or_create(chat_id.0).await?;
         bot.send_message(
             chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanRestrictMembers"),
+            get_text(&locale, "warn.mode_current")
+                .replace("{mode}", &mode_label(&data.config))
+                .replace("{desc}", &get_text(&locale, data.config.mode.description_key())),
         )
+        .parse_mode(ParseMode::Html)
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
     }
 
-    let text = msg.text().unwrap_or("");
-    let args: Vec<&str> = text.split_whitespace().skip(1).collect();
-
-    if args.is_empty() {
-        // Show current
-        let data = state.warns.get_or_create(chat_id.0).await?;
+    let Some(mode) = WarnMode::from_str(args[0]) else {
         bot.send_message(
             chat_id,
-            get_text(&locale, "warn.mode_current")
-                .replace("{mode}", &data.config.mode.as_str())
-                .replace("{desc}", &data.config.mode.description()),
+            get_text(&locale, "warn.mode_invalid"),
         )
-        .parse_mode(ParseMode::Html)
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
-    } else {
-        match WarnMode::from_str(args[0]) {
-            Some(mode) => {
-                let mut data = state.warns.get_or_create(chat_id.0).await?;
-                data.config.mode = mode.clone();
-                state.warns.save(&data).await?;
-                bot.send_message(
-                    chat_id,
-                    get_text(&locale, "warn.mode_set")
-                        .replace("{mode}", &mode.as_str())
-                        .replace("{desc}", &mode.description()),
-                )
-                .parse_mode(ParseMode::Html)
-                .reply_parameters(ReplyParameters::new(msg.id))
-                .await?;
-            }
+        return Ok(());
+    };
+
+    // Temporary modes need their duration, permanent ones take none
+    let duration = match (mode.is_temporary(), args.get(1)) {
+        (true, Some(arg)) => match parse_duration(arg) {
+            Some(d) => Some(d.max(MIN_TEMP_RESTRICTION)),
             None => {
-                bot.send_message(
-                    chat_id,
-                    get_text(&locale, "warn.mode_invalid"),
-                )
+                bot.send_message(chat_id, get_text(&locale, "warn.error_time_format"))
+                    .reply_parameters(ReplyParameters::new(msg.id))
+                    .await?;
+                return Ok(());
+            }
+        },
+        (false, None) => None,
+        (temporary, _) => {
+            let key = if temporary { "warn.mode_duration_required" } else { "warn.mode_no_duration" };
+            bot.send_message(chat_id, get_text(&locale, key).replace("{mode}", mode.as_str()))
+                .parse_mode(ParseMode::Html)
                 .reply_parameters(ReplyParameters::new(msg.id))
                 .await?;
-            }
+            return Ok(());
         }
+    };
+
+    let mut data = state.warns.get_or_create(chat_id.0).await?;
+    data.config.mode = mode.clone();
+    if let Some(duration) = duration {
+        data.config.action_duration_secs = duration.as_secs();
     }
+    state.warns.save(&data).await?;
+    bot.send_message(
+        chat_id,
+        get_text(&locale, "warn.mode_set")
+            .replace("{mode}", &mode_label(&data.config))
+            .replace("{desc}", &get_text(&locale, mode.description_key())),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
 
     Ok(())
 }
 
+/// Warn mode as shown to admins, with the duration for temporary modes.
+fn mode_label(config: &WarnConfig) -> String {
+    if config.mode.is_temporary() {
+        format!("{} {}", config.mode.as_str(), format_duration(config.action_duration_secs))
+    } else {
+        config.mode.as_str().to_string()
+    }
+}
+
 /// Handle /warnlimit command.
 pub async fn warnlimit_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    // Resolve locale
-    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
 
     // Permission check
-    if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanRestrictMembers"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().skip(1).collect();
@@ -625,22 +676,14 @@ pub async fn warnlimit_command(bot: ThrottledBot, msg: Message, state: AppState)
 /// Handle /warntime command.
 pub async fn warntime_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let admin_id = msg.from.as_ref().map(|u| u.id).unwrap_or(UserId(0));
-
-    // Resolve locale
-    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        return Ok(());
-    }
 
     // Permission check
-    if !state.permissions.can_restrict_members(chat_id, admin_id).await.unwrap_or(false) {
-        bot.send_message(chat_id, "❌ Anda tidak memiliki izin untuk mengubah pengaturan.")
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
         return Ok(());
-    }
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
 
     let text = msg.text().unwrap_or("");
     let args: Vec<&str> = text.split_whitespace().skip(1).collect();
@@ -666,7 +709,7 @@ pub async fn warntime_command(bot: ThrottledBot, msg: Message, state: AppState)
         bot.send_message(chat_id, get_text(&locale, "warn.time_set_permanent"))
             .reply_parameters(ReplyParameters::new(msg.id))
             .await?;
-    } else if let Some(duration) = parse_duration(args[0]) {
+    } else if let Some(duration) = parse_duration(args[0]).filter(|d| d.as_secs() > 0) {
         let secs = duration.as_secs();
         let mut data = state.warns.get_or_create(chat_id.0).await?;
         data.config.warn_time_secs = Some(secs);
@@ -691,6 +734,133 @@ pub async fn warntime_command(bot: ThrottledBot, msg: Message, state: AppState)
     Ok(())
 }
 
+/// Handle /addwarnreason command - store a named quick-reason.
+///
+/// Usage: /addwarnreason <name> <reason text>
+pub async fn addwarnreason_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    // Permission check
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
+        return Ok(());
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let parsed = text
+        .split_once(char::is_whitespace)
+        .and_then(|(_, rest)| rest.trim().split_once(char::is_whitespace))
+        .map(|(name, reason)| {
+            let name = name.trim_start_matches(':').to_lowercase();
+            let reason = reason.trim().trim_matches('"').trim().to_string();
+            (name, reason)
+        })
+        .filter(|(name, reason)| !name.is_empty() && !reason.is_empty());
+
+    let (name, reason) = match parsed {
+        Some(p) => p,
+        None => {
+            bot.send_message(chat_id, get_text(&locale, "warn.reason_add_usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut data = state.warns.get_or_create(chat_id.0).await?;
+    data.config.reasons.insert(name.clone(), reason.clone());
+    state.warns.save(&data).await?;
+
+    bot.send_message(
+        chat_id,
+        get_text(&locale, "warn.reason_added")
+            .replace("{name}", &html_escape(&name))
+            .replace("{reason}", &html_escape(&reason)),
+    )
+    .parse_mode(ParseMode::Html)
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
+
+    Ok(())
+}
+
+/// Handle /rmwarnreason command - delete a named quick-reason.
+pub async fn rmwarnreason_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+
+    // Permission check
+    let Some(admin_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
+        return Ok(());
+    };
+
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(admin_id.0)).await;
+
+    let text = msg.text().unwrap_or("");
+    let name = match text.split_whitespace().nth(1) {
+        Some(n) => n.trim_start_matches(':').to_lowercase(),
+        None => {
+            bot.send_message(chat_id, get_text(&locale, "warn.reason_rm_usage"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let mut data = state.warns.get_or_create(chat_id.0).await?;
+    let key = if data.config.reasons.remove(&name).is_some() {
+        state.warns.save(&data).await?;
+        "warn.reason_removed"
+    } else {
+        "warn.reason_not_found"
+    };
+
+    bot.send_message(chat_id, get_text(&locale, key).replace("{name}", &html_escape(&name)))
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Handle /warnreasons command - list stored quick-reasons.
+pub async fn warnreasons_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    // Resolve locale
+    let locale = state.get_locale(Some(chat_id.0), Some(msg.from.as_ref().map(|u| u.id.0).unwrap_or(0))).await;
+
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return Ok(());
+    }
+
+    let data = state.warns.get_or_create(chat_id.0).await?;
+
+    let message = if data.config.reasons.is_empty() {
+        get_text(&locale, "warn.reasons_empty")
+    } else {
+        let mut text = get_text(&locale, "warn.reasons_header");
+        for (name, reason) in &data.config.reasons {
+            text.push_str(&format!(
+                "• <code>:{}</code> - {}\n",
+                html_escape(name),
+                html_escape(reason)
+            ));
+        }
+        text
+    };
+
+    bot.send_message(chat_id, message)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
 /// Handle callback for removing warning.
 pub async fn warn_callback_handler(
     bot: ThrottledBot,
@@ -708,15 +878,16 @@ pub async fn warn_callback_handler(
 
     let parts: Vec<&str> = data.split(':').collect();
     if parts.len() != 3 {
-        bot.answer_callback_query(&q.id).text("❌ Data tidak valid.").await?;
+        let locale = state.get_locale(None, Some(q.from.id.0)).await;
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "warn.callback_invalid_data"))
+            .await?;
         return Ok(());
     }
 
     let chat_id: i64 = parts[1].parse().unwrap_or(0);
     let target_id: u64 = parts[2].parse().unwrap_or(0);
 
-    // Initial locale resolution (default until we parse chat_id)
-    // Actually we can resolve it after parsing.
     let locale = state.get_locale(Some(chat_id), Some(q.from.id.0)).await;
 
     if chat_id == 0 || target_id == 0 {
@@ -787,6 +958,143 @@ pub async fn warn_callback_handler(
     Ok(())
 }
 
+/// Handle /warnlist command - list every user with active warnings.
+pub async fn warnlist_command(bot: ThrottledBot, msg: Message, state: AppState) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::RestrictMembers).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let (text, keyboard) = render_warnlist(&state, chat_id.0, &locale, 0).await?;
+    let mut req = bot
+        .send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id));
+    if let Some(keyboard) = keyboard {
+        req = req.reply_markup(keyboard);
+    }
+    req.await?;
+
+    Ok(())
+}
+
+/// Render one page of /warnlist starting at `offset`, with its page buttons.
+async fn render_warnlist(
+    state: &AppState,
+    chat_id: i64,
+    locale: &str,
+    offset: usize,
+) -> anyhow::Result<(String, Option<InlineKeyboardMarkup>)> {
+    let Some(data) = state.warns.get(chat_id).await? else {
+        return Ok((get_text(locale, "warn.warnlist_empty"), None));
+    };
+
+    let warn_time_secs = data.config.warn_time_secs;
+    let mut warned: Vec<(u64, usize)> = data
+        .user_warns
+        .iter()
+        .map(|u| (u.user_id, u.active_count(warn_time_secs)))
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    if warned.is_empty() {
+        return Ok((get_text(locale, "warn.warnlist_empty"), None));
+    }
+    // Most warned first
+    warned.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let total = warned.len();
+    let pages = total.div_ceil(WARNLIST_PAGE_SIZE);
+    // A stale button may point past the end once warnings are removed
+    let offset = offset.min((pages - 1) * WARNLIST_PAGE_SIZE);
+
+    let mut text = get_text(locale, "warn.warnlist_header").replace("{count}", &total.to_string());
+    for (user_id, count) in warned.iter().skip(offset).take(WARNLIST_PAGE_SIZE) {
+        text.push_str(&format!(
+            "• {} — {}/{}\n",
+            mention_user_id(state, *user_id).await,
+            count,
+            data.config.limit
+        ));
+    }
+
+    if pages == 1 {
+        return Ok((text, None));
+    }
+
+    text.push_str(
+        &get_text(locale, "warn.warnlist_page")
+            .replace("{page}", &(offset / WARNLIST_PAGE_SIZE + 1).to_string())
+            .replace("{pages}", &pages.to_string()),
+    );
+
+    let mut row = Vec::new();
+    if offset > 0 {
+        row.push(InlineKeyboardButton::callback(
+            get_text(locale, "warn.warnlist_prev"),
+            format!("warnlist_page:{}:{}", chat_id, offset - WARNLIST_PAGE_SIZE),
+        ));
+    }
+    if offset + WARNLIST_PAGE_SIZE < total {
+        row.push(InlineKeyboardButton::callback(
+            get_text(locale, "warn.warnlist_next"),
+            format!("warnlist_page:{}:{}", chat_id, offset + WARNLIST_PAGE_SIZE),
+        ));
+    }
+
+    Ok((text, Some(InlineKeyboardMarkup::new(vec![row]))))
+}
+
+/// Handle /warnlist page buttons.
+///
+/// Callback data: `warnlist_page:<chat>:<offset>`
+pub async fn warnlist_callback_handler(
+    bot: ThrottledBot,
+    q: CallbackQuery,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let Some(data) = &q.data else {
+        return Ok(());
+    };
+
+    let mut parts = data.split(':').skip(1);
+    let chat_id: i64 = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+    let offset: Option<usize> = parts.next().and_then(|p| p.parse().ok());
+
+    let locale = state.get_locale(Some(chat_id), Some(q.from.id.0)).await;
+
+    let (Some(offset), Some(msg)) = (offset.filter(|_| chat_id != 0), &q.message) else {
+        bot.answer_callback_query(&q.id).text(get_text(&locale, "warn.callback_invalid_data")).await?;
+        return Ok(());
+    };
+
+    // The list names everyone warned, so paging through it is for admins too
+    if !state.permissions.can_restrict_members(ChatId(chat_id), q.from.id).await.unwrap_or(false) {
+        bot.answer_callback_query(&q.id)
+            .text(
+                get_text(&locale, "common.error_missing_permission")
+                    .replace("{permission}", "CanRestrictMembers"),
+            )
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    let (text, keyboard) = render_warnlist(&state, chat_id, &locale, offset).await?;
+    let mut req = bot
+        .edit_message_text(msg.chat().id, msg.id(), text)
+        .parse_mode(ParseMode::Html);
+    if let Some(keyboard) = keyboard {
+        req = req.reply_markup(keyboard);
+    }
+    // Editing to the same page again fails harmlessly
+    let _ = req.await;
+
+    bot.answer_callback_query(&q.id).await?;
+    Ok(())
+}
+
 /// Get target user from message (reply or args).
 /// Returns (user_id, first_name, skip_words_count)
 async fn get_target_from_msg(
@@ -3,16 +3,31 @@
 //! Commands for configuring welcome messages in groups.
 //! Refactored to use decentralized WelcomeRepository.
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use teloxide::prelude::*;
 use teloxide::types::{
-    InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ParseMode, ReplyParameters,
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup, ParseMode, ReplyParameters,
 };
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
-use crate::database::{InlineButton, WelcomeSettings};
-use crate::utils::html_escape;
+use crate::constants::MAX_WELCOME_COOLDOWN_SECS;
+use crate::database::{ContentFormat, InlineButton, WelcomeSettings, WelcomeVariant};
+use crate::utils::{apply_notelink_filling, apply_rules_filling, merge_filling_buttons, format_duration_full, format_template_source, html_escape, is_invalid_media, parse_duration, require_admin, send_content, Content};
+use crate::utils::parser::{self, parse_format_tag};
 use crate::i18n::get_text;
+use crate::permissions::Permission;
+use crate::plugins::mute::unmuted_permissions;
+
+/// Callback data prefix of the "I'm human" button on gated welcomes.
+pub const VERIFY_CALLBACK_PREFIX: &str = "welcome_verify:";
+
+/// Most welcome variants a chat can have.
+const MAX_WELCOME_VARIANTS: usize = 10;
+
+/// Characters of each variant's text shown by /welcomevariants.
+const VARIANT_PREVIEW_CHARS: usize = 60;
 
 /// Handle /welcome command - show or toggle welcome.
 pub async fn welcome_command(
@@ -21,37 +36,12 @@ pub async fn welcome_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
-    };
-
-    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
-        let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
-        bot.send_message(chat_id, get_text(&locale, "welcome.error_group_only"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let settings = state.welcome.get_or_create(chat_id.0).await?;
 
     let text = msg.text().unwrap_or("");
@@ -86,7 +76,66 @@ pub async fn welcome_command(
         }
         "preview" => {
             // Show preview of welcome message
-            send_welcome_preview(&bot, chat_id, &settings, &msg).await?;
+            send_welcome_preview(&bot, &state, chat_id, &settings, &msg).await?;
+        }
+        "cooldown" => {
+            // Set rejoin cooldown: /welcome cooldown <1h|off>
+            let cooldown_secs = match args.get(1).map(|a| a.to_lowercase()) {
+                Some(a) if a == "off" || a == "0" => Some(0),
+                Some(a) => parse_duration(&a)
+                    .map(|d| d.as_secs())
+                    .filter(|&secs| secs <= MAX_WELCOME_COOLDOWN_SECS),
+                None => None,
+            };
+
+            let Some(cooldown_secs) = cooldown_secs else {
+                bot.send_message(
+                    chat_id,
+                    get_text(&locale, "welcome.cooldown_usage")
+                        .replace("{current}", &format_cooldown(settings.cooldown_secs, &locale)),
+                )
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+                return Ok(());
+            };
+
+            let mut new_settings = settings.clone();
+            new_settings.cooldown_secs = cooldown_secs;
+            state.welcome.save(&new_settings).await?;
+            bot.send_message(
+                chat_id,
+                get_text(&locale, "welcome.cooldown_set")
+                    .replace("{duration}", &format_cooldown(cooldown_secs, &locale)),
+            )
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        }
+        "clean" => {
+            set_clean_welcome(&bot, &msg, &state, settings, args.get(1).copied(), &locale).await?;
+        }
+        "mute" => {
+            // Mute new members until they verify: /welcome mute <on|off>
+            let welcome_mute = match args.get(1).map(|a| a.to_lowercase()).as_deref() {
+                Some("on" | "yes") => true,
+                Some("off" | "no") => false,
+                _ => {
+                    bot.send_message(chat_id, get_text(&locale, "welcome.mute_usage"))
+                        .parse_mode(ParseMode::Html)
+                        .reply_parameters(ReplyParameters::new(msg.id))
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let mut new_settings = settings.clone();
+            new_settings.welcome_mute = welcome_mute;
+            state.welcome.save(&new_settings).await?;
+            let key = if welcome_mute { "welcome.mute_on" } else { "welcome.mute_off" };
+            bot.send_message(chat_id, get_text(&locale, key))
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
         }
         _ => {
             bot.send_message(
@@ -102,35 +151,73 @@ pub async fn welcome_command(
     Ok(())
 }
 
-/// Handle /setwelcome command - set welcome message by replying to a message.
-pub async fn setwelcome_command(
+/// Handle /cleanwelcome command - delete the previous welcome on each join.
+///
+/// Usage: /cleanwelcome <on|off>
+pub async fn cleanwelcome_command(
     bot: ThrottledBot,
     msg: Message,
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+    let settings = state.welcome.get_or_create(chat_id.0).await?;
+    let arg = msg.text().and_then(|text| text.split_whitespace().nth(1));
+    set_clean_welcome(&bot, &msg, &state, settings, arg, &locale).await
+}
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(
-            chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
-        )
+/// Turn clean_welcome on or off, showing the usage for any other `arg`.
+async fn set_clean_welcome(
+    bot: &ThrottledBot,
+    msg: &Message,
+    state: &AppState,
+    mut settings: WelcomeSettings,
+    arg: Option<&str>,
+    locale: &str,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let clean = match arg.map(str::to_lowercase).as_deref() {
+        Some("on" | "yes") => true,
+        Some("off" | "no") => false,
+        _ => {
+            let current = if settings.clean_welcome {
+                get_text(locale, "welcome.status_active")
+            } else {
+                get_text(locale, "welcome.status_inactive")
+            };
+            bot.send_message(chat_id, get_text(locale, "welcome.clean_usage").replace("{current}", &current))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    settings.clean_welcome = clean;
+    state.welcome.save(&settings).await?;
+    let key = if clean { "welcome.clean_on" } else { "welcome.clean_off" };
+    bot.send_message(chat_id, get_text(locale, key))
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
+    Ok(())
+}
+
+/// Handle /setwelcome command - set welcome message by replying to a message.
+pub async fn setwelcome_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
         return Ok(());
-    }
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
     let mut settings = state.welcome.get_or_create(chat_id.0).await?;
 
@@ -140,13 +227,14 @@ pub async fn setwelcome_command(
     let args_text = text_content
         .split_once(char::is_whitespace)
         .map(|(_, rest)| rest.trim())
-        .filter(|s| !s.is_empty());
+        .filter(|s| !s.is_empty())
+        .map(|args| crate::utils::tail_html(text_content, msg.entities(), args));
 
     if let Some(reply) = replied {
         // Get message content from reply
         let (message_text, media_file_id, media_type) = extract_message_content(reply);
 
-        if let Some(text) = message_text.or_else(|| args_text.map(String::from)) {
+        if let Some(text) = message_text.or(args_text) {
             settings.message = Some(text);
         }
 
@@ -156,9 +244,15 @@ pub async fn setwelcome_command(
         }
 
         state.welcome.save(&settings).await?;
-        bot.send_message(chat_id, get_text(&locale, "welcome.set_success"))
-            .reply_parameters(ReplyParameters::new(msg.id))
-            .await?;
+        confirm_with_media(
+            &bot,
+            &msg,
+            get_text(&locale, "welcome.set_success"),
+            get_text(&locale, "welcome.media_invalid"),
+            settings.media_type.as_deref(),
+            settings.media_file_id.as_deref(),
+        )
+        .await?;
         info!("Welcome message set in chat {}", chat_id);
     } else if let Some(text) = args_text {
         // Direct text after command
@@ -182,6 +276,36 @@ pub async fn setwelcome_command(
     Ok(())
 }
 
+/// Confirm a /setwelcome or /addwelcome, sending the stored media along to
+/// check it still works.
+///
+/// Replying with text only keeps the old media, whose file ID may have gone
+/// stale since it was set. Better to hear it now than from a failed join.
+/// When Telegram rejects the media, `success` is sent with `invalid` instead.
+async fn confirm_with_media(
+    bot: &ThrottledBot,
+    msg: &Message,
+    success: String,
+    invalid: String,
+    media_type: Option<&str>,
+    media_file_id: Option<&str>,
+) -> anyhow::Result<()> {
+    let content = Content::new(success.clone(), ParseMode::Html)
+        .media(media_type, media_file_id)
+        .reply_to(msg.id);
+    match send_content(bot, msg.chat.id, content).await {
+        Ok(_) => Ok(()),
+        Err(e) if is_invalid_media(&e) => {
+            bot.send_message(msg.chat.id, success + "\n\n" + &invalid)
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 /// Handle /setwelcomebuttons command - set inline buttons.
 pub async fn setwelcomebuttons_command(
     bot: ThrottledBot,
@@ -189,25 +313,12 @@ pub async fn setwelcomebuttons_command(
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
-        bot.send_message(chat_id, get_text(&locale, "welcome.error_permission"))
-        .reply_parameters(ReplyParameters::new(msg.id))
-        .await?;
-        return Ok(());
-    }
-
     let text = msg.text().unwrap_or("");
     let args = text
         .split_once(char::is_whitespace)
@@ -257,36 +368,205 @@ pub async fn setwelcomebuttons_command(
     Ok(())
 }
 
-/// Handle /resetwelcome command.
-pub async fn resetwelcome_command(
+/// Handle /addwelcome command - add a welcome variant.
+///
+/// Works like /setwelcome (reply to a message, or text after the command),
+/// with buttons written inline as `{button:Text|URL}`. Each join gets one
+/// variant at random; see [`WelcomeSettings::pick_variant`].
+pub async fn addwelcome_command(
     bot: ThrottledBot,
     msg: Message,
     state: AppState,
 ) -> anyhow::Result<()> {
     let chat_id = msg.chat.id;
-    let user_id = match msg.from.as_ref() {
-        Some(user) => user.id,
-        None => return Ok(()),
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
     };
 
     let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
 
-    if !state
-        .permissions
-        .can_change_info(chat_id, user_id)
-        .await
-        .unwrap_or(false)
-    {
+    let text_content = msg.text().unwrap_or("");
+    let args_text = text_content
+        .split_once(char::is_whitespace)
+        .map(|(_, rest)| rest.trim())
+        .filter(|s| !s.is_empty())
+        .map(|args| crate::utils::tail_html(text_content, msg.entities(), args));
+
+    let (message_text, media_file_id, media_type) = match msg.reply_to_message() {
+        Some(reply) => {
+            let (text, file_id, media_type) = extract_message_content(reply);
+            (text.or(args_text), file_id, media_type)
+        }
+        None => (args_text, None, None),
+    };
+
+    let (message, buttons) = match message_text {
+        Some(text) => {
+            let (text, buttons) = parser::parse_buttons(&text);
+            let text = text.trim().to_string();
+            ((!text.is_empty()).then_some(text), buttons)
+        }
+        None => (None, Vec::new()),
+    };
+
+    if message.is_none() && media_file_id.is_none() {
+        bot.send_message(chat_id, get_text(&locale, "welcome.add_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    }
+
+    let mut settings = state.welcome.get_or_create(chat_id.0).await?;
+    if settings.variants.len() >= MAX_WELCOME_VARIANTS {
         bot.send_message(
             chat_id,
-            get_text(&locale, "common.error_missing_permission")
-                .replace("{permission}", "CanChangeInfo"),
+            get_text(&locale, "welcome.variant_limit").replace("{max}", &MAX_WELCOME_VARIANTS.to_string()),
         )
         .reply_parameters(ReplyParameters::new(msg.id))
         .await?;
         return Ok(());
     }
 
+    settings.variants.push(WelcomeVariant { message, media_file_id, media_type, buttons });
+    state.welcome.save(&settings).await?;
+
+    let number = settings.variants.len().to_string();
+    let variant = &settings.variants[settings.variants.len() - 1];
+    confirm_with_media(
+        &bot,
+        &msg,
+        get_text(&locale, "welcome.variant_added").replace("{n}", &number),
+        get_text(&locale, "welcome.variant_media_invalid").replace("{n}", &number),
+        variant.media_type.as_deref(),
+        variant.media_file_id.as_deref(),
+    )
+    .await?;
+    info!("Welcome variant {} added in chat {}", number, chat_id);
+
+    Ok(())
+}
+
+/// Handle /welcomevariants command - list the welcome variants.
+pub async fn welcomevariants_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let settings = state.welcome.get_or_create(chat_id.0).await?;
+
+    let text = if settings.variants.is_empty() {
+        get_text(&locale, "welcome.variants_none")
+    } else {
+        let mut text = get_text(&locale, "welcome.variants_header")
+            .replace("{count}", &settings.variants.len().to_string());
+        for (i, variant) in settings.variants.iter().enumerate() {
+            text.push_str(
+                &get_text(&locale, "welcome.variants_entry")
+                    .replace("{n}", &(i + 1).to_string())
+                    .replace("{preview}", &format_variant_preview(variant, &locale)),
+            );
+        }
+        text.push_str(&get_text(&locale, "welcome.variants_footer"));
+        text
+    };
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Handle /rmwelcome command - remove a welcome variant.
+///
+/// Usage: /rmwelcome <number>, numbered as in /welcomevariants
+pub async fn rmwelcome_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let mut settings = state.welcome.get_or_create(chat_id.0).await?;
+
+    let index = msg
+        .text()
+        .and_then(|text| text.split_whitespace().nth(1))
+        .and_then(|arg| arg.parse::<usize>().ok())
+        .filter(|n| (1..=settings.variants.len()).contains(n));
+    let Some(n) = index else {
+        bot.send_message(chat_id, get_text(&locale, "welcome.rm_usage"))
+            .parse_mode(ParseMode::Html)
+            .reply_parameters(ReplyParameters::new(msg.id))
+            .await?;
+        return Ok(());
+    };
+
+    settings.variants.remove(n - 1);
+    state.welcome.save(&settings).await?;
+
+    let key = if settings.variants.is_empty() {
+        "welcome.variant_removed_last"
+    } else {
+        "welcome.variant_removed"
+    };
+    bot.send_message(chat_id, get_text(&locale, key).replace("{n}", &n.to_string()))
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
+/// One-line summary of a variant: media type, start of the text, buttons.
+fn format_variant_preview(variant: &WelcomeVariant, locale: &str) -> String {
+    let mut preview = String::new();
+    if variant.media_file_id.is_some() {
+        preview.push_str(&format!("[{}] ", variant.media_type.as_deref().unwrap_or("media")));
+    }
+    match variant.message.as_deref() {
+        Some(message) => {
+            let mut text: String = message.chars().take(VARIANT_PREVIEW_CHARS).collect();
+            if message.chars().count() > VARIANT_PREVIEW_CHARS {
+                text.push('…');
+            }
+            preview.push_str(&html_escape(&text));
+        }
+        None => preview.push_str(&get_text(locale, "welcome.variant_no_text")),
+    }
+    let buttons: usize = variant.buttons.iter().map(|r| r.len()).sum();
+    if buttons > 0 {
+        preview.push_str(&get_text(locale, "welcome.variant_buttons").replace("{count}", &buttons.to_string()));
+    }
+    preview
+}
+
+/// Handle /resetwelcome command.
+pub async fn resetwelcome_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
     let mut _settings = state.welcome.get_or_create(chat_id.0).await?;
     _settings = WelcomeSettings::new(chat_id.0); // Reset to default
     state.welcome.save(&_settings).await?;
@@ -298,9 +578,42 @@ pub async fn resetwelcome_command(
     Ok(())
 }
 
+/// Handle /dumpwelcome command - show the raw welcome template.
+pub async fn dumpwelcome_command(
+    bot: ThrottledBot,
+    msg: Message,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(user_id) = require_admin(&bot, &msg, &state, Permission::ChangeInfo).await? else {
+        return Ok(());
+    };
+
+    let locale = state.get_locale(Some(chat_id.0), Some(user_id.0)).await;
+
+    let settings = state.welcome.get_or_create(chat_id.0).await?;
+
+    let text = format_template_source(
+        &locale,
+        &get_text(&locale, "welcome.dump_header"),
+        settings.message.as_deref(),
+        &settings.buttons,
+        settings.media_file_id.as_ref().and(settings.media_type.as_deref()),
+        "setwelcomebuttons",
+    );
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::Html)
+        .reply_parameters(ReplyParameters::new(msg.id))
+        .await?;
+
+    Ok(())
+}
+
 /// Extract message content (text, media file_id, media type).
 fn extract_message_content(msg: &Message) -> (Option<String>, Option<String>, Option<String>) {
-    let text = msg.text().or_else(|| msg.caption()).map(String::from);
+    // Keep the message's formatting (bold, links, ...) as HTML
+    let text = crate::utils::message_html(msg);
 
     let (file_id, media_type) = if let Some(photo) = msg.photo() {
         // Get largest photo
@@ -316,6 +629,10 @@ fn extract_message_content(msg: &Message) -> (Option<String>, Option<String>, Op
             Some(animation.file.id.clone()),
             Some("animation".to_string()),
         )
+    } else if let Some(voice) = msg.voice() {
+        (Some(voice.file.id.clone()), Some("voice".to_string()))
+    } else if let Some(audio) = msg.audio() {
+        (Some(audio.file.id.clone()), Some("audio".to_string()))
     } else if let Some(sticker) = msg.sticker() {
         (Some(sticker.file.id.clone()), Some("sticker".to_string()))
     } else if let Some(document) = msg.document() {
@@ -450,96 +767,236 @@ fn format_welcome_status(settings: &WelcomeSettings, locale: &str) -> String {
             .replace("{count}", &count.to_string())
     };
 
-    get_text(locale, "welcome.status_header")
+    let clean = if settings.clean_welcome {
+        get_text(locale, "welcome.status_active")
+    } else {
+        get_text(locale, "welcome.status_inactive")
+    };
+
+    let verify = if settings.welcome_mute {
+        get_text(locale, "welcome.status_active")
+    } else {
+        get_text(locale, "welcome.status_inactive")
+    };
+
+    let mut text = get_text(locale, "welcome.status_header")
         .replace("{status}", &status)
+        .replace("{verify}", &verify)
+        .replace("{clean}", &clean)
         .replace("{media}", &media)
         .replace("{buttons}", &buttons)
-        .replace("{message}", message)
+        .replace("{message}", message);
+    if !settings.variants.is_empty() {
+        text.push_str(
+            &get_text(locale, "welcome.status_variants")
+                .replace("{count}", &settings.variants.len().to_string()),
+        );
+    }
+    text
+}
+
+/// Format the welcome cooldown for display.
+fn format_cooldown(cooldown_secs: u64, locale: &str) -> String {
+    if cooldown_secs == 0 {
+        get_text(locale, "welcome.cooldown_off")
+    } else {
+        format_duration_full(cooldown_secs)
+    }
+}
+
+/// Roll for [`WelcomeSettings::pick_variant`].
+///
+/// Joins and previews come at unpredictable times, so the clock's
+/// nanoseconds spread picks well enough.
+pub fn variant_roll() -> usize {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as usize)
+        .unwrap_or(0)
 }
 
-/// Send welcome preview.
+/// Send welcome preview, using a random variant if there are several.
 async fn send_welcome_preview(
     bot: &ThrottledBot,
+    state: &AppState,
     chat_id: ChatId,
     settings: &WelcomeSettings,
     msg: &Message,
 ) -> anyhow::Result<()> {
     let user = msg.from.as_ref().unwrap();
-    let formatted = format_welcome_text(
-        settings.message.as_deref().unwrap_or("Selamat datang!"),
+
+    // Real member count so {count} previews accurately (optional, may fail)
+    let member_count = bot.get_chat_member_count(chat_id).await.unwrap_or(0) as u64;
+
+    let welcome = settings.pick_variant(variant_roll());
+    let (formatted, format, rule_buttons) = format_welcome_text(
+        welcome.message.as_deref().unwrap_or("Selamat datang!"),
         user,
+        None,
         msg.chat.title().unwrap_or("Grup"),
-        0, // member count placeholder
+        member_count,
+        chat_id.0,
+        &state.bot_username,
     );
 
-    let keyboard = build_welcome_keyboard(&settings.buttons);
-
-    if let Some(ref file_id) = settings.media_file_id {
-        match settings.media_type.as_deref() {
-            Some("photo") => {
-                bot.send_photo(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            Some("video") => {
-                bot.send_video(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            Some("animation") => {
-                bot.send_animation(chat_id, InputFile::file_id(file_id))
-                    .caption(formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
-            _ => {
-                bot.send_message(chat_id, formatted)
-                    .parse_mode(ParseMode::Html)
-                    .reply_markup(keyboard)
-                    .reply_parameters(ReplyParameters::new(msg.id))
-                    .await?;
-            }
+    let (formatted, note_links) =
+        apply_notelink_filling(&formatted, chat_id.0, &state.bot_username);
+    let mut buttons = welcome.buttons;
+    merge_filling_buttons(&mut buttons, rule_buttons);
+    buttons.extend(note_links.into_iter().map(|btn| vec![btn]));
+
+    let keyboard = build_welcome_keyboard(&buttons);
+
+    let content = Content::new(formatted, format.parse_mode())
+        .media(welcome.media_type.as_deref(), welcome.media_file_id.as_deref())
+        .keyboard(keyboard)
+        .reply_to(msg.id);
+    match send_content(bot, chat_id, content).await {
+        Ok(_) => {}
+        Err(e) if welcome.media_file_id.is_some() && is_invalid_media(&e) => {
+            let locale = state.get_locale(Some(chat_id.0), Some(user.id.0)).await;
+            bot.send_message(chat_id, get_text(&locale, "welcome.media_invalid"))
+                .parse_mode(ParseMode::Html)
+                .reply_parameters(ReplyParameters::new(msg.id))
+                .await?;
         }
-    } else {
-        bot.send_message(chat_id, formatted)
-            .parse_mode(ParseMode::Html)
-            .reply_markup(keyboard)
-            .reply_parameters(ReplyParameters::new(msg.id))
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(())
+}
+
+/// Handle the "I'm human" button on gated welcomes: unmute the member.
+///
+/// Only the member the button was made for can press it.
+pub async fn verify_callback_handler(
+    bot: ThrottledBot,
+    q: CallbackQuery,
+    state: AppState,
+) -> anyhow::Result<()> {
+    let Some(data) = q.data.as_deref() else {
+        return Ok(());
+    };
+
+    let parts: Vec<&str> = data
+        .strip_prefix(VERIFY_CALLBACK_PREFIX)
+        .unwrap_or_default()
+        .split(':')
+        .collect();
+    let (chat_id, target_id) = match parts.as_slice() {
+        [chat, user] => (chat.parse::<i64>().unwrap_or(0), user.parse::<u64>().unwrap_or(0)),
+        _ => (0, 0),
+    };
+
+    let locale = state.get_locale(Some(chat_id), Some(q.from.id.0)).await;
+
+    if chat_id == 0 || target_id == 0 {
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "warn.callback_invalid_data"))
             .await?;
+        return Ok(());
+    }
+
+    if q.from.id.0 != target_id {
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "welcome.verify_not_you"))
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    // Already verified, or kicked after the timeout
+    let Some(kick_at) = state.welcome.take_pending_verification(chat_id, target_id).await? else {
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "welcome.verify_expired"))
+            .await?;
+        return Ok(());
+    };
+
+    let chat_id = ChatId(chat_id);
+    if let Err(e) = bot
+        .restrict_chat_member(chat_id, q.from.id, unmuted_permissions())
+        .await
+    {
+        warn!("Failed to unmute verified member {} in chat {}: {}", target_id, chat_id, e);
+        // Keep them pending so they can try again before the timeout
+        state.welcome.mark_pending_verification(chat_id.0, target_id, kick_at).await?;
+        bot.answer_callback_query(&q.id)
+            .text(get_text(&locale, "welcome.verify_failed"))
+            .show_alert(true)
+            .await?;
+        return Ok(());
+    }
+
+    bot.answer_callback_query(&q.id)
+        .text(get_text(&locale, "welcome.verify_success"))
+        .await?;
+    info!("Member {} verified in chat {}", target_id, chat_id);
+
+    // Drop the verify button, keeping the welcome's own buttons
+    if let Some(message) = q.regular_message()
+        && let Some(markup) = message.reply_markup()
+    {
+        let rows: Vec<Vec<InlineKeyboardButton>> = markup
+            .inline_keyboard
+            .iter()
+            .filter(|row| {
+                !row.iter().any(|btn| {
+                    matches!(&btn.kind, InlineKeyboardButtonKind::CallbackData(d) if d.starts_with(VERIFY_CALLBACK_PREFIX))
+                })
+            })
+            .cloned()
+            .collect();
+        let _ = bot
+            .edit_message_reply_markup(message.chat.id, message.id)
+            .reply_markup(InlineKeyboardMarkup::new(rows))
+            .await;
     }
 
     Ok(())
 }
 
 /// Format welcome text with placeholders.
-pub fn format_welcome_text(template: &str, user: &teloxide::types::User, group: &str, count: u64) -> String {
+///
+/// `inviter` is the member who added the user, if any; `{inviter}` renders
+/// empty otherwise. A `{markdown}` / `{noformat}` tag in the template picks
+/// the format, returned alongside the text for the send's parse mode.
+/// `{rules}` / `{rules:same}` become deep-link buttons to the chat's rules,
+/// returned for [`merge_filling_buttons`].
+pub fn format_welcome_text(
+    template: &str,
+    user: &teloxide::types::User,
+    inviter: Option<&teloxide::types::User>,
+    group: &str,
+    count: u64,
+    chat_id: i64,
+    bot_username: &str,
+) -> (String, ContentFormat, Vec<(InlineButton, bool)>) {
+    let (template, format) = parse_format_tag(template);
+    let template = format.prepare(&template);
+    let (template, rule_buttons) = apply_rules_filling(&template, chat_id, bot_username);
+
     let name = user.first_name.clone();
     let username = user
         .username
         .as_ref()
         .map(|u| format!("@{}", u))
         .unwrap_or_else(|| name.clone());
-    let mention = format!(
-        "<a href=\"tg://user?id={}\">{}</a>",
-        user.id,
-        html_escape(&name)
-    );
+    let mention = format.mention(user.id, &name);
+
+    let inviter = inviter
+        .map(|u| format.mention(u.id, &u.first_name))
+        .unwrap_or_default();
 
-    template
-        .replace("{name}", &html_escape(&name))
-        .replace("{username}", &html_escape(&username))
+    let text = template
+        .replace("{inviter}", &inviter)
+        .replace("{name}", &format.escape(&name))
+        .replace("{username}", &format.escape(&username))
         .replace("{mention}", &mention)
         .replace("{id}", &user.id.to_string())
-        .replace("{group}", &html_escape(group))
-        .replace("{count}", &count.to_string())
+        .replace("{group}", &format.escape(group))
+        .replace("{count}", &count.to_string());
+    (text, format, rule_buttons)
 }
 
 /// Build inline keyboard from buttons config.
@@ -0,0 +1,109 @@
+//! Time source for timed restrictions.
+//!
+//! Temp-bans, temp-mutes and penalties compute their `until_date` through
+//! a [`Clock`] stored on `AppState`, so there is one consistent notion of
+//! "now" and the expiry math can be tested with a fixed time.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use teloxide::types::UntilDate;
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// Current UTC time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Manually driven clock for tests.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct FixedClock(parking_lot::Mutex<DateTime<Utc>>);
+
+#[cfg(test)]
+impl FixedClock {
+    pub fn at(timestamp: i64) -> Self {
+        Self(parking_lot::Mutex::new(
+            DateTime::from_timestamp(timestamp, 0).expect("valid timestamp"),
+        ))
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.0.lock();
+        *now += chrono::Duration::from_std(by).expect("duration in range");
+    }
+}
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock()
+    }
+}
+
+/// Compute when a restriction of `duration` starting now should end.
+///
+/// Sub-second precision is dropped, since Telegram works in whole seconds.
+pub fn restrict_until(clock: &dyn Clock, duration: Duration) -> DateTime<Utc> {
+    let secs = i64::try_from(duration.as_secs()).unwrap_or(i64::MAX);
+    let secs = clock.now().timestamp().saturating_add(secs);
+    DateTime::from_timestamp(secs, 0).unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// Whether a new restriction ending at `until` outlasts an existing one.
+///
+/// Telegram replaces `until_date` on re-restricting, so a shorter new
+/// duration shortens the restriction rather than stacking.
+pub fn extends(existing: UntilDate, until: DateTime<Utc>) -> bool {
+    match existing {
+        UntilDate::Date(end) => until > end,
+        UntilDate::Forever => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restrict_until() {
+        let clock = FixedClock::at(1_700_000_000);
+        let until = restrict_until(&clock, Duration::from_secs(3600));
+        assert_eq!(until.timestamp(), 1_700_003_600);
+
+        clock.advance(Duration::from_secs(60));
+        let until = restrict_until(&clock, Duration::from_millis(30_500));
+        assert_eq!(until.timestamp(), 1_700_000_090);
+    }
+
+    #[test]
+    fn test_extends() {
+        let clock = FixedClock::at(1_700_000_000);
+        let until = restrict_until(&clock, Duration::from_secs(7200));
+        let hour = DateTime::from_timestamp(1_700_003_600, 0).unwrap();
+        let day = DateTime::from_timestamp(1_700_086_400, 0).unwrap();
+
+        assert!(extends(UntilDate::Date(hour), until));
+        assert!(!extends(UntilDate::Date(day), until));
+        assert!(!extends(UntilDate::Forever, until));
+    }
+
+    #[test]
+    fn test_restrict_until_saturates() {
+        let clock = FixedClock::at(0);
+
+        // Overflowing durations saturate instead of wrapping
+        let until = restrict_until(&clock, Duration::from_secs(u64::MAX));
+        assert_eq!(until, DateTime::<Utc>::MAX_UTC);
+    }
+}
@@ -0,0 +1,249 @@
+//! Message entity rendering.
+//!
+//! Telegram delivers formatting as plain text plus entities. Content
+//! captured from a replied message (notes, filters, greetings) is stored as
+//! HTML, so the entities are rendered back into tags to keep the formatting.
+
+use teloxide::types::{Message, MessageEntity, MessageEntityKind};
+
+use super::parser::{html_escape, parse_format_tag};
+use crate::database::ContentFormat;
+
+/// Render `text` with its `entities` as Telegram HTML.
+///
+/// Entity offsets are in UTF-16 code units. Auto-detected entities
+/// (mentions, URLs, hashtags, ...) stay plain text since Telegram detects
+/// them again on send. Entities overlapping their parent are cut at the
+/// parent's end so the tags always nest.
+pub fn entities_to_html(text: &str, entities: &[MessageEntity]) -> String {
+    let utf16: Vec<u16> = text.encode_utf16().collect();
+    let len = utf16.len();
+
+    let mut sorted: Vec<(&MessageEntity, String, &'static str)> = entities
+        .iter()
+        .filter_map(|e| tags(&e.kind).map(|(open, close)| (e, open, close)))
+        .collect();
+    // Outer entities first when several start at the same offset
+    sorted.sort_by(|a, b| a.0.offset.cmp(&b.0.offset).then(b.0.length.cmp(&a.0.length)));
+
+    let mut out = String::with_capacity(text.len() * 2);
+    // Open entities: (end offset, closing tag)
+    let mut stack: Vec<(usize, &'static str)> = Vec::new();
+    let mut next = 0;
+    let mut pos = 0;
+
+    loop {
+        while let Some(&(end, close)) = stack.last()
+            && end <= pos
+        {
+            out.push_str(close);
+            stack.pop();
+        }
+
+        while let Some((entity, open, close)) = sorted.get(next)
+            && entity.offset <= pos
+        {
+            let end = (entity.offset + entity.length).min(len);
+            let end = stack.last().map_or(end, |&(parent_end, _)| end.min(parent_end));
+            out.push_str(open);
+            stack.push((end, close));
+            next += 1;
+        }
+
+        if pos >= len {
+            break;
+        }
+
+        let mut until = len;
+        if let Some(&(end, _)) = stack.last() {
+            until = until.min(end);
+        }
+        if let Some((entity, _, _)) = sorted.get(next) {
+            until = until.min(entity.offset);
+        }
+        // Zero-length entities close on the next pass without consuming text
+        if until > pos {
+            out.push_str(&html_escape(&String::from_utf16_lossy(&utf16[pos..until])));
+        }
+        pos = until;
+    }
+
+    while let Some((_, close)) = stack.pop() {
+        out.push_str(close);
+    }
+    out
+}
+
+/// Opening and closing tags for an entity, if it's one we render.
+fn tags(kind: &MessageEntityKind) -> Option<(String, &'static str)> {
+    let tags = match kind {
+        MessageEntityKind::Bold => ("<b>".to_string(), "</b>"),
+        MessageEntityKind::Italic => ("<i>".to_string(), "</i>"),
+        MessageEntityKind::Underline => ("<u>".to_string(), "</u>"),
+        MessageEntityKind::Strikethrough => ("<s>".to_string(), "</s>"),
+        MessageEntityKind::Spoiler => ("<tg-spoiler>".to_string(), "</tg-spoiler>"),
+        MessageEntityKind::Blockquote => ("<blockquote>".to_string(), "</blockquote>"),
+        MessageEntityKind::Code => ("<code>".to_string(), "</code>"),
+        MessageEntityKind::Pre { language: Some(lang) } => (
+            format!("<pre><code class=\"language-{}\">", attr_escape(lang)),
+            "</code></pre>",
+        ),
+        MessageEntityKind::Pre { language: None } => ("<pre>".to_string(), "</pre>"),
+        MessageEntityKind::TextLink { url } => {
+            (format!("<a href=\"{}\">", attr_escape(url.as_str())), "</a>")
+        }
+        MessageEntityKind::TextMention { user } => {
+            (format!("<a href=\"tg://user?id={}\">", user.id), "</a>")
+        }
+        MessageEntityKind::CustomEmoji { custom_emoji_id } => (
+            format!("<tg-emoji emoji-id=\"{}\">", attr_escape(custom_emoji_id)),
+            "</tg-emoji>",
+        ),
+        _ => return None,
+    };
+    Some(tags)
+}
+
+/// Escape a value for a double-quoted HTML attribute.
+fn attr_escape(value: &str) -> String {
+    html_escape(value).replace('"', "&quot;")
+}
+
+/// Text (or caption) of a message to store as content, keeping formatting.
+///
+/// Formatted messages are rendered to HTML. Messages without formatting, or
+/// marked `{markdown}`/`{noformat}`, are returned as typed, since their text
+/// is already the intended source. `{button:...}` markup is kept unescaped
+/// so the button parser still sees the original URLs.
+pub fn message_html(msg: &Message) -> Option<String> {
+    let (text, entities) = match msg.text() {
+        Some(text) => (text, msg.entities()),
+        None => (msg.caption()?, msg.caption_entities()),
+    };
+    Some(capture_html(text, entities.unwrap_or_default()))
+}
+
+/// Render the trailing `tail` of a command's text, keeping its formatting.
+///
+/// `tail` is the content part of e.g. `/save name <content>` and must be a
+/// suffix of `text` (trailing whitespace aside); otherwise it's returned as
+/// typed. Same rules as [`message_html`].
+pub fn tail_html(text: &str, entities: Option<&[MessageEntity]>, tail: &str) -> String {
+    let text = text.trim_end();
+    let Some(start) = text.len().checked_sub(tail.len()).filter(|&start| text[start..] == *tail)
+    else {
+        return tail.to_string();
+    };
+
+    // Shift entities to be relative to the tail, dropping ones before it
+    let shift = text[..start].encode_utf16().count();
+    let entities: Vec<MessageEntity> = entities
+        .unwrap_or_default()
+        .iter()
+        .filter(|e| e.offset + e.length > shift)
+        .map(|e| {
+            let offset = e.offset.max(shift);
+            MessageEntity::new(e.kind.clone(), offset - shift, e.offset + e.length - offset)
+        })
+        .collect();
+
+    capture_html(tail, &entities)
+}
+
+/// Apply the [`message_html`] rules to a text and its entities.
+fn capture_html(text: &str, entities: &[MessageEntity]) -> String {
+    let formatted = entities.iter().any(|e| tags(&e.kind).is_some());
+    if formatted && parse_format_tag(text).1 == ContentFormat::Html {
+        unescape_buttons(&entities_to_html(text, entities))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Undo HTML escaping inside `{button:...}` markup.
+fn unescape_buttons(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    // ASCII lowercasing keeps byte offsets intact
+    while let Some(start) = rest.to_ascii_lowercase().find("{button:")
+        && let Some(len) = rest[start..].find('}')
+    {
+        let end = start + len + 1;
+        out.push_str(&rest[..start]);
+        out.push_str(
+            &rest[start..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entities_to_html() {
+        // Plain text is only escaped
+        assert_eq!(entities_to_html("a < b", &[]), "a &lt; b");
+
+        // Nested and adjacent entities
+        let text = "bold italic link";
+        let entities = [
+            MessageEntity::bold(0, 11),
+            MessageEntity::italic(5, 6),
+            MessageEntity::text_link("https://example.com/?a=1&b=2".parse().unwrap(), 12, 4),
+        ];
+        assert_eq!(
+            entities_to_html(text, &entities),
+            "<b>bold <i>italic</i></b> <a href=\"https://example.com/?a=1&amp;b=2\">link</a>"
+        );
+
+        // Offsets are UTF-16: the emoji takes two units
+        let text = "😀 hi";
+        assert_eq!(entities_to_html(text, &[MessageEntity::code(3, 2)]), "😀 <code>hi</code>");
+
+        // Auto-detected entities stay plain
+        let mention = MessageEntity::new(MessageEntityKind::Mention, 0, 4);
+        assert_eq!(entities_to_html("@bob", &[mention]), "@bob");
+    }
+
+    #[test]
+    fn test_tail_html() {
+        // "/save hi " + bold "there"
+        let text = "/save hi there";
+        let entities = [
+            MessageEntity::new(MessageEntityKind::BotCommand, 0, 5),
+            MessageEntity::bold(9, 5),
+        ];
+        assert_eq!(tail_html(text, Some(&entities), "there"), "<b>there</b>");
+
+        // Entities starting before the tail are cut to it
+        let entities = [MessageEntity::italic(6, 8)];
+        assert_eq!(tail_html(text, Some(&entities), "there"), "<i>there</i>");
+
+        // Unformatted content is kept as typed, HTML included
+        assert_eq!(tail_html("/save a <b>x</b>", None, "<b>x</b>"), "<b>x</b>");
+        assert_eq!(tail_html("/save a x", None, "nope"), "nope");
+    }
+
+    #[test]
+    fn test_unescape_buttons() {
+        let html = "a &amp; b {button:Go|https://x.com/?a=1&amp;b=2} {Button:C|https://y.com}";
+        assert_eq!(
+            unescape_buttons(html),
+            "a &amp; b {button:Go|https://x.com/?a=1&b=2} {Button:C|https://y.com}"
+        );
+    }
+
+    #[test]
+    fn test_entities_overlap_is_clamped() {
+        // An entity running past its parent is cut at the parent's end
+        let entities = [MessageEntity::bold(0, 3), MessageEntity::italic(1, 4)];
+        assert_eq!(entities_to_html("abcdef", &entities), "<b>a<i>bc</i></b>def");
+    }
+}
@@ -0,0 +1,46 @@
+//! Command guards.
+//!
+//! Admin commands all open the same way: make sure they run in a group,
+//! check the caller's rights and tell them what's missing. [`require_admin`]
+//! does all of it so handlers can't skip a step or pick the wrong denial.
+
+use teloxide::prelude::*;
+use teloxide::types::{Message, ReplyParameters, UserId};
+
+use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
+use crate::permissions::Permission;
+
+/// Check that `msg` is a group command from a user with `permission`.
+///
+/// Returns the caller's ID, or `None` when the command should stop, after
+/// telling the caller why in their language: the command was sent outside a
+/// group, or they lack the permission.
+pub async fn require_admin(
+    bot: &ThrottledBot,
+    msg: &Message,
+    state: &AppState,
+    permission: Permission,
+) -> anyhow::Result<Option<UserId>> {
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(None);
+    };
+    let chat_id = msg.chat.id;
+
+    let denial = if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        "common.error_group_only"
+    } else if state.permissions.has(chat_id, user.id, permission).await.unwrap_or(false) {
+        return Ok(Some(user.id));
+    } else {
+        "common.error_missing_permission"
+    };
+
+    let locale = state.locale_for_user(chat_id.0, user.id.0).await;
+    bot.send_message(
+        chat_id,
+        get_text(&locale, denial).replace("{permission}", permission.name()),
+    )
+    .reply_parameters(ReplyParameters::new(msg.id))
+    .await?;
+    Ok(None)
+}
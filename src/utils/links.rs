@@ -0,0 +1,74 @@
+//! Link detection in free text.
+
+/// Normalize user input or a URL to a bare lowercase domain.
+///
+/// `https://www.YouTube.com/watch?v=x` becomes `youtube.com`.
+pub fn normalize_domain(input: &str) -> Option<String> {
+    let input = input.trim().to_lowercase();
+    let without_scheme = input
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(&input);
+
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()?
+        .rsplit('@')
+        .next()?
+        .split(':')
+        .next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host).trim_end_matches('.');
+
+    let valid = host.contains('.')
+        && !host.starts_with('.')
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-');
+
+    valid.then(|| host.to_string())
+}
+
+/// Check if free text (e.g. a display name) contains something link-like.
+///
+/// Catches URLs, `t.me/...` and bare domains such as `example.com`, but not
+/// initials like `J.R.`, since the last label must be at least two letters.
+pub fn contains_link(text: &str) -> bool {
+    text.split_whitespace().any(|word| {
+        let word = word.to_lowercase();
+        word.contains("://")
+            || word.contains("t.me/")
+            || normalize_domain(&word).is_some_and(|domain| {
+                domain
+                    .rsplit('.')
+                    .next()
+                    .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_link() {
+        assert!(contains_link("Join t.me/spamchannel"));
+        assert!(contains_link("Crypto https://x.io"));
+        assert!(contains_link("Visit Example.COM now"));
+        assert!(!contains_link("J.R. Smith"));
+        assert!(!contains_link("Budi 2.0"));
+        assert!(!contains_link("Alice"));
+    }
+
+    #[test]
+    fn test_normalize_domain() {
+        assert_eq!(normalize_domain("YouTube.com"), Some("youtube.com".to_string()));
+        assert_eq!(
+            normalize_domain("https://www.youtube.com/watch?v=x"),
+            Some("youtube.com".to_string())
+        );
+        assert_eq!(normalize_domain("t.me:443/foo"), Some("t.me".to_string()));
+        assert_eq!(normalize_domain("localhost"), None);
+        assert_eq!(normalize_domain("bad domain.com"), None);
+    }
+}
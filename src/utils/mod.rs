@@ -2,13 +2,27 @@
 //!
 //! Collection of helper functions used across the bot.
 
+pub mod clock;
+pub mod entities;
+pub mod guard;
+pub mod links;
 pub mod parser;
+pub mod send;
 pub mod target;
 
 pub use parser::{
-    parse_content, apply_fillings as apply_fillings_new, html_escape, format_duration_full, parse_duration,
+    parse_content, apply_fillings as apply_fillings_new, apply_fillings_as, apply_notelink_filling, apply_rules_filling, merge_filling_buttons, html_escape, format_duration_full, parse_duration,
 };
-pub use target::get_target_from_msg;
+pub use entities::{message_html, tail_html};
+pub use guard::require_admin;
+pub use send::{is_invalid_media, is_pm_unreachable, send_content, Content};
+pub use clock::{extends, restrict_until, Clock, SystemClock};
+pub use target::{format_audit, get_target_from_msg, get_target_ids, is_flag, mention_user_id};
+
+use teloxide::types::LinkPreviewOptions;
+
+use crate::database::InlineButton;
+use crate::i18n::get_text;
 
 /// Format a username for display.
 ///
@@ -22,6 +36,69 @@ pub fn format_username(username: Option<&str>, first_name: &str) -> String {
     }
 }
 
+/// Build link preview options for note/filter sends.
+///
+/// Previews are disabled unless the content carried a `{preview}` tag.
+pub fn link_preview_options(preview: bool) -> LinkPreviewOptions {
+    LinkPreviewOptions {
+        is_disabled: !preview,
+        url: None,
+        prefer_small_media: false,
+        prefer_large_media: false,
+        show_above_text: false,
+    }
+}
+
+/// Rebuild `{button:Text|url}` source from stored button rows.
+///
+/// Buttons in the same row are joined with `:` and rows are separated by
+/// newlines, which is the syntax the button parsers accept.
+pub fn buttons_to_source(buttons: &[Vec<InlineButton>]) -> String {
+    buttons
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|btn| format!("{{button:{}|{}}}", btn.text, btn.url))
+                .collect::<Vec<_>>()
+                .join(":")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format a stored template as raw, copyable source.
+///
+/// Placeholders and button syntax are shown un-rendered so admins can edit
+/// and re-apply them.
+pub fn format_template_source(
+    locale: &str,
+    header: &str,
+    message: Option<&str>,
+    buttons: &[Vec<InlineButton>],
+    media_type: Option<&str>,
+    buttons_command: &str,
+) -> String {
+    let mut text = format!("{}\n", header);
+
+    match message.filter(|m| !m.trim().is_empty()) {
+        Some(message) => text.push_str(&format!("<pre>{}</pre>", html_escape(message))),
+        None => text.push_str(&get_text(locale, "common.dump_no_text")),
+    }
+
+    if !buttons.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(&get_text(locale, "common.dump_buttons").replace("{command}", buttons_command));
+        text.push_str(&format!("\n<pre>{}</pre>", html_escape(&buttons_to_source(buttons))));
+    }
+
+    if let Some(media_type) = media_type {
+        text.push_str("\n\n");
+        text.push_str(&get_text(locale, "common.dump_media").replace("{type}", media_type));
+    }
+
+    text
+}
+
 /// Escape special characters for MarkdownV2.
 #[allow(dead_code)]
 pub fn escape_markdown(text: &str) -> String {
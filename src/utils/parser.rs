@@ -4,12 +4,13 @@
 //! welcome messages, and other content that supports:
 //! - Buttons: `{button:Text|url}` syntax
 //! - Tags: `{admin}`, `{user}`, `{protect}`, etc.
+//! - Format tags: `{markdown}`, `{noformat}`
 //! - Fillings: `{first}`, `{mention}`, `{chatname}`, etc.
 //! - Random content: `%%%` separator
 
 use teloxide::types::User;
 
-use crate::database::InlineButton;
+use crate::database::{ContentFormat, InlineButton};
 
 /// Content tags that modify behavior.
 #[derive(Debug, Clone, Default)]
@@ -32,6 +33,8 @@ pub struct ContentTags {
     pub media_spoiler: bool,
     /// Reply to the user that was replied to (for filters)
     pub replytag: bool,
+    /// Markup of the content
+    pub format: ContentFormat,
 }
 
 /// Result of parsing content.
@@ -211,7 +214,8 @@ fn try_parse_button(chars: &[char], start: usize) -> Option<(InlineButton, usize
 /// Parse tags from text.
 ///
 /// Tags: `{admin}`, `{user}`, `{private}`, `{noprivate}`, `{protect}`,
-///       `{preview}`, `{nonotif}`, `{mediaspoiler}`, `{replytag}`
+///       `{preview}`, `{nonotif}`, `{mediaspoiler}`, `{replytag}`,
+///       `{markdown}`, `{noformat}`
 ///
 /// Returns (text without tags, parsed tags)
 pub fn parse_tags(input: &str) -> (String, ContentTags) {
@@ -254,10 +258,28 @@ pub fn parse_tags(input: &str) -> (String, ContentTags) {
         tags.replytag = true;
         text = text.replace("{replytag}", "");
     }
+    let (text, format) = parse_format_tag(&text);
+    tags.format = format;
 
     (text, tags)
 }
 
+/// Strip `{markdown}` / `{noformat}` tags and return the format selected.
+///
+/// `{noformat}` wins if both are present, since it can't fail to send.
+pub fn parse_format_tag(input: &str) -> (String, ContentFormat) {
+    let format = if input.contains("{noformat}") {
+        ContentFormat::Plain
+    } else if input.contains("{markdown}") {
+        ContentFormat::Markdown
+    } else {
+        ContentFormat::Html
+    };
+
+    let text = input.replace("{noformat}", "").replace("{markdown}", "");
+    (text, format)
+}
+
 /// Apply fillings (placeholders) to text.
 ///
 /// Fillings:
@@ -270,6 +292,17 @@ pub fn parse_tags(input: &str) -> (String, ContentTags) {
 /// - `{chatname}` / `{group}` - Chat name
 /// - `{count}` - Member count (if provided)
 pub fn apply_fillings(text: &str, user: &User, chat_name: &str, count: Option<u64>) -> String {
+    apply_fillings_as(text, user, chat_name, count, ContentFormat::Html)
+}
+
+/// Same as [`apply_fillings`], for content written in `format`.
+pub fn apply_fillings_as(
+    text: &str,
+    user: &User,
+    chat_name: &str,
+    count: Option<u64>,
+    format: ContentFormat,
+) -> String {
     let first = &user.first_name;
     let last = user.last_name.as_deref().unwrap_or("");
     let fullname = if last.is_empty() {
@@ -277,40 +310,30 @@ pub fn apply_fillings(text: &str, user: &User, chat_name: &str, count: Option<u6
     } else {
         format!("{} {}", first, last)
     };
+    let mention = format.mention(user.id, first);
     let username = user
         .username
         .as_ref()
-        .map(|u| format!("@{}", u))
-        .unwrap_or_else(|| {
-            format!(
-                "<a href=\"tg://user?id={}\">{}</a>",
-                user.id,
-                html_escape(first)
-            )
-        });
-    let mention = format!(
-        "<a href=\"tg://user?id={}\">{}</a>",
-        user.id,
-        html_escape(first)
-    );
+        .map(|u| format.escape(&format!("@{}", u)))
+        .unwrap_or_else(|| mention.clone());
     let count_str = count.map(|c| c.to_string()).unwrap_or_default();
 
-    text.replace("{first}", &html_escape(first))
-        .replace("{firstname}", &html_escape(first))
-        .replace("{last}", &html_escape(last))
-        .replace("{lastname}", &html_escape(last))
-        .replace("{fullname}", &html_escape(&fullname))
+    text.replace("{first}", &format.escape(first))
+        .replace("{firstname}", &format.escape(first))
+        .replace("{last}", &format.escape(last))
+        .replace("{lastname}", &format.escape(last))
+        .replace("{fullname}", &format.escape(&fullname))
         .replace("{username}", &username)
         .replace("{mention}", &mention)
         .replace("{id}", &user.id.to_string())
-        .replace("{chatname}", &html_escape(chat_name))
-        .replace("{group}", &html_escape(chat_name))
+        .replace("{chatname}", &format.escape(chat_name))
+        .replace("{group}", &format.escape(chat_name))
         .replace("{count}", &count_str)
 }
 
 /// Apply {rules} filling - creates a deep link button.
 /// Returns (text, additional rule buttons with same_row flag)
-pub fn _apply_rules_filling(
+pub fn apply_rules_filling(
     text: &str,
     chat_id: i64,
     bot_username: &str,
@@ -335,6 +358,61 @@ pub fn _apply_rules_filling(
     (result, buttons)
 }
 
+/// Add buttons produced by fillings to configured button rows.
+///
+/// A `same_row` button joins the last row; the rest each start a new row.
+pub fn merge_filling_buttons(rows: &mut Vec<Vec<InlineButton>>, buttons: Vec<(InlineButton, bool)>) {
+    for (button, same_row) in buttons {
+        match rows.last_mut() {
+            Some(row) if same_row => row.push(button),
+            _ => rows.push(vec![button]),
+        }
+    }
+}
+
+/// Apply {notelink:name} fillings - creates deep link buttons to notes.
+///
+/// Each link becomes its own button row that opens the note in PM via
+/// `/start note_<chat_id>_<name>`. Names Telegram can't carry in a start
+/// parameter are left in the text untouched.
+/// Returns (text, note buttons)
+pub fn apply_notelink_filling(
+    text: &str,
+    chat_id: i64,
+    bot_username: &str,
+) -> (String, Vec<InlineButton>) {
+    const OPEN: &str = "{notelink:";
+
+    let mut result = String::new();
+    let mut buttons = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find(OPEN) {
+        let after = &rest[start + OPEN.len()..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+
+        let name = after[..end].trim().trim_start_matches('#').to_lowercase();
+        let payload = format!("note_{}_{}", chat_id, name);
+        let valid = !name.is_empty()
+            && payload.len() <= 64
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if valid {
+            result.push_str(&rest[..start]);
+            let url = format!("https://t.me/{}?start={}", bot_username, payload);
+            buttons.push(InlineButton::new(format!("📝 {}", name), url));
+        } else {
+            result.push_str(&rest[..start + OPEN.len() + end + 1]);
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+
+    (result, buttons)
+}
+
 /// Escape HTML special characters.
 pub fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -381,31 +459,43 @@ pub fn format_duration_full(secs: u64) -> String {
     }
 }
 
-/// Parse duration string (e.g., "1h", "30m", "1d").
+/// Parse duration string (e.g., "1h", "30m", "1d", "3600").
 ///
 /// Supported units:
+/// - (none): seconds
+/// - s: seconds
 /// - m: minutes
 /// - h: hours
 /// - d: days
 /// - w: weeks
 pub fn parse_duration(input: &str) -> Option<std::time::Duration> {
-    let input = input.trim();
+    let input = input.trim().to_lowercase();
     if input.is_empty() {
         return None;
     }
 
-    let (digits, unit) = input.split_at(input.len() - 1);
+    // Bare integers are seconds
+    if input.chars().all(|c| c.is_ascii_digit()) {
+        return input.parse().ok().map(std::time::Duration::from_secs);
+    }
+
+    let unit = input.chars().last()?;
+    let digits = &input[..input.len() - unit.len_utf8()];
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
     let amount: u64 = digits.parse().ok()?;
 
-    let seconds = match unit {
-        "m" => amount * 60,
-        "h" => amount * 3600,
-        "d" => amount * 86400,
-        "w" => amount * 604800,
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 604800,
         _ => return None,
     };
 
-    Some(std::time::Duration::from_secs(seconds))
+    Some(std::time::Duration::from_secs(amount.checked_mul(multiplier)?))
 }
 
 #[cfg(test)]
@@ -421,6 +511,55 @@ mod tests {
         assert_eq!(buttons[0].len(), 2); // Two buttons
     }
 
+    #[test]
+    fn test_apply_notelink_filling() {
+        let (text, buttons) = apply_notelink_filling(
+            "Read {notelink:Rules} and {notelink:bad name} {notelink:faq}",
+            -100123,
+            "elysiumbot",
+        );
+
+        assert_eq!(text, "Read  and {notelink:bad name} ");
+        assert_eq!(buttons.len(), 2);
+        assert_eq!(buttons[0].text, "📝 rules");
+        assert_eq!(buttons[0].url, "https://t.me/elysiumbot?start=note_-100123_rules");
+        assert_eq!(buttons[1].url, "https://t.me/elysiumbot?start=note_-100123_faq");
+    }
+
+    #[test]
+    fn test_apply_rules_filling() {
+        let (text, buttons) = apply_rules_filling("Read {rules}", -100, "bot");
+        assert_eq!(text, "Read ");
+        assert_eq!(buttons.len(), 1);
+        assert_eq!(buttons[0].0.url, "https://t.me/bot?start=rules_-100");
+        assert!(!buttons[0].1);
+
+        // {rules:same} joins the last configured row
+        let (_, buttons) = apply_rules_filling("{rules:same}", -100, "bot");
+        let mut rows = vec![vec![InlineButton::new("Site", "https://example.com")]];
+        merge_filling_buttons(&mut rows, buttons);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].len(), 2);
+
+        // With nothing configured it starts a row of its own
+        let mut rows = Vec::new();
+        merge_filling_buttons(&mut rows, vec![(InlineButton::new("A", "https://a.example"), true)]);
+        assert_eq!(rows.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_format_tag() {
+        let (text, format) = parse_format_tag("Hi *{first}*{markdown}");
+        assert_eq!(text, "Hi *{first}*");
+        assert_eq!(format, ContentFormat::Markdown);
+
+        let (text, format) = parse_format_tag("{noformat}1 < 2");
+        assert_eq!(format, ContentFormat::Plain);
+        assert_eq!(format.prepare(&text), "1 &lt; 2");
+
+        assert_eq!(parse_format_tag("<b>hi</b>").1, ContentFormat::Html);
+    }
+
     #[test]
     fn test_parse_tags() {
         let input = "Hello {admin} {user} world";
@@ -447,4 +586,24 @@ mod tests {
         assert_eq!(parse_duration("1w"), Some(std::time::Duration::from_secs(604800)));
         assert_eq!(parse_duration("invalid"), None);
     }
+
+    #[test]
+    fn test_format_duration_full() {
+        assert_eq!(format_duration_full(45), "45 detik");
+        assert_eq!(format_duration_full(1800), "30 menit");
+        assert_eq!(format_duration_full(3600), "1 jam");
+        assert_eq!(format_duration_full(5400), "1 jam 30 menit");
+        assert_eq!(format_duration_full(3 * 86400), "3 hari");
+    }
+
+    #[test]
+    fn test_parse_duration_bare_seconds() {
+        assert_eq!(parse_duration("3600"), Some(std::time::Duration::from_secs(3600)));
+        assert_eq!(parse_duration("45s"), Some(std::time::Duration::from_secs(45)));
+        assert_eq!(parse_duration("2H"), Some(std::time::Duration::from_secs(7200)));
+        assert_eq!(parse_duration("m"), None);
+        assert_eq!(parse_duration("-5"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+        assert_eq!(parse_duration("5é"), None);
+    }
 }
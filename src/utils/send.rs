@@ -0,0 +1,455 @@
+//! Content delivery.
+//!
+//! Notes, filters, greetings and rules all send user-written content that
+//! can outgrow Telegram's limits: 4096 characters for a message and only
+//! 1024 for a media caption. [`send_content`] keeps those sends from failing
+//! by moving an oversized caption into a separate message after the media,
+//! and by truncating text that doesn't fit a message at all. A `{markdown}`
+//! template Telegram can't parse goes out as plain text instead.
+
+use teloxide::prelude::*;
+use teloxide::types::{
+    InlineKeyboardMarkup, InputFile, LinkPreviewOptions, MessageId, ParseMode, ReplyParameters,
+};
+use teloxide::{ApiError, RequestError};
+use tracing::warn;
+
+use crate::bot::dispatcher::ThrottledBot;
+use crate::utils::html_escape;
+
+/// Longest text a message can carry.
+pub const MESSAGE_LIMIT: usize = 4096;
+
+/// Longest caption a media message can carry.
+pub const CAPTION_LIMIT: usize = 1024;
+
+/// User content to send: text, optional media and delivery options.
+#[derive(Clone)]
+pub struct Content<'a> {
+    text: String,
+    parse_mode: ParseMode,
+    media: Option<(&'a str, &'a str)>,
+    keyboard: Option<InlineKeyboardMarkup>,
+    reply_to: Option<MessageId>,
+    preview: Option<LinkPreviewOptions>,
+    protect: bool,
+}
+
+impl<'a> Content<'a> {
+    /// Content with `text` sent in `parse_mode`.
+    pub fn new(text: impl Into<String>, parse_mode: ParseMode) -> Self {
+        Self {
+            text: text.into(),
+            parse_mode,
+            media: None,
+            keyboard: None,
+            reply_to: None,
+            preview: None,
+            protect: false,
+        }
+    }
+
+    /// Attach stored media (`photo`, `video`, `sticker`, ...) by file ID.
+    pub fn media(mut self, media_type: Option<&'a str>, file_id: Option<&'a str>) -> Self {
+        self.media = media_type.zip(file_id);
+        self
+    }
+
+    /// Inline keyboard, placed on the last message sent.
+    pub fn keyboard(mut self, keyboard: impl Into<Option<InlineKeyboardMarkup>>) -> Self {
+        self.keyboard = keyboard.into();
+        self
+    }
+
+    /// Reply to a message with the first message sent.
+    pub fn reply_to(mut self, reply_to: impl Into<Option<MessageId>>) -> Self {
+        self.reply_to = reply_to.into();
+        self
+    }
+
+    /// Link preview options for text messages.
+    pub fn preview(mut self, preview: LinkPreviewOptions) -> Self {
+        self.preview = Some(preview);
+        self
+    }
+
+    /// Send with protected content (no forwarding or saving).
+    pub fn protect(mut self, protect: bool) -> Self {
+        self.protect = protect;
+        self
+    }
+
+    /// The same content with its text shown as written, markup and all.
+    fn as_plain(&self) -> Self {
+        Self {
+            text: html_escape(&self.text),
+            parse_mode: ParseMode::Html,
+            ..self.clone()
+        }
+    }
+
+    /// Whether `err` is Telegram rejecting this content's MarkdownV2.
+    ///
+    /// Admins write `{markdown}` templates by hand, and a single unescaped
+    /// `.` or `!` makes every send fail.
+    fn is_markdown_error(&self, err: &RequestError) -> bool {
+        self.parse_mode == ParseMode::MarkdownV2
+            && matches!(err, RequestError::Api(ApiError::CantParseEntities(_)))
+    }
+}
+
+/// Apply the delivery options shared by every request kind and send,
+/// evaluating to the sent message's ID.
+macro_rules! deliver {
+    ($req:expr, $content:expr, $keyboard:expr, $reply_to:expr) => {{
+        let mut req = $req;
+        if let Some(keyboard) = $keyboard {
+            req = req.reply_markup(keyboard);
+        }
+        if let Some(id) = $reply_to {
+            req = req.reply_parameters(ReplyParameters::new(id));
+        }
+        if $content.protect {
+            req = req.protect_content(true);
+        }
+        req.await?.id
+    }};
+}
+
+/// Send user content, keeping within Telegram's length limits.
+///
+/// Media that takes a caption gets the text as caption when it fits;
+/// otherwise the media goes out bare and the text follows as a message.
+/// Stickers and voice notes always send their text separately. Text longer
+/// than a message is truncated with an ellipsis.
+///
+/// Returns the IDs of the messages sent, in order.
+pub async fn send_content(
+    bot: &ThrottledBot,
+    chat_id: ChatId,
+    content: Content<'_>,
+) -> Result<Vec<MessageId>, RequestError> {
+    match send_parts(bot, chat_id, &content).await {
+        // Text messages fall back on their own, so only a caption gets here,
+        // before anything was sent
+        Err(e) if content.is_markdown_error(&e) => {
+            warn!("Markdown caption in chat {} doesn't parse, sending it as plain text: {}", chat_id, e);
+            send_parts(bot, chat_id, &content.as_plain()).await
+        }
+        result => result,
+    }
+}
+
+/// Send the media and text of `content`.
+async fn send_parts(
+    bot: &ThrottledBot,
+    chat_id: ChatId,
+    content: &Content<'_>,
+) -> Result<Vec<MessageId>, RequestError> {
+    let Some((media_type, file_id)) = content.media else {
+        let sent = send_text(bot, chat_id, content, content.keyboard.clone(), content.reply_to).await?;
+        return Ok(sent.into_iter().collect());
+    };
+    let file = InputFile::file_id(file_id);
+
+    let captioned = matches!(media_type, "photo" | "video" | "animation" | "audio" | "document");
+    if !captioned && !matches!(media_type, "sticker" | "voice") {
+        // Unknown media type, send just the text
+        let sent = send_text(bot, chat_id, content, content.keyboard.clone(), content.reply_to).await?;
+        return Ok(sent.into_iter().collect());
+    }
+
+    let caption = (captioned
+        && !content.text.is_empty()
+        && visible_len(&content.text, content.parse_mode) <= CAPTION_LIMIT)
+        .then_some(content.text.as_str());
+    let text_after = caption.is_none() && !content.text.is_empty();
+    // The keyboard goes on whichever message comes last
+    let media_keyboard = if text_after { None } else { content.keyboard.clone() };
+
+    macro_rules! captioned {
+        ($req:expr) => {{
+            let mut req = $req;
+            if let Some(caption) = caption {
+                req = req.caption(caption).parse_mode(content.parse_mode);
+            }
+            deliver!(req, content, media_keyboard, content.reply_to)
+        }};
+    }
+
+    let media_id = match media_type {
+        "photo" => captioned!(bot.send_photo(chat_id, file)),
+        "video" => captioned!(bot.send_video(chat_id, file)),
+        "animation" => captioned!(bot.send_animation(chat_id, file)),
+        "audio" => captioned!(bot.send_audio(chat_id, file)),
+        "document" => captioned!(bot.send_document(chat_id, file)),
+        // Voice notes carry no formatted caption, the text is sent after
+        "voice" => {
+            // SendVoice has no protect_content setter
+            let mut req = bot.send_voice(chat_id, file);
+            if let Some(keyboard) = media_keyboard {
+                req = req.reply_markup(keyboard);
+            }
+            if let Some(id) = content.reply_to {
+                req = req.reply_parameters(ReplyParameters::new(id));
+            }
+            req.await?.id
+        }
+        _ => deliver!(bot.send_sticker(chat_id, file), content, media_keyboard, content.reply_to),
+    };
+
+    let mut sent = vec![media_id];
+    if text_after {
+        sent.extend(send_text(bot, chat_id, content, content.keyboard.clone(), None).await?);
+    }
+    Ok(sent)
+}
+
+/// Send the content's text as a message, truncated to fit.
+///
+/// Markdown Telegram can't parse is sent again as plain text.
+///
+/// Returns the message's ID, or `None` when there was no text to send.
+async fn send_text(
+    bot: &ThrottledBot,
+    chat_id: ChatId,
+    content: &Content<'_>,
+    keyboard: Option<InlineKeyboardMarkup>,
+    reply_to: Option<MessageId>,
+) -> Result<Option<MessageId>, RequestError> {
+    match send_text_as(bot, chat_id, content, keyboard.clone(), reply_to).await {
+        Err(e) if content.is_markdown_error(&e) => {
+            warn!("Markdown in chat {} doesn't parse, sending it as plain text: {}", chat_id, e);
+            send_text_as(bot, chat_id, &content.as_plain(), keyboard, reply_to).await
+        }
+        result => result,
+    }
+}
+
+/// Send the content's text in its own parse mode.
+async fn send_text_as(
+    bot: &ThrottledBot,
+    chat_id: ChatId,
+    content: &Content<'_>,
+    keyboard: Option<InlineKeyboardMarkup>,
+    reply_to: Option<MessageId>,
+) -> Result<Option<MessageId>, RequestError> {
+    if content.text.is_empty() {
+        return Ok(None);
+    }
+
+    let mut req = match content.parse_mode {
+        ParseMode::Html => {
+            bot.send_message(chat_id, truncate_html(&content.text, MESSAGE_LIMIT))
+                .parse_mode(ParseMode::Html)
+        }
+        // Cutting Markdown can leave entities unbalanced, so an oversized
+        // text goes out as plain text instead
+        parse_mode if visible_len(&content.text, parse_mode) <= MESSAGE_LIMIT => {
+            bot.send_message(chat_id, &content.text).parse_mode(parse_mode)
+        }
+        _ => bot.send_message(chat_id, truncate_plain(&content.text, MESSAGE_LIMIT)),
+    };
+    if let Some(preview) = content.preview.clone() {
+        req = req.link_preview_options(preview);
+    }
+    Ok(Some(deliver!(req, content, keyboard, reply_to)))
+}
+
+/// Whether a send failed because the user never started the bot (or blocked it).
+pub fn is_pm_unreachable(err: &RequestError) -> bool {
+    match err {
+        RequestError::Api(ApiError::BotBlocked | ApiError::CantInitiateConversation) => true,
+        RequestError::Api(ApiError::Unknown(desc)) => desc.starts_with("Forbidden"),
+        _ => false,
+    }
+}
+
+/// Whether a send failed because Telegram rejected a stored media file ID.
+///
+/// File IDs stop working when the bot token changes or the file is very
+/// old, and a file sent as the wrong kind of media is rejected too.
+pub fn is_invalid_media(err: &RequestError) -> bool {
+    match err {
+        RequestError::Api(ApiError::WrongFileId | ApiError::WrongFileIdOrUrl | ApiError::FileIdInvalid) => true,
+        RequestError::Api(ApiError::Unknown(desc)) => {
+            desc.starts_with("Bad Request") && (desc.contains("file identifier") || desc.contains("file of type"))
+        }
+        _ => false,
+    }
+}
+
+/// A piece of Telegram HTML.
+enum Token<'a> {
+    /// Opening tag and its name
+    Open(&'a str, &'a str),
+    /// Closing tag
+    Close(&'a str),
+    /// Visible text and its length in UTF-16 units
+    Text(&'a str, usize),
+}
+
+/// Split off the next token of `html`.
+fn next_token(html: &str) -> (Token<'_>, &str) {
+    if html.starts_with('<')
+        && let Some(end) = html.find('>')
+    {
+        let (tag, rest) = html.split_at(end + 1);
+        if tag.starts_with("</") {
+            return (Token::Close(tag), rest);
+        }
+        let name = tag[1..tag.len() - 1]
+            .split(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        return (Token::Open(tag, name), rest);
+    }
+
+    // An entity like `&amp;` shows as a single character
+    if html.starts_with('&')
+        && let Some(end) = html.bytes().take(10).position(|b| b == b';')
+        && end > 1
+        && html[1..end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')
+    {
+        let (entity, rest) = html.split_at(end + 1);
+        return (Token::Text(entity, 1), rest);
+    }
+
+    let len = html.chars().next().map_or(0, char::len_utf8);
+    let (text, rest) = html.split_at(len);
+    (Token::Text(text, text.encode_utf16().count()), rest)
+}
+
+/// Length of `text` as Telegram counts it against its limits.
+///
+/// HTML tags don't count and entities count as one character. For other
+/// modes the markup is counted too, which errs on the safe side.
+fn visible_len(text: &str, parse_mode: ParseMode) -> usize {
+    if parse_mode != ParseMode::Html {
+        return text.encode_utf16().count();
+    }
+
+    let mut len = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (token, next) = next_token(rest);
+        if let Token::Text(_, units) = token {
+            len += units;
+        }
+        rest = next;
+    }
+    len
+}
+
+/// Cut HTML to `limit` visible characters, ending with an ellipsis and
+/// closing any tags left open.
+fn truncate_html(html: &str, limit: usize) -> String {
+    if visible_len(html, ParseMode::Html) <= limit {
+        return html.to_string();
+    }
+
+    let budget = limit - 1;
+    let mut out = String::with_capacity(html.len().min(limit * 4));
+    let mut open: Vec<&str> = Vec::new();
+    let mut used = 0;
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (token, next) = next_token(rest);
+        match token {
+            Token::Open(tag, name) => {
+                out.push_str(tag);
+                open.push(name);
+            }
+            Token::Close(tag) => {
+                out.push_str(tag);
+                open.pop();
+            }
+            Token::Text(text, units) => {
+                if used + units > budget {
+                    break;
+                }
+                out.push_str(text);
+                used += units;
+            }
+        }
+        rest = next;
+    }
+
+    out.push('…');
+    for name in open.iter().rev() {
+        out.push_str(&format!("</{}>", name));
+    }
+    out
+}
+
+/// Cut plain text to `limit` characters, ending with an ellipsis.
+fn truncate_plain(text: &str, limit: usize) -> String {
+    let budget = limit - 1;
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        if used + c.len_utf16() > budget {
+            break;
+        }
+        out.push(c);
+        used += c.len_utf16();
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_len() {
+        assert_eq!(visible_len("<b>hi</b> &amp; <a href=\"x\">yo</a>", ParseMode::Html), 7);
+        // Emoji take two UTF-16 units
+        assert_eq!(visible_len("😀", ParseMode::Html), 2);
+        // A bare ampersand is just a character
+        assert_eq!(visible_len("a & b;", ParseMode::Html), 6);
+        assert_eq!(visible_len("*hi*", ParseMode::MarkdownV2), 4);
+    }
+
+    #[test]
+    fn test_truncate_html() {
+        // Short text is left alone
+        assert_eq!(truncate_html("<b>hi</b>", 10), "<b>hi</b>");
+
+        // Open tags are closed after the ellipsis
+        assert_eq!(truncate_html("<b>hello <i>world</i></b>", 8), "<b>hello <i>w…</i></b>");
+
+        // Entities are never split
+        assert_eq!(truncate_html("a &amp; b c d", 4), "a &amp;…");
+
+        assert_eq!(truncate_plain("abcdef", 4), "abc…");
+    }
+
+    #[test]
+    fn test_markdown_fallback() {
+        let err = RequestError::Api(ApiError::CantParseEntities(
+            "Bad Request: can't parse entities: Character '.' is reserved".into(),
+        ));
+        let content = Content::new("Hi *there*.", ParseMode::MarkdownV2);
+        assert!(content.is_markdown_error(&err));
+        assert!(!Content::new("Hi", ParseMode::Html).is_markdown_error(&err));
+
+        let plain = content.as_plain();
+        assert_eq!(plain.parse_mode, ParseMode::Html);
+        assert_eq!(plain.text, "Hi *there*.");
+        assert_eq!(Content::new("a < b", ParseMode::MarkdownV2).as_plain().text, "a &lt; b");
+    }
+
+    #[test]
+    fn test_is_invalid_media() {
+        assert!(is_invalid_media(&RequestError::Api(ApiError::WrongFileIdOrUrl)));
+        assert!(is_invalid_media(&RequestError::Api(ApiError::Unknown(
+            "Bad Request: wrong remote file identifier specified: Wrong string length".into()
+        ))));
+        assert!(is_invalid_media(&RequestError::Api(ApiError::Unknown(
+            "Bad Request: can't use file of type Video as Photo".into()
+        ))));
+        assert!(!is_invalid_media(&RequestError::Api(ApiError::BotBlocked)));
+    }
+}
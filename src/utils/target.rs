@@ -7,6 +7,7 @@ use teloxide::prelude::*;
 use teloxide::types::{Message, MessageEntityKind, UserId};
 
 use crate::bot::dispatcher::{AppState, ThrottledBot};
+use crate::i18n::get_text;
 
 /// Get target user from message (reply, ID, TextMention, @username).
 /// Returns (user_id, first_name, skip_words_count for args after target).
@@ -16,20 +17,27 @@ use crate::bot::dispatcher::{AppState, ThrottledBot};
 /// 2. ID argument → lookup via `UserRepo.get_by_id`
 /// 3. TextMention entity → extract user from entity
 /// 4. @username → lookup via `UserRepo.get_by_username`, fallback to `get_chat`
+///
+/// Leading flags (see [`is_flag`]) are skipped and included in the count.
 pub async fn get_target_from_msg(
     bot: &ThrottledBot,
     msg: &Message,
     state: &AppState,
 ) -> Option<(UserId, String, usize)> {
+    let flags = msg
+        .text()
+        .map(|t| t.split_whitespace().skip(1).take_while(|a| is_flag(a)).count())
+        .unwrap_or(0);
+
     // 1. Check reply
-    if let Some(reply) = msg.reply_to_message() {
-        if let Some(user) = &reply.from {
-            return Some((user.id, user.first_name.clone(), 0));
+    if let Some(reply) = msg.reply_to_message()
+        && let Some(user) = &reply.from {
+            return Some((user.id, user.first_name.clone(), flags));
         }
-    }
 
     if let Some(text) = msg.text() {
-        let parts: Vec<&str> = text.split_whitespace().collect();
+        let mut parts: Vec<&str> = text.split_whitespace().collect();
+        parts.drain(1..1 + flags);
         if parts.len() > 1 {
             let arg = parts[1];
 
@@ -41,7 +49,7 @@ pub async fn get_target_from_msg(
                 } else {
                     format!("User {}", id)
                 };
-                return Some((UserId(id), name, 1));
+                return Some((UserId(id), name, 1 + flags));
             }
 
             // 3. Try TextMention
@@ -50,7 +58,7 @@ pub async fn get_target_from_msg(
                     if let MessageEntityKind::TextMention { user } = &entity.kind {
                         // Only consider entities near the command (first 20 chars)
                         if entity.offset < 20 {
-                            return Some((user.id, user.first_name.clone(), 1));
+                            return Some((user.id, user.first_name.clone(), 1 + flags));
                         }
                     }
                 }
@@ -60,18 +68,112 @@ pub async fn get_target_from_msg(
             if arg.starts_with('@') {
                 let username = arg.trim_start_matches('@');
                 if let Ok(Some(user)) = state.users.get_by_username(username).await {
-                    return Some((UserId(user.user_id), user.first_name, 1));
+                    return Some((UserId(user.user_id), user.first_name, 1 + flags));
                 }
                 // Fallback to get_chat (for bots/users not in our cache)
-                if let Ok(chat) = bot.get_chat(arg.to_string()).await {
-                    if chat.is_private() {
+                if let Ok(chat) = bot.get_chat(arg.to_string()).await
+                    && chat.is_private() {
                         let name = chat.first_name().unwrap_or("User").to_string();
-                        return Some((UserId(chat.id.0 as u64), name, 1));
+                        return Some((UserId(chat.id.0 as u64), name, 1 + flags));
                     }
-                }
             }
         }
     }
 
     None
 }
+
+/// User IDs listed at the start of a multi-target command.
+///
+/// Collects the consecutive numeric arguments after any leading flags, so
+/// `/ban 123 456 spam` yields both IDs and leaves "spam" as the reason.
+/// Replies always target a single user, so nothing is collected then.
+///
+/// With `takes_duration`, a duration must follow the targets; when the
+/// argument after the IDs isn't one, the last ID is taken as a duration in
+/// seconds instead (`/tban 123 3600`).
+pub fn get_target_ids(msg: &Message, takes_duration: bool) -> Vec<UserId> {
+    if msg.reply_to_message().is_some() {
+        return Vec::new();
+    }
+    let text = msg.text().unwrap_or("");
+    leading_ids(text, takes_duration).into_iter().map(UserId).collect()
+}
+
+/// The ID-collecting part of [`get_target_ids`].
+fn leading_ids(text: &str, takes_duration: bool) -> Vec<u64> {
+    let mut args = text.split_whitespace().skip(1).skip_while(|a| is_flag(a)).peekable();
+    let mut ids = Vec::new();
+    while let Some(id) = args.peek().and_then(|a| a.parse::<u64>().ok()) {
+        ids.push(id);
+        args.next();
+    }
+    if takes_duration && args.next().and_then(crate::utils::parse_duration).is_none() {
+        ids.pop();
+    }
+    ids
+}
+
+/// Whether a command argument is a flag like `-d`.
+pub fn is_flag(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Render a user ID as a mention, using the cached name when known.
+pub async fn mention_user_id(state: &AppState, user_id: u64) -> String {
+    match state.users.get_by_id(user_id).await {
+        Ok(Some(user)) => format!(
+            "<a href=\"tg://user?id={}\">{}</a>",
+            user_id,
+            crate::utils::html_escape(&user.first_name)
+        ),
+        _ => format!("<code>{}</code>", user_id),
+    }
+}
+
+/// Format the created/updated audit lines shared by /noteinfo and /filterinfo.
+pub async fn format_audit(
+    state: &AppState,
+    locale: &str,
+    created_by: Option<u64>,
+    created_at: Option<i64>,
+    updated_by: Option<u64>,
+    updated_at: Option<i64>,
+) -> String {
+    let unknown = get_text(locale, "common.audit_unknown");
+
+    let who = |id: Option<u64>| async move {
+        match id {
+            Some(id) => mention_user_id(state, id).await,
+            None => get_text(locale, "common.audit_unknown"),
+        }
+    };
+    let when = |ts: Option<i64>| {
+        ts.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| unknown.clone())
+    };
+
+    get_text(locale, "common.audit_info")
+        .replace("{created_by}", &who(created_by).await)
+        .replace("{created_at}", &when(created_at))
+        .replace("{updated_by}", &who(updated_by).await)
+        .replace("{updated_at}", &when(updated_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leading_ids() {
+        assert_eq!(leading_ids("/ban 1 2 3 spam bot", false), vec![1, 2, 3]);
+        assert_eq!(leading_ids("/ban -d 1 2", false), vec![1, 2]);
+        assert_eq!(leading_ids("/ban @user 1", false), Vec::<u64>::new());
+
+        // A trailing number is the duration when one is expected
+        assert_eq!(leading_ids("/tban 1 2 1h spam", true), vec![1, 2]);
+        assert_eq!(leading_ids("/tban 1 2 3600", true), vec![1, 2]);
+        assert_eq!(leading_ids("/tban 1 3600 spam", true), vec![1]);
+    }
+}